@@ -0,0 +1,137 @@
+//! Capture Log Adapter
+//!
+//! This module provides a logger that keeps every message in memory instead
+//! of writing it anywhere, so that tests can check what was logged, at which
+//! level and with which fields.
+
+use std::fmt;
+use std::sync::{Mutex, MutexGuard};
+
+use crate::ports::log_port::{LogField, LogLevel, LoggerPort};
+
+/// A message captured by a `CapturingLogger`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CapturedLog {
+    /// The severity of the message.
+    pub level: LogLevel,
+    /// The message without its fields.
+    pub message: String,
+    /// The fields attached to the message, in order.
+    pub fields: Vec<(String, String)>,
+}
+
+impl CapturedLog {
+    /// Returns the value of a field of the message, if it has one.
+    pub fn field(&self, key: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(field, _)| field == key)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// A logger that captures messages in memory.
+#[derive(Default)]
+pub struct CapturingLogger {
+    logs: Mutex<Vec<CapturedLog>>,
+}
+
+impl CapturingLogger {
+    /// Creates a new instance of `CapturingLogger`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the messages captured so far, in the order they were logged.
+    pub fn logs(&self) -> Vec<CapturedLog> {
+        self.lock().clone()
+    }
+
+    /// Returns whether a message of a level containing `text` was captured.
+    pub fn contains(&self, level: LogLevel, text: &str) -> bool {
+        self.lock()
+            .iter()
+            .any(|log| log.level == level && log.message.contains(text))
+    }
+
+    /// Discards the messages captured so far.
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    /// Locks the captured messages, recovering them if a thread panicked while logging.
+    fn lock(&self) -> MutexGuard<'_, Vec<CapturedLog>> {
+        self.logs.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl fmt::Debug for CapturingLogger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "CapturingLogger ({} messages)", self.lock().len())
+    }
+}
+
+impl LoggerPort for CapturingLogger {
+    fn log_info(&self, message: &str) {
+        self.log_with(LogLevel::Info, message, &[]);
+    }
+
+    fn log_warn(&self, message: &str) {
+        self.log_with(LogLevel::Warn, message, &[]);
+    }
+
+    fn log_error(&self, message: &str) {
+        self.log_with(LogLevel::Error, message, &[]);
+    }
+
+    fn log_debug(&self, message: &str) {
+        self.log_with(LogLevel::Debug, message, &[]);
+    }
+
+    fn log_trace(&self, message: &str) {
+        self.log_with(LogLevel::Trace, message, &[]);
+    }
+
+    fn log_with(&self, level: LogLevel, message: &str, fields: &[LogField]) {
+        self.lock().push(CapturedLog {
+            level,
+            message: message.to_string(),
+            fields: fields
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn captures_the_level_message_and_fields_of_messages() {
+        let logger = CapturingLogger::new();
+        logger.log_info("Run started");
+        logger.log_with(
+            LogLevel::Warn,
+            "Stressor failed",
+            &[("stressor", "cpu"), ("pid", "42")],
+        );
+
+        let logs = logger.logs();
+        assert_eq!(logs.len(), 2);
+        assert_eq!(logs[0].level, LogLevel::Info);
+        assert_eq!(logs[0].message, "Run started");
+        assert!(logs[0].fields.is_empty());
+        assert_eq!(logs[1].level, LogLevel::Warn);
+        assert_eq!(logs[1].message, "Stressor failed");
+        assert_eq!(logs[1].field("stressor"), Some("cpu"));
+        assert_eq!(logs[1].field("pid"), Some("42"));
+        assert_eq!(logs[1].field("run_id"), None);
+
+        assert!(logger.contains(LogLevel::Warn, "failed"));
+        assert!(!logger.contains(LogLevel::Error, "failed"));
+        logger.clear();
+        assert!(logger.logs().is_empty());
+    }
+}
@@ -1,18 +1,28 @@
 // Importing the necessary modules and types from external crates and the standard library.
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
 use std::fmt;
 use std::fs;
 // For filesystem operations like creating directories.
-use std::fs::File;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::panic::Location;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use std::time::{Duration, SystemTime};
 
-use chrono::Local;
+use chrono::{Local, SecondsFormat};
 // For timestamping log messages with the current local time.
 use colored::*;
 // To colorize log messages based on their severity level.
-use fern::{log_file, Dispatch};
+use fern::Dispatch;
+use log::kv::{Key, Value, VisitSource};
 use log::LevelFilter;
+use serde::{Deserialize, Serialize};
 
 // For setting up the logging infrastructure.
-use crate::ports::log_port::LoggerPort;
+use crate::ports::log_port::{format_fields, LogField, LogLevel, LoggerPort};
 
 // For file operations like creating log files. // For formatting.
 
@@ -39,6 +49,12 @@ impl FernLogger {
     }
 }
 
+impl Default for FernLogger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 // Implement the Debug trait for FernLogger.
 impl fmt::Debug for FernLogger {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -54,7 +70,7 @@ impl LoggerPort for FernLogger {
     ///
     /// * `message` - The message to log.
     fn log_info(&self, message: &str) {
-        log::info!("{}", message);
+        self.log_with(LogLevel::Info, message, &[]);
     }
 
     /// Logs a warning message.
@@ -63,7 +79,7 @@ impl LoggerPort for FernLogger {
     ///
     /// * `message` - The message to log.
     fn log_warn(&self, message: &str) {
-        log::warn!("{}", message);
+        self.log_with(LogLevel::Warn, message, &[]);
     }
 
     /// Logs an error message.
@@ -72,7 +88,7 @@ impl LoggerPort for FernLogger {
     ///
     /// * `message` - The message to log.
     fn log_error(&self, message: &str) {
-        log::error!("{}", message);
+        self.log_with(LogLevel::Error, message, &[]);
     }
 
     /// Logs a debug message.
@@ -81,7 +97,7 @@ impl LoggerPort for FernLogger {
     ///
     /// * `message` - The message to log.
     fn log_debug(&self, message: &str) {
-        log::debug!("{}", message);
+        self.log_with(LogLevel::Debug, message, &[]);
     }
 
     /// Logs a trace message.
@@ -90,90 +106,757 @@ impl LoggerPort for FernLogger {
     ///
     /// * `message` - The message to log.
     fn log_trace(&self, message: &str) {
-        log::trace!("{}", message);
+        self.log_with(LogLevel::Trace, message, &[]);
+    }
+
+    /// Logs a message with key-value fields, which are appended to text
+    /// messages as `key=value` pairs and written as the `fields` object of
+    /// JSON lines. The message is attributed to the module of the caller,
+    /// which its level is filtered by and which JSON lines name.
+    ///
+    /// # Arguments
+    ///
+    /// * `level` - The severity of the message.
+    /// * `message` - The message to log.
+    /// * `fields` - The fields attached to the message.
+    fn log_with(&self, level: LogLevel, message: &str, fields: &[LogField]) {
+        let level = log_level(level);
+        if level > log::max_level() {
+            return;
+        }
+        let caller = Location::caller();
+        let crate_name = LOG_STATE
+            .get()
+            .map_or("", |state| state.crate_name.as_str());
+        let module = module_of(caller.file(), crate_name);
+        log::logger().log(
+            &log::Record::builder()
+                .args(format_args!("{}", message))
+                .level(level)
+                .target(&module)
+                .module_path(Some(&module))
+                .file_static(Some(caller.file()))
+                .line(Some(caller.line()))
+                .key_values(&fields)
+                .build(),
+        );
+    }
+
+    /// Starts writing every message to `runs/<run-id>.log` in the log
+    /// directory, replacing the log file of any earlier run, and removes the
+    /// oldest run log files beyond those the rotation keeps.
+    ///
+    /// # Arguments
+    ///
+    /// * `run_id` - The identifier of the run.
+    fn start_run_log(&self, run_id: &str) -> Option<String> {
+        let state = LOG_STATE.get()?;
+        let directory = state.directory.join("runs");
+        let path = directory.join(format!("{}.log", run_id));
+        let file = fs::create_dir_all(&directory)
+            .map_err(|e| format!("Unable to create {}: {}", directory.display(), e))
+            .and_then(|_| LogFile::open(path.clone(), state.rotation));
+        match file {
+            Ok(file) => {
+                *lock(&state.run) = Some(RunLog {
+                    run_id: run_id.to_string(),
+                    file,
+                });
+                remove_old_run_logs(&directory, state.rotation.keep_runs);
+                Some(path.to_string_lossy().into_owned())
+            }
+            Err(e) => {
+                log::error!("{}", e);
+                None
+            }
+        }
+    }
+
+    /// Stops writing messages to the log file of the current run.
+    fn finish_run_log(&self) {
+        if let Some(state) = LOG_STATE.get() {
+            *lock(&state.run) = None;
+        }
+    }
+}
+
+/// Where log messages are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogSink {
+    /// Standard output.
+    Stdout,
+    /// One file per level in the log directory, e.g. `one_4_all_info.log`.
+    LevelFiles,
+    /// A single file with every level in the log directory, `one_4_all.log`.
+    CombinedFile,
+}
+
+/// How log messages are formatted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    /// Human readable lines, colored by level on standard output.
+    #[default]
+    Text,
+    /// One JSON object per line with the timestamp, level, module, run id,
+    /// host and message.
+    Json,
+}
+
+/// When log files are rotated: the file is renamed with a `.1` suffix, older
+/// rotated files are shifted to `.2`, `.3` and so on, and the oldest ones
+/// beyond `keep` are removed. Run log files are rotated the same way, and
+/// the oldest ones beyond `keep_runs` are removed when a run log starts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogRotation {
+    /// The size in MiB a log file is rotated at; 0 never rotates by size.
+    pub max_size_mib: u64,
+    /// The age in hours a log file is rotated at; 0 never rotates by age.
+    pub max_age_hours: u64,
+    /// The number of rotated files kept of every log file.
+    pub keep: usize,
+    /// The number of run log files kept; 0 keeps every run log file.
+    pub keep_runs: usize,
+}
+
+impl Default for LogRotation {
+    fn default() -> Self {
+        LogRotation {
+            max_size_mib: 10,
+            max_age_hours: 24,
+            keep: 5,
+            keep_runs: 100,
+        }
+    }
+}
+
+/// How the logging system is set up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogSettings {
+    /// The directory the log files are written to.
+    pub directory: PathBuf,
+    /// The minimum severity level of logged messages.
+    pub level: LevelFilter,
+    /// Levels overriding `level` for the messages of modules, e.g. `actix_server`.
+    pub modules: Vec<(String, LevelFilter)>,
+    /// Where messages are written.
+    pub sinks: Vec<LogSink>,
+    /// How messages are formatted.
+    pub format: LogFormat,
+    /// When the log files of the sinks are rotated.
+    pub rotation: LogRotation,
+    /// The host messages are tagged with in JSON lines.
+    pub host: String,
+    /// The crate the messages of the root package are attributed to, e.g.
+    /// `commandant_rs`. Messages of workspace members are attributed to the
+    /// crate named after their directory.
+    pub crate_name: String,
+}
+
+impl LogSettings {
+    /// Creates settings that write text messages to stdout and to a file per level.
+    ///
+    /// # Arguments
+    ///
+    /// * `directory` - The directory the log files are written to.
+    /// * `level` - The minimum severity level of logged messages.
+    pub fn new(directory: impl Into<PathBuf>, level: LevelFilter) -> Self {
+        LogSettings {
+            directory: directory.into(),
+            level,
+            modules: Vec::new(),
+            sinks: vec![LogSink::Stdout, LogSink::LevelFiles],
+            format: LogFormat::Text,
+            rotation: LogRotation::default(),
+            host: String::new(),
+            crate_name: String::new(),
+        }
+    }
+
+    /// Sets the level of the messages of a module and its submodules.
+    pub fn with_module(mut self, module: &str, level: LevelFilter) -> Self {
+        self.modules.push((module.to_string(), level));
+        self
     }
+
+    /// Sets where messages are written.
+    pub fn with_sinks(mut self, sinks: Vec<LogSink>) -> Self {
+        self.sinks = sinks;
+        self
+    }
+
+    /// Sets how messages are formatted.
+    pub fn with_format(mut self, format: LogFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Sets when log files are rotated.
+    pub fn with_rotation(mut self, rotation: LogRotation) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// Sets the host messages are tagged with.
+    pub fn with_host(mut self, host: &str) -> Self {
+        self.host = host.to_string();
+        self
+    }
+
+    /// Sets the crate the messages of the root package are attributed to.
+    pub fn with_crate_name(mut self, crate_name: &str) -> Self {
+        self.crate_name = crate_name.to_string();
+        self
+    }
+
+    /// Raises the level by `verbose` steps and lowers it by `quiet` steps,
+    /// e.g. `-vv` turns `Info` into `Trace` and `-q` turns it into `Warn`.
+    /// Module levels are kept as they are.
+    pub fn with_verbosity(mut self, verbose: u8, quiet: u8) -> Self {
+        let levels = LevelFilter::iter().collect::<Vec<_>>();
+        let step = levels
+            .iter()
+            .position(|level| *level == self.level)
+            .unwrap_or(0) as i64
+            + verbose as i64
+            - quiet as i64;
+        self.level = levels[step.clamp(0, levels.len() as i64 - 1) as usize];
+        self
+    }
+}
+
+/// Parses a level name such as "Info", "debug" or "off".
+pub fn parse_level(level: &str) -> Result<LevelFilter, String> {
+    LevelFilter::from_str(level.trim()).map_err(|_| {
+        format!(
+            "Invalid log level {}, expected off, error, warn, info, debug or trace",
+            level
+        )
+    })
+}
+
+/// A message formatted as a JSON line.
+#[derive(Serialize)]
+struct JsonLine<'a> {
+    timestamp: String,
+    level: &'a str,
+    module: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    run_id: Option<&'a str>,
+    host: &'a str,
+    message: String,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    fields: BTreeMap<String, String>,
+}
+
+/// Collects the key-value fields of a record.
+struct FieldCollector(Vec<(String, String)>);
+
+impl<'kvs> VisitSource<'kvs> for FieldCollector {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), log::kv::Error> {
+        self.0.push((key.to_string(), value.to_string()));
+        Ok(())
+    }
+}
+
+/// Returns the key-value fields of a record, in order.
+fn record_fields(record: &log::Record) -> Vec<(String, String)> {
+    let mut fields = FieldCollector(Vec::new());
+    let _ = record.key_values().visit(&mut fields);
+    fields.0
+}
+
+/// Formats a message as text, followed by the fields of its record as `key=value` pairs.
+fn text_message(message: &fmt::Arguments, record: &log::Record) -> String {
+    match record.key_values().count() {
+        0 => message.to_string(),
+        _ => format_fields(&message.to_string(), &record_fields(record)),
+    }
+}
+
+/// Returns the module a source file of the workspace holds, named like
+/// `module_path!` names it, e.g. `commandant_rs::adapters::fio_adapter` for
+/// `src/adapters/fio_adapter.rs` and `common::ports::log_port` for
+/// `common/src/ports/log_port.rs`.
+///
+/// # Arguments
+///
+/// * `file` - The path of the file relative to the workspace, as reported
+///   by `Location::file`.
+/// * `crate_name` - The crate of the files of the root package.
+fn module_of(file: &str, crate_name: &str) -> String {
+    let file = file.replace('\\', "/");
+    let components = file.split('/').collect::<Vec<_>>();
+    let src = components.iter().rposition(|component| *component == "src");
+    let package = src
+        .and_then(|src| src.checked_sub(1))
+        .map(|i| components[i]);
+    let mut module = match package {
+        Some(package) => package.replace('-', "_"),
+        None => crate_name.to_string(),
+    };
+    for component in &components[src.map_or(0, |src| src + 1)..] {
+        let name = component.strip_suffix(".rs").unwrap_or(component);
+        if name.is_empty() || matches!(name, "lib" | "main" | "mod") {
+            continue;
+        }
+        if !module.is_empty() {
+            module.push_str("::");
+        }
+        module.push_str(name);
+    }
+    module
+}
+
+/// Converts the level of a message to the level of the `log` crate.
+fn log_level(level: LogLevel) -> log::Level {
+    match level {
+        LogLevel::Error => log::Level::Error,
+        LogLevel::Warn => log::Level::Warn,
+        LogLevel::Info => log::Level::Info,
+        LogLevel::Debug => log::Level::Debug,
+        LogLevel::Trace => log::Level::Trace,
+    }
+}
+
+/// The log file of the run messages are currently attributed to.
+struct RunLog {
+    run_id: String,
+    file: LogFile,
+}
+
+/// What the formats and outputs of the initialized logger share.
+struct LogState {
+    directory: PathBuf,
+    host: String,
+    crate_name: String,
+    rotation: LogRotation,
+    run: Mutex<Option<RunLog>>,
+}
+
+impl LogState {
+    /// Returns the id of the current run, if any.
+    fn run_id(&self) -> Option<String> {
+        lock(&self.run).as_ref().map(|run| run.run_id.clone())
+    }
+
+    /// Formats a message as a JSON line. A `run_id` field is written as the run
+    /// id of messages logged outside of a run log.
+    fn json_line(&self, message: &fmt::Arguments, record: &log::Record) -> String {
+        let mut fields = record_fields(record)
+            .into_iter()
+            .collect::<BTreeMap<_, _>>();
+        let run_id_field = fields.remove("run_id");
+        let run_id = self.run_id().or(run_id_field);
+        let line = JsonLine {
+            timestamp: Local::now().to_rfc3339_opts(SecondsFormat::Millis, false),
+            level: record.level().as_str(),
+            module: record.target(),
+            run_id: run_id.as_deref(),
+            host: &self.host,
+            message: message.to_string(),
+            fields,
+        };
+        serde_json::to_string(&line).unwrap_or_else(|_| message.to_string())
+    }
+
+    /// Appends a formatted message to the log file of the current run, if any.
+    fn write_run_log(&self, line: &fmt::Arguments) {
+        if let Some(run) = lock(&self.run).as_ref() {
+            run.file.write_line(line);
+        }
+    }
+}
+
+/// The state of the initialized logger, shared with `FernLogger`.
+static LOG_STATE: OnceLock<Arc<LogState>> = OnceLock::new();
+
+/// Locks a mutex, recovering it if a thread panicked while logging.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// A log file that is appended to and rotated according to a `LogRotation`.
+struct LogFile {
+    path: PathBuf,
+    rotation: LogRotation,
+    file: Mutex<OpenLogFile>,
+}
+
+/// The open log file of a `LogFile` and its size and age.
+struct OpenLogFile {
+    file: File,
+    size: u64,
+    opened_at: SystemTime,
+}
+
+impl LogFile {
+    /// Opens a log file for appending, creating it if it doesn't exist.
+    fn open(path: PathBuf, rotation: LogRotation) -> Result<Self, String> {
+        let file = Self::open_file(&path)?;
+        Ok(LogFile {
+            path,
+            rotation,
+            file: Mutex::new(file),
+        })
+    }
+
+    /// Opens the file at `path` for appending, taking its creation time as its age.
+    fn open_file(path: &Path) -> Result<OpenLogFile, String> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| format!("Unable to open the log file {}: {}", path.display(), e))?;
+        let metadata = file.metadata().ok();
+        Ok(OpenLogFile {
+            file,
+            size: metadata.as_ref().map_or(0, |metadata| metadata.len()),
+            opened_at: metadata
+                .and_then(|metadata| metadata.created().ok())
+                .unwrap_or_else(SystemTime::now),
+        })
+    }
+
+    /// Returns the path of the rotated file with a suffix, e.g. `one_4_all.log.1`.
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{}", index));
+        PathBuf::from(path)
+    }
+
+    /// Returns whether a file must be rotated before a line of `len` bytes is appended.
+    fn rotation_due(&self, file: &OpenLogFile, len: u64) -> bool {
+        if file.size == 0 {
+            return false;
+        }
+        let max_size = self.rotation.max_size_mib * 1024 * 1024;
+        let max_age = Duration::from_secs(self.rotation.max_age_hours * 3600);
+        let age = file.opened_at.elapsed().unwrap_or_default();
+        (max_size > 0 && file.size + len > max_size) || (!max_age.is_zero() && age >= max_age)
+    }
+
+    /// Shifts the rotated files and starts a new file.
+    fn rotate(&self) -> Result<OpenLogFile, String> {
+        let keep = self.rotation.keep;
+        let _ = fs::remove_file(self.rotated_path(keep.max(1)));
+        for index in (1..keep).rev() {
+            let _ = fs::rename(self.rotated_path(index), self.rotated_path(index + 1));
+        }
+        if keep > 0 {
+            let _ = fs::rename(&self.path, self.rotated_path(1));
+        } else {
+            let _ = fs::remove_file(&self.path);
+        }
+        Self::open_file(&self.path)
+    }
+
+    /// Appends a formatted message as a line, rotating the file first if it is due.
+    fn write_line(&self, line: &fmt::Arguments) {
+        let line = format!("{}\n", line);
+        let mut file = lock(&self.file);
+        if self.rotation_due(&file, line.len() as u64) {
+            // If the file cannot be rotated, keep appending to the current one.
+            if let Ok(rotated) = self.rotate() {
+                *file = rotated;
+            }
+        }
+        if file.file.write_all(line.as_bytes()).is_ok() {
+            file.size += line.len() as u64;
+        }
+    }
+}
+
+/// Removes the oldest run log files of a directory, with their rotated
+/// files, keeping the `keep` most recently modified ones.
+///
+/// # Arguments
+///
+/// * `directory` - The directory of the run log files.
+/// * `keep` - The number of run log files kept; 0 keeps every file.
+fn remove_old_run_logs(directory: &Path, keep: usize) {
+    if keep == 0 {
+        return;
+    }
+    let Ok(entries) = fs::read_dir(directory) else {
+        return;
+    };
+    let files = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .collect::<Vec<_>>();
+    let mut logs = files
+        .iter()
+        .filter(|path| path.extension().is_some_and(|extension| extension == "log"))
+        .map(|path| {
+            let modified = fs::metadata(path).and_then(|metadata| metadata.modified());
+            (modified.unwrap_or(SystemTime::UNIX_EPOCH), path)
+        })
+        .collect::<Vec<_>>();
+    logs.sort_by_key(|(modified, _)| Reverse(*modified));
+    for (_, log) in logs.into_iter().skip(keep) {
+        let rotated = format!("{}.", log.file_name().unwrap_or_default().to_string_lossy());
+        for path in &files {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            if path == log || name.starts_with(&rotated) {
+                let _ = fs::remove_file(path);
+            }
+        }
+    }
+}
+
+/// Creates an output appending to a rotated log file in the log directory.
+fn log_file_output(settings: &LogSettings, name: &str) -> Result<fern::Output, String> {
+    let file = LogFile::open(settings.directory.join(name), settings.rotation)?;
+    Ok(fern::Output::call(move |record| {
+        file.write_line(record.args())
+    }))
 }
 
 /// Initializes the logging system.
 ///
+/// Log files are appended to and rotated, and never contain color codes.
+/// While a run log is started with `FernLogger::start_run_log`, every
+/// message is also written to `runs/<run-id>.log` in the log directory.
+///
 /// # Arguments
 ///
-/// * `log_dir_path` - The path to the directory where the log files will be stored.
-/// * `level_filter` - The minimum severity level of log messages that should be logged.
+/// * `settings` - The levels, sinks, format and rotation of the logger.
 ///
 /// # Returns
 ///
-/// * `FernLogger` - The initialized `FernLogger` instance.
-pub fn init(log_dir_path: &str, level_filter: LevelFilter) -> FernLogger {
+/// * `Result<FernLogger, String>` - The initialized `FernLogger` instance, or
+///   an error message if the log files could not be created or a logger was
+///   already initialized.
+pub fn init(settings: &LogSettings) -> Result<FernLogger, String> {
+    let directory = &settings.directory;
     // Ensure the log directory exists, creating it if necessary.
-    fs::create_dir_all(log_dir_path).expect("Failed to create log directory");
-
-    // Create or truncate log files for each log level.
-    let log_levels = ["error", "warn", "info", "debug", "trace"];
-    for level in &log_levels {
-        let file_path = format!("{}/one_4_all_{}.log", log_dir_path, level);
-        File::create(&file_path)
-            .unwrap_or_else(|_| panic!("Failed to create or truncate log file: {}", file_path));
-    }
-
-    // Set up the base configuration for the logger, including message formatting.
-    let base_config = Dispatch::new()
-        .format(move |out, message, record| {
-            // Colorize messages based on their log level.
-            let color_message = match record.level() {
-                log::Level::Error => message.to_string().red(),
-                log::Level::Warn => message.to_string().yellow(),
-                log::Level::Info => message.to_string().green(),
-                log::Level::Debug => message.to_string().blue(),
-                log::Level::Trace => message.to_string().cyan(),
-            };
-            // Format the log message with a timestamp, level, and the colorized message.
-            out.finish(format_args!(
-                "{} [{}] - {}",
-                Local::now().format("%Y-%m-%dT%H:%M:%S"),
-                record.level(),
-                color_message
-            ))
-        })
-        .level(level_filter); // Apply the specified level filter to the logger.
-
-    // Set up individual log files for each log level.
-    let error_log = log_file(&format!("{}/one_4_all_error.log", log_dir_path)).unwrap();
-    let warn_log = log_file(&format!("{}/one_4_all_warn.log", log_dir_path)).unwrap();
-    let info_log = log_file(&format!("{}/one_4_all_info.log", log_dir_path)).unwrap();
-    let debug_log = log_file(&format!("{}/one_4_all_debug.log", log_dir_path)).unwrap();
-    let trace_log = log_file(&format!("{}/one_4_all_trace.log", log_dir_path)).unwrap();
-
-    // Create dispatch configurations for each log level, filtering and chaining to the respective log file.
-    let error_dispatch = Dispatch::new()
-        .filter(|meta| meta.level() == log::Level::Error)
-        .chain(error_log);
-    let warn_dispatch = Dispatch::new()
-        .filter(|meta| meta.level() == log::Level::Warn)
-        .chain(warn_log);
-    let info_dispatch = Dispatch::new()
-        .filter(|meta| meta.level() == log::Level::Info)
-        .chain(info_log);
-    let debug_dispatch = Dispatch::new()
-        .filter(|meta| meta.level() == log::Level::Debug)
-        .chain(debug_log);
-    let trace_dispatch = Dispatch::new()
-        .filter(|meta| meta.level() == log::Level::Trace)
-        .chain(trace_log);
-
-    // Combine all dispatch configurations into one.
-    let combined_config = base_config
-        .chain(error_dispatch)
-        .chain(warn_dispatch)
-        .chain(info_dispatch)
-        .chain(debug_dispatch)
-        .chain(trace_dispatch)
-        .chain(std::io::stdout()); // Also log to standard output.
+    fs::create_dir_all(directory).map_err(|e| {
+        format!(
+            "Unable to create the log directory {}: {}",
+            directory.display(),
+            e
+        )
+    })?;
+    let state = Arc::new(LogState {
+        directory: directory.clone(),
+        host: settings.host.clone(),
+        crate_name: settings.crate_name.clone(),
+        rotation: settings.rotation,
+        run: Mutex::new(None),
+    });
+
+    // Set up the base configuration for the logger, applying the level filters.
+    let mut base_config = Dispatch::new().level(settings.level);
+    for (module, level) in &settings.modules {
+        base_config = base_config.level_for(module.clone(), *level);
+    }
+
+    if settings.sinks.contains(&LogSink::Stdout) {
+        let stdout_state = state.clone();
+        let format = settings.format;
+        base_config = base_config.chain(
+            Dispatch::new()
+                .format(move |out, message, record| match format {
+                    LogFormat::Text => {
+                        // Colorize messages based on their log level.
+                        let color_message = match record.level() {
+                            log::Level::Error => text_message(message, record).red(),
+                            log::Level::Warn => text_message(message, record).yellow(),
+                            log::Level::Info => text_message(message, record).green(),
+                            log::Level::Debug => text_message(message, record).blue(),
+                            log::Level::Trace => text_message(message, record).cyan(),
+                        };
+                        // Format the log message with a timestamp, level, and the colorized message.
+                        out.finish(format_args!(
+                            "{} [{}] - {}",
+                            Local::now().format("%Y-%m-%dT%H:%M:%S"),
+                            record.level(),
+                            color_message
+                        ))
+                    }
+                    LogFormat::Json => {
+                        out.finish(format_args!("{}", stdout_state.json_line(message, record)))
+                    }
+                })
+                .chain(std::io::stdout()), // Also log to standard output.
+        );
+    }
+
+    // Files get the same format without colors, and the log file of the current run
+    // gets every message whatever the sinks are.
+    let file_state = state.clone();
+    let format = settings.format;
+    let mut files = Dispatch::new().format(move |out, message, record| match format {
+        LogFormat::Text => out.finish(format_args!(
+            "{} [{}] - {}",
+            Local::now().format("%Y-%m-%dT%H:%M:%S"),
+            record.level(),
+            text_message(message, record)
+        )),
+        LogFormat::Json => out.finish(format_args!("{}", file_state.json_line(message, record))),
+    });
+    for sink in &settings.sinks {
+        match sink {
+            LogSink::Stdout => {}
+            // Set up individual log files for each log level, filtering and chaining
+            // to the respective log file.
+            LogSink::LevelFiles => {
+                for level in log::Level::iter() {
+                    let name = format!("one_4_all_{}.log", level.as_str().to_lowercase());
+                    files = files.chain(
+                        Dispatch::new()
+                            .filter(move |meta| meta.level() == level)
+                            .chain(log_file_output(settings, &name)?),
+                    );
+                }
+            }
+            // Set up a single log file with every level.
+            LogSink::CombinedFile => {
+                files = files.chain(log_file_output(settings, "one_4_all.log")?)
+            }
+        }
+    }
+    let run_state = state.clone();
+    files = files.chain(fern::Output::call(move |record| {
+        run_state.write_run_log(record.args())
+    }));
 
     // Apply the combined logger configuration.
-    combined_config
+    base_config
+        .chain(files)
         .apply()
-        .expect("Failed to initialize logger.");
+        .map_err(|e| format!("Unable to initialize the logger: {}", e))?;
+    let _ = LOG_STATE.set(state);
     // Return the logger instance for use by other modules in the application.
-    FernLogger
+    Ok(FernLogger)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters::scoped_log_adapter::ScopedLogger;
+
+    #[test]
+    fn attributes_files_to_the_module_they_hold() {
+        let module = |file| module_of(file, "commandant_rs");
+        assert_eq!(
+            module("src/adapters/fio_adapter.rs"),
+            "commandant_rs::adapters::fio_adapter"
+        );
+        assert_eq!(module("src/main.rs"), "commandant_rs");
+        assert_eq!(
+            module("common/src/ports/log_port.rs"),
+            "common::ports::log_port"
+        );
+        assert_eq!(module("common/src/adapters/mod.rs"), "common::adapters");
+        assert_eq!(module("web-app/src/lib.rs"), "web_app");
+    }
+
+    #[test]
+    fn formats_the_fields_of_messages() {
+        let fields: &[LogField] = &[("run_id", "42"), ("stressor", "cpu"), ("note", "two words")];
+        let record = log::Record::builder()
+            .args(format_args!("Stressor started"))
+            .level(log::Level::Info)
+            .target("commandant_rs::adapters::stress_ng_adapter")
+            .key_values(&fields)
+            .build();
+
+        assert_eq!(
+            text_message(record.args(), &record),
+            r#"Stressor started run_id=42 stressor=cpu note="two words""#
+        );
+
+        let state = LogState {
+            directory: PathBuf::new(),
+            host: "node1".to_string(),
+            crate_name: String::new(),
+            rotation: LogRotation::default(),
+            run: Mutex::new(None),
+        };
+        let line: serde_json::Value =
+            serde_json::from_str(&state.json_line(record.args(), &record)).unwrap();
+        assert_eq!(line["level"], "INFO");
+        assert_eq!(line["module"], "commandant_rs::adapters::stress_ng_adapter");
+        assert_eq!(line["host"], "node1");
+        assert_eq!(line["message"], "Stressor started");
+        // A run_id field is the run id of messages logged outside of a run log.
+        assert_eq!(line["run_id"], "42");
+        assert_eq!(
+            line["fields"],
+            serde_json::json!({"stressor": "cpu", "note": "two words"})
+        );
+    }
+
+    #[test]
+    fn removes_the_oldest_run_logs_with_their_rotated_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |name: &str, age_secs: u64| {
+            let file = File::create(dir.path().join(name)).unwrap();
+            file.set_modified(SystemTime::now() - Duration::from_secs(age_secs))
+                .unwrap();
+        };
+        write("oldest.log", 300);
+        write("oldest.log.1", 400);
+        write("older.log", 200);
+        write("old.log", 100);
+        write("new.log", 0);
+        write("notes.txt", 500);
+
+        remove_old_run_logs(dir.path(), 2);
+        let mut names = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, vec!["new.log", "notes.txt", "old.log"]);
+
+        remove_old_run_logs(dir.path(), 0);
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 3);
+    }
+
+    #[test]
+    fn attributes_messages_to_their_caller() {
+        struct Recorder(Mutex<Vec<String>>);
+
+        impl log::Log for Recorder {
+            fn enabled(&self, _: &log::Metadata) -> bool {
+                true
+            }
+
+            fn log(&self, record: &log::Record) {
+                lock(&self.0).push(format!(
+                    "{} {}:{}",
+                    record.target(),
+                    record.file().unwrap_or_default(),
+                    record.line().unwrap_or_default()
+                ));
+            }
+
+            fn flush(&self) {}
+        }
+
+        static RECORDER: Recorder = Recorder(Mutex::new(Vec::new()));
+        log::set_logger(&RECORDER).unwrap();
+        log::set_max_level(LevelFilter::Trace);
+
+        let logger: Arc<dyn LoggerPort> = Arc::new(ScopedLogger::new(
+            Arc::new(FernLogger::new()),
+            &[("run_id", "42")],
+        ));
+        let line = line!() + 1;
+        logger.log_warn("attributed");
+        let records = lock(&RECORDER.0).clone();
+        assert_eq!(
+            records,
+            vec![format!(
+                "common::adapters::log_adapter {}:{}",
+                file!(),
+                line
+            )]
+        );
+    }
 }
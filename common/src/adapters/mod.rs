@@ -1,5 +1,7 @@
+pub mod capture_log_adapter;
 pub mod log_adapter;
 pub mod ps_wasm_adapter;
+pub mod scoped_log_adapter;
 
 mod system_info_adapter;
 pub mod web_server_adapter;
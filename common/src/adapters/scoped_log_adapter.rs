@@ -0,0 +1,134 @@
+//! Scoped Log Adapter
+//!
+//! This module provides a logger that attaches context fields, such as the
+//! run id or the stressor, to every message logged through it before passing
+//! the message on to another logger. Scoped loggers can be nested, like
+//! spans: an inner scope adds its fields to those of the outer scope.
+
+use std::fmt;
+use std::sync::Arc;
+
+use crate::ports::log_port::{LogField, LogLevel, LoggerPort};
+
+/// A logger that attaches context fields to every message of a scope.
+pub struct ScopedLogger {
+    inner: Arc<dyn LoggerPort>,
+    fields: Vec<(String, String)>,
+}
+
+impl ScopedLogger {
+    /// Creates a new instance of `ScopedLogger`.
+    ///
+    /// # Arguments
+    ///
+    /// * `inner` - The logger messages are passed on to.
+    /// * `fields` - The fields attached to every message.
+    pub fn new(inner: Arc<dyn LoggerPort>, fields: &[LogField]) -> Self {
+        ScopedLogger {
+            inner,
+            fields: Vec::new(),
+        }
+        .with_fields(fields)
+    }
+
+    /// Adds fields to the scope, replacing fields of the scope with the same key.
+    pub fn with_fields(mut self, fields: &[LogField]) -> Self {
+        for (key, value) in fields {
+            self.fields.retain(|(scoped, _)| scoped != key);
+            self.fields.push((key.to_string(), value.to_string()));
+        }
+        self
+    }
+}
+
+impl fmt::Debug for ScopedLogger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ScopedLogger")
+            .field("fields", &self.fields)
+            .finish()
+    }
+}
+
+impl LoggerPort for ScopedLogger {
+    fn log_info(&self, message: &str) {
+        self.log_with(LogLevel::Info, message, &[]);
+    }
+
+    fn log_warn(&self, message: &str) {
+        self.log_with(LogLevel::Warn, message, &[]);
+    }
+
+    fn log_error(&self, message: &str) {
+        self.log_with(LogLevel::Error, message, &[]);
+    }
+
+    fn log_debug(&self, message: &str) {
+        self.log_with(LogLevel::Debug, message, &[]);
+    }
+
+    fn log_trace(&self, message: &str) {
+        self.log_with(LogLevel::Trace, message, &[]);
+    }
+
+    /// Logs a message with the fields of the scope followed by `fields`.
+    /// Fields of the message replace fields of the scope with the same key.
+    fn log_with(&self, level: LogLevel, message: &str, fields: &[LogField]) {
+        let mut all = self
+            .fields
+            .iter()
+            .filter(|(key, _)| !fields.iter().any(|(field, _)| field == key))
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect::<Vec<_>>();
+        all.extend_from_slice(fields);
+        self.inner.log_with(level, message, &all);
+    }
+
+    fn start_run_log(&self, run_id: &str) -> Option<String> {
+        self.inner.start_run_log(run_id)
+    }
+
+    fn finish_run_log(&self) {
+        self.inner.finish_run_log();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters::capture_log_adapter::CapturingLogger;
+
+    #[test]
+    fn attaches_the_fields_of_its_scope() {
+        let captured = Arc::new(CapturingLogger::new());
+        let run = ScopedLogger::new(captured.clone(), &[("run_id", "42")]);
+        run.log_info("Run started");
+
+        let stressor: Arc<dyn LoggerPort> = Arc::new(
+            ScopedLogger::new(Arc::new(run), &[("stressor", "cpu")])
+                .with_fields(&[("pid", "1"), ("pid", "2")]),
+        );
+        stressor.log_error("Stressor failed");
+        stressor.log_with(LogLevel::Debug, "Stressor retried", &[("pid", "3")]);
+
+        let logs = captured.logs();
+        assert_eq!(logs[0].level, LogLevel::Info);
+        assert_eq!(
+            logs[0].fields,
+            vec![("run_id".to_string(), "42".to_string())]
+        );
+        assert_eq!(logs[1].level, LogLevel::Error);
+        assert_eq!(logs[1].message, "Stressor failed");
+        let fields = |index: usize| {
+            logs[index]
+                .fields
+                .iter()
+                .map(|(key, value)| format!("{}={}", key, value))
+                .collect::<Vec<_>>()
+        };
+        // Inner scopes add their fields to the outer ones, and fields of the
+        // same key replace earlier ones.
+        assert_eq!(fields(1), ["run_id=42", "stressor=cpu", "pid=2"]);
+        assert_eq!(fields(2), ["run_id=42", "stressor=cpu", "pid=3"]);
+        assert_eq!(stressor.start_run_log("42"), None);
+    }
+}
@@ -2,9 +2,11 @@ use std::future;
 use std::sync::Arc;
 
 use actix_web::{web, App, HttpResponse, HttpServer, Responder};
+use serde::Deserialize;
 use tokio::io;
 
 use crate::ports::log_port::LoggerPort;
+use crate::ports::report_port::{ReportPort, RunFilter};
 // web_server_adapter.rs
 use crate::ports::web_server_port::WebServerPort;
 
@@ -13,6 +15,7 @@ use crate::ports::web_server_port::WebServerPort;
 /// Adapter for the web server, integrating a logging facility.
 pub struct WebServerAdapter {
     logger: Arc<dyn LoggerPort>, // Use LoggerPort trait for the logger
+    report: Option<Arc<dyn ReportPort>>, // Renders run history for the /api routes
 }
 
 // Implement the Sync trait for the WebServerAdapter struct.
@@ -21,7 +24,18 @@ impl WebServerAdapter {
     ///
     /// Constructs a new WebServerAdapter instance.
     pub fn new(logger: Arc<dyn LoggerPort>) -> Self {
-        Self { logger }
+        Self {
+            logger,
+            report: None,
+        }
+    }
+
+    /// with_report_port
+    ///
+    /// Enables the `/api` chart routes, rendered by the given `ReportPort`.
+    pub fn with_report_port(mut self, report: Arc<dyn ReportPort>) -> Self {
+        self.report = Some(report);
+        self
     }
 }
 // show_dashboard
@@ -244,15 +258,140 @@ async fn get_status() -> impl Responder {
     HttpResponse::Ok().body("Server is running")
 }
 
+/// HistoryQuery
+///
+/// Query parameters accepted by the host history routes.
+#[derive(Debug, Deserialize)]
+struct HistoryQuery {
+    /// Limits the history to the last `hours` hours.
+    hours: Option<u64>,
+}
+
+/// svg_response
+///
+/// Turns the result of a `ReportPort` call into an SVG response, a 404 when
+/// nothing was found, or a 500 when rendering failed.
+fn svg_response(result: Result<Option<String>, String>) -> HttpResponse {
+    match result {
+        Ok(Some(svg)) => HttpResponse::Ok().content_type("image/svg+xml").body(svg),
+        Ok(None) => HttpResponse::NotFound().body("Not found"),
+        Err(e) => HttpResponse::InternalServerError().body(e),
+    }
+}
+
+/// get_run_chart
+///
+/// Renders CPU utilization, temperature and bogo ops/s of a single run as SVG.
+async fn get_run_chart(
+    report: web::Data<Arc<dyn ReportPort>>,
+    path: web::Path<String>,
+) -> impl Responder {
+    svg_response(report.run_chart_svg(&path.into_inner()))
+}
+
+/// get_host_history
+///
+/// Renders the stored sample history of a host as SVG.
+async fn get_host_history(
+    report: web::Data<Arc<dyn ReportPort>>,
+    path: web::Path<String>,
+    query: web::Query<HistoryQuery>,
+) -> impl Responder {
+    svg_response(report.host_history_svg(&path.into_inner(), query.hours))
+}
+
+/// json_response
+///
+/// Turns the result of a `ReportPort` call into a JSON response, a 404 when
+/// nothing was found, or a 500 when it failed.
+fn json_response(result: Result<Option<String>, String>) -> HttpResponse {
+    match result {
+        Ok(Some(json)) => HttpResponse::Ok()
+            .content_type("application/json")
+            .body(json),
+        Ok(None) => HttpResponse::NotFound().body("Not found"),
+        Err(e) => HttpResponse::InternalServerError().body(e),
+    }
+}
+
+/// get_host_history_json
+///
+/// Returns the stored sample history of a host at the resolution picked for
+/// the requested hours.
+async fn get_host_history_json(
+    report: web::Data<Arc<dyn ReportPort>>,
+    path: web::Path<String>,
+    query: web::Query<HistoryQuery>,
+) -> impl Responder {
+    json_response(report.host_history_json(&path.into_inner(), query.hours))
+}
+
+/// get_run_baseline
+///
+/// Compares a run with the baseline of its SKU, kind and profile, flagging
+/// regressions.
+async fn get_run_baseline(
+    report: web::Data<Arc<dyn ReportPort>>,
+    path: web::Path<String>,
+) -> impl Responder {
+    json_response(report.run_baseline_json(&path.into_inner()))
+}
+
+/// get_runs
+///
+/// Lists the stored runs newest first, filtered by host, kind and start time
+/// and paginated with `offset` and `limit`.
+async fn get_runs(
+    report: web::Data<Arc<dyn ReportPort>>,
+    query: web::Query<RunFilter>,
+) -> impl Responder {
+    json_response(report.runs_json(&query).map(Some))
+}
+
+/// get_baseline
+///
+/// Returns the baselines of a SKU, one for every kind and profile of runs.
+async fn get_baseline(
+    report: web::Data<Arc<dyn ReportPort>>,
+    path: web::Path<String>,
+) -> impl Responder {
+    json_response(report.baseline_json(&path.into_inner()))
+}
+
 // Implement the WebServerPort trait for the WebServerAdapter struct.
 #[async_trait::async_trait]
 impl WebServerPort for WebServerAdapter {
     async fn start_server(&self) -> io::Result<()> {
-        let server = HttpServer::new(|| {
-            App::new()
+        let report = self.report.clone();
+        if report.is_none() {
+            self.logger
+                .log_debug("No report port configured, chart routes are disabled.");
+        }
+
+        let server = HttpServer::new(move || {
+            let app = App::new()
                 .route("/", web::get().to(HttpResponse::Ok)) // Default route
                 .route("/status", web::get().to(get_status)) // Route for get_status
-                .route("/console", web::get().to(show_console)) // Route for show console
+                .route("/console", web::get().to(show_console)); // Route for show console
+
+            match &report {
+                // Routes for the server-rendered run history charts
+                Some(report) => app
+                    .app_data(web::Data::new(report.clone()))
+                    .route("/api/runs", web::get().to(get_runs))
+                    .route("/api/runs/{id}/chart.svg", web::get().to(get_run_chart))
+                    .route(
+                        "/api/hosts/{host}/history.svg",
+                        web::get().to(get_host_history),
+                    )
+                    .route(
+                        "/api/hosts/{host}/history",
+                        web::get().to(get_host_history_json),
+                    )
+                    .route("/api/runs/{id}/baseline", web::get().to(get_run_baseline))
+                    .route("/api/baselines/{sku}", web::get().to(get_baseline)),
+                None => app,
+            }
         })
         .workers(1) // set the number of workers
         .bind("127.0.0.1:8000")?
@@ -7,7 +7,7 @@ use async_trait::async_trait;
 // Importing the `async_trait` crate. This crate is used to enable async functions in traits,
 // which is not natively supported in Rust.
 use crate::adapters::log_adapter::FernLogger;
-use crate::ports::log_port::LoggerPort;
+use crate::ports::log_port::{LogField, LogLevel, LoggerPort};
 
 pub mod adapters;
 pub mod ports;
@@ -36,6 +36,12 @@ impl ConsoleLogger {
     }
 }
 
+impl Default for ConsoleLogger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Implements the `Debug` trait for the `ConsoleLogger` struct.
 ///
 /// The `Debug` trait provides a method for formatting an instance of `ConsoleLogger` for output,
@@ -108,6 +114,18 @@ impl LoggerPort for ConsoleLogger {
     fn log_trace(&self, message: &str) {
         self.fern_logger.log_trace(message);
     }
+
+    fn log_with(&self, level: LogLevel, message: &str, fields: &[LogField]) {
+        self.fern_logger.log_with(level, message, fields);
+    }
+
+    fn start_run_log(&self, run_id: &str) -> Option<String> {
+        self.fern_logger.start_run_log(run_id)
+    }
+
+    fn finish_run_log(&self) {
+        self.fern_logger.finish_run_log();
+    }
 }
 
 // The main function where the `ConsoleLogger` is used.
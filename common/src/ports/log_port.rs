@@ -1,4 +1,17 @@
-use std::fmt::Debug;
+use std::fmt::{self, Debug, Write};
+
+/// The severity of a logged message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+/// A key-value field attached to a logged message, e.g. `("run_id", run_id)`.
+pub type LogField<'a> = (&'a str, &'a str);
 
 /// The `LoggerPort` trait acts as a port in the hexagonal architecture.
 /// It defines a standard interface for logging functionality.
@@ -16,13 +29,21 @@ use std::fmt::Debug;
 /// - `log_error`: Logs an error message.
 /// - `log_debug`: Logs a debug message.
 /// - `log_trace`: Logs a trace message.
+/// - `log_with`: Logs a message of any level with key-value fields.
+/// - `start_run_log`: Starts writing messages to the log file of a run.
+/// - `finish_run_log`: Stops writing messages to the log file of a run.
 /// - `log_sled_error`: Logs an error message with an associated `sled::Error`.
+///
+/// The logging methods track their caller, so that a logger can find the
+/// module that logged a message with `std::panic::Location::caller` rather
+/// than attributing every message to itself.
 pub trait LoggerPort: Sync + Send + Debug {
     /// Logs an informational message.
     ///
     /// # Arguments
     ///
     /// * `message` - The informational message to be logged.
+    #[track_caller]
     fn log_info(&self, message: &str);
 
     /// Logs a warning message.
@@ -30,6 +51,7 @@ pub trait LoggerPort: Sync + Send + Debug {
     /// # Arguments
     ///
     /// * `message` - The warning message to be logged.
+    #[track_caller]
     fn log_warn(&self, message: &str);
 
     /// Logs an error message.
@@ -37,6 +59,7 @@ pub trait LoggerPort: Sync + Send + Debug {
     /// # Arguments
     ///
     /// * `message` - The error message to be logged.
+    #[track_caller]
     fn log_error(&self, message: &str);
 
     /// Logs a debug message.
@@ -44,6 +67,7 @@ pub trait LoggerPort: Sync + Send + Debug {
     /// # Arguments
     ///
     /// * `message` - The debug message to be logged.
+    #[track_caller]
     fn log_debug(&self, message: &str);
 
     /// Logs a trace message.
@@ -51,5 +75,72 @@ pub trait LoggerPort: Sync + Send + Debug {
     /// # Arguments
     ///
     /// * `message` - The trace message to be logged.
+    #[track_caller]
     fn log_trace(&self, message: &str);
+
+    /// Logs a message with key-value fields, e.g. the run id, stressor or pid
+    /// the message is about, so that they can be filtered on rather than
+    /// parsed out of the message.
+    ///
+    /// Loggers that have no notion of fields log the message with the fields
+    /// appended as `key=value` pairs.
+    ///
+    /// # Arguments
+    ///
+    /// * `level` - The severity of the message.
+    /// * `message` - The message to be logged.
+    /// * `fields` - The fields attached to the message.
+    #[track_caller]
+    fn log_with(&self, level: LogLevel, message: &str, fields: &[LogField]) {
+        let message = format_fields(message, fields);
+        match level {
+            LogLevel::Error => self.log_error(&message),
+            LogLevel::Warn => self.log_warn(&message),
+            LogLevel::Info => self.log_info(&message),
+            LogLevel::Debug => self.log_debug(&message),
+            LogLevel::Trace => self.log_trace(&message),
+        }
+    }
+
+    /// Starts writing every message to a log file of its own for a run, and
+    /// tagging messages with the run id, until `finish_run_log` is called.
+    ///
+    /// # Arguments
+    ///
+    /// * `run_id` - The identifier of the run.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<String>` - The path of the log file of the run, or `None` if
+    ///   the logger does not write run log files.
+    fn start_run_log(&self, _run_id: &str) -> Option<String> {
+        None
+    }
+
+    /// Stops writing messages to the log file of the current run.
+    fn finish_run_log(&self) {}
+}
+
+/// Appends fields to a message as `key=value` pairs, quoting values that are
+/// empty or contain whitespace, quotes or `=`.
+///
+/// # Arguments
+///
+/// * `message` - The message the fields are appended to.
+/// * `fields` - The fields to append.
+///
+/// # Returns
+///
+/// * `String` - The message followed by the fields, e.g. `Stress run started run_id=42`.
+pub fn format_fields<K: fmt::Display, V: fmt::Display>(message: &str, fields: &[(K, V)]) -> String {
+    let mut out = message.to_string();
+    for (key, value) in fields {
+        let value = value.to_string();
+        if value.is_empty() || value.contains(|c: char| c.is_whitespace() || c == '"' || c == '=') {
+            let _ = write!(out, " {}={:?}", key, value);
+        } else {
+            let _ = write!(out, " {}={}", key, value);
+        }
+    }
+    out
 }
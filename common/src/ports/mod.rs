@@ -1,3 +1,4 @@
 pub mod log_port;
 
+pub mod report_port;
 pub mod web_server_port;
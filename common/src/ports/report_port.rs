@@ -0,0 +1,114 @@
+// src/ports/report_port.rs
+
+use serde::Deserialize;
+
+/// `RunFilter` Struct
+///
+/// Filters and pagination of the run listing; unset filters match every run.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RunFilter {
+    /// Only runs on this host.
+    pub host: Option<String>,
+    /// Only runs of this kind, e.g. "benchmark".
+    pub kind: Option<String>,
+    /// Only runs started at or after this RFC 3339 time.
+    pub since: Option<String>,
+    /// Only runs started before this RFC 3339 time.
+    pub until: Option<String>,
+    /// The number of matching runs skipped.
+    pub offset: Option<usize>,
+    /// The maximum number of runs returned.
+    pub limit: Option<usize>,
+}
+
+/// `ReportPort` Trait
+///
+/// Defines the interface the web server uses to render reports over stored run
+/// history. Implementations live next to the database and charting adapters, so
+/// the web server does not need to know how runs and samples are stored.
+pub trait ReportPort: Send + Sync {
+    /// Renders the chart of a single run as an SVG document.
+    ///
+    /// # Arguments
+    ///
+    /// * `run_id` - The identifier of the run.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(svg))` - The rendered chart.
+    /// * `Ok(None)` - No run with the given identifier exists.
+    /// * `Err(message)` - The chart could not be rendered.
+    fn run_chart_svg(&self, run_id: &str) -> Result<Option<String>, String>;
+
+    /// Renders the sample history of a host as an SVG document.
+    ///
+    /// # Arguments
+    ///
+    /// * `host` - The name of the host.
+    /// * `hours` - Limits the history to the last `hours` hours, if given.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(svg))` - The rendered chart.
+    /// * `Ok(None)` - No samples exist for the host.
+    /// * `Err(message)` - The chart could not be rendered.
+    fn host_history_svg(&self, host: &str, hours: Option<u64>) -> Result<Option<String>, String>;
+
+    /// Returns the sample history of a host as a JSON document, at the
+    /// resolution picked for the time range: raw samples, or 1-minute or
+    /// hourly rollups with the minimum, average, maximum and 95th percentile
+    /// of every metric.
+    ///
+    /// # Arguments
+    ///
+    /// * `host` - The name of the host.
+    /// * `hours` - Limits the history to the last `hours` hours, if given.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(json))` - The resolution and points of the history.
+    /// * `Ok(None)` - No samples exist for the host.
+    /// * `Err(message)` - The history could not be read.
+    fn host_history_json(&self, host: &str, hours: Option<u64>) -> Result<Option<String>, String>;
+
+    /// Compares a run with the baseline of its SKU, kind and profile as a JSON
+    /// document.
+    ///
+    /// # Arguments
+    ///
+    /// * `run_id` - The identifier of the run.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(json))` - The comparison, with every regressed metric flagged.
+    /// * `Ok(None)` - The run does not exist, or its SKU has no baseline for
+    ///   runs of its kind and profile.
+    /// * `Err(message)` - The comparison could not be made.
+    fn run_baseline_json(&self, run_id: &str) -> Result<Option<String>, String>;
+
+    /// Returns the baselines of a SKU, one for every kind and profile of runs,
+    /// as a JSON array.
+    ///
+    /// # Arguments
+    ///
+    /// * `sku` - The SKU of the baseline.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(json))` - The baselines.
+    /// * `Ok(None)` - The SKU has no baseline.
+    /// * `Err(message)` - The baselines could not be read.
+    fn baseline_json(&self, sku: &str) -> Result<Option<String>, String>;
+
+    /// Lists the runs matching a filter as a JSON document, newest first.
+    ///
+    /// # Arguments
+    ///
+    /// * `filter` - The filters and page of the runs.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(json)` - The page of runs with the total number of matching runs.
+    /// * `Err(message)` - The filter is invalid or the runs could not be read.
+    fn runs_json(&self, filter: &RunFilter) -> Result<String, String>;
+}
@@ -0,0 +1,152 @@
+//! Bzip2 Archive Adapter
+//!
+//! This module provides an adapter that bundles files into tar archives
+//! compressed with bzip2, which can also be listed and extracted with
+//! `tar -tjf` and `tar -xjf`.
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use bzip2::read::BzDecoder;
+use bzip2::write::BzEncoder;
+use bzip2::Compression;
+use chrono::Utc;
+use tar::{Archive, Builder, EntryType, Header};
+
+use crate::domain::archive::ArchiveEntry;
+use crate::ports::archive_port::ArchivePort;
+
+/// The largest entry read from an archive, 1 GiB. Archives may come from
+/// other nodes, so the size in the header of an entry is not trusted to fit
+/// in memory: larger entries are rejected instead.
+pub const MAX_ENTRY_SIZE: u64 = 1 << 30;
+
+/// Writes and reads `.tar.bz2` archives.
+pub struct Bzip2ArchiveAdapter {
+    /// The largest entry read from an archive.
+    max_entry_size: u64,
+}
+
+impl Bzip2ArchiveAdapter {
+    /// Creates a new instance of `Bzip2ArchiveAdapter`.
+    pub fn new() -> Self {
+        Bzip2ArchiveAdapter {
+            max_entry_size: MAX_ENTRY_SIZE,
+        }
+    }
+}
+
+impl Default for Bzip2ArchiveAdapter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ArchivePort for Bzip2ArchiveAdapter {
+    fn write_archive(&self, path: &Path, entries: &[ArchiveEntry]) -> Result<(), String> {
+        let file = File::create(path)
+            .map_err(|e| format!("Unable to create {}: {}", path.display(), e))?;
+        let error = |e: std::io::Error| format!("Unable to write {}: {}", path.display(), e);
+
+        let mut builder = Builder::new(BzEncoder::new(BufWriter::new(file), Compression::best()));
+        let modified = Utc::now().timestamp().max(0) as u64;
+        for entry in entries {
+            let mut header = Header::new_gnu();
+            header.set_entry_type(EntryType::Regular);
+            header.set_size(entry.data.len() as u64);
+            header.set_mode(0o644);
+            header.set_mtime(modified);
+            builder
+                .append_data(&mut header, &entry.path, entry.data.as_slice())
+                .map_err(error)?;
+        }
+        // Finish the tar stream, then the bzip2 stream, then flush the file.
+        let encoder = builder.into_inner().map_err(error)?;
+        encoder.finish().map_err(error)?.flush().map_err(error)
+    }
+
+    fn read_archive(&self, path: &Path) -> Result<Vec<ArchiveEntry>, String> {
+        let file =
+            File::open(path).map_err(|e| format!("Unable to open {}: {}", path.display(), e))?;
+        let error = |e: std::io::Error| format!("Unable to read {}: {}", path.display(), e);
+
+        let mut archive = Archive::new(BzDecoder::new(BufReader::new(file)));
+        let mut entries = Vec::new();
+        for entry in archive.entries().map_err(error)? {
+            let mut entry = entry.map_err(error)?;
+            // Directories and links carry no data of their own.
+            if entry.header().entry_type() != EntryType::Regular {
+                continue;
+            }
+            let entry_path = entry.path().map_err(error)?.to_string_lossy().into_owned();
+            let too_large = || {
+                format!(
+                    "Unable to read {}: {} is larger than {} bytes",
+                    path.display(),
+                    entry_path,
+                    self.max_entry_size
+                )
+            };
+            if entry.size() > self.max_entry_size {
+                return Err(too_large());
+            }
+            let mut data = Vec::new();
+            (&mut entry)
+                .take(self.max_entry_size + 1)
+                .read_to_end(&mut data)
+                .map_err(error)?;
+            if data.len() as u64 > self.max_entry_size {
+                return Err(too_large());
+            }
+            entries.push(ArchiveEntry::new(&entry_path, data));
+        }
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_back_the_entries_it_wrote() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("run.tar.bz2");
+        let entries = vec![
+            ArchiveEntry::new("run.json", b"{}\n".to_vec()),
+            ArchiveEntry::new("artifacts/out.bin", vec![0, 0xff, 0xfe, 0x80]),
+        ];
+        let adapter = Bzip2ArchiveAdapter::new();
+        adapter.write_archive(&path, &entries).unwrap();
+
+        let read = adapter.read_archive(&path).unwrap();
+        assert_eq!(read.len(), 2);
+        for (read, written) in read.iter().zip(&entries) {
+            assert_eq!(read.path, written.path);
+            assert_eq!(read.data, written.data);
+        }
+    }
+
+    #[test]
+    fn rejects_entries_larger_than_the_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("run.tar.bz2");
+        let entries = vec![
+            ArchiveEntry::new("run.json", vec![b' '; 16]),
+            ArchiveEntry::new("samples.jsonl", vec![b' '; 17]),
+        ];
+        Bzip2ArchiveAdapter::new()
+            .write_archive(&path, &entries)
+            .unwrap();
+
+        let error = Bzip2ArchiveAdapter { max_entry_size: 16 }
+            .read_archive(&path)
+            .unwrap_err();
+        assert!(
+            error.contains("samples.jsonl is larger than 16 bytes"),
+            "{}",
+            error
+        );
+    }
+}
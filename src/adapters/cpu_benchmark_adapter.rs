@@ -0,0 +1,493 @@
+//! CPU Benchmark Adapter
+//!
+//! This module implements native CPU microbenchmarks: integer ALU, floating
+//! point fused multiply-add throughput, SHA-256 and CRC32 hashing, bzip2
+//! compression and branchy integer code. Every workload runs on a single
+//! thread and on one thread per CPU the process may run on, each pinned to
+//! its CPU so that the scheduler does not migrate the threads mid-iteration.
+
+use std::hint::black_box;
+use std::io::Write;
+use std::sync::{Arc, Barrier};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use bzip2::write::BzEncoder;
+use bzip2::Compression;
+use sha2::{Digest, Sha256};
+
+use common::ports::log_port::LoggerPort;
+
+use crate::domain::benchmark::{BenchmarkCase, BenchmarkMeasurement};
+use crate::domain::run::MetricDirection;
+use crate::ports::benchmark_port::BenchmarkPort;
+
+/// The size of the buffers hashed in a single chunk of work.
+const HASH_BUFFER_SIZE: usize = 64 * 1024;
+
+/// The size of the buffer compressed in a single chunk of work.
+const COMPRESS_BUFFER_SIZE: usize = 256 * 1024;
+
+/// The number of loop rounds in a single chunk of the integer and FMA work.
+const ALU_ROUNDS: u64 = 1 << 16;
+
+/// The number of starting values walked in a single chunk of branchy work.
+const BRANCHY_VALUES: usize = 1024;
+
+/// A CPU workload.
+///
+/// `setup` prepares the per-thread state and returns a closure that performs
+/// a short chunk of work and returns the number of operations it performed,
+/// in the unit of the workload.
+struct Workload {
+    name: &'static str,
+    unit: &'static str,
+    setup: fn() -> Box<dyn FnMut() -> u64>,
+}
+
+/// The workloads, in the order they are run.
+const WORKLOADS: &[Workload] = &[
+    Workload {
+        name: "integer",
+        unit: "ops/s",
+        setup: integer_workload,
+    },
+    Workload {
+        name: "fma",
+        unit: "FLOP/s",
+        setup: fma_workload,
+    },
+    Workload {
+        name: "sha256",
+        unit: "B/s",
+        setup: sha256_workload,
+    },
+    Workload {
+        name: "crc32",
+        unit: "B/s",
+        setup: crc32_workload,
+    },
+    Workload {
+        name: "bzip2",
+        unit: "B/s",
+        setup: bzip2_workload,
+    },
+    Workload {
+        name: "branchy",
+        unit: "ops/s",
+        setup: branchy_workload,
+    },
+];
+
+/// Runs the CPU microbenchmarks natively.
+pub struct CpuBenchmarkAdapter {
+    logger: Arc<dyn LoggerPort>,
+    cpus: Vec<usize>,
+}
+
+impl CpuBenchmarkAdapter {
+    /// Creates a new instance of `CpuBenchmarkAdapter` that runs the
+    /// multi-threaded cases on every CPU the process may run on.
+    pub fn new(logger: Arc<dyn LoggerPort>) -> Self {
+        let cpus = allowed_cpus();
+        logger.log_debug(&format!("CPU benchmarks may run on CPUs {:?}", cpus));
+        CpuBenchmarkAdapter { logger, cpus }
+    }
+}
+
+impl BenchmarkPort for CpuBenchmarkAdapter {
+    /// Returns "cpu".
+    fn suite(&self) -> &str {
+        "cpu"
+    }
+
+    /// The workloads keep their CPUs busy.
+    fn cpu_bound(&self) -> bool {
+        true
+    }
+
+    /// Lists every workload single-threaded and, on hosts with more than one
+    /// CPU, on every CPU.
+    fn cases(&self) -> Vec<BenchmarkCase> {
+        let mut thread_counts = vec![1];
+        if self.cpus.len() > 1 {
+            thread_counts.push(self.cpus.len());
+        }
+        thread_counts
+            .into_iter()
+            .flat_map(|threads| {
+                WORKLOADS.iter().map(move |workload| BenchmarkCase {
+                    name: workload.name.to_string(),
+                    threads,
+                    unit: workload.unit.to_string(),
+                    direction: MetricDirection::HigherIsBetter,
+                })
+            })
+            .collect()
+    }
+
+    /// Runs a workload on the requested number of pinned threads.
+    ///
+    /// The threads start together and each runs for `duration`; the rate is
+    /// the sum of the rates of every thread.
+    fn run_iteration(
+        &self,
+        case: &BenchmarkCase,
+        duration: Duration,
+    ) -> Result<BenchmarkMeasurement, String> {
+        let workload = WORKLOADS
+            .iter()
+            .find(|workload| workload.name == case.name)
+            .ok_or_else(|| format!("Unknown CPU benchmark {}", case.name))?;
+        if case.threads == 0 || case.threads > self.cpus.len() {
+            return Err(format!(
+                "Cannot run CPU benchmark {} on {} threads with {} CPUs available",
+                case.name,
+                case.threads,
+                self.cpus.len()
+            ));
+        }
+
+        run_pinned(
+            &self.cpus[..case.threads],
+            duration,
+            &|_| (workload.setup)(),
+            self.logger.as_ref(),
+        )
+        .map(BenchmarkMeasurement::from)
+        .map_err(|e| format!("CPU benchmark {}: {}", case.name, e))
+    }
+}
+
+/// Runs a chunked workload on one thread pinned to each of `cpus`.
+///
+/// Every thread calls `setup` with its CPU to prepare its state, e.g. to
+/// allocate and first-touch its buffers, then the threads start together and
+/// each calls its chunk closure until `duration` has passed.
+///
+/// # Returns
+/// A `Result` containing the sum of the rates of every thread, in operations
+/// per second as counted by the chunk closures, or an error if a thread
+/// panicked. Threads that could not be pinned are logged and still counted.
+pub(crate) fn run_pinned(
+    cpus: &[usize],
+    duration: Duration,
+    setup: &(dyn Fn(usize) -> Box<dyn FnMut() -> u64> + Sync),
+    logger: &dyn LoggerPort,
+) -> Result<f64, String> {
+    let barrier = Barrier::new(cpus.len());
+    let outcomes: Vec<_> = thread::scope(|scope| {
+        let handles: Vec<_> = cpus
+            .iter()
+            .map(|&cpu| {
+                let barrier = &barrier;
+                scope.spawn(move || {
+                    let pinned = pin_to_cpu(cpu);
+                    let mut chunk = setup(cpu);
+                    barrier.wait();
+                    let start = Instant::now();
+                    let mut ops = 0u64;
+                    while start.elapsed() < duration {
+                        ops += chunk();
+                    }
+                    (pinned, ops as f64 / start.elapsed().as_secs_f64())
+                })
+            })
+            .collect();
+        handles.into_iter().map(|handle| handle.join()).collect()
+    });
+
+    let mut rate = 0.0;
+    for outcome in outcomes {
+        let (pinned, thread_rate) = outcome.map_err(|_| "benchmark thread panicked".to_string())?;
+        if let Err(e) = pinned {
+            logger.log_warn(&e);
+        }
+        rate += thread_rate;
+    }
+    Ok(rate)
+}
+
+/// Lists the CPUs the process may run on.
+#[cfg(target_os = "linux")]
+pub(crate) fn allowed_cpus() -> Vec<usize> {
+    // SAFETY: `cpu_set_t` is a plain bit mask for which all zeroes is valid,
+    // and the kernel writes at most `size_of::<cpu_set_t>()` bytes into it.
+    let cpus: Vec<usize> = unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        if libc::sched_getaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &mut set) != 0 {
+            Vec::new()
+        } else {
+            (0..libc::CPU_SETSIZE as usize)
+                .filter(|&cpu| libc::CPU_ISSET(cpu, &set))
+                .collect()
+        }
+    };
+    if cpus.is_empty() {
+        available_cpus()
+    } else {
+        cpus
+    }
+}
+
+/// Lists the CPUs the process may run on.
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn allowed_cpus() -> Vec<usize> {
+    available_cpus()
+}
+
+/// Numbers the CPUs reported by the standard library from zero.
+fn available_cpus() -> Vec<usize> {
+    let count = thread::available_parallelism().map_or(1, |count| count.get());
+    (0..count).collect()
+}
+
+/// Pins the calling thread to a CPU.
+#[cfg(target_os = "linux")]
+pub(crate) fn pin_to_cpu(cpu: usize) -> Result<(), String> {
+    // SAFETY: the set is initialized before use and only the calling thread
+    // (pid 0) is affected.
+    let result = unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_SET(cpu, &mut set);
+        libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set)
+    };
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(format!(
+            "Failed to pin benchmark thread to CPU {}: {}",
+            cpu,
+            std::io::Error::last_os_error()
+        ))
+    }
+}
+
+/// Pins the calling thread to a CPU.
+///
+/// Thread affinity is only supported on Linux; elsewhere the threads are
+/// left to the scheduler.
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn pin_to_cpu(_cpu: usize) -> Result<(), String> {
+    Ok(())
+}
+
+/// Fills a buffer with pseudo-random bytes from a 16 symbol alphabet, which
+/// compresses to roughly half its size like typical text or logs.
+fn test_data(len: usize) -> Vec<u8> {
+    let mut state = 0x9e37_79b9_7f4a_7c15u64;
+    (0..len)
+        .map(|_| {
+            state = xorshift(state);
+            b"etaoinshrdlucmfw"[(state >> 60) as usize]
+        })
+        .collect()
+}
+
+/// Advances a xorshift64 generator.
+#[inline(always)]
+pub(crate) fn xorshift(mut x: u64) -> u64 {
+    x ^= x << 13;
+    x ^= x >> 7;
+    x ^= x << 17;
+    x
+}
+
+/// Integer ALU throughput: four independent lanes of a xorshift step followed
+/// by a multiply-add, counting each shift, xor, multiply and add as one op.
+fn integer_workload() -> Box<dyn FnMut() -> u64> {
+    let mut lanes = [1u64, 2, 3, 4];
+    Box::new(move || {
+        for _ in 0..ALU_ROUNDS {
+            for (i, lane) in lanes.iter_mut().enumerate() {
+                *lane = xorshift(*lane)
+                    .wrapping_mul(0x2545_f491_4f6c_dd1d)
+                    .wrapping_add(i as u64);
+            }
+        }
+        black_box(&lanes);
+        ALU_ROUNDS * lanes.len() as u64 * 8
+    })
+}
+
+/// Floating point throughput: sixteen independent chains of fused
+/// multiply-adds, counting each as two FLOPs.
+fn fma_workload() -> Box<dyn FnMut() -> u64> {
+    let mut chains = [1.0f64; 16];
+    for (i, chain) in chains.iter_mut().enumerate() {
+        *chain += i as f64;
+    }
+    Box::new(move || {
+        fma_chunk(&mut chains);
+        black_box(&chains);
+        ALU_ROUNDS * chains.len() as u64 * 2
+    })
+}
+
+/// Runs a chunk of FMA work, using the FMA instructions when the CPU has them.
+fn fma_chunk(chains: &mut [f64; 16]) {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("fma") {
+            // SAFETY: the CPU supports the FMA instructions enabled below.
+            unsafe { fma_chunk_fma(chains) };
+            return;
+        }
+    }
+    fma_chunk_generic(chains);
+}
+
+/// Runs a chunk of FMA work compiled for the FMA instructions.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "fma")]
+unsafe fn fma_chunk_fma(chains: &mut [f64; 16]) {
+    for _ in 0..ALU_ROUNDS {
+        for chain in chains.iter_mut() {
+            // Multiplying by slightly less than one keeps the chains bounded.
+            *chain = chain.mul_add(0.999_999, 0.5);
+        }
+    }
+}
+
+/// Runs a chunk of FMA work as separate multiplies and adds, since `mul_add`
+/// falls back to a slow software implementation without the instructions.
+fn fma_chunk_generic(chains: &mut [f64; 16]) {
+    for _ in 0..ALU_ROUNDS {
+        for chain in chains.iter_mut() {
+            *chain = *chain * 0.999_999 + 0.5;
+        }
+    }
+}
+
+/// SHA-256 hashing throughput in bytes hashed.
+fn sha256_workload() -> Box<dyn FnMut() -> u64> {
+    let data = test_data(HASH_BUFFER_SIZE);
+    Box::new(move || {
+        black_box(Sha256::digest(&data));
+        data.len() as u64
+    })
+}
+
+/// CRC32 checksum throughput in bytes checksummed.
+fn crc32_workload() -> Box<dyn FnMut() -> u64> {
+    let data = test_data(HASH_BUFFER_SIZE);
+    Box::new(move || {
+        black_box(crc32fast::hash(&data));
+        data.len() as u64
+    })
+}
+
+/// bzip2 compression throughput in uncompressed bytes.
+fn bzip2_workload() -> Box<dyn FnMut() -> u64> {
+    let data = test_data(COMPRESS_BUFFER_SIZE);
+    let mut output = Vec::with_capacity(COMPRESS_BUFFER_SIZE);
+    Box::new(move || {
+        output.clear();
+        let mut encoder = BzEncoder::new(&mut output, Compression::default());
+        // Writing into memory cannot fail.
+        let _ = encoder.write_all(&data);
+        let _ = encoder.finish();
+        black_box(&output);
+        data.len() as u64
+    })
+}
+
+/// Branchy code: walks Collatz sequences from pseudo-random starting values,
+/// whose data-dependent branches defeat the branch predictor. Each step of a
+/// sequence counts as one op.
+fn branchy_workload() -> Box<dyn FnMut() -> u64> {
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    Box::new(move || {
+        let mut steps = 0u64;
+        for _ in 0..BRANCHY_VALUES {
+            state = xorshift(state);
+            let mut value = (state >> 44) | 1;
+            while value != 1 {
+                value = if value & 1 == 0 {
+                    value / 2
+                } else {
+                    3 * value + 1
+                };
+                steps += 1;
+            }
+        }
+        black_box(steps)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use common::adapters::capture_log_adapter::CapturingLogger;
+    use common::ports::log_port::LogLevel;
+
+    use super::*;
+
+    #[test]
+    fn runs_every_workload() {
+        let adapter = CpuBenchmarkAdapter::new(Arc::new(CapturingLogger::new()));
+        let cases: Vec<BenchmarkCase> = adapter
+            .cases()
+            .into_iter()
+            .filter(|case| case.threads == 1)
+            .collect();
+        let names: Vec<&str> = cases.iter().map(|case| case.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["integer", "fma", "sha256", "crc32", "bzip2", "branchy"]
+        );
+
+        for case in &cases {
+            let measurement = adapter
+                .run_iteration(case, Duration::from_millis(5))
+                .unwrap();
+            assert!(
+                measurement.value.is_finite() && measurement.value > 0.0,
+                "{} measured {} {}",
+                case.name,
+                measurement.value,
+                case.unit
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_workloads_and_more_threads_than_cpus() {
+        let adapter = CpuBenchmarkAdapter {
+            logger: Arc::new(CapturingLogger::new()),
+            cpus: vec![0],
+        };
+        let case = |name: &str, threads| BenchmarkCase {
+            name: name.to_string(),
+            threads,
+            unit: "ops/s".to_string(),
+            direction: MetricDirection::HigherIsBetter,
+        };
+        let error = adapter
+            .run_iteration(&case("matmul", 1), Duration::from_millis(1))
+            .unwrap_err();
+        assert_eq!(error, "Unknown CPU benchmark matmul");
+        let error = adapter
+            .run_iteration(&case("integer", 2), Duration::from_millis(1))
+            .unwrap_err();
+        assert_eq!(
+            error,
+            "Cannot run CPU benchmark integer on 2 threads with 1 CPUs available"
+        );
+    }
+
+    #[test]
+    fn runs_on_cpu_0_or_warns_if_it_may_not() {
+        let logger = CapturingLogger::new();
+        let rate =
+            run_pinned(&[0], Duration::from_millis(5), &|_| Box::new(|| 1), &logger).unwrap();
+        assert!(rate > 0.0);
+
+        // CPU 0 may be outside of the CPU set of a container, in which case
+        // the thread runs unpinned.
+        let warned = logger.contains(LogLevel::Warn, "Failed to pin benchmark thread to CPU 0");
+        assert_eq!(
+            warned,
+            cfg!(target_os = "linux") && !allowed_cpus().contains(&0)
+        );
+    }
+}
@@ -0,0 +1,458 @@
+//! CPU Discovery Adapter
+//!
+//! This module reads the processor inventory from `/proc/cpuinfo` and the
+//! `/sys/devices/system/cpu` and `/sys/devices/system/node` trees.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::adapters::discovery_adapter::{parse_cpu_list, parse_size_kib, SysfsReader};
+use crate::domain::inventory::{CacheInfo, CpuFrequency, CpuInventory, NumaNode};
+
+const CPU_DIR: &str = "/sys/devices/system/cpu";
+const NODE_DIR: &str = "/sys/devices/system/node";
+
+/// Discovers the processors of the host.
+///
+/// # Arguments
+/// * `reader` - The reader used to access procfs and sysfs.
+///
+/// # Returns
+/// A `Result` containing the `CpuInventory`, or an error if `/proc/cpuinfo`
+/// cannot be read.
+pub fn discover_cpu(reader: &SysfsReader) -> Result<CpuInventory, String> {
+    let cpuinfo = reader
+        .read_string("/proc/cpuinfo")
+        .ok_or_else(|| "Unable to read /proc/cpuinfo".to_string())?;
+    let processors = parse_cpuinfo(&cpuinfo);
+    let first = processors.first().cloned().unwrap_or_default();
+
+    let cpus = online_cpus(reader, processors.len());
+
+    // Count sockets and cores from the topology of each online CPU.
+    let mut packages = BTreeSet::new();
+    let mut cores = BTreeSet::new();
+    for cpu in &cpus {
+        let topology = format!("{}/cpu{}/topology", CPU_DIR, cpu);
+        let package = reader.read_u64(format!("{}/physical_package_id", topology));
+        let core = reader.read_u64(format!("{}/core_id", topology));
+        if let (Some(package), Some(core)) = (package, core) {
+            packages.insert(package);
+            cores.insert((package, core));
+        }
+    }
+
+    let threads = cpus.len().max(processors.len());
+    let (sockets, core_count) = if cores.is_empty() {
+        // Without sysfs topology fall back to the cpuinfo summary fields.
+        let sockets = processors
+            .iter()
+            .filter_map(|p| p.get("physical id"))
+            .collect::<BTreeSet<_>>()
+            .len()
+            .max(1);
+        let per_socket = first
+            .get("cpu cores")
+            .and_then(|value| value.parse::<usize>().ok())
+            .unwrap_or(threads / sockets);
+        (sockets, per_socket * sockets)
+    } else {
+        (packages.len(), cores.len())
+    };
+
+    let smt_active = match reader.read_u64(format!("{}/smt/active", CPU_DIR)) {
+        Some(active) => active == 1,
+        None => threads > core_count,
+    };
+
+    Ok(CpuInventory {
+        vendor: first
+            .get("vendor_id")
+            .or_else(|| first.get("CPU implementer"))
+            .cloned()
+            .unwrap_or_else(|| "unknown".to_string()),
+        model: first
+            .get("model name")
+            .or_else(|| first.get("Processor"))
+            .or_else(|| first.get("cpu model"))
+            .cloned()
+            .unwrap_or_else(|| "unknown".to_string()),
+        sockets,
+        cores: core_count,
+        threads,
+        smt_active,
+        caches: discover_caches(reader, &cpus),
+        numa_nodes: discover_numa_nodes(reader),
+        frequency: discover_frequency(reader, &cpus, &processors),
+        flags: first
+            .get("flags")
+            .or_else(|| first.get("Features"))
+            .map(|flags| flags.split_whitespace().map(str::to_string).collect())
+            .unwrap_or_default(),
+    })
+}
+
+/// Splits `/proc/cpuinfo` into one key-value map per logical processor.
+fn parse_cpuinfo(cpuinfo: &str) -> Vec<HashMap<String, String>> {
+    let mut processors = Vec::new();
+    let mut current = HashMap::new();
+    for line in cpuinfo.lines() {
+        if line.trim().is_empty() {
+            if !current.is_empty() {
+                processors.push(std::mem::take(&mut current));
+            }
+            continue;
+        }
+        if let Some((key, value)) = line.split_once(':') {
+            current.insert(key.trim().to_string(), value.trim().to_string());
+        }
+    }
+    if !current.is_empty() {
+        processors.push(current);
+    }
+    processors
+}
+
+/// Returns the online logical CPUs.
+fn online_cpus(reader: &SysfsReader, processor_count: usize) -> Vec<u32> {
+    if let Some(online) = reader.read_string(format!("{}/online", CPU_DIR)) {
+        let cpus = parse_cpu_list(&online);
+        if !cpus.is_empty() {
+            return cpus;
+        }
+    }
+    (0..processor_count as u32).collect()
+}
+
+/// Collects one `CacheInfo` per cache level, type and size.
+///
+/// Caches shared between CPUs appear once per CPU in sysfs, so instances are
+/// counted by their distinct `shared_cpu_list`. Hybrid processors have caches
+/// of different sizes on their performance and efficiency cores, which are
+/// reported separately.
+fn discover_caches(reader: &SysfsReader, cpus: &[u32]) -> Vec<CacheInfo> {
+    // (level, type, size) -> distinct sharing lists
+    let mut caches: BTreeMap<(u8, String, u64), BTreeSet<String>> = BTreeMap::new();
+
+    for cpu in cpus {
+        let cache_dir = format!("{}/cpu{}/cache", CPU_DIR, cpu);
+        for index in reader.list_dir(&cache_dir) {
+            if !index.starts_with("index") {
+                continue;
+            }
+            let dir = format!("{}/{}", cache_dir, index);
+            let level = reader.read_u64(format!("{}/level", dir));
+            let cache_type = reader.read_string(format!("{}/type", dir));
+            let size = reader
+                .read_string(format!("{}/size", dir))
+                .and_then(|size| parse_size_kib(&size));
+            let (Some(level), Some(cache_type), Some(size)) = (level, cache_type, size) else {
+                continue;
+            };
+            let shared = reader
+                .read_string(format!("{}/shared_cpu_list", dir))
+                .unwrap_or_else(|| cpu.to_string());
+
+            caches
+                .entry((level as u8, cache_type, size))
+                .or_default()
+                .insert(shared);
+        }
+    }
+
+    caches
+        .into_iter()
+        .map(|((level, cache_type, size_kib), shared)| CacheInfo {
+            level,
+            cache_type,
+            size_kib,
+            instances: shared.len(),
+        })
+        .collect()
+}
+
+/// Collects the NUMA nodes with their CPU lists and memory totals.
+pub fn discover_numa_nodes(reader: &SysfsReader) -> Vec<NumaNode> {
+    let mut nodes: Vec<NumaNode> = reader
+        .list_dir(NODE_DIR)
+        .into_iter()
+        .filter_map(|name| {
+            let id = name.strip_prefix("node")?.parse::<u32>().ok()?;
+            let dir = format!("{}/{}", NODE_DIR, name);
+            let cpus = reader
+                .read_string(format!("{}/cpulist", dir))
+                .map(|list| parse_cpu_list(&list))
+                .unwrap_or_default();
+            // Lines look like "Node 0 MemTotal:       32768000 kB".
+            let memory_total_kib = reader
+                .read_string(format!("{}/meminfo", dir))
+                .and_then(|meminfo| {
+                    meminfo
+                        .lines()
+                        .find(|line| line.contains("MemTotal:"))
+                        .and_then(|line| line.split_whitespace().nth(3))
+                        .and_then(|value| value.parse().ok())
+                });
+            Some(NumaNode {
+                id,
+                cpus,
+                memory_total_kib,
+            })
+        })
+        .collect();
+    nodes.sort_by_key(|node| node.id);
+    nodes
+}
+
+/// Aggregates the cpufreq limits and governors over all CPUs.
+///
+/// Falls back to the "cpu MHz" field of `/proc/cpuinfo` for the current
+/// frequency when cpufreq is unavailable, e.g. in virtual machines.
+fn discover_frequency(
+    reader: &SysfsReader,
+    cpus: &[u32],
+    processors: &[HashMap<String, String>],
+) -> CpuFrequency {
+    let mut min_khz: Option<u64> = None;
+    let mut max_khz: Option<u64> = None;
+    let mut current_khz = Vec::new();
+    let mut governors = BTreeSet::new();
+
+    for cpu in cpus {
+        let dir = format!("{}/cpu{}/cpufreq", CPU_DIR, cpu);
+        if let Some(value) = reader.read_u64(format!("{}/cpuinfo_min_freq", dir)) {
+            min_khz = Some(min_khz.map_or(value, |min| min.min(value)));
+        }
+        if let Some(value) = reader.read_u64(format!("{}/cpuinfo_max_freq", dir)) {
+            max_khz = Some(max_khz.map_or(value, |max| max.max(value)));
+        }
+        if let Some(value) = reader.read_u64(format!("{}/scaling_cur_freq", dir)) {
+            current_khz.push(value as f64);
+        }
+        if let Some(governor) = reader.read_string(format!("{}/scaling_governor", dir)) {
+            governors.insert(governor);
+        }
+    }
+
+    let current_mhz = if current_khz.is_empty() {
+        let mhz: Vec<f64> = processors
+            .iter()
+            .filter_map(|p| p.get("cpu MHz")?.parse::<f64>().ok())
+            .collect();
+        mean(&mhz)
+    } else {
+        mean(&current_khz).map(|khz| khz / 1000.0)
+    };
+
+    CpuFrequency {
+        min_mhz: min_khz.map(|khz| khz as f64 / 1000.0),
+        max_mhz: max_khz.map(|khz| khz as f64 / 1000.0),
+        current_mhz,
+        governors: governors.into_iter().collect(),
+    }
+}
+
+/// Returns the arithmetic mean of the values, or `None` if there are none.
+fn mean(values: &[f64]) -> Option<f64> {
+    if values.is_empty() {
+        None
+    } else {
+        Some(values.iter().sum::<f64>() / values.len() as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::adapters::discovery_adapter::write_tree;
+
+    /// Returns the `/proc/cpuinfo` of logical processors, given by their
+    /// physical package, and the MHz they run at.
+    fn cpuinfo(processors: &[(u32, f64)], cores: usize) -> String {
+        processors
+            .iter()
+            .enumerate()
+            .map(|(processor, (package, mhz))| {
+                format!(
+                    "processor\t: {}\nvendor_id\t: GenuineIntel\n\
+                     model name\t: 12th Gen Intel(R) Core(TM) i7-1260P\n\
+                     physical id\t: {}\ncpu cores\t: {}\ncpu MHz\t\t: {:.3}\n\
+                     flags\t\t: fpu sse2 avx2\n\n",
+                    processor, package, cores, mhz
+                )
+            })
+            .collect()
+    }
+
+    /// Writes the tree of a hybrid processor with one performance core
+    /// running two threads and two efficiency cores.
+    fn write_hybrid_tree(root: &Path) {
+        let mut files = vec![
+            (
+                "proc/cpuinfo".to_string(),
+                cpuinfo(&[(0, 2100.0), (0, 2100.0), (0, 1500.0), (0, 1500.0)], 3),
+            ),
+            (format!("{}/online", CPU_DIR), "0-3\n".to_string()),
+            (format!("{}/smt/active", CPU_DIR), "1\n".to_string()),
+            (format!("{}/node0/cpulist", NODE_DIR), "0-3\n".to_string()),
+            (
+                format!("{}/node0/meminfo", NODE_DIR),
+                "Node 0 MemTotal:       16281172 kB\nNode 0 MemFree:        9817364 kB\n"
+                    .to_string(),
+            ),
+            (format!("{}/has_cpu", NODE_DIR), "0\n".to_string()),
+        ];
+        // (cpu, core, L1 data size and sharing, L2 size and sharing, max kHz)
+        let cpus = [
+            (0, 0, "48K", "0-1", "1280K", "0-1", 4_700_000),
+            (1, 0, "48K", "0-1", "1280K", "0-1", 4_700_000),
+            (2, 8, "32K", "2", "2048K", "2-3", 3_400_000),
+            (3, 9, "32K", "3", "2048K", "2-3", 3_400_000),
+        ];
+        for (cpu, core, l1_size, l1_shared, l2_size, l2_shared, max_khz) in cpus {
+            let dir = format!("{}/cpu{}", CPU_DIR, cpu);
+            let caches = [
+                ("index0", "1", "Data", l1_size, l1_shared),
+                ("index2", "2", "Unified", l2_size, l2_shared),
+                ("index3", "3", "Unified", "18432K", "0-3"),
+            ];
+            for (index, level, cache_type, size, shared) in caches {
+                let cache = format!("{}/cache/{}", dir, index);
+                for (name, value) in [
+                    ("level", level),
+                    ("type", cache_type),
+                    ("size", size),
+                    ("shared_cpu_list", shared),
+                ] {
+                    files.push((format!("{}/{}", cache, name), format!("{}\n", value)));
+                }
+            }
+            let current_khz = if cpu < 2 { 2_100_000 } else { 1_500_000 };
+            for (name, value) in [
+                ("topology/physical_package_id", "0".to_string()),
+                ("topology/core_id", core.to_string()),
+                ("cache/uevent", String::new()),
+                ("cpufreq/cpuinfo_min_freq", "400000".to_string()),
+                ("cpufreq/cpuinfo_max_freq", max_khz.to_string()),
+                ("cpufreq/scaling_cur_freq", current_khz.to_string()),
+                ("cpufreq/scaling_governor", "powersave".to_string()),
+            ] {
+                files.push((format!("{}/{}", dir, name), format!("{}\n", value)));
+            }
+        }
+        let files: Vec<(&str, &str)> = files
+            .iter()
+            .map(|(path, contents)| (path.trim_start_matches('/'), contents.as_str()))
+            .collect();
+        write_tree(root, &files);
+    }
+
+    /// Returns a cache entry.
+    fn cache(level: u8, cache_type: &str, size_kib: u64, instances: usize) -> CacheInfo {
+        CacheInfo {
+            level,
+            cache_type: cache_type.to_string(),
+            size_kib,
+            instances,
+        }
+    }
+
+    #[test]
+    fn discovers_a_hybrid_processor() {
+        let root = tempfile::tempdir().unwrap();
+        write_hybrid_tree(root.path());
+        let cpu = discover_cpu(&SysfsReader::new(root.path())).unwrap();
+
+        assert_eq!(cpu.vendor, "GenuineIntel");
+        assert_eq!(cpu.model, "12th Gen Intel(R) Core(TM) i7-1260P");
+        assert_eq!((cpu.sockets, cpu.cores, cpu.threads), (1, 3, 4));
+        assert!(cpu.smt_active);
+        assert_eq!(cpu.flags, vec!["fpu", "sse2", "avx2"]);
+
+        // The efficiency cores have smaller L1 and larger shared L2 caches.
+        assert_eq!(
+            cpu.caches,
+            vec![
+                cache(1, "Data", 32, 2),
+                cache(1, "Data", 48, 1),
+                cache(2, "Unified", 1280, 1),
+                cache(2, "Unified", 2048, 1),
+                cache(3, "Unified", 18432, 1),
+            ]
+        );
+        let total_l1: u64 = cpu
+            .caches
+            .iter()
+            .filter(|cache| cache.level == 1)
+            .map(CacheInfo::total_kib)
+            .sum();
+        assert_eq!(total_l1, 112);
+
+        assert_eq!(
+            cpu.numa_nodes,
+            vec![NumaNode {
+                id: 0,
+                cpus: vec![0, 1, 2, 3],
+                memory_total_kib: Some(16_281_172),
+            }]
+        );
+
+        assert_eq!(
+            cpu.frequency,
+            CpuFrequency {
+                min_mhz: Some(400.0),
+                max_mhz: Some(4700.0),
+                current_mhz: Some(1800.0),
+                governors: vec!["powersave".to_string()],
+            }
+        );
+    }
+
+    #[test]
+    fn falls_back_to_cpuinfo_without_sysfs() {
+        let root = tempfile::tempdir().unwrap();
+        write_tree(
+            root.path(),
+            &[(
+                "proc/cpuinfo",
+                &cpuinfo(&[(0, 2000.0), (0, 2200.0), (1, 2400.0), (1, 2600.0)], 2),
+            )],
+        );
+        let cpu = discover_cpu(&SysfsReader::new(root.path())).unwrap();
+
+        assert_eq!((cpu.sockets, cpu.cores, cpu.threads), (2, 4, 4));
+        assert!(!cpu.smt_active);
+        assert_eq!(cpu.caches, vec![]);
+        assert_eq!(cpu.numa_nodes, vec![]);
+        assert_eq!(
+            cpu.frequency,
+            CpuFrequency {
+                current_mhz: Some(2300.0),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn requires_cpuinfo() {
+        let root = tempfile::tempdir().unwrap();
+        let error = discover_cpu(&SysfsReader::new(root.path())).unwrap_err();
+        assert_eq!(error, "Unable to read /proc/cpuinfo");
+    }
+
+    #[test]
+    fn splits_cpuinfo_into_processors() {
+        let processors = parse_cpuinfo(
+            "processor\t: 0\nmodel name\t: Neoverse-N1\nflags\t\t: fp asimd\n\n\n\
+             processor\t: 1\nBogoMIPS\t: 50.00\nCPU revision\t: 1\nno separator\n",
+        );
+        assert_eq!(processors.len(), 2);
+        assert_eq!(processors[0]["model name"], "Neoverse-N1");
+        assert_eq!(processors[0]["flags"], "fp asimd");
+        assert_eq!(processors[1]["processor"], "1");
+        assert_eq!(processors[1]["BogoMIPS"], "50.00");
+        assert_eq!(processors[1].len(), 3);
+        assert!(parse_cpuinfo("\n\n").is_empty());
+    }
+}
@@ -0,0 +1,143 @@
+//! CPU Frequency Adapter
+//!
+//! This module provides an adapter that reads the current frequency of every
+//! CPU from cpufreq and the thermal throttling counters of every CPU from
+//! `/sys/devices/system/cpu`.
+
+use crate::adapters::discovery_adapter::SysfsReader;
+use crate::domain::benchmark::CpuFrequencyReading;
+use crate::ports::cpu_frequency_port::CpuFrequencyPort;
+
+/// The sysfs directory of the CPUs.
+const CPU_DIR: &str = "/sys/devices/system/cpu";
+
+/// Reads CPU frequencies and throttling counters from sysfs.
+pub struct CpuFrequencyAdapter {
+    reader: SysfsReader,
+}
+
+impl CpuFrequencyAdapter {
+    /// Creates a new instance of `CpuFrequencyAdapter` reading the live system.
+    pub fn new() -> Self {
+        CpuFrequencyAdapter {
+            reader: SysfsReader::new("/"),
+        }
+    }
+
+    /// Returns the directories of the CPUs, e.g. "cpu0".
+    fn cpus(&self) -> Vec<String> {
+        self.reader
+            .list_dir(CPU_DIR)
+            .into_iter()
+            .filter(|name| {
+                name.strip_prefix("cpu")
+                    .is_some_and(|id| !id.is_empty() && id.bytes().all(|b| b.is_ascii_digit()))
+            })
+            .collect()
+    }
+}
+
+impl Default for CpuFrequencyAdapter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CpuFrequencyPort for CpuFrequencyAdapter {
+    fn read(&self) -> Result<CpuFrequencyReading, String> {
+        let mut cpu_mhz = Vec::new();
+        let mut throttle_events: Option<u64> = None;
+        for cpu in self.cpus() {
+            let dir = format!("{}/{}", CPU_DIR, cpu);
+            if let Some(value) = self
+                .reader
+                .read_u64(format!("{}/cpufreq/scaling_cur_freq", dir))
+            {
+                cpu_mhz.push(value as f64 / 1000.0);
+            }
+            // Package counters repeat on every CPU of the package, which only
+            // matters for the total and not for whether it grew.
+            for counter in ["core_throttle_count", "package_throttle_count"] {
+                if let Some(count) = self
+                    .reader
+                    .read_u64(format!("{}/thermal_throttle/{}", dir, counter))
+                {
+                    throttle_events = Some(throttle_events.unwrap_or(0) + count);
+                }
+            }
+        }
+
+        cpu_mhz.sort_by(|a, b| b.total_cmp(a));
+        Ok(CpuFrequencyReading {
+            cpu_mhz,
+            throttle_events,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use super::*;
+    use crate::domain::benchmark::assess_throttling;
+
+    /// Writes the frequency in kHz and the throttling count of a CPU.
+    fn write_cpu(root: &Path, cpu: usize, khz: u64, throttle_count: u64) {
+        let dir = root.join(format!("sys/devices/system/cpu/cpu{}", cpu));
+        fs::create_dir_all(dir.join("cpufreq")).unwrap();
+        fs::create_dir_all(dir.join("thermal_throttle")).unwrap();
+        fs::write(dir.join("cpufreq/scaling_cur_freq"), khz.to_string()).unwrap();
+        fs::write(
+            dir.join("thermal_throttle/core_throttle_count"),
+            throttle_count.to_string(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn reads_every_cpu_fastest_first() {
+        let root = tempfile::tempdir().unwrap();
+        for (cpu, khz) in [800_000, 4_800_000, 1_200_000, 4_600_000]
+            .into_iter()
+            .enumerate()
+        {
+            write_cpu(root.path(), cpu, khz, 2);
+        }
+        fs::create_dir_all(root.path().join("sys/devices/system/cpu/cpufreq")).unwrap();
+        let adapter = CpuFrequencyAdapter {
+            reader: SysfsReader::new(root.path()),
+        };
+
+        let reading = adapter.read().unwrap();
+        assert_eq!(reading.cpu_mhz, vec![4800.0, 4600.0, 1200.0, 800.0]);
+        assert_eq!(reading.throttle_events, Some(8));
+        assert_eq!(reading.busiest_mhz(1), Some(4800.0));
+        assert_eq!(reading.busiest_mhz(2), Some(4700.0));
+        assert_eq!(reading.busiest_mhz(16), Some(2850.0));
+    }
+
+    #[test]
+    fn ignores_idle_cpus_clocking_down() {
+        let reading = |cpu_mhz: Vec<f64>| CpuFrequencyReading {
+            cpu_mhz,
+            throttle_events: Some(0),
+        };
+        let before = reading(vec![4800.0, 3000.0, 3000.0, 3000.0]);
+        // A single thread keeps one CPU at full speed while the others idle
+        // down, which would drop the mean over every CPU by over 40%.
+        let during = [
+            reading(vec![4800.0, 3000.0, 3000.0, 3000.0]),
+            reading(vec![4790.0, 800.0, 800.0, 800.0]),
+        ];
+        assert_eq!(assess_throttling(&before, &during, 1), None);
+
+        let throttled = [
+            reading(vec![4800.0, 800.0, 800.0, 800.0]),
+            reading(vec![3900.0, 800.0, 800.0, 800.0]),
+        ];
+        let reason = assess_throttling(&before, &throttled, 1).unwrap();
+        assert!(reason.contains("from 4800 MHz to 3900 MHz"), "{}", reason);
+    }
+}
@@ -0,0 +1,235 @@
+//! Discovery Adapter
+//!
+//! This module provides an adapter that discovers hardware by reading the
+//! procfs and sysfs trees. All paths are resolved against a configurable root
+//! so that a tree captured from another node can be inspected as well.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use common::ports::log_port::LoggerPort;
+
+use crate::adapters::pci_discovery_adapter::PciIds;
+use crate::adapters::{
+    cpu_discovery_adapter, network_discovery_adapter, pci_discovery_adapter, smbios_adapter,
+    storage_discovery_adapter,
+};
+use crate::domain::inventory::{
+    CpuInventory, MemoryInventory, NetworkInventory, PciDevice, PlatformInfo, StorageInventory,
+};
+use crate::ports::discovery_port::DiscoveryPort;
+
+/// Reads files below a root directory, treating missing files as absent values.
+pub struct SysfsReader {
+    root: PathBuf,
+}
+
+impl SysfsReader {
+    /// Creates a reader resolving absolute paths against `root`.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        SysfsReader { root: root.into() }
+    }
+
+    /// Resolves an absolute path such as `/sys/block` against the root.
+    pub fn path(&self, path: impl AsRef<Path>) -> PathBuf {
+        let path = path.as_ref();
+        self.root.join(path.strip_prefix("/").unwrap_or(path))
+    }
+
+    /// Reads a file to a trimmed string.
+    pub fn read_string(&self, path: impl AsRef<Path>) -> Option<String> {
+        fs::read_to_string(self.path(path))
+            .ok()
+            .map(|value| value.trim().to_string())
+    }
+
+    /// Reads a file holding a single unsigned integer.
+    pub fn read_u64(&self, path: impl AsRef<Path>) -> Option<u64> {
+        self.read_string(path)?.parse().ok()
+    }
+
+    /// Returns the file name a symbolic link points to, e.g. the driver name
+    /// of a `driver` link.
+    pub fn read_link_name(&self, path: impl AsRef<Path>) -> Option<String> {
+        let target = fs::read_link(self.path(path)).ok()?;
+        Some(target.file_name()?.to_string_lossy().to_string())
+    }
+
+    /// Resolves all symbolic links of a path, e.g. to find the bus a device
+    /// sits on.
+    pub fn canonicalize(&self, path: impl AsRef<Path>) -> Option<PathBuf> {
+        fs::canonicalize(self.path(path)).ok()
+    }
+
+    /// Lists the entry names of a directory in sorted order.
+    pub fn list_dir(&self, path: impl AsRef<Path>) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(self.path(path))
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .map(|entry| entry.file_name().to_string_lossy().to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+        names.sort();
+        names
+    }
+}
+
+/// Writes the files of a sysfs or procfs tree below a root.
+#[cfg(test)]
+pub(crate) fn write_tree(root: &Path, files: &[(&str, &str)]) {
+    for (path, contents) in files {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+}
+
+/// Parses a kernel CPU list such as "0-3,8,10-11" into CPU numbers.
+pub fn parse_cpu_list(list: &str) -> Vec<u32> {
+    let mut cpus = Vec::new();
+    for part in list.trim().split(',').filter(|part| !part.is_empty()) {
+        match part.split_once('-') {
+            Some((start, end)) => {
+                if let (Ok(start), Ok(end)) = (start.parse::<u32>(), end.parse::<u32>()) {
+                    cpus.extend(start..=end);
+                }
+            }
+            None => cpus.extend(part.parse::<u32>().ok()),
+        }
+    }
+    cpus
+}
+
+/// Parses a size with an optional binary suffix such as "32K" into KiB.
+pub fn parse_size_kib(size: &str) -> Option<u64> {
+    let size = size.trim();
+    let (digits, multiplier) = match size.chars().last()? {
+        'K' | 'k' => (&size[..size.len() - 1], 1),
+        'M' | 'm' => (&size[..size.len() - 1], 1024),
+        'G' | 'g' => (&size[..size.len() - 1], 1024 * 1024),
+        _ => return size.parse::<u64>().ok().map(|bytes| bytes / 1024),
+    };
+    digits
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .map(|value| value * multiplier)
+}
+
+/// Discovers hardware from procfs and sysfs.
+pub struct SysfsDiscoveryAdapter {
+    logger: Arc<dyn LoggerPort>,
+    reader: SysfsReader,
+}
+
+impl SysfsDiscoveryAdapter {
+    /// Creates a new instance of `SysfsDiscoveryAdapter` reading the live system.
+    pub fn new(logger: Arc<dyn LoggerPort>) -> Self {
+        Self::with_root(logger, "/")
+    }
+
+    /// Creates a new instance of `SysfsDiscoveryAdapter` reading below `root`.
+    ///
+    /// # Arguments
+    /// * `logger` - Logger implementation for logging messages.
+    /// * `root` - The directory containing the `proc` and `sys` trees.
+    pub fn with_root(logger: Arc<dyn LoggerPort>, root: impl Into<PathBuf>) -> Self {
+        SysfsDiscoveryAdapter {
+            logger,
+            reader: SysfsReader::new(root),
+        }
+    }
+}
+
+impl DiscoveryPort for SysfsDiscoveryAdapter {
+    fn discover_cpu(&self) -> Result<CpuInventory, String> {
+        self.logger.log_debug("Discovering CPU inventory.");
+        let cpu = cpu_discovery_adapter::discover_cpu(&self.reader)?;
+        self.logger.log_info(&format!(
+            "Discovered {} socket(s), {} core(s), {} thread(s) of {}",
+            cpu.sockets, cpu.cores, cpu.threads, cpu.model
+        ));
+        Ok(cpu)
+    }
+
+    fn discover_platform(&self) -> Result<PlatformInfo, String> {
+        self.logger
+            .log_debug("Discovering platform identification.");
+        let structures = smbios_adapter::read_dmi_table(&self.reader)?;
+        Ok(smbios_adapter::platform_info(&structures))
+    }
+
+    fn discover_memory(&self) -> Result<MemoryInventory, String> {
+        self.logger.log_debug("Discovering memory inventory.");
+        let structures = smbios_adapter::read_dmi_table(&self.reader)?;
+        let memory = smbios_adapter::memory_inventory(&structures);
+        self.logger.log_info(&format!(
+            "Discovered {} of {} DIMM slot(s) populated, {} MiB total",
+            memory.populated().count(),
+            memory.devices.len(),
+            memory.total_mib()
+        ));
+        Ok(memory)
+    }
+
+    fn discover_pci(&self) -> Result<Vec<PciDevice>, String> {
+        self.logger.log_debug("Discovering PCI devices.");
+        let ids = PciIds::load(&self.reader);
+        let devices = pci_discovery_adapter::discover_pci(&self.reader, &ids)?;
+        self.logger
+            .log_info(&format!("Discovered {} PCI device(s)", devices.len()));
+        Ok(devices)
+    }
+
+    fn discover_storage(&self) -> Result<StorageInventory, String> {
+        self.logger.log_debug("Discovering storage inventory.");
+        let storage = storage_discovery_adapter::discover_storage(&self.reader)?;
+        self.logger.log_info(&format!(
+            "Discovered {} block device(s), {} mounted filesystem(s)",
+            storage.devices.len(),
+            storage.filesystems.len()
+        ));
+        Ok(storage)
+    }
+
+    fn discover_network(&self) -> Result<NetworkInventory, String> {
+        self.logger.log_debug("Discovering network inventory.");
+        let network = network_discovery_adapter::discover_network(&self.reader)?;
+        self.logger.log_info(&format!(
+            "Discovered {} network interface(s), {} RDMA device(s)",
+            network.interfaces.len(),
+            network.rdma_devices.len()
+        ));
+        Ok(network)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_cpu_lists() {
+        assert_eq!(parse_cpu_list("0-3,8,10-11\n"), vec![0, 1, 2, 3, 8, 10, 11]);
+        assert_eq!(parse_cpu_list("5"), vec![5]);
+        assert_eq!(parse_cpu_list("0-1,x,4,3-a"), vec![0, 1, 4]);
+        assert_eq!(parse_cpu_list(""), Vec::<u32>::new());
+        assert_eq!(parse_cpu_list("\n"), Vec::<u32>::new());
+    }
+
+    #[test]
+    fn parses_sizes_into_kib() {
+        assert_eq!(parse_size_kib("32K"), Some(32));
+        assert_eq!(parse_size_kib("1280K\n"), Some(1280));
+        assert_eq!(parse_size_kib("2M"), Some(2048));
+        assert_eq!(parse_size_kib("1G"), Some(1024 * 1024));
+        assert_eq!(parse_size_kib("16 k"), Some(16));
+        // Sizes without a suffix are in bytes.
+        assert_eq!(parse_size_kib("49152"), Some(48));
+        assert_eq!(parse_size_kib(""), None);
+        assert_eq!(parse_size_kib("largeK"), None);
+    }
+}
@@ -0,0 +1,549 @@
+//! Fio Adapter
+//!
+//! This module runs storage benchmarks with fio. A job file is generated from
+//! each profile, fio is run with `--output-format=json`, and the bandwidth,
+//! IOPS and completion latency percentiles of the job are parsed from its
+//! output.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use common::ports::log_port::LoggerPort;
+
+use crate::domain::benchmark::{BenchmarkCase, BenchmarkDetail, BenchmarkMeasurement};
+use crate::domain::fio::{FioIoResult, FioJobResult, FioProfile};
+use crate::domain::run::MetricDirection;
+use crate::ports::benchmark_port::BenchmarkPort;
+
+/// The default size of the file fio runs against in MiB.
+const DEFAULT_SIZE_MIB: u64 = 1024;
+
+/// The default asynchronous I/O engine.
+#[cfg(target_os = "linux")]
+const DEFAULT_IOENGINE: &str = "libaio";
+#[cfg(not(target_os = "linux"))]
+const DEFAULT_IOENGINE: &str = "posixaio";
+
+/// The subset of fio's JSON output that is parsed.
+#[derive(Debug, Deserialize)]
+struct FioOutput {
+    jobs: Vec<FioJob>,
+}
+
+/// A job in fio's JSON output.
+#[derive(Debug, Deserialize)]
+struct FioJob {
+    jobname: String,
+    #[serde(default)]
+    error: i64,
+    read: FioIo,
+    write: FioIo,
+}
+
+/// The results of a job in one direction in fio's JSON output.
+#[derive(Debug, Deserialize)]
+struct FioIo {
+    #[serde(default)]
+    io_bytes: u64,
+    /// Bandwidth in bytes per second, reported by fio 3.x.
+    #[serde(default)]
+    bw_bytes: Option<f64>,
+    /// Bandwidth in KiB per second.
+    #[serde(default)]
+    bw: f64,
+    #[serde(default)]
+    iops: f64,
+    #[serde(default)]
+    clat_ns: Option<FioLatency>,
+    /// Completion latency in microseconds, reported by fio 2.x instead of `clat_ns`.
+    #[serde(default)]
+    clat: Option<FioLatency>,
+}
+
+/// Completion latency statistics in fio's JSON output.
+#[derive(Debug, Default, Deserialize)]
+struct FioLatency {
+    #[serde(default)]
+    mean: f64,
+    /// Percentiles keyed by e.g. "99.900000".
+    #[serde(default)]
+    percentile: BTreeMap<String, f64>,
+}
+
+/// Runs storage benchmarks with fio.
+pub struct FioAdapter {
+    logger: Arc<dyn LoggerPort>,
+    fio_path: String,
+    target: PathBuf,
+    size_mib: u64,
+    ioengine: String,
+    profiles: Vec<FioProfile>,
+    /// How long every iteration runs, overriding the configured default.
+    iteration_duration: Option<Duration>,
+    /// Whether the target did not exist before, so that it is removed again.
+    created_target: bool,
+}
+
+impl FioAdapter {
+    /// Creates a new instance of `FioAdapter` that runs the default profiles
+    /// against a file at `target`.
+    ///
+    /// # Arguments
+    /// * `logger` - The logger used to report progress and errors.
+    /// * `target` - The file fio reads and writes. A file that does not exist
+    ///   yet is created by fio and removed when the adapter is dropped.
+    pub fn new(logger: Arc<dyn LoggerPort>, target: impl Into<PathBuf>) -> Self {
+        let target = target.into();
+        FioAdapter {
+            logger,
+            fio_path: "fio".to_string(),
+            created_target: !target.exists(),
+            target,
+            size_mib: DEFAULT_SIZE_MIB,
+            ioengine: DEFAULT_IOENGINE.to_string(),
+            profiles: FioProfile::defaults(),
+            iteration_duration: None,
+        }
+    }
+
+    /// Sets the fio binary to run.
+    pub fn with_fio_path(mut self, fio_path: impl Into<String>) -> Self {
+        self.fio_path = fio_path.into();
+        self
+    }
+
+    /// Sets the size of the target file in MiB.
+    pub fn with_size_mib(mut self, size_mib: u64) -> Self {
+        self.size_mib = size_mib;
+        self
+    }
+
+    /// Sets the fio I/O engine, e.g. "io_uring".
+    pub fn with_ioengine(mut self, ioengine: impl Into<String>) -> Self {
+        self.ioengine = ioengine.into();
+        self
+    }
+
+    /// Sets the profiles to run; empty keeps the defaults.
+    pub fn with_profiles(mut self, profiles: Vec<FioProfile>) -> Self {
+        if !profiles.is_empty() {
+            self.profiles = profiles;
+        }
+        self
+    }
+
+    /// Sets how long every iteration runs, instead of the configured default
+    /// of all benchmarks.
+    pub fn with_iteration_duration(mut self, duration: Duration) -> Self {
+        self.iteration_duration = Some(duration);
+        self
+    }
+
+    /// Returns whether the fio binary can be run.
+    pub fn is_available(&self) -> bool {
+        Command::new(&self.fio_path)
+            .arg("--version")
+            .output()
+            .is_ok_and(|output| output.status.success())
+    }
+
+    /// Generates the job file of a profile.
+    ///
+    /// The job is time based, so it runs for `duration` however large the
+    /// target is, and reports its jobs as a single group.
+    pub fn job_file(&self, profile: &FioProfile, duration: Duration) -> String {
+        let mut job = format!(
+            "[global]\n\
+             ioengine={}\n\
+             filename={}\n\
+             size={}m\n\
+             direct={}\n\
+             time_based=1\n\
+             runtime={}ms\n\
+             group_reporting=1\n\
+             \n\
+             [{}]\n\
+             rw={}\n\
+             bs={}\n\
+             iodepth={}\n\
+             numjobs={}\n",
+            self.ioengine,
+            // fio separates multiple file names with colons.
+            self.target.display().to_string().replace(':', "\\:"),
+            self.size_mib,
+            u8::from(profile.direct),
+            duration.as_millis().max(1),
+            profile.name,
+            profile.rw,
+            profile.block_size,
+            profile.iodepth,
+            profile.numjobs,
+        );
+        if let Some(rwmixread) = profile.rwmixread {
+            job.push_str(&format!("rwmixread={}\n", rwmixread));
+        }
+        job
+    }
+
+    /// Parses fio's JSON output into the results of every job.
+    ///
+    /// fio may print notes before the JSON document, which are skipped.
+    /// Directions in which a job issued no I/O are left empty.
+    pub fn parse_output(output: &str) -> Result<Vec<FioJobResult>, String> {
+        let start = output
+            .find('{')
+            .ok_or_else(|| "fio output contains no JSON document".to_string())?;
+        let parsed: FioOutput = serde_json::from_str(&output[start..])
+            .map_err(|e| format!("Invalid fio JSON output: {}", e))?;
+
+        parsed
+            .jobs
+            .into_iter()
+            .map(|job| {
+                if job.error != 0 {
+                    return Err(format!(
+                        "fio job {} failed with error {}",
+                        job.jobname, job.error
+                    ));
+                }
+                Ok(FioJobResult {
+                    read: io_result(job.read),
+                    write: io_result(job.write),
+                    job: job.jobname,
+                })
+            })
+            .collect()
+    }
+
+    /// Runs a profile once and returns the result of its job.
+    pub fn run_profile(
+        &self,
+        profile: &FioProfile,
+        duration: Duration,
+    ) -> Result<FioJobResult, String> {
+        self.check_target(profile)?;
+
+        let job_path = std::env::temp_dir().join(format!(
+            "commandant-rs-{}-{}.fio",
+            std::process::id(),
+            profile.name
+        ));
+        fs::write(&job_path, self.job_file(profile, duration))
+            .map_err(|e| format!("Failed to write fio job file: {}", e))?;
+        self.logger.log_debug(&format!(
+            "Running {} --output-format=json {}",
+            self.fio_path,
+            job_path.display()
+        ));
+        let output = Command::new(&self.fio_path)
+            .arg("--output-format=json")
+            .arg(&job_path)
+            .output();
+        let _ = fs::remove_file(&job_path);
+
+        let output = output
+            .map_err(|e| format!("Failed to run {}: {}. Is fio installed?", self.fio_path, e))?;
+        if !output.status.success() {
+            return Err(format!(
+                "fio exited with {}: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+
+        Self::parse_output(&String::from_utf8_lossy(&output.stdout))?
+            .into_iter()
+            .find(|job| job.job == profile.name)
+            .ok_or_else(|| format!("fio output has no results for job {}", profile.name))
+    }
+
+    /// Refuses to write to block devices, which would destroy their data.
+    fn check_target(&self, profile: &FioProfile) -> Result<(), String> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::FileTypeExt;
+            let is_block_device = fs::metadata(&self.target)
+                .map(|metadata| metadata.file_type().is_block_device())
+                .unwrap_or(false);
+            if is_block_device && profile.writes() {
+                return Err(format!(
+                    "Refusing to run the writing profile {} against the block device {}",
+                    profile.name,
+                    self.target.display()
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl BenchmarkPort for FioAdapter {
+    /// Returns "storage".
+    fn suite(&self) -> &str {
+        "storage"
+    }
+
+    /// Lists a case per profile, measured in IOPS for random profiles and in
+    /// bandwidth for sequential ones.
+    fn cases(&self) -> Vec<BenchmarkCase> {
+        self.profiles
+            .iter()
+            .map(|profile| BenchmarkCase {
+                name: profile.name.clone(),
+                threads: profile.numjobs as usize,
+                unit: if profile.is_random() { "ops/s" } else { "B/s" }.to_string(),
+                direction: MetricDirection::HigherIsBetter,
+            })
+            .collect()
+    }
+
+    fn iteration_duration(&self, default: Duration) -> Duration {
+        self.iteration_duration.unwrap_or(default)
+    }
+
+    /// Runs the profile of a case with fio, reporting the bandwidth, IOPS and
+    /// latency percentiles of every direction as details.
+    fn run_iteration(
+        &self,
+        case: &BenchmarkCase,
+        duration: Duration,
+    ) -> Result<BenchmarkMeasurement, String> {
+        let profile = self
+            .profiles
+            .iter()
+            .find(|profile| profile.name == case.name)
+            .ok_or_else(|| format!("Unknown storage benchmark {}", case.name))?;
+        let result = self.run_profile(profile, duration)?;
+
+        let mut details = Vec::new();
+        for (direction, io) in result.directions() {
+            let mut detail = |name: &str, unit: &str, preferred, value: Option<f64>| {
+                if let Some(value) = value {
+                    details.push(BenchmarkDetail {
+                        name: format!("{} {}", direction, name),
+                        unit: unit.to_string(),
+                        direction: preferred,
+                        value,
+                    });
+                }
+            };
+            detail(
+                "bandwidth",
+                "B/s",
+                MetricDirection::HigherIsBetter,
+                Some(io.bandwidth_bytes_per_sec),
+            );
+            detail(
+                "IOPS",
+                "ops/s",
+                MetricDirection::HigherIsBetter,
+                Some(io.iops),
+            );
+            detail(
+                "mean latency",
+                "ns",
+                MetricDirection::LowerIsBetter,
+                Some(io.mean_latency_ns),
+            );
+            detail(
+                "p50 latency",
+                "ns",
+                MetricDirection::LowerIsBetter,
+                io.p50_latency_ns,
+            );
+            detail(
+                "p99 latency",
+                "ns",
+                MetricDirection::LowerIsBetter,
+                io.p99_latency_ns,
+            );
+            detail(
+                "p99.9 latency",
+                "ns",
+                MetricDirection::LowerIsBetter,
+                io.p99_9_latency_ns,
+            );
+            detail(
+                "p99.99 latency",
+                "ns",
+                MetricDirection::LowerIsBetter,
+                io.p99_99_latency_ns,
+            );
+        }
+
+        Ok(BenchmarkMeasurement {
+            value: if profile.is_random() {
+                result.iops()
+            } else {
+                result.bandwidth_bytes_per_sec()
+            },
+            details,
+        })
+    }
+}
+
+impl Drop for FioAdapter {
+    /// Removes the target file if fio created it.
+    fn drop(&mut self) {
+        if self.created_target && self.target.is_file() {
+            if let Err(e) = fs::remove_file(&self.target) {
+                self.logger.log_warn(&format!(
+                    "Failed to remove fio target {}: {}",
+                    self.target.display(),
+                    e
+                ));
+            }
+        }
+    }
+}
+
+/// Converts the results of a direction, or `None` if no I/O was issued.
+fn io_result(io: FioIo) -> Option<FioIoResult> {
+    if io.io_bytes == 0 {
+        return None;
+    }
+    let (latency, scale) = match (io.clat_ns, io.clat) {
+        (Some(latency), _) => (latency, 1.0),
+        (None, Some(latency)) => (latency, 1000.0),
+        (None, None) => (FioLatency::default(), 1.0),
+    };
+    let percentile = |p: f64| {
+        latency
+            .percentile
+            .iter()
+            .find(|(key, _)| key.parse::<f64>().is_ok_and(|key| (key - p).abs() < 1e-6))
+            .map(|(_, value)| *value * scale)
+    };
+    Some(FioIoResult {
+        bandwidth_bytes_per_sec: io.bw_bytes.unwrap_or(io.bw * 1024.0),
+        iops: io.iops,
+        mean_latency_ns: latency.mean * scale,
+        p50_latency_ns: percentile(50.0),
+        p99_latency_ns: percentile(99.0),
+        p99_9_latency_ns: percentile(99.9),
+        p99_99_latency_ns: percentile(99.99),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use common::adapters::capture_log_adapter::CapturingLogger;
+
+    use super::*;
+
+    /// Creates an adapter against `target` with a small file.
+    fn adapter(target: impl Into<PathBuf>) -> FioAdapter {
+        FioAdapter::new(Arc::new(CapturingLogger::new()), target).with_size_mib(4)
+    }
+
+    #[test]
+    fn parses_mixed_output() {
+        let jobs =
+            FioAdapter::parse_output(include_str!("../../tests/fixtures/fio/randrw.json")).unwrap();
+
+        assert_eq!(jobs.len(), 1);
+        let job = &jobs[0];
+        assert_eq!(job.job, "mixed");
+        let read = job.read.as_ref().unwrap();
+        assert_eq!(read.bandwidth_bytes_per_sec, 250848025.0);
+        assert_eq!(read.iops, 61242.193561);
+        assert_eq!(read.mean_latency_ns, 368452.716503);
+        assert_eq!(read.p50_latency_ns, Some(350208.0));
+        assert_eq!(read.p99_latency_ns, Some(716800.0));
+        assert_eq!(read.p99_9_latency_ns, Some(1122304.0));
+        assert_eq!(read.p99_99_latency_ns, Some(2637824.0));
+        let write = job.write.as_ref().unwrap();
+        assert_eq!(write.iops, 26248.350330);
+        assert_eq!(job.iops(), 61242.193561 + 26248.350330);
+        assert_eq!(job.bandwidth_bytes_per_sec(), 250848025.0 + 107513242.0);
+    }
+
+    #[test]
+    fn parses_the_output_of_fio_2() {
+        let jobs =
+            FioAdapter::parse_output(include_str!("../../tests/fixtures/fio/seq_read_2.json"))
+                .unwrap();
+
+        let job = &jobs[0];
+        assert!(job.write.is_none());
+        let read = job.read.as_ref().unwrap();
+        // fio 2.x reports the bandwidth in KiB/s and latencies in microseconds.
+        assert_eq!(read.bandwidth_bytes_per_sec, 512614.0 * 1024.0);
+        assert_eq!(read.mean_latency_ns, 63817.14 * 1000.0);
+        assert_eq!(read.p99_latency_ns, Some(97792.0 * 1000.0));
+        assert_eq!(read.p99_9_latency_ns, None);
+    }
+
+    #[test]
+    fn reports_failed_jobs() {
+        let e = FioAdapter::parse_output(include_str!("../../tests/fixtures/fio/error.json"))
+            .unwrap_err();
+        assert_eq!(e, "fio job seq-write failed with error 28");
+
+        let e = FioAdapter::parse_output("fio: failed parsing ioengine=nope").unwrap_err();
+        assert_eq!(e, "fio output contains no JSON document");
+    }
+
+    #[test]
+    fn generates_time_based_job_files() {
+        let fio = adapter("/mnt/a:b/fio.dat").with_ioengine("io_uring");
+        let profile = FioProfile::new("mixed", "randrw", "4k", Some(70));
+
+        let job = fio.job_file(&profile, Duration::from_millis(1500));
+        assert!(job.contains("ioengine=io_uring\n"), "{}", job);
+        assert!(job.contains("filename=/mnt/a\\:b/fio.dat\n"), "{}", job);
+        assert!(job.contains("size=4m\n"), "{}", job);
+        assert!(job.contains("time_based=1\nruntime=1500ms\n"), "{}", job);
+        assert!(job.contains("[mixed]\nrw=randrw\nbs=4k\n"), "{}", job);
+        assert!(job.ends_with("rwmixread=70\n"), "{}", job);
+    }
+
+    #[test]
+    fn overrides_the_iteration_duration() {
+        let default = Duration::from_secs(1);
+        assert_eq!(adapter("fio.dat").iteration_duration(default), default);
+        let fio = adapter("fio.dat").with_iteration_duration(Duration::from_secs(30));
+        assert_eq!(fio.iteration_duration(default), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn removes_only_a_target_it_created() {
+        let directory = tempfile::tempdir().unwrap();
+        let created = directory.path().join("created.dat");
+        let existing = directory.path().join("existing.dat");
+        fs::write(&existing, b"data").unwrap();
+
+        let fio = adapter(&created);
+        let kept = adapter(&existing);
+        // Stand in for the file fio lays out before the first job.
+        fs::write(&created, b"data").unwrap();
+        drop(fio);
+        drop(kept);
+
+        assert!(!created.exists());
+        assert!(existing.exists());
+    }
+
+    #[test]
+    #[ignore = "needs fio installed"]
+    fn runs_a_short_job_against_a_temp_file() {
+        let directory = tempfile::tempdir().unwrap();
+        let fio = adapter(directory.path().join("fio.dat")).with_ioengine("psync");
+        let mut profile = FioProfile::new("rand-read", "randread", "4k", None);
+        profile.iodepth = 1;
+        profile.direct = false;
+
+        let result = fio
+            .run_profile(&profile, Duration::from_millis(200))
+            .unwrap();
+        assert_eq!(result.job, "rand-read");
+        assert!(result.write.is_none());
+        assert!(result.iops() > 0.0);
+    }
+}
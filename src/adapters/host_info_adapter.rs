@@ -0,0 +1,32 @@
+//! Host Info Adapter
+//!
+//! This module provides helpers for identifying the host commandant-rs is
+//! running on.
+
+use std::fs;
+use std::process::Command;
+
+/// Returns the hostname of the current machine.
+///
+/// The hostname is read from procfs on Linux and falls back to the `hostname`
+/// command on other systems. If neither is available, "localhost" is returned.
+pub fn hostname() -> String {
+    if let Ok(name) = fs::read_to_string("/proc/sys/kernel/hostname") {
+        let name = name.trim();
+        if !name.is_empty() {
+            return name.to_string();
+        }
+    }
+
+    match Command::new("hostname").output() {
+        Ok(output) if output.status.success() => {
+            let name = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            if name.is_empty() {
+                "localhost".to_string()
+            } else {
+                name
+            }
+        }
+        _ => "localhost".to_string(),
+    }
+}
@@ -0,0 +1,537 @@
+//! Iperf Adapter
+//!
+//! This module runs network throughput benchmarks with iperf3. Each profile
+//! is run as an iperf3 client with `-J`, and the throughput, TCP
+//! retransmits and UDP jitter and loss are parsed from its JSON output. The
+//! adapter can also start an iperf3 server, either on loopback to benchmark
+//! the local network stack or for clients on other hosts.
+
+use std::net::{TcpStream, ToSocketAddrs};
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+
+use common::ports::log_port::LoggerPort;
+
+use crate::domain::benchmark::{BenchmarkCase, BenchmarkDetail, BenchmarkMeasurement};
+use crate::domain::iperf::{IperfProfile, IperfResult};
+use crate::domain::run::MetricDirection;
+use crate::ports::benchmark_port::BenchmarkPort;
+
+/// The default port iperf3 listens on.
+const DEFAULT_PORT: u16 = 5201;
+
+/// How long a started server has to accept connections.
+const SERVER_START_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The subset of iperf3's JSON output that is parsed.
+#[derive(Debug, Deserialize)]
+struct IperfOutput {
+    #[serde(default)]
+    end: IperfEnd,
+    /// The reason the test failed, e.g. "unable to connect to server".
+    #[serde(default)]
+    error: Option<String>,
+}
+
+/// The totals of a test in iperf3's JSON output.
+#[derive(Debug, Default, Deserialize)]
+struct IperfEnd {
+    /// TCP, and UDP since iperf3 3.11, totals of the sender.
+    sum_sent: Option<IperfSum>,
+    /// TCP, and UDP since iperf3 3.11, totals of the receiver.
+    sum_received: Option<IperfSum>,
+    /// UDP totals of older iperf3 versions.
+    sum: Option<IperfSum>,
+    cpu_utilization_percent: Option<IperfCpu>,
+}
+
+/// Totals over every stream in iperf3's JSON output.
+#[derive(Debug, Deserialize)]
+struct IperfSum {
+    bits_per_second: f64,
+    retransmits: Option<u64>,
+    jitter_ms: Option<f64>,
+    lost_packets: Option<u64>,
+    lost_percent: Option<f64>,
+}
+
+/// CPU utilization in iperf3's JSON output.
+#[derive(Debug, Deserialize)]
+struct IperfCpu {
+    host_total: f64,
+    remote_total: f64,
+}
+
+/// Runs network benchmarks with iperf3.
+pub struct IperfAdapter {
+    logger: Arc<dyn LoggerPort>,
+    iperf_path: String,
+    server: String,
+    port: u16,
+    profiles: Vec<IperfProfile>,
+    /// Whether to start a server on `server` before the first test.
+    local_server: bool,
+    /// The server started for the tests, stopped when the adapter is dropped.
+    server_process: Mutex<Option<Child>>,
+}
+
+impl IperfAdapter {
+    /// Creates a new instance of `IperfAdapter` that runs the default
+    /// profiles against an iperf3 server.
+    ///
+    /// # Arguments
+    /// * `logger` - The logger used to report progress and errors.
+    /// * `server` - The host name or address of the iperf3 server.
+    pub fn new(logger: Arc<dyn LoggerPort>, server: impl Into<String>) -> Self {
+        IperfAdapter {
+            logger,
+            iperf_path: "iperf3".to_string(),
+            server: server.into(),
+            port: DEFAULT_PORT,
+            profiles: IperfProfile::defaults(),
+            local_server: false,
+            server_process: Mutex::new(None),
+        }
+    }
+
+    /// Sets the iperf3 binary to run.
+    pub fn with_iperf_path(mut self, iperf_path: impl Into<String>) -> Self {
+        self.iperf_path = iperf_path.into();
+        self
+    }
+
+    /// Sets the port of the server.
+    pub fn with_port(mut self, port: u16) -> Self {
+        self.port = port;
+        self
+    }
+
+    /// Sets the profiles to run; empty keeps the defaults.
+    pub fn with_profiles(mut self, profiles: Vec<IperfProfile>) -> Self {
+        if !profiles.is_empty() {
+            self.profiles = profiles;
+        }
+        self
+    }
+
+    /// Starts a server bound to the server address before the first test,
+    /// e.g. on loopback to benchmark the local network stack.
+    pub fn with_local_server(mut self) -> Self {
+        self.local_server = true;
+        self
+    }
+
+    /// Runs an iperf3 server bound to the server address until it exits,
+    /// serving clients on other hosts.
+    pub fn serve(&self) -> Result<(), String> {
+        self.logger.log_info(&format!(
+            "Serving iperf3 clients on {}:{}",
+            self.server, self.port
+        ));
+        let status = Command::new(&self.iperf_path)
+            .args(["-s", "-B", &self.server, "-p", &self.port.to_string()])
+            .status()
+            .map_err(|e| {
+                format!(
+                    "Failed to run {}: {}. Is iperf3 installed?",
+                    self.iperf_path, e
+                )
+            })?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(format!("iperf3 server exited with {}", status))
+        }
+    }
+
+    /// Returns the client arguments of a profile.
+    ///
+    /// iperf3 runs tests for whole seconds, so `duration` is rounded up to
+    /// at least one second.
+    pub fn client_args(&self, profile: &IperfProfile, duration: Duration) -> Vec<String> {
+        let seconds = duration.as_millis().div_ceil(1000).max(1);
+        let mut args = vec![
+            "-c".to_string(),
+            self.server.clone(),
+            "-p".to_string(),
+            self.port.to_string(),
+            "-J".to_string(),
+            "-t".to_string(),
+            seconds.to_string(),
+            "-P".to_string(),
+            profile.parallel.max(1).to_string(),
+        ];
+        if profile.is_udp() {
+            args.push("-u".to_string());
+        }
+        if let Some(bitrate) = &profile.bitrate {
+            args.push("-b".to_string());
+            args.push(bitrate.clone());
+        }
+        if profile.reverse {
+            args.push("-R".to_string());
+        }
+        args
+    }
+
+    /// Parses the JSON output of an iperf3 client.
+    ///
+    /// UDP outputs of iperf3 before 3.11 only report the sender's rate; the
+    /// received rate is then derived from the datagrams lost.
+    pub fn parse_output(output: &str) -> Result<IperfResult, String> {
+        let parsed: IperfOutput = serde_json::from_str(output)
+            .map_err(|e| format!("Invalid iperf3 JSON output: {}", e))?;
+        if let Some(error) = parsed.error {
+            return Err(format!("iperf3 failed: {}", error));
+        }
+
+        let end = parsed.end;
+        let (sent, received) = match (end.sum_sent, end.sum_received, end.sum) {
+            (Some(sent), Some(received), _) => (sent, received),
+            (_, _, Some(sum)) => {
+                let received = IperfSum {
+                    bits_per_second: sum.bits_per_second
+                        * (1.0 - sum.lost_percent.unwrap_or(0.0) / 100.0),
+                    retransmits: None,
+                    jitter_ms: sum.jitter_ms,
+                    lost_packets: sum.lost_packets,
+                    lost_percent: sum.lost_percent,
+                };
+                (sum, received)
+            }
+            _ => return Err("iperf3 output contains no totals".to_string()),
+        };
+
+        Ok(IperfResult {
+            sent_bits_per_sec: sent.bits_per_second,
+            received_bits_per_sec: received.bits_per_second,
+            retransmits: sent.retransmits,
+            jitter_ms: received.jitter_ms,
+            lost_packets: received.lost_packets,
+            lost_percent: received.lost_percent,
+            host_cpu_percent: end
+                .cpu_utilization_percent
+                .as_ref()
+                .map(|cpu| cpu.host_total),
+            remote_cpu_percent: end
+                .cpu_utilization_percent
+                .as_ref()
+                .map(|cpu| cpu.remote_total),
+        })
+    }
+
+    /// Runs a profile once and returns its result.
+    pub fn run_profile(
+        &self,
+        profile: &IperfProfile,
+        duration: Duration,
+    ) -> Result<IperfResult, String> {
+        if self.local_server {
+            self.ensure_server()?;
+        }
+
+        let args = self.client_args(profile, duration);
+        self.logger
+            .log_debug(&format!("Running {} {}", self.iperf_path, args.join(" ")));
+        let output = Command::new(&self.iperf_path)
+            .args(&args)
+            .output()
+            .map_err(|e| {
+                format!(
+                    "Failed to run {}: {}. Is iperf3 installed?",
+                    self.iperf_path, e
+                )
+            })?;
+
+        // iperf3 reports most failures in its JSON output, with a failing status.
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        if stdout.trim().is_empty() && !output.status.success() {
+            return Err(format!(
+                "iperf3 exited with {}: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Self::parse_output(&stdout)
+    }
+
+    /// Starts the local server unless it is running, and waits until it
+    /// accepts connections.
+    fn ensure_server(&self) -> Result<(), String> {
+        let mut server_process = self
+            .server_process
+            .lock()
+            .map_err(|_| "iperf3 server lock poisoned".to_string())?;
+        if let Some(child) = server_process.as_mut() {
+            match child.try_wait() {
+                Ok(None) => return Ok(()),
+                _ => self.logger.log_warn("iperf3 server exited, restarting it"),
+            }
+        }
+
+        self.logger.log_info(&format!(
+            "Starting iperf3 server on {}:{}",
+            self.server, self.port
+        ));
+        let mut child = Command::new(&self.iperf_path)
+            .args(["-s", "-B", &self.server, "-p", &self.port.to_string()])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| {
+                format!(
+                    "Failed to run {}: {}. Is iperf3 installed?",
+                    self.iperf_path, e
+                )
+            })?;
+
+        let started = Instant::now();
+        loop {
+            if let Ok(Some(status)) = child.try_wait() {
+                return Err(format!(
+                    "iperf3 server exited with {}; is port {} in use?",
+                    status, self.port
+                ));
+            }
+            if self.accepts_connections() {
+                break;
+            }
+            if started.elapsed() > SERVER_START_TIMEOUT {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!(
+                    "iperf3 server did not accept connections on {}:{}",
+                    self.server, self.port
+                ));
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+        *server_process = Some(child);
+        Ok(())
+    }
+
+    /// Returns whether the server accepts TCP connections.
+    fn accepts_connections(&self) -> bool {
+        (self.server.as_str(), self.port)
+            .to_socket_addrs()
+            .map(|mut addrs| {
+                addrs.any(|addr| {
+                    TcpStream::connect_timeout(&addr, Duration::from_millis(200)).is_ok()
+                })
+            })
+            .unwrap_or(false)
+    }
+}
+
+impl BenchmarkPort for IperfAdapter {
+    /// Returns "network".
+    fn suite(&self) -> &str {
+        "network"
+    }
+
+    /// Lists a case per profile, measured in the bits per second received.
+    fn cases(&self) -> Vec<BenchmarkCase> {
+        self.profiles
+            .iter()
+            .map(|profile| BenchmarkCase {
+                name: profile.name.clone(),
+                threads: profile.parallel.max(1) as usize,
+                unit: "bit/s".to_string(),
+                direction: MetricDirection::HigherIsBetter,
+            })
+            .collect()
+    }
+
+    /// Runs the profile of a case with iperf3, reporting the sent and
+    /// received rates, TCP retransmits, UDP jitter and loss, and the CPU
+    /// utilization of both ends as details.
+    fn run_iteration(
+        &self,
+        case: &BenchmarkCase,
+        duration: Duration,
+    ) -> Result<BenchmarkMeasurement, String> {
+        let profile = self
+            .profiles
+            .iter()
+            .find(|profile| profile.name == case.name)
+            .ok_or_else(|| format!("Unknown network benchmark {}", case.name))?;
+        let result = self.run_profile(profile, duration)?;
+
+        let mut details = Vec::new();
+        let mut detail = |name: &str, unit: &str, direction, value: Option<f64>| {
+            if let Some(value) = value {
+                details.push(BenchmarkDetail {
+                    name: name.to_string(),
+                    unit: unit.to_string(),
+                    direction,
+                    value,
+                });
+            }
+        };
+        detail(
+            "sent",
+            "bit/s",
+            MetricDirection::HigherIsBetter,
+            Some(result.sent_bits_per_sec),
+        );
+        detail(
+            "received",
+            "bit/s",
+            MetricDirection::HigherIsBetter,
+            Some(result.received_bits_per_sec),
+        );
+        detail(
+            "retransmits",
+            "segments",
+            MetricDirection::LowerIsBetter,
+            result.retransmits.map(|n| n as f64),
+        );
+        detail(
+            "jitter",
+            "ns",
+            MetricDirection::LowerIsBetter,
+            result.jitter_ms.map(|ms| ms * 1e6),
+        );
+        detail(
+            "lost",
+            "packets",
+            MetricDirection::LowerIsBetter,
+            result.lost_packets.map(|n| n as f64),
+        );
+        detail(
+            "loss",
+            "%",
+            MetricDirection::LowerIsBetter,
+            result.lost_percent,
+        );
+        detail(
+            "client CPU",
+            "%",
+            MetricDirection::LowerIsBetter,
+            result.host_cpu_percent,
+        );
+        detail(
+            "server CPU",
+            "%",
+            MetricDirection::LowerIsBetter,
+            result.remote_cpu_percent,
+        );
+
+        Ok(BenchmarkMeasurement {
+            value: result.received_bits_per_sec,
+            details,
+        })
+    }
+}
+
+impl Drop for IperfAdapter {
+    /// Stops the server started for the tests.
+    fn drop(&mut self) {
+        if let Ok(server_process) = self.server_process.get_mut() {
+            if let Some(mut child) = server_process.take() {
+                let _ = child.kill();
+                let _ = child.wait();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use common::adapters::capture_log_adapter::CapturingLogger;
+
+    use crate::domain::iperf::IperfProtocol;
+
+    const TCP: &str = include_str!("../../tests/fixtures/iperf3/tcp.json");
+    const UDP: &str = include_str!("../../tests/fixtures/iperf3/udp.json");
+    const UDP_3_9: &str = include_str!("../../tests/fixtures/iperf3/udp_3.9.json");
+    const ERROR: &str = include_str!("../../tests/fixtures/iperf3/error.json");
+
+    #[test]
+    fn parses_tcp_output() {
+        let result = IperfAdapter::parse_output(TCP).unwrap();
+        assert_eq!(result.sent_bits_per_sec, 47846562130.9);
+        assert_eq!(result.received_bits_per_sec, 47821987460.2);
+        assert_eq!(result.retransmits, Some(3));
+        assert_eq!(result.jitter_ms, None);
+        assert_eq!(result.lost_packets, None);
+        assert_eq!(result.host_cpu_percent, Some(99.071));
+        assert_eq!(result.remote_cpu_percent, Some(61.325));
+    }
+
+    #[test]
+    fn parses_udp_output() {
+        let result = IperfAdapter::parse_output(UDP).unwrap();
+        assert_eq!(result.sent_bits_per_sec, 1000027355.4);
+        assert_eq!(result.received_bits_per_sec, 994884900.2);
+        assert_eq!(result.jitter_ms, Some(0.0068));
+        assert_eq!(result.lost_packets, Some(19));
+        assert_eq!(result.lost_percent, Some(0.498));
+    }
+
+    #[test]
+    fn derives_the_received_rate_of_old_udp_output() {
+        let result = IperfAdapter::parse_output(UDP_3_9).unwrap();
+        assert_eq!(result.sent_bits_per_sec, 999966304.1);
+        assert!((result.received_bits_per_sec - 999966304.1 * 0.99).abs() < 1.0);
+        assert_eq!(result.retransmits, None);
+        assert_eq!(result.lost_packets, Some(38));
+    }
+
+    #[test]
+    fn reports_iperf3_errors() {
+        let error = IperfAdapter::parse_output(ERROR).unwrap_err();
+        assert!(error.starts_with("iperf3 failed: unable to connect to server"));
+        assert!(IperfAdapter::parse_output("iperf3: error").is_err());
+        assert!(IperfAdapter::parse_output(r#"{"end": {}}"#).is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_protocols() {
+        let profile: IperfProfile =
+            serde_yaml::from_str("name: udp-10g\nprotocol: udp\nbitrate: 10G").unwrap();
+        assert_eq!(profile.protocol, IperfProtocol::Udp);
+        let profile: IperfProfile = serde_yaml::from_str("name: tcp").unwrap();
+        assert_eq!(profile.protocol, IperfProtocol::Tcp);
+        assert!(serde_yaml::from_str::<IperfProfile>("name: x\nprotocol: sctp").is_err());
+    }
+
+    #[test]
+    fn passes_the_profile_to_the_client() {
+        let adapter = IperfAdapter::new(Arc::new(CapturingLogger::new()), "10.0.0.2");
+        let mut profile = IperfProfile::new("udp", IperfProtocol::Udp, 2, Some("10G"));
+        profile.reverse = true;
+        let args = adapter.client_args(&profile, Duration::from_millis(1500));
+        assert_eq!(
+            args,
+            ["-c", "10.0.0.2", "-p", "5201", "-J", "-t", "2", "-P", "2", "-u", "-b", "10G", "-R"]
+        );
+    }
+
+    /// Runs a TCP test against an iperf3 server started on loopback; needs
+    /// iperf3 on the path, run with `cargo test -- --ignored`.
+    #[test]
+    #[ignore = "needs iperf3 installed"]
+    fn runs_against_a_loopback_server() {
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let adapter = IperfAdapter::new(Arc::new(CapturingLogger::new()), "127.0.0.1")
+            .with_port(port)
+            .with_local_server();
+        let profile = IperfProfile::new("tcp", IperfProtocol::Tcp, 1, None);
+        let result = adapter
+            .run_profile(&profile, Duration::from_secs(1))
+            .unwrap();
+        assert!(result.received_bits_per_sec > 0.0);
+        assert!(result.retransmits.is_some());
+    }
+}
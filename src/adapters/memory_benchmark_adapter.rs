@@ -0,0 +1,440 @@
+//! Memory Benchmark Adapter
+//!
+//! This module implements STREAM-style memory bandwidth benchmarks (copy,
+//! scale, add and triad) and a randomized pointer-chasing latency benchmark
+//! over working sets from L1-sized to DRAM-sized. On hosts with several NUMA
+//! nodes it also compares triad bandwidth and latency with the memory on the
+//! node the threads run on against memory on a remote node.
+//!
+//! Memory is placed on a NUMA node by first touch: the thread that first
+//! writes a page decides its node, so every buffer is initialized by a thread
+//! pinned to a CPU of the node that should hold it.
+
+use std::hint::black_box;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use common::ports::log_port::LoggerPort;
+
+use crate::adapters::cpu_benchmark_adapter::{allowed_cpus, pin_to_cpu, run_pinned, xorshift};
+use crate::adapters::cpu_discovery_adapter::discover_numa_nodes;
+use crate::adapters::discovery_adapter::SysfsReader;
+use crate::domain::benchmark::{BenchmarkCase, BenchmarkMeasurement};
+use crate::domain::run::MetricDirection;
+use crate::ports::benchmark_port::BenchmarkPort;
+
+/// The default size of each of the three STREAM arrays in MiB. STREAM asks
+/// for arrays of at least four times the last level cache.
+const DEFAULT_ARRAY_SIZES_MIB: &[usize] = &[256];
+
+/// The default pointer-chasing working sets in KiB, sized to land in L1, L2,
+/// L3 and DRAM on common server CPUs.
+const DEFAULT_WORKING_SETS_KIB: &[usize] = &[16, 128, 1024, 8192, 65536, 262144];
+
+/// The size of a cache line; the pointer chase touches one pointer per line.
+const CACHE_LINE: usize = 64;
+
+/// The number of dependent loads in a single chunk of pointer chasing.
+const CHASE_STEPS: u64 = 1 << 16;
+
+/// The scalar of the STREAM scale and triad kernels.
+const SCALAR: f64 = 3.0;
+
+/// The STREAM kernels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StreamKernel {
+    /// `c = a`
+    Copy,
+    /// `b = scalar * c`
+    Scale,
+    /// `c = a + b`
+    Add,
+    /// `a = b + scalar * c`
+    Triad,
+}
+
+impl StreamKernel {
+    const ALL: [StreamKernel; 4] = [
+        StreamKernel::Copy,
+        StreamKernel::Scale,
+        StreamKernel::Add,
+        StreamKernel::Triad,
+    ];
+
+    /// Returns the name of the kernel.
+    fn name(self) -> &'static str {
+        match self {
+            StreamKernel::Copy => "copy",
+            StreamKernel::Scale => "scale",
+            StreamKernel::Add => "add",
+            StreamKernel::Triad => "triad",
+        }
+    }
+
+    /// Returns the number of arrays the kernel reads or writes, which STREAM
+    /// counts to compute the bytes moved.
+    fn arrays(self) -> usize {
+        match self {
+            StreamKernel::Copy | StreamKernel::Scale => 2,
+            StreamKernel::Add | StreamKernel::Triad => 3,
+        }
+    }
+}
+
+/// What a memory benchmark case measures.
+#[derive(Debug, Clone)]
+enum Workload {
+    /// STREAM bandwidth with arrays of the given total size in bytes.
+    Stream {
+        kernel: StreamKernel,
+        array_bytes: usize,
+    },
+    /// Pointer-chasing latency over a working set of the given size in bytes.
+    Latency { working_set: usize },
+}
+
+/// A memory benchmark case and where it runs.
+#[derive(Debug, Clone)]
+struct MemoryCase {
+    case: BenchmarkCase,
+    workload: Workload,
+    /// The CPUs the threads run on.
+    cpus: Vec<usize>,
+    /// The CPUs whose nodes hold the memory of each thread, by thread index;
+    /// empty to keep the memory local to each thread.
+    memory_cpus: Vec<usize>,
+}
+
+/// Runs the memory bandwidth and latency benchmarks natively.
+pub struct MemoryBenchmarkAdapter {
+    logger: Arc<dyn LoggerPort>,
+    reader: SysfsReader,
+    cpus: Vec<usize>,
+    array_sizes_mib: Vec<usize>,
+    thread_counts: Vec<usize>,
+    working_sets_kib: Vec<usize>,
+    /// The cases, planned once on first use.
+    plan: OnceLock<Vec<MemoryCase>>,
+}
+
+impl MemoryBenchmarkAdapter {
+    /// Creates a new instance of `MemoryBenchmarkAdapter` with the default
+    /// array sizes and working sets, running STREAM single-threaded and on
+    /// every CPU the process may run on.
+    pub fn new(logger: Arc<dyn LoggerPort>) -> Self {
+        MemoryBenchmarkAdapter {
+            logger,
+            reader: SysfsReader::new("/"),
+            cpus: allowed_cpus(),
+            array_sizes_mib: DEFAULT_ARRAY_SIZES_MIB.to_vec(),
+            thread_counts: Vec::new(),
+            working_sets_kib: DEFAULT_WORKING_SETS_KIB.to_vec(),
+            plan: OnceLock::new(),
+        }
+    }
+
+    /// Sets the sizes of each STREAM array in MiB; empty keeps the defaults.
+    pub fn with_array_sizes_mib(mut self, sizes: Vec<usize>) -> Self {
+        if !sizes.is_empty() {
+            self.array_sizes_mib = sizes;
+        }
+        self
+    }
+
+    /// Sets the thread counts STREAM runs with; empty runs single-threaded
+    /// and on every CPU. Counts above the number of CPUs are capped.
+    pub fn with_thread_counts(mut self, counts: Vec<usize>) -> Self {
+        self.thread_counts = counts;
+        self
+    }
+
+    /// Sets the pointer-chasing working sets in KiB; empty keeps the defaults.
+    pub fn with_working_sets_kib(mut self, sizes: Vec<usize>) -> Self {
+        if !sizes.is_empty() {
+            self.working_sets_kib = sizes;
+        }
+        self
+    }
+
+    /// Returns every case in the order they are run, planning them on first
+    /// use.
+    fn plan(&self) -> &[MemoryCase] {
+        self.plan.get_or_init(|| self.plan_cases())
+    }
+
+    /// Plans every case in the order they are run.
+    fn plan_cases(&self) -> Vec<MemoryCase> {
+        let mut thread_counts: Vec<usize> = if self.thread_counts.is_empty() {
+            vec![1, self.cpus.len()]
+        } else {
+            self.thread_counts
+                .iter()
+                .map(|&count| count.clamp(1, self.cpus.len()))
+                .collect()
+        };
+        thread_counts.sort_unstable();
+        thread_counts.dedup();
+
+        let mut cases = Vec::new();
+        for &array_mib in &self.array_sizes_mib {
+            for &threads in &thread_counts {
+                for kernel in StreamKernel::ALL {
+                    cases.push(MemoryCase {
+                        case: BenchmarkCase {
+                            name: format!("stream-{} {}MiB", kernel.name(), array_mib),
+                            threads,
+                            unit: "B/s".to_string(),
+                            direction: MetricDirection::HigherIsBetter,
+                        },
+                        workload: Workload::Stream {
+                            kernel,
+                            array_bytes: array_mib << 20,
+                        },
+                        cpus: self.cpus[..threads].to_vec(),
+                        memory_cpus: Vec::new(),
+                    });
+                }
+            }
+        }
+
+        for &working_set_kib in &self.working_sets_kib {
+            cases.push(MemoryCase {
+                case: BenchmarkCase {
+                    name: format!("latency {}KiB", working_set_kib),
+                    threads: 1,
+                    unit: "ns".to_string(),
+                    direction: MetricDirection::LowerIsBetter,
+                },
+                workload: Workload::Latency {
+                    working_set: working_set_kib << 10,
+                },
+                cpus: self.cpus[..1].to_vec(),
+                memory_cpus: Vec::new(),
+            });
+        }
+
+        if let Some((local, remote)) = self.numa_pair() {
+            let array_bytes = self.array_sizes_mib.iter().max().copied().unwrap_or(0) << 20;
+            let working_set = self.working_sets_kib.iter().max().copied().unwrap_or(0) << 10;
+            for (placement, memory_cpus) in [("local", Vec::new()), ("remote", remote)] {
+                cases.push(MemoryCase {
+                    case: BenchmarkCase {
+                        name: format!("numa-{} triad", placement),
+                        threads: local.len(),
+                        unit: "B/s".to_string(),
+                        direction: MetricDirection::HigherIsBetter,
+                    },
+                    workload: Workload::Stream {
+                        kernel: StreamKernel::Triad,
+                        array_bytes,
+                    },
+                    cpus: local.clone(),
+                    memory_cpus: memory_cpus.clone(),
+                });
+                cases.push(MemoryCase {
+                    case: BenchmarkCase {
+                        name: format!("numa-{} latency", placement),
+                        threads: 1,
+                        unit: "ns".to_string(),
+                        direction: MetricDirection::LowerIsBetter,
+                    },
+                    workload: Workload::Latency { working_set },
+                    cpus: local[..1].to_vec(),
+                    memory_cpus,
+                });
+            }
+        }
+
+        cases
+    }
+
+    /// Returns the CPUs of the first two NUMA nodes the process may run on,
+    /// or `None` on hosts with a single node.
+    fn numa_pair(&self) -> Option<(Vec<usize>, Vec<usize>)> {
+        let mut nodes = discover_numa_nodes(&self.reader)
+            .into_iter()
+            .filter_map(|node| {
+                let cpus: Vec<usize> = node
+                    .cpus
+                    .iter()
+                    .map(|&cpu| cpu as usize)
+                    .filter(|cpu| self.cpus.contains(cpu))
+                    .collect();
+                (!cpus.is_empty()).then_some(cpus)
+            });
+        Some((nodes.next()?, nodes.next()?))
+    }
+}
+
+impl BenchmarkPort for MemoryBenchmarkAdapter {
+    /// Returns "memory".
+    fn suite(&self) -> &str {
+        "memory"
+    }
+
+    /// The kernels keep their CPUs busy moving and chasing memory.
+    fn cpu_bound(&self) -> bool {
+        true
+    }
+
+    /// Lists the STREAM kernels for every array size and thread count, the
+    /// latency for every working set and, on NUMA hosts, the local and remote
+    /// triad bandwidth and latency.
+    fn cases(&self) -> Vec<BenchmarkCase> {
+        self.plan()
+            .iter()
+            .map(|planned| planned.case.clone())
+            .collect()
+    }
+
+    /// Runs a case on its pinned threads.
+    ///
+    /// STREAM cases report the bytes moved per second summed over every
+    /// thread, and latency cases report the average nanoseconds per
+    /// dependent load.
+    fn run_iteration(
+        &self,
+        case: &BenchmarkCase,
+        duration: Duration,
+    ) -> Result<BenchmarkMeasurement, String> {
+        let planned = self
+            .plan()
+            .iter()
+            .find(|planned| planned.case == *case)
+            .ok_or_else(|| format!("Unknown memory benchmark {}", case.name))?;
+
+        let threads = planned.cpus.len();
+        let setup = |cpu: usize| {
+            // Initialize the buffers on the CPU whose node should hold them.
+            let index = planned.cpus.iter().position(|&c| c == cpu).unwrap_or(0);
+            let memory_cpu = planned
+                .memory_cpus
+                .get(index % planned.memory_cpus.len().max(1));
+            if let Some(&memory_cpu) = memory_cpu {
+                if let Err(e) = pin_to_cpu(memory_cpu) {
+                    self.logger.log_warn(&e);
+                }
+            }
+            let chunk = match planned.workload {
+                Workload::Stream {
+                    kernel,
+                    array_bytes,
+                } => stream_workload(kernel, array_bytes / threads),
+                Workload::Latency { working_set } => latency_workload(working_set),
+            };
+            if memory_cpu.is_some() {
+                if let Err(e) = pin_to_cpu(cpu) {
+                    self.logger.log_warn(&e);
+                }
+            }
+            chunk
+        };
+
+        let rate = run_pinned(&planned.cpus, duration, &setup, self.logger.as_ref())
+            .map_err(|e| format!("memory benchmark {}: {}", case.name, e))?;
+        match planned.workload {
+            Workload::Stream { .. } => Ok(rate.into()),
+            Workload::Latency { .. } if rate > 0.0 => Ok((1e9 / rate).into()),
+            Workload::Latency { .. } => {
+                Err(format!("memory benchmark {} completed no loads", case.name))
+            }
+        }
+    }
+}
+
+/// A STREAM kernel over three arrays of `array_bytes` each, counting the
+/// bytes moved the way STREAM does.
+fn stream_workload(kernel: StreamKernel, array_bytes: usize) -> Box<dyn FnMut() -> u64> {
+    let elements = (array_bytes / std::mem::size_of::<f64>()).max(1);
+    // Non-zero fill values make the allocations touch every page now rather
+    // than on first use.
+    let mut a = vec![1.0f64; elements];
+    let mut b = vec![2.0f64; elements];
+    let mut c = vec![0.5f64; elements];
+    let bytes = (kernel.arrays() * elements * std::mem::size_of::<f64>()) as u64;
+    Box::new(move || {
+        match kernel {
+            StreamKernel::Copy => c.copy_from_slice(&a),
+            StreamKernel::Scale => {
+                for (b, c) in b.iter_mut().zip(&c) {
+                    *b = SCALAR * c;
+                }
+            }
+            StreamKernel::Add => {
+                for ((c, a), b) in c.iter_mut().zip(&a).zip(&b) {
+                    *c = a + b;
+                }
+            }
+            StreamKernel::Triad => {
+                for ((a, b), c) in a.iter_mut().zip(&b).zip(&c) {
+                    *a = b + SCALAR * c;
+                }
+            }
+        }
+        black_box((&a, &b, &c));
+        bytes
+    })
+}
+
+/// Pointer chasing over a working set of `working_set` bytes: every cache
+/// line holds the index of the next line of a single random cycle, so every
+/// load depends on the previous one and defeats the prefetchers. Each load
+/// counts as one op.
+fn latency_workload(working_set: usize) -> Box<dyn FnMut() -> u64> {
+    let stride = CACHE_LINE / std::mem::size_of::<usize>();
+    let lines = (working_set / CACHE_LINE).max(2);
+
+    // Sattolo's algorithm shuffles the lines into a single cycle.
+    let mut order: Vec<usize> = (0..lines).collect();
+    let mut state = 0x9e37_79b9_7f4a_7c15u64;
+    for i in (1..lines).rev() {
+        state = xorshift(state);
+        order.swap(i, (state % i as u64) as usize);
+    }
+    let mut chain = vec![0usize; lines * stride];
+    for (line, next) in order.into_iter().enumerate() {
+        chain[line * stride] = next * stride;
+    }
+
+    let mut position = 0;
+    Box::new(move || {
+        for _ in 0..CHASE_STEPS {
+            position = chain[position];
+        }
+        black_box(position);
+        CHASE_STEPS
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use common::adapters::capture_log_adapter::CapturingLogger;
+
+    use super::*;
+
+    #[test]
+    fn plans_every_thread_count_once() {
+        let sysfs = tempfile::tempdir().unwrap();
+        let adapter = MemoryBenchmarkAdapter {
+            logger: Arc::new(CapturingLogger::new()),
+            reader: SysfsReader::new(sysfs.path()),
+            cpus: vec![0, 1, 2, 3],
+            array_sizes_mib: vec![1],
+            thread_counts: vec![4, 1, 8, 2, 1],
+            working_sets_kib: vec![16],
+            plan: OnceLock::new(),
+        };
+
+        let cases = adapter.cases();
+        let threads: Vec<usize> = cases
+            .iter()
+            .filter(|case| case.name == "stream-triad 1MiB")
+            .map(|case| case.threads)
+            .collect();
+        // Counts above the number of CPUs are capped, and duplicates that are
+        // not adjacent are dropped too.
+        assert_eq!(threads, vec![1, 2, 4]);
+        assert_eq!(cases.len(), 3 * StreamKernel::ALL.len() + 1);
+        assert!(std::ptr::eq(adapter.plan(), adapter.plan()));
+    }
+}
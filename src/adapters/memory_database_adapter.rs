@@ -0,0 +1,184 @@
+//! Memory Database Adapter
+//!
+//! This module provides an in-memory implementation of the `DatabasePort`
+//! trait for ephemeral runs and tests: every tree is an ordered map guarded by
+//! a lock, and everything stored is gone once the adapter is dropped.
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::ops::Bound;
+use std::sync::{Arc, Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use crate::domain::database::DEFAULT_TREE;
+use crate::ports::database_port::{
+    BatchOp, DatabasePort, KeyValue, Transaction, TransactionFn, WriteBatch,
+};
+
+/// The pairs of a tree in key order.
+type Map = BTreeMap<Vec<u8>, Vec<u8>>;
+
+/// Stores the trees of a database in memory.
+pub struct MemoryDatabaseAdapter {
+    trees: Arc<Mutex<BTreeMap<String, Arc<RwLock<Map>>>>>,
+    tree: Arc<RwLock<Map>>,
+}
+
+impl MemoryDatabaseAdapter {
+    /// Creates an empty database with only the default tree.
+    pub fn new() -> Self {
+        let tree = Arc::new(RwLock::new(Map::new()));
+        let trees = BTreeMap::from([(DEFAULT_TREE.to_string(), tree.clone())]);
+        MemoryDatabaseAdapter {
+            trees: Arc::new(Mutex::new(trees)),
+            tree,
+        }
+    }
+
+    /// Locks the tree for reading.
+    fn read(&self) -> Result<RwLockReadGuard<'_, Map>, Box<dyn Error>> {
+        self.tree.read().map_err(|e| e.to_string().into())
+    }
+
+    /// Locks the tree for writing.
+    fn write(&self) -> Result<RwLockWriteGuard<'_, Map>, Box<dyn Error>> {
+        self.tree.write().map_err(|e| e.to_string().into())
+    }
+}
+
+impl Default for MemoryDatabaseAdapter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The reads and writes of a transaction: writes are kept aside, shadowing
+/// the tree, until the transaction commits.
+struct MemoryTransaction<'a> {
+    tree: &'a Map,
+    writes: RefCell<BTreeMap<Vec<u8>, Vec<u8>>>,
+}
+
+impl Transaction for MemoryTransaction<'_> {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
+        Ok(match self.writes.borrow().get(key) {
+            Some(value) => Some(value.clone()),
+            None => self.tree.get(key).cloned(),
+        })
+    }
+
+    fn insert(&self, key: &[u8], value: &[u8]) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
+        let previous = self.get(key)?;
+        self.writes
+            .borrow_mut()
+            .insert(key.to_vec(), value.to_vec());
+        Ok(previous)
+    }
+}
+
+impl DatabasePort for MemoryDatabaseAdapter {
+    fn insert(&self, key: &[u8], value: &[u8]) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
+        Ok(self.write()?.insert(key.to_vec(), value.to_vec()))
+    }
+
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
+        Ok(self.read()?.get(key).cloned())
+    }
+
+    fn scan_prefix(&self, prefix: &[u8]) -> Result<Vec<KeyValue>, Box<dyn Error>> {
+        Ok(self
+            .read()?
+            .range::<[u8], _>((Bound::Included(prefix), Bound::Unbounded))
+            .take_while(|(key, _)| key.starts_with(prefix))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect())
+    }
+
+    fn scan_range(&self, start: &[u8], end: &[u8]) -> Result<Vec<KeyValue>, Box<dyn Error>> {
+        if start >= end {
+            return Ok(Vec::new());
+        }
+        Ok(self
+            .read()?
+            .range::<[u8], _>((Bound::Included(start), Bound::Excluded(end)))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect())
+    }
+
+    fn first_in_range(&self, start: &[u8], end: &[u8]) -> Result<Option<KeyValue>, Box<dyn Error>> {
+        if start >= end {
+            return Ok(None);
+        }
+        Ok(self
+            .read()?
+            .range::<[u8], _>((Bound::Included(start), Bound::Excluded(end)))
+            .next()
+            .map(|(key, value)| (key.clone(), value.clone())))
+    }
+
+    fn last_in_range(&self, start: &[u8], end: &[u8]) -> Result<Option<KeyValue>, Box<dyn Error>> {
+        if start >= end {
+            return Ok(None);
+        }
+        Ok(self
+            .read()?
+            .range::<[u8], _>((Bound::Included(start), Bound::Excluded(end)))
+            .next_back()
+            .map(|(key, value)| (key.clone(), value.clone())))
+    }
+
+    /// Applies a batch under a single lock, so readers see all of it or none.
+    fn apply_batch(&self, batch: &WriteBatch) -> Result<(), Box<dyn Error>> {
+        let mut tree = self.write()?;
+        for op in batch.ops() {
+            match op {
+                BatchOp::Insert(key, value) => tree.insert(key.clone(), value.clone()),
+                BatchOp::Remove(key) => tree.remove(key),
+            };
+        }
+        Ok(())
+    }
+
+    /// Runs a transaction holding the lock of the tree, so transactions on a
+    /// tree never conflict and are never retried.
+    fn transaction(&self, body: &TransactionFn) -> Result<(), Box<dyn Error>> {
+        let mut tree = self.write()?;
+        let transaction = MemoryTransaction {
+            tree: &tree,
+            writes: RefCell::new(BTreeMap::new()),
+        };
+        body(&transaction)?;
+
+        let writes = transaction.writes.into_inner();
+        tree.extend(writes);
+        Ok(())
+    }
+
+    fn open_tree(&self, name: &str) -> Result<Arc<dyn DatabasePort>, Box<dyn Error>> {
+        let mut trees = self.trees.lock().map_err(|e| e.to_string())?;
+        let tree = trees.entry(name.to_string()).or_default().clone();
+        Ok(Arc::new(MemoryDatabaseAdapter {
+            trees: self.trees.clone(),
+            tree,
+        }))
+    }
+
+    fn tree_names(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        let trees = self.trees.lock().map_err(|e| e.to_string())?;
+        Ok(trees.keys().cloned().collect())
+    }
+
+    /// Returns the size of the keys and values of every tree.
+    fn size_on_disk(&self) -> Result<u64, Box<dyn Error>> {
+        let trees = self.trees.lock().map_err(|e| e.to_string())?;
+        let mut size = 0;
+        for tree in trees.values() {
+            let tree = tree.read().map_err(|e| e.to_string())?;
+            size += tree
+                .iter()
+                .map(|(key, value)| (key.len() + value.len()) as u64)
+                .sum::<u64>();
+        }
+        Ok(size)
+    }
+}
@@ -1,6 +1,26 @@
 // src/adapters/domain
-pub mod database_adapter;
+pub mod bzip2_archive_adapter;
+pub mod cpu_benchmark_adapter;
+pub mod cpu_discovery_adapter;
+pub mod cpu_frequency_adapter;
+pub mod discovery_adapter;
+pub mod fio_adapter;
+pub mod host_info_adapter;
+pub mod iperf_adapter;
+pub mod memory_benchmark_adapter;
+pub mod memory_database_adapter;
+pub mod native_storage_benchmark_adapter;
+pub mod netbench_adapter;
+pub mod network_discovery_adapter;
+pub mod pci_discovery_adapter;
+pub mod plotters_chart_adapter;
 pub(crate) mod ps_command_adapter;
+pub mod report_adapter;
+pub mod repository_adapter;
+pub mod sled_database_adapter;
+pub mod smbios_adapter;
+pub mod storage_discovery_adapter;
 pub mod stress_ng_adapter;
 pub mod stress_ng_manager_adapter;
+pub mod system_metrics_adapter;
 pub mod burn_ai_model;
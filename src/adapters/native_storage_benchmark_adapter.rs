@@ -0,0 +1,596 @@
+//! Native Storage Benchmark Adapter
+//!
+//! This module implements a pure Rust storage benchmark for hosts without
+//! fio: sequential and random reads and writes against a file on a chosen
+//! mount, bypassing the page cache with O_DIRECT on Linux. The queue depth is
+//! emulated with one thread per outstanding I/O, and the latency of every
+//! I/O is recorded in a histogram.
+//!
+//! Every 4 KiB sector of the file is self-describing: it starts with its own
+//! offset, the seed of the run and the seed of the write, and ends with a
+//! CRC32 of the rest of the sector. Reads verify every sector they return,
+//! and writes are read back and verified once the timed part of an iteration
+//! is over, so that corrupted, torn, misdirected or lost writes, and data
+//! left behind by another run, fail the benchmark.
+
+use std::alloc::{self, Layout};
+use std::fs::{self, File, OpenOptions};
+use std::ops::{Deref, DerefMut};
+#[cfg(unix)]
+use std::os::unix::fs::FileExt;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Barrier, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use common::ports::log_port::LoggerPort;
+
+use crate::adapters::cpu_benchmark_adapter::xorshift;
+use crate::domain::benchmark::{BenchmarkCase, BenchmarkDetail, BenchmarkMeasurement};
+use crate::domain::latency_histogram::LatencyHistogram;
+use crate::domain::run::MetricDirection;
+use crate::ports::benchmark_port::BenchmarkPort;
+
+/// The size of a self-describing sector, and the alignment of every I/O.
+const SECTOR_SIZE: usize = 4096;
+
+/// The offset of the CRC32 at the end of every sector.
+const CRC_OFFSET: usize = SECTOR_SIZE - 4;
+
+/// The size of the writes that fill the file before the first iteration.
+const FILL_CHUNK: usize = 1 << 20;
+
+/// The I/O patterns of the benchmark.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Pattern {
+    SequentialRead,
+    SequentialWrite,
+    RandomRead,
+    RandomWrite,
+}
+
+impl Pattern {
+    const ALL: [Pattern; 4] = [
+        Pattern::SequentialRead,
+        Pattern::SequentialWrite,
+        Pattern::RandomRead,
+        Pattern::RandomWrite,
+    ];
+
+    /// Returns the case name of the pattern.
+    fn name(self) -> &'static str {
+        match self {
+            Pattern::SequentialRead => "seq-read",
+            Pattern::SequentialWrite => "seq-write",
+            Pattern::RandomRead => "rand-read",
+            Pattern::RandomWrite => "rand-write",
+        }
+    }
+
+    /// Returns whether the pattern picks random offsets.
+    fn is_random(self) -> bool {
+        matches!(self, Pattern::RandomRead | Pattern::RandomWrite)
+    }
+
+    /// Returns whether the pattern writes.
+    fn writes(self) -> bool {
+        matches!(self, Pattern::SequentialWrite | Pattern::RandomWrite)
+    }
+}
+
+/// A heap buffer aligned to the sector size, as O_DIRECT requires.
+struct AlignedBuffer {
+    ptr: *mut u8,
+    layout: Layout,
+}
+
+// SAFETY: the buffer exclusively owns its allocation.
+unsafe impl Send for AlignedBuffer {}
+
+impl AlignedBuffer {
+    /// Allocates a zeroed buffer of `len` bytes, a multiple of the sector size.
+    fn new(len: usize) -> Self {
+        let layout = Layout::from_size_align(len.max(SECTOR_SIZE), SECTOR_SIZE)
+            .expect("sector aligned layout");
+        // SAFETY: the layout has a non-zero size.
+        let ptr = unsafe { alloc::alloc_zeroed(layout) };
+        if ptr.is_null() {
+            alloc::handle_alloc_error(layout);
+        }
+        AlignedBuffer { ptr, layout }
+    }
+}
+
+impl Deref for AlignedBuffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        // SAFETY: the allocation holds `layout.size()` initialized bytes.
+        unsafe { std::slice::from_raw_parts(self.ptr, self.layout.size()) }
+    }
+}
+
+impl DerefMut for AlignedBuffer {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        // SAFETY: the allocation holds `layout.size()` initialized bytes and
+        // is borrowed mutably through `self`.
+        unsafe { std::slice::from_raw_parts_mut(self.ptr, self.layout.size()) }
+    }
+}
+
+impl Drop for AlignedBuffer {
+    fn drop(&mut self) {
+        // SAFETY: the pointer was allocated with this layout.
+        unsafe { alloc::dealloc(self.ptr, self.layout) };
+    }
+}
+
+/// What a thread measured in the timed part of an iteration.
+struct ThreadOutcome {
+    bytes: u64,
+    ios: u64,
+    elapsed: Duration,
+    latencies: LatencyHistogram,
+    /// The offsets the thread wrote, to be read back and verified.
+    written: Vec<u64>,
+}
+
+/// Runs storage benchmarks natively against a file.
+pub struct NativeStorageBenchmarkAdapter {
+    logger: Arc<dyn LoggerPort>,
+    path: PathBuf,
+    size_bytes: u64,
+    seq_block_size: usize,
+    random_block_size: usize,
+    queue_depth: usize,
+    direct: bool,
+    /// How long every iteration runs, overriding the configured default.
+    iteration_duration: Option<Duration>,
+    /// Whether the file has been filled with valid sectors.
+    prepared: Mutex<bool>,
+    /// The random seed of the run, stamped into every sector so that data
+    /// written by another run does not verify.
+    run_seed: u64,
+    /// The seed of the next iteration, so that every write is distinct.
+    next_seed: AtomicU64,
+}
+
+impl NativeStorageBenchmarkAdapter {
+    /// Creates a new instance of `NativeStorageBenchmarkAdapter` that runs
+    /// against a 1 GiB file in `directory`, with 1 MiB sequential and 4 KiB
+    /// random I/Os at a queue depth of 16, bypassing the page cache.
+    ///
+    /// # Arguments
+    /// * `logger` - The logger used to report progress and errors.
+    /// * `directory` - A directory on the mount to benchmark. The benchmark
+    ///   file is created there and removed when the adapter is dropped.
+    pub fn new(logger: Arc<dyn LoggerPort>, directory: impl Into<PathBuf>) -> Self {
+        let path = directory
+            .into()
+            .join(format!("commandant-rs-bench-{}.dat", std::process::id()));
+        NativeStorageBenchmarkAdapter {
+            logger,
+            path,
+            size_bytes: 1 << 30,
+            seq_block_size: 1 << 20,
+            random_block_size: SECTOR_SIZE,
+            queue_depth: 16,
+            direct: true,
+            iteration_duration: None,
+            prepared: Mutex::new(false),
+            run_seed: uuid::Uuid::new_v4().as_u64_pair().0,
+            next_seed: AtomicU64::new(1),
+        }
+    }
+
+    /// Sets the size of the benchmark file in MiB.
+    pub fn with_size_mib(mut self, size_mib: u64) -> Self {
+        self.size_bytes = size_mib << 20;
+        self
+    }
+
+    /// Sets the block sizes of the sequential and random I/Os in KiB.
+    ///
+    /// Sizes are rounded up to a multiple of 4 KiB.
+    pub fn with_block_sizes_kib(mut self, sequential: usize, random: usize) -> Self {
+        let round = |kib: usize| (kib << 10).max(1).div_ceil(SECTOR_SIZE) * SECTOR_SIZE;
+        self.seq_block_size = round(sequential);
+        self.random_block_size = round(random);
+        self
+    }
+
+    /// Sets the number of I/Os in flight, each issued by its own thread.
+    pub fn with_queue_depth(mut self, queue_depth: usize) -> Self {
+        self.queue_depth = queue_depth.max(1);
+        self
+    }
+
+    /// Sets whether to bypass the page cache with O_DIRECT.
+    pub fn with_direct(mut self, direct: bool) -> Self {
+        self.direct = direct;
+        self
+    }
+
+    /// Sets how long every iteration runs, instead of the configured default
+    /// of all benchmarks.
+    pub fn with_iteration_duration(mut self, duration: Duration) -> Self {
+        self.iteration_duration = Some(duration);
+        self
+    }
+
+    /// Returns the block size of a pattern.
+    fn block_size(&self, pattern: Pattern) -> usize {
+        if pattern.is_random() {
+            self.random_block_size
+        } else {
+            self.seq_block_size
+        }
+    }
+
+    /// Returns the usable size of the file: a whole number of the largest
+    /// blocks, and at least one.
+    fn file_size(&self) -> u64 {
+        let block = self.seq_block_size.max(self.random_block_size) as u64;
+        (self.size_bytes / block).max(1) * block
+    }
+
+    /// Opens the benchmark file, with O_DIRECT when requested.
+    fn open(&self) -> Result<File, String> {
+        let mut options = OpenOptions::new();
+        options.read(true).write(true).create(true).truncate(false);
+        #[cfg(target_os = "linux")]
+        if self.direct {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.custom_flags(libc::O_DIRECT);
+        }
+        options.open(&self.path).map_err(|e| {
+            let hint = if self.direct {
+                "; the filesystem may not support O_DIRECT, set direct: false"
+            } else {
+                ""
+            };
+            format!("Failed to open {}: {}{}", self.path.display(), e, hint)
+        })
+    }
+
+    /// Fills the file with valid sectors before the first iteration, so that
+    /// reads never hit holes.
+    fn prepare(&self, file: &File) -> Result<(), String> {
+        let mut prepared = self.prepared.lock().map_err(|e| e.to_string())?;
+        if *prepared {
+            return Ok(());
+        }
+        let size = self.file_size();
+        self.logger.log_info(&format!(
+            "Filling storage benchmark file {} with {} MiB",
+            self.path.display(),
+            size >> 20
+        ));
+        let mut buffer = AlignedBuffer::new(FILL_CHUNK);
+        fill_payload(&mut buffer, 0);
+        let mut offset = 0;
+        while offset < size {
+            let len = FILL_CHUNK.min((size - offset) as usize);
+            stamp_sectors(&mut buffer[..len], offset, self.run_seed, 0);
+            file.write_all_at(&buffer[..len], offset)
+                .map_err(|e| format!("Failed to fill {}: {}", self.path.display(), e))?;
+            offset += len as u64;
+        }
+        file.sync_all()
+            .map_err(|e| format!("Failed to sync {}: {}", self.path.display(), e))?;
+        *prepared = true;
+        Ok(())
+    }
+
+    /// Runs a pattern on every thread for `duration`, stamping writes with
+    /// the seed of the iteration.
+    fn run_pattern(
+        &self,
+        file: &File,
+        pattern: Pattern,
+        seed: u64,
+        duration: Duration,
+    ) -> Result<Vec<ThreadOutcome>, String> {
+        let block_size = self.block_size(pattern);
+        let blocks = self.file_size() / block_size as u64;
+        let barrier = Barrier::new(self.queue_depth);
+
+        thread::scope(|scope| {
+            let handles: Vec<_> = (0..self.queue_depth)
+                .map(|index| {
+                    let barrier = &barrier;
+                    scope.spawn(move || -> Result<ThreadOutcome, String> {
+                        let mut buffer = AlignedBuffer::new(block_size);
+                        if pattern.writes() {
+                            fill_payload(&mut buffer, seed ^ index as u64);
+                        }
+                        // Sequential threads start at evenly spaced blocks.
+                        let mut block = blocks * index as u64 / self.queue_depth as u64;
+                        let mut random = xorshift(seed.wrapping_add(index as u64 + 1) | 1);
+                        let mut outcome = ThreadOutcome {
+                            bytes: 0,
+                            ios: 0,
+                            elapsed: Duration::ZERO,
+                            latencies: LatencyHistogram::new(),
+                            written: Vec::new(),
+                        };
+
+                        barrier.wait();
+                        let start = Instant::now();
+                        while start.elapsed() < duration {
+                            if pattern.is_random() {
+                                random = xorshift(random);
+                                block = random % blocks;
+                            }
+                            let offset = block * block_size as u64;
+                            let io_start = Instant::now();
+                            if pattern.writes() {
+                                stamp_sectors(&mut buffer, offset, self.run_seed, seed);
+                                file.write_all_at(&buffer, offset)
+                                    .map_err(|e| format!("Write at {} failed: {}", offset, e))?;
+                                outcome.latencies.record(io_start.elapsed());
+                                outcome.written.push(offset);
+                            } else {
+                                file.read_exact_at(&mut buffer, offset)
+                                    .map_err(|e| format!("Read at {} failed: {}", offset, e))?;
+                                outcome.latencies.record(io_start.elapsed());
+                                verify_sectors(&buffer, offset, self.run_seed, None)?;
+                            }
+                            outcome.bytes += block_size as u64;
+                            outcome.ios += 1;
+                            block = (block + 1) % blocks;
+                        }
+                        outcome.elapsed = start.elapsed();
+                        Ok(outcome)
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| {
+                    handle
+                        .join()
+                        .map_err(|_| "storage benchmark thread panicked".to_string())?
+                })
+                .collect()
+        })
+    }
+
+    /// Reads back and verifies every block written in the iteration of `seed`.
+    fn verify_written(
+        &self,
+        file: &File,
+        block_size: usize,
+        seed: u64,
+        outcomes: &[ThreadOutcome],
+    ) -> Result<u64, String> {
+        let mut offsets: Vec<u64> = outcomes
+            .iter()
+            .flat_map(|outcome| outcome.written.iter().copied())
+            .collect();
+        offsets.sort_unstable();
+        offsets.dedup();
+
+        let mut buffer = AlignedBuffer::new(block_size);
+        for &offset in &offsets {
+            file.read_exact_at(&mut buffer, offset)
+                .map_err(|e| format!("Read back at {} failed: {}", offset, e))?;
+            verify_sectors(&buffer, offset, self.run_seed, Some(seed))?;
+        }
+        Ok(offsets.len() as u64)
+    }
+}
+
+impl BenchmarkPort for NativeStorageBenchmarkAdapter {
+    /// Returns "storage".
+    fn suite(&self) -> &str {
+        "storage"
+    }
+
+    /// Lists sequential and random reads and writes, measured in bandwidth
+    /// for sequential patterns and in IOPS for random ones.
+    fn cases(&self) -> Vec<BenchmarkCase> {
+        Pattern::ALL
+            .iter()
+            .map(|pattern| BenchmarkCase {
+                name: pattern.name().to_string(),
+                threads: self.queue_depth,
+                unit: if pattern.is_random() { "ops/s" } else { "B/s" }.to_string(),
+                direction: MetricDirection::HigherIsBetter,
+            })
+            .collect()
+    }
+
+    fn iteration_duration(&self, default: Duration) -> Duration {
+        self.iteration_duration.unwrap_or(default)
+    }
+
+    /// Runs a pattern, reporting the bandwidth, IOPS and latency percentiles
+    /// as details and failing if any sector does not verify.
+    fn run_iteration(
+        &self,
+        case: &BenchmarkCase,
+        duration: Duration,
+    ) -> Result<BenchmarkMeasurement, String> {
+        let pattern = Pattern::ALL
+            .into_iter()
+            .find(|pattern| pattern.name() == case.name)
+            .ok_or_else(|| format!("Unknown storage benchmark {}", case.name))?;
+
+        let file = self.open()?;
+        self.prepare(&file)?;
+        let seed = self.next_seed.fetch_add(1, Ordering::Relaxed);
+        let outcomes = self.run_pattern(&file, pattern, seed, duration)?;
+        if pattern.writes() {
+            file.sync_all()
+                .map_err(|e| format!("Failed to sync {}: {}", self.path.display(), e))?;
+            let verified = self.verify_written(&file, self.block_size(pattern), seed, &outcomes)?;
+            self.logger.log_debug(&format!(
+                "Verified {} blocks written by {}",
+                verified, case.name
+            ));
+        }
+
+        let mut latencies = LatencyHistogram::new();
+        let (mut bandwidth, mut iops) = (0.0, 0.0);
+        for outcome in &outcomes {
+            let seconds = outcome.elapsed.as_secs_f64().max(f64::EPSILON);
+            bandwidth += outcome.bytes as f64 / seconds;
+            iops += outcome.ios as f64 / seconds;
+            latencies.merge(&outcome.latencies);
+        }
+
+        let mut details = vec![
+            BenchmarkDetail {
+                name: "bandwidth".to_string(),
+                unit: "B/s".to_string(),
+                direction: MetricDirection::HigherIsBetter,
+                value: bandwidth,
+            },
+            BenchmarkDetail {
+                name: "IOPS".to_string(),
+                unit: "ops/s".to_string(),
+                direction: MetricDirection::HigherIsBetter,
+                value: iops,
+            },
+        ];
+        let latency_details = [
+            ("mean latency", latencies.mean_ns()),
+            ("p50 latency", latencies.percentile_ns(50.0)),
+            ("p99 latency", latencies.percentile_ns(99.0)),
+            ("p99.9 latency", latencies.percentile_ns(99.9)),
+            ("max latency", latencies.max_ns().map(|ns| ns as f64)),
+        ];
+        for (name, value) in latency_details {
+            if let Some(value) = value {
+                details.push(BenchmarkDetail {
+                    name: name.to_string(),
+                    unit: "ns".to_string(),
+                    direction: MetricDirection::LowerIsBetter,
+                    value,
+                });
+            }
+        }
+
+        Ok(BenchmarkMeasurement {
+            value: if pattern.is_random() { iops } else { bandwidth },
+            details,
+        })
+    }
+}
+
+impl Drop for NativeStorageBenchmarkAdapter {
+    /// Removes the benchmark file.
+    fn drop(&mut self) {
+        if self.path.is_file() {
+            if let Err(e) = fs::remove_file(&self.path) {
+                self.logger.log_warn(&format!(
+                    "Failed to remove storage benchmark file {}: {}",
+                    self.path.display(),
+                    e
+                ));
+            }
+        }
+    }
+}
+
+/// Fills the payload of every sector with pseudo-random bytes.
+fn fill_payload(buffer: &mut [u8], seed: u64) {
+    let mut state = xorshift(seed | 1);
+    for word in buffer.chunks_exact_mut(8) {
+        state = xorshift(state);
+        word.copy_from_slice(&state.to_le_bytes());
+    }
+}
+
+/// Writes the offset, run seed, write seed and CRC32 of every sector of a
+/// block about to be written at `offset`.
+fn stamp_sectors(block: &mut [u8], offset: u64, run_seed: u64, seed: u64) {
+    for (index, sector) in block.chunks_exact_mut(SECTOR_SIZE).enumerate() {
+        let sector_offset = offset + (index * SECTOR_SIZE) as u64;
+        sector[..8].copy_from_slice(&sector_offset.to_le_bytes());
+        sector[8..16].copy_from_slice(&run_seed.to_le_bytes());
+        sector[16..24].copy_from_slice(&seed.to_le_bytes());
+        let crc = crc32fast::hash(&sector[..CRC_OFFSET]);
+        sector[CRC_OFFSET..].copy_from_slice(&crc.to_le_bytes());
+    }
+}
+
+/// Verifies every sector of a block read from `offset`.
+///
+/// # Arguments
+/// * `block` - The sectors read.
+/// * `offset` - The offset the block was read from.
+/// * `run_seed` - The seed of the run every sector must have been written by.
+/// * `seed` - The seed of the write every sector must hold, or `None` to
+///   accept any write of the run.
+fn verify_sectors(
+    block: &[u8],
+    offset: u64,
+    run_seed: u64,
+    seed: Option<u64>,
+) -> Result<(), String> {
+    for (index, sector) in block.chunks_exact(SECTOR_SIZE).enumerate() {
+        let sector_offset = offset + (index * SECTOR_SIZE) as u64;
+        let stored_crc = u32::from_le_bytes(sector[CRC_OFFSET..].try_into().unwrap_or_default());
+        if crc32fast::hash(&sector[..CRC_OFFSET]) != stored_crc {
+            return Err(format!(
+                "Data integrity check failed: checksum mismatch in sector at {}",
+                sector_offset
+            ));
+        }
+        let stored_offset = u64::from_le_bytes(sector[..8].try_into().unwrap_or_default());
+        if stored_offset != sector_offset {
+            return Err(format!(
+                "Data integrity check failed: sector at {} holds the data of {}",
+                sector_offset, stored_offset
+            ));
+        }
+        let stored_run_seed = u64::from_le_bytes(sector[8..16].try_into().unwrap_or_default());
+        if stored_run_seed != run_seed {
+            return Err(format!(
+                "Data integrity check failed: sector at {} was written by another run",
+                sector_offset
+            ));
+        }
+        let stored_seed = u64::from_le_bytes(sector[16..24].try_into().unwrap_or_default());
+        if seed.is_some_and(|seed| seed != stored_seed) {
+            return Err(format!(
+                "Data integrity check failed: sector at {} holds an earlier write",
+                sector_offset
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_sectors_of_other_runs_and_earlier_writes() {
+        let mut block = vec![0u8; 2 * SECTOR_SIZE];
+        fill_payload(&mut block, 7);
+        stamp_sectors(&mut block, 8 * SECTOR_SIZE as u64, 42, 3);
+
+        let offset = 8 * SECTOR_SIZE as u64;
+        assert_eq!(verify_sectors(&block, offset, 42, Some(3)), Ok(()));
+        assert_eq!(verify_sectors(&block, offset, 42, None), Ok(()));
+        let error = verify_sectors(&block, offset, 42, Some(4)).unwrap_err();
+        assert!(error.contains("earlier write"), "{}", error);
+        let error = verify_sectors(&block, offset, 43, None).unwrap_err();
+        assert!(error.contains("another run"), "{}", error);
+        let error = verify_sectors(&block, 0, 42, None).unwrap_err();
+        assert!(error.contains("holds the data of"), "{}", error);
+
+        block[SECTOR_SIZE + 100] ^= 1;
+        let error = verify_sectors(&block, offset, 42, None).unwrap_err();
+        assert!(
+            error.contains("checksum mismatch in sector at 36864"),
+            "{}",
+            error
+        );
+    }
+}
@@ -0,0 +1,732 @@
+//! Netbench Adapter
+//!
+//! This module provides a native network benchmark that needs nothing
+//! installed on either host. `NetbenchServer` serves the benchmarks over TCP
+//! and UDP on a single port, and `NetbenchAdapter` runs them as a client:
+//! throughput over parallel TCP streams, the round trip latency of TCP and
+//! UDP messages, and the rate TCP connections are set up at.
+//!
+//! Every TCP connection opens with a header of a mode byte and the message
+//! size as a big-endian `u32`.
+
+use std::io;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream, UdpSocket};
+use tokio::runtime::Handle;
+use tokio::time::timeout;
+
+use common::ports::log_port::LoggerPort;
+
+use crate::domain::benchmark::{BenchmarkCase, BenchmarkDetail, BenchmarkMeasurement};
+use crate::domain::latency_histogram::LatencyHistogram;
+use crate::domain::run::MetricDirection;
+use crate::ports::benchmark_port::BenchmarkPort;
+
+/// The default port the server listens on.
+const DEFAULT_PORT: u16 = 5300;
+
+/// The mode of connections streaming data to the server, which replies with
+/// the bytes it received once the client stops sending.
+const MODE_THROUGHPUT: u8 = b'T';
+
+/// The mode of connections whose messages the server echoes back.
+const MODE_PING_PONG: u8 = b'P';
+
+/// The mode of connections the server acknowledges with a single byte.
+const MODE_CONNECT: u8 = b'C';
+
+/// The size of the writes of throughput streams.
+const THROUGHPUT_CHUNK: usize = 128 << 10;
+
+/// The largest TCP message the server echoes.
+const MAX_TCP_MESSAGE: usize = 16 << 20;
+
+/// The largest UDP payload over IPv4.
+const MAX_UDP_MESSAGE: usize = 65507;
+
+/// The bytes of a UDP message holding its sequence number.
+const SEQUENCE_BYTES: usize = 8;
+
+/// How long a UDP message may take to come back before it counts as lost.
+const UDP_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// The default number of parallel throughput streams.
+const DEFAULT_STREAMS: usize = 4;
+
+/// The default sizes of latency messages in bytes.
+const DEFAULT_MESSAGE_SIZES: [usize; 2] = [64, 4096];
+
+/// Serves the benchmarks of `NetbenchAdapter` clients.
+pub struct NetbenchServer {
+    logger: Arc<dyn LoggerPort>,
+    listener: TcpListener,
+    udp: UdpSocket,
+}
+
+impl NetbenchServer {
+    /// Binds the server to a TCP and a UDP socket on the same address.
+    ///
+    /// # Arguments
+    /// * `logger` - The logger used to report clients and errors.
+    /// * `address` - The address to listen on, e.g. "0.0.0.0".
+    /// * `port` - The TCP and UDP port to listen on; 0 picks a port that is
+    ///   free for TCP and binds UDP to the same one.
+    pub async fn bind(
+        logger: Arc<dyn LoggerPort>,
+        address: &str,
+        port: u16,
+    ) -> Result<Self, String> {
+        let listener = TcpListener::bind((address, port))
+            .await
+            .map_err(|e| format!("Failed to listen on TCP {}:{}: {}", address, port, e))?;
+        let port = listener
+            .local_addr()
+            .map_err(|e| format!("Failed to listen on TCP {}:{}: {}", address, port, e))?
+            .port();
+        let udp = UdpSocket::bind((address, port))
+            .await
+            .map_err(|e| format!("Failed to listen on UDP {}:{}: {}", address, port, e))?;
+        Ok(NetbenchServer {
+            logger,
+            listener,
+            udp,
+        })
+    }
+
+    /// Returns the port the server listens on.
+    pub fn port(&self) -> u16 {
+        self.listener
+            .local_addr()
+            .map(|address| address.port())
+            .unwrap_or_default()
+    }
+
+    /// Serves clients until the task is dropped.
+    pub async fn run(self) {
+        if let Ok(address) = self.listener.local_addr() {
+            self.logger
+                .log_info(&format!("Serving netbench clients on {}", address));
+        }
+        tokio::spawn(echo_datagrams(self.udp));
+        loop {
+            match self.listener.accept().await {
+                Ok((stream, peer)) => {
+                    let logger = self.logger.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = serve_connection(stream).await {
+                            logger.log_debug(&format!("netbench client {}: {}", peer, e));
+                        }
+                    });
+                }
+                Err(e) => self
+                    .logger
+                    .log_warn(&format!("Failed to accept netbench client: {}", e)),
+            }
+        }
+    }
+}
+
+/// Serves a TCP connection in the mode of its header.
+async fn serve_connection(mut stream: TcpStream) -> io::Result<()> {
+    stream.set_nodelay(true)?;
+    let mode = stream.read_u8().await?;
+    let size = stream.read_u32().await? as usize;
+    match mode {
+        MODE_THROUGHPUT => {
+            let mut buffer = vec![0; THROUGHPUT_CHUNK];
+            let mut received = 0u64;
+            loop {
+                let read = stream.read(&mut buffer).await?;
+                if read == 0 {
+                    break;
+                }
+                received += read as u64;
+            }
+            stream.write_u64(received).await?;
+        }
+        MODE_PING_PONG => {
+            if size == 0 || size > MAX_TCP_MESSAGE {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("invalid message size {}", size),
+                ));
+            }
+            let mut message = vec![0; size];
+            loop {
+                match stream.read_exact(&mut message).await {
+                    Ok(_) => stream.write_all(&message).await?,
+                    Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+        MODE_CONNECT => {
+            stream.write_u8(MODE_CONNECT).await?;
+            // Let the client close first, so that it resets the connection
+            // rather than either end holding it in TIME_WAIT.
+            let mut rest = [0; 1];
+            let _ = stream.read(&mut rest).await;
+        }
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unknown mode {}", mode),
+            ))
+        }
+    }
+    Ok(())
+}
+
+/// Echoes every UDP datagram back to its sender.
+async fn echo_datagrams(socket: UdpSocket) {
+    let mut buffer = vec![0; MAX_UDP_MESSAGE];
+    loop {
+        if let Ok((length, peer)) = socket.recv_from(&mut buffer).await {
+            let _ = socket.send_to(&buffer[..length], peer).await;
+        }
+    }
+}
+
+/// What a netbench case measures.
+#[derive(Debug, Clone, Copy)]
+enum Workload {
+    /// Throughput over parallel TCP streams.
+    Throughput,
+    /// The round trip latency of TCP messages of the given size.
+    TcpLatency { size: usize },
+    /// The round trip latency of UDP messages of the given size.
+    UdpLatency { size: usize },
+    /// The rate TCP connections are set up at.
+    Connect,
+}
+
+/// A netbench case and what it measures.
+#[derive(Debug, Clone)]
+struct NetbenchCase {
+    case: BenchmarkCase,
+    workload: Workload,
+}
+
+/// Runs the native network benchmarks against a `NetbenchServer`.
+pub struct NetbenchAdapter {
+    logger: Arc<dyn LoggerPort>,
+    runtime: Handle,
+    server: String,
+    port: u16,
+    streams: usize,
+    message_sizes: Vec<usize>,
+    /// The cases, planned once on first use.
+    plan: OnceLock<Vec<NetbenchCase>>,
+}
+
+impl NetbenchAdapter {
+    /// Creates a new instance of `NetbenchAdapter` with the default streams
+    /// and message sizes. It must be created on a multi-threaded tokio
+    /// runtime, which runs the benchmarks.
+    ///
+    /// # Arguments
+    /// * `logger` - The logger used to report progress and errors.
+    /// * `server` - The host name or address of the server.
+    pub fn new(logger: Arc<dyn LoggerPort>, server: impl Into<String>) -> Self {
+        NetbenchAdapter {
+            logger,
+            runtime: Handle::current(),
+            server: server.into(),
+            port: DEFAULT_PORT,
+            streams: DEFAULT_STREAMS,
+            message_sizes: DEFAULT_MESSAGE_SIZES.to_vec(),
+            plan: OnceLock::new(),
+        }
+    }
+
+    /// Sets the port of the server.
+    pub fn with_port(mut self, port: u16) -> Self {
+        self.port = port;
+        self
+    }
+
+    /// Sets the number of parallel throughput streams.
+    pub fn with_streams(mut self, streams: usize) -> Self {
+        self.streams = streams.max(1);
+        self
+    }
+
+    /// Sets the sizes of latency messages in bytes; empty keeps the defaults.
+    pub fn with_message_sizes(mut self, sizes: Vec<usize>) -> Self {
+        if !sizes.is_empty() {
+            self.message_sizes = sizes;
+        }
+        self
+    }
+
+    /// Returns every case in the order they are run, planning them on first
+    /// use.
+    fn plan(&self) -> &[NetbenchCase] {
+        self.plan.get_or_init(|| self.plan_cases())
+    }
+
+    /// Plans every case in the order they are run.
+    fn plan_cases(&self) -> Vec<NetbenchCase> {
+        let mut cases = vec![NetbenchCase {
+            case: BenchmarkCase {
+                name: "tcp-throughput".to_string(),
+                threads: self.streams,
+                unit: "bit/s".to_string(),
+                direction: MetricDirection::HigherIsBetter,
+            },
+            workload: Workload::Throughput,
+        }];
+        for &size in &self.message_sizes {
+            let size = size.clamp(1, MAX_TCP_MESSAGE);
+            cases.push(NetbenchCase {
+                case: BenchmarkCase {
+                    name: format!("tcp-latency {}B", size),
+                    threads: 1,
+                    unit: "ns".to_string(),
+                    direction: MetricDirection::LowerIsBetter,
+                },
+                workload: Workload::TcpLatency { size },
+            });
+        }
+        for &size in &self.message_sizes {
+            let size = size.clamp(SEQUENCE_BYTES, MAX_UDP_MESSAGE);
+            cases.push(NetbenchCase {
+                case: BenchmarkCase {
+                    name: format!("udp-latency {}B", size),
+                    threads: 1,
+                    unit: "ns".to_string(),
+                    direction: MetricDirection::LowerIsBetter,
+                },
+                workload: Workload::UdpLatency { size },
+            });
+        }
+        cases.push(NetbenchCase {
+            case: BenchmarkCase {
+                name: "tcp-connect".to_string(),
+                threads: 1,
+                unit: "conn/s".to_string(),
+                direction: MetricDirection::HigherIsBetter,
+            },
+            workload: Workload::Connect,
+        });
+        cases
+    }
+
+    /// Connects to the server and sends the header of a mode.
+    async fn connect(&self, mode: u8, size: usize) -> io::Result<TcpStream> {
+        let mut stream = TcpStream::connect((self.server.as_str(), self.port)).await?;
+        stream.set_nodelay(true)?;
+        let mut header = [0; 5];
+        header[0] = mode;
+        header[1..].copy_from_slice(&(size as u32).to_be_bytes());
+        stream.write_all(&header).await?;
+        Ok(stream)
+    }
+
+    /// Streams data over parallel connections for `duration`, returning the
+    /// bits per second sent and received.
+    async fn throughput(&self, duration: Duration) -> io::Result<(f64, f64)> {
+        let mut streams = Vec::with_capacity(self.streams);
+        for _ in 0..self.streams {
+            streams.push(self.connect(MODE_THROUGHPUT, 0).await?);
+        }
+
+        let started = Instant::now();
+        let tasks: Vec<_> = streams
+            .into_iter()
+            .map(|mut stream| {
+                tokio::spawn(async move {
+                    let chunk = vec![0x5a; THROUGHPUT_CHUNK];
+                    let mut sent = 0u64;
+                    while started.elapsed() < duration {
+                        stream.write_all(&chunk).await?;
+                        sent += chunk.len() as u64;
+                    }
+                    stream.shutdown().await?;
+                    let received = stream.read_u64().await?;
+                    Ok::<_, io::Error>((sent, received))
+                })
+            })
+            .collect();
+
+        let (mut sent, mut received) = (0u64, 0u64);
+        for task in tasks {
+            let (stream_sent, stream_received) = task.await.map_err(io::Error::other)??;
+            sent += stream_sent;
+            received += stream_received;
+        }
+        let seconds = started.elapsed().as_secs_f64();
+        Ok((sent as f64 * 8.0 / seconds, received as f64 * 8.0 / seconds))
+    }
+
+    /// Sends TCP messages and waits for their echo for `duration`, returning
+    /// the latencies and the time the messages were sent over.
+    async fn tcp_latency(
+        &self,
+        size: usize,
+        duration: Duration,
+    ) -> io::Result<(LatencyHistogram, Duration)> {
+        let mut stream = self.connect(MODE_PING_PONG, size).await?;
+        let request = vec![0x5a; size];
+        let mut response = vec![0; size];
+        let mut histogram = LatencyHistogram::new();
+        let started = Instant::now();
+        while started.elapsed() < duration {
+            let sent = Instant::now();
+            stream.write_all(&request).await?;
+            stream.read_exact(&mut response).await?;
+            histogram.record(sent.elapsed());
+        }
+        Ok((histogram, started.elapsed()))
+    }
+
+    /// Sends UDP messages and waits for their echo for `duration`, returning
+    /// the latencies, the number of messages lost and the time the messages
+    /// were sent over, which includes waiting for the last echo.
+    async fn udp_latency(
+        &self,
+        size: usize,
+        duration: Duration,
+    ) -> io::Result<(LatencyHistogram, u64, Duration)> {
+        let server = tokio::net::lookup_host((self.server.as_str(), self.port))
+            .await?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "server has no address"))?;
+        let local: SocketAddr = if server.is_ipv4() {
+            (Ipv4Addr::UNSPECIFIED, 0).into()
+        } else {
+            (Ipv6Addr::UNSPECIFIED, 0).into()
+        };
+        let socket = UdpSocket::bind(local).await?;
+        socket.connect(server).await?;
+        let mut request = vec![0x5a; size];
+        let mut response = vec![0; MAX_UDP_MESSAGE];
+        let mut histogram = LatencyHistogram::new();
+        let mut lost = 0;
+        let started = Instant::now();
+        let mut sequence = 0u64;
+        while started.elapsed() < duration {
+            sequence += 1;
+            request[..SEQUENCE_BYTES].copy_from_slice(&sequence.to_be_bytes());
+            let sent = Instant::now();
+            socket.send(&request).await?;
+            // Skip late echoes of messages already counted as lost.
+            let echoed = timeout(UDP_TIMEOUT, async {
+                loop {
+                    let length = socket.recv(&mut response).await?;
+                    if length >= SEQUENCE_BYTES
+                        && response[..SEQUENCE_BYTES] == request[..SEQUENCE_BYTES]
+                    {
+                        return Ok::<_, io::Error>(());
+                    }
+                }
+            })
+            .await;
+            match echoed {
+                Ok(result) => {
+                    result?;
+                    histogram.record(sent.elapsed());
+                }
+                Err(_) => lost += 1,
+            }
+        }
+        let elapsed = started.elapsed();
+        if lost > 0 {
+            self.logger.log_debug(&format!(
+                "{} of {} UDP messages of {} bytes were lost",
+                lost, sequence, size
+            ));
+        }
+        Ok((histogram, lost, elapsed))
+    }
+
+    /// Sets up TCP connections one after another for `duration`, returning
+    /// the connections per second and their setup latencies.
+    async fn connect_rate(&self, duration: Duration) -> io::Result<(f64, LatencyHistogram)> {
+        let mut histogram = LatencyHistogram::new();
+        let mut connections = 0u64;
+        let started = Instant::now();
+        while started.elapsed() < duration {
+            let attempt = Instant::now();
+            let mut stream = self.connect(MODE_CONNECT, 0).await?;
+            stream.read_u8().await?;
+            histogram.record(attempt.elapsed());
+            connections += 1;
+            // Reset the connection rather than leave it in TIME_WAIT, which
+            // would exhaust the ephemeral ports.
+            stream.set_linger(Some(Duration::ZERO))?;
+        }
+        Ok((
+            connections as f64 / started.elapsed().as_secs_f64(),
+            histogram,
+        ))
+    }
+
+    /// Runs a workload for `duration`.
+    async fn measure(
+        &self,
+        workload: Workload,
+        duration: Duration,
+    ) -> io::Result<BenchmarkMeasurement> {
+        match workload {
+            Workload::Throughput => {
+                let (sent, received) = self.throughput(duration).await?;
+                Ok(BenchmarkMeasurement {
+                    value: received,
+                    details: vec![
+                        detail("sent", "bit/s", MetricDirection::HigherIsBetter, sent),
+                        detail(
+                            "received",
+                            "bit/s",
+                            MetricDirection::HigherIsBetter,
+                            received,
+                        ),
+                    ],
+                })
+            }
+            Workload::TcpLatency { size } => {
+                let (histogram, elapsed) = self.tcp_latency(size, duration).await?;
+                latency_measurement(&histogram, elapsed, Vec::new())
+            }
+            Workload::UdpLatency { size } => {
+                let (histogram, lost, elapsed) = self.udp_latency(size, duration).await?;
+                latency_measurement(
+                    &histogram,
+                    elapsed,
+                    vec![detail(
+                        "lost",
+                        "packets",
+                        MetricDirection::LowerIsBetter,
+                        lost as f64,
+                    )],
+                )
+            }
+            Workload::Connect => {
+                let (rate, histogram) = self.connect_rate(duration).await?;
+                let mut details = latency_details(&histogram);
+                details.insert(
+                    0,
+                    detail(
+                        "connections",
+                        "conn/s",
+                        MetricDirection::HigherIsBetter,
+                        rate,
+                    ),
+                );
+                Ok(BenchmarkMeasurement {
+                    value: rate,
+                    details,
+                })
+            }
+        }
+    }
+}
+
+impl BenchmarkPort for NetbenchAdapter {
+    /// Returns "netbench".
+    fn suite(&self) -> &str {
+        "netbench"
+    }
+
+    /// Lists the TCP throughput over the parallel streams, the TCP and UDP
+    /// latency for every message size, and the TCP connection rate.
+    fn cases(&self) -> Vec<BenchmarkCase> {
+        self.plan()
+            .iter()
+            .map(|planned| planned.case.clone())
+            .collect()
+    }
+
+    /// Runs a case on the tokio runtime the adapter was created on.
+    ///
+    /// Throughput cases report the bits per second received by the server,
+    /// latency cases the mean round trip in nanoseconds with its percentiles
+    /// as details, and the connect case the connections per second.
+    fn run_iteration(
+        &self,
+        case: &BenchmarkCase,
+        duration: Duration,
+    ) -> Result<BenchmarkMeasurement, String> {
+        let planned = self
+            .plan()
+            .iter()
+            .find(|planned| planned.case == *case)
+            .ok_or_else(|| format!("Unknown netbench benchmark {}", case.name))?;
+
+        let measure = || {
+            self.runtime
+                .block_on(self.measure(planned.workload, duration))
+        };
+        // Blocking on the runtime from one of its own workers needs the
+        // worker to hand its other tasks, e.g. a local server, to another
+        // thread first.
+        let result = if Handle::try_current().is_ok() {
+            tokio::task::block_in_place(measure)
+        } else {
+            measure()
+        };
+        result.map_err(|e| {
+            format!(
+                "netbench {} against {}:{}: {}",
+                case.name, self.server, self.port, e
+            )
+        })
+    }
+}
+
+/// Creates a detail.
+fn detail(name: &str, unit: &str, direction: MetricDirection, value: f64) -> BenchmarkDetail {
+    BenchmarkDetail {
+        name: name.to_string(),
+        unit: unit.to_string(),
+        direction,
+        value,
+    }
+}
+
+/// Returns the percentiles of a histogram as details.
+fn latency_details(histogram: &LatencyHistogram) -> Vec<BenchmarkDetail> {
+    let mut details = Vec::new();
+    for (name, percentile) in [
+        ("p50 latency", 50.0),
+        ("p99 latency", 99.0),
+        ("p99.9 latency", 99.9),
+    ] {
+        if let Some(value) = histogram.percentile_ns(percentile) {
+            details.push(detail(name, "ns", MetricDirection::LowerIsBetter, value));
+        }
+    }
+    if let Some(max) = histogram.max_ns() {
+        details.push(detail(
+            "max latency",
+            "ns",
+            MetricDirection::LowerIsBetter,
+            max as f64,
+        ));
+    }
+    details
+}
+
+/// Converts the round trips of a latency case into a measurement of their
+/// mean, with the round trips per second and the percentiles as details.
+///
+/// The rate is taken over `elapsed`, the time the round trips actually took,
+/// which overruns the requested duration by the last round trip.
+fn latency_measurement(
+    histogram: &LatencyHistogram,
+    elapsed: Duration,
+    extra: Vec<BenchmarkDetail>,
+) -> io::Result<BenchmarkMeasurement> {
+    let mean = histogram
+        .mean_ns()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::TimedOut, "no message was echoed back"))?;
+    let mut details = vec![detail(
+        "round trips",
+        "ops/s",
+        MetricDirection::HigherIsBetter,
+        histogram.count() as f64 / elapsed.as_secs_f64().max(f64::EPSILON),
+    )];
+    details.extend(latency_details(histogram));
+    details.extend(extra);
+    Ok(BenchmarkMeasurement {
+        value: mean,
+        details,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use common::adapters::capture_log_adapter::CapturingLogger;
+
+    use super::*;
+
+    #[test]
+    fn takes_the_round_trip_rate_over_the_elapsed_time() {
+        let mut histogram = LatencyHistogram::new();
+        for _ in 0..100 {
+            histogram.record(Duration::from_micros(20));
+        }
+
+        let measurement =
+            latency_measurement(&histogram, Duration::from_millis(1250), Vec::new()).unwrap();
+        let round_trips = &measurement.details[0];
+        assert_eq!(round_trips.name, "round trips");
+        assert_eq!(round_trips.value, 80.0);
+
+        let e =
+            latency_measurement(&LatencyHistogram::new(), Duration::ZERO, Vec::new()).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::TimedOut);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn runs_every_case_against_a_loopback_server() {
+        let logger = Arc::new(CapturingLogger::new());
+        let server = NetbenchServer::bind(logger.clone(), "127.0.0.1", 0)
+            .await
+            .unwrap();
+        let port = server.port();
+        let serving = tokio::spawn(server.run());
+
+        let adapter = NetbenchAdapter::new(logger, "127.0.0.1")
+            .with_port(port)
+            .with_streams(2)
+            .with_message_sizes(vec![64]);
+        let names = adapter
+            .cases()
+            .iter()
+            .map(|case| case.name.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![
+                "tcp-throughput",
+                "tcp-latency 64B",
+                "udp-latency 64B",
+                "tcp-connect"
+            ]
+        );
+
+        for case in adapter.cases() {
+            let measurement = adapter
+                .run_iteration(&case, Duration::from_millis(100))
+                .unwrap();
+            assert!(measurement.value > 0.0, "{}: {:?}", case.name, measurement);
+            if case.unit == "ns" {
+                let round_trips = &measurement.details[0];
+                assert_eq!(round_trips.name, "round trips");
+                assert!(round_trips.value > 0.0, "{}", case.name);
+            }
+        }
+        serving.abort();
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn reports_an_unreachable_server() {
+        // Bind and drop a listener to find a port nothing listens on.
+        let port = TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let adapter =
+            NetbenchAdapter::new(Arc::new(CapturingLogger::new()), "127.0.0.1").with_port(port);
+
+        let case = adapter.cases().remove(0);
+        let e = adapter
+            .run_iteration(&case, Duration::from_millis(100))
+            .unwrap_err();
+        assert!(
+            e.starts_with(&format!(
+                "netbench tcp-throughput against 127.0.0.1:{}",
+                port
+            )),
+            "{}",
+            e
+        );
+    }
+}
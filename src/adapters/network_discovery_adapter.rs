@@ -0,0 +1,295 @@
+//! Network Discovery Adapter
+//!
+//! This module reads the network interfaces under `/sys/class/net` and the
+//! RDMA devices under `/sys/class/infiniband`.
+
+use crate::adapters::discovery_adapter::SysfsReader;
+use crate::adapters::pci_discovery_adapter::device_pci_address;
+use crate::domain::inventory::{NetworkInterface, NetworkInventory, RdmaDevice, RdmaPort};
+
+const NET_DIR: &str = "/sys/class/net";
+const INFINIBAND_DIR: &str = "/sys/class/infiniband";
+const PCI_DEVICES_DIR: &str = "/sys/bus/pci/devices";
+
+/// Discovers the network interfaces and RDMA devices.
+///
+/// # Arguments
+/// * `reader` - The reader used to access sysfs.
+///
+/// # Returns
+/// A `Result` containing the `NetworkInventory`, or an error if
+/// `/sys/class/net` is not available. Hosts without RDMA devices simply
+/// report none.
+pub fn discover_network(reader: &SysfsReader) -> Result<NetworkInventory, String> {
+    if !reader.path(NET_DIR).is_dir() {
+        return Err(format!("{} is not available", NET_DIR));
+    }
+
+    let interfaces = reader
+        .list_dir(NET_DIR)
+        .into_iter()
+        .map(|name| read_interface(reader, name))
+        .collect();
+
+    let rdma_devices = reader
+        .list_dir(INFINIBAND_DIR)
+        .into_iter()
+        .map(|name| read_rdma_device(reader, name))
+        .collect();
+
+    Ok(NetworkInventory {
+        interfaces,
+        rdma_devices,
+    })
+}
+
+/// Reads a network interface.
+fn read_interface(reader: &SysfsReader, name: String) -> NetworkInterface {
+    let dir = format!("{}/{}", NET_DIR, name);
+    let read = |path: &str| reader.read_string(format!("{}/{}", dir, path));
+
+    // Virtual interfaces such as lo, bridges and veth pairs have no device.
+    let is_virtual = !reader.path(format!("{}/device", dir)).exists();
+    let pci_address = device_pci_address(reader, &format!("{}/device", dir));
+
+    // The NUMA node lives on the PCI function, which may be a parent of the
+    // device on buses such as virtio.
+    let numa_node = pci_address
+        .as_ref()
+        .and_then(|address| {
+            reader.read_string(format!("{}/{}/numa_node", PCI_DEVICES_DIR, address))
+        })
+        .or_else(|| read("device/numa_node"))
+        .and_then(|node| node.parse::<u32>().ok());
+
+    let queues = reader.list_dir(format!("{}/queues", dir));
+    let count = |prefix: &str| queues.iter().filter(|q| q.starts_with(prefix)).count();
+
+    NetworkInterface {
+        driver: reader.read_link_name(format!("{}/device/driver", dir)),
+        mac: read("address").filter(|mac| !mac.is_empty()),
+        mtu: read("mtu").and_then(|mtu| mtu.parse().ok()),
+        // Reading the speed fails or yields -1 without a link.
+        speed_mbps: read("speed")
+            .and_then(|speed| speed.parse::<i64>().ok())
+            .filter(|&speed| speed > 0)
+            .map(|speed| speed as u64),
+        duplex: read("duplex").filter(|duplex| duplex != "unknown"),
+        operstate: read("operstate").unwrap_or_else(|| "unknown".to_string()),
+        carrier_changes: read("carrier_changes").and_then(|changes| changes.parse().ok()),
+        numa_node,
+        rx_queues: count("rx-"),
+        tx_queues: count("tx-"),
+        pci_address,
+        is_virtual,
+        name,
+    }
+}
+
+/// Reads an RDMA device and its ports.
+fn read_rdma_device(reader: &SysfsReader, name: String) -> RdmaDevice {
+    let dir = format!("{}/{}", INFINIBAND_DIR, name);
+
+    let ports = reader
+        .list_dir(format!("{}/ports", dir))
+        .into_iter()
+        .filter_map(|port| {
+            let port_dir = format!("{}/ports/{}", dir, port);
+            let read = |path: &str| reader.read_string(format!("{}/{}", port_dir, path));
+            // States read "4: ACTIVE" and "5: LinkUp"; keep the names.
+            let state_name = |state: String| match state.split_once(':') {
+                Some((_, name)) => name.trim().to_string(),
+                None => state,
+            };
+            let rate = read("rate");
+            Some(RdmaPort {
+                port: port.parse().ok()?,
+                state: read("state").map(state_name),
+                physical_state: read("phys_state").map(state_name),
+                link_layer: read("link_layer"),
+                // Rates read "100 Gb/sec (4X EDR)".
+                rate_gbps: rate
+                    .as_ref()
+                    .and_then(|rate| rate.split_whitespace().next()?.parse().ok()),
+                rate,
+            })
+        })
+        .collect();
+
+    RdmaDevice {
+        firmware: reader.read_string(format!("{}/fw_ver", dir)),
+        node_guid: reader.read_string(format!("{}/node_guid", dir)),
+        pci_address: device_pci_address(reader, &format!("{}/device", dir)),
+        ports,
+        name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::adapters::discovery_adapter::write_tree;
+    use crate::application::inventory_checks::{check_network, InventoryExpectations};
+
+    /// Links a sysfs entry such as `device` to a directory below the root.
+    #[cfg(unix)]
+    fn link(root: &Path, path: &str, target: &str) {
+        std::fs::create_dir_all(root.join(target)).unwrap();
+        std::os::unix::fs::symlink(root.join(target), root.join(path)).unwrap();
+    }
+
+    /// Writes a tree with two ports of a ConnectX NIC, one of them without a
+    /// link, a virtio interface, the loopback interface and the RDMA device of
+    /// the first port.
+    #[cfg(unix)]
+    fn write_network_tree(root: &Path) {
+        let port0 = "sys/devices/pci0000:3a/0000:3a:00.0/0000:3b:00.0";
+        let port1 = "sys/devices/pci0000:3a/0000:3a:00.0/0000:3b:00.1";
+        let virtio = "sys/devices/pci0000:00/0000:00:03.0/virtio0";
+        write_tree(
+            root,
+            &[
+                ("sys/class/net/ens1f0np0/address", "b8:ce:f6:0a:1b:2c\n"),
+                ("sys/class/net/ens1f0np0/mtu", "9000\n"),
+                ("sys/class/net/ens1f0np0/speed", "100000\n"),
+                ("sys/class/net/ens1f0np0/duplex", "full\n"),
+                ("sys/class/net/ens1f0np0/operstate", "up\n"),
+                ("sys/class/net/ens1f0np0/carrier_changes", "2\n"),
+                ("sys/class/net/ens1f0np0/queues/rx-0/rps_cpus", "0\n"),
+                ("sys/class/net/ens1f0np0/queues/rx-1/rps_cpus", "0\n"),
+                ("sys/class/net/ens1f0np0/queues/tx-0/xps_cpus", "0\n"),
+                ("sys/class/net/ens1f1np1/speed", "-1\n"),
+                ("sys/class/net/ens1f1np1/duplex", "unknown\n"),
+                ("sys/class/net/ens1f1np1/operstate", "down\n"),
+                ("sys/class/net/eth0/speed", "-1\n"),
+                ("sys/class/net/eth0/operstate", "up\n"),
+                ("sys/class/net/lo/address", "00:00:00:00:00:00\n"),
+                ("sys/class/net/lo/mtu", "65536\n"),
+                ("sys/class/net/lo/operstate", "unknown\n"),
+                ("sys/bus/pci/devices/0000:3b:00.0/numa_node", "1\n"),
+                ("sys/bus/pci/devices/0000:00:03.0/numa_node", "-1\n"),
+                ("sys/class/infiniband/mlx5_0/fw_ver", "22.36.1010\n"),
+                (
+                    "sys/class/infiniband/mlx5_0/node_guid",
+                    "b8ce:f603:000a:1b2c\n",
+                ),
+                ("sys/class/infiniband/mlx5_0/ports/1/state", "4: ACTIVE\n"),
+                (
+                    "sys/class/infiniband/mlx5_0/ports/1/phys_state",
+                    "5: LinkUp\n",
+                ),
+                (
+                    "sys/class/infiniband/mlx5_0/ports/1/link_layer",
+                    "Ethernet\n",
+                ),
+                (
+                    "sys/class/infiniband/mlx5_0/ports/1/rate",
+                    "100 Gb/sec (4X EDR)\n",
+                ),
+            ],
+        );
+        link(root, "sys/class/net/ens1f0np0/device", port0);
+        link(root, "sys/class/net/ens1f1np1/device", port1);
+        link(root, "sys/class/net/eth0/device", virtio);
+        link(root, "sys/class/infiniband/mlx5_0/device", port0);
+        link(
+            root,
+            &format!("{}/driver", port0),
+            "sys/bus/pci/drivers/mlx5_core",
+        );
+        link(
+            root,
+            &format!("{}/driver", virtio),
+            "sys/bus/virtio/drivers/virtio_net",
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn reads_physical_virtual_and_rdma_devices() {
+        let root = tempfile::tempdir().unwrap();
+        write_network_tree(root.path());
+        let network = discover_network(&SysfsReader::new(root.path())).unwrap();
+
+        let names: Vec<&str> = network.interfaces.iter().map(|i| i.name.as_str()).collect();
+        assert_eq!(names, vec!["ens1f0np0", "ens1f1np1", "eth0", "lo"]);
+        let [up, down, virtio, lo] = &network.interfaces[..] else {
+            unreachable!();
+        };
+
+        assert_eq!(up.driver.as_deref(), Some("mlx5_core"));
+        assert_eq!(up.mac.as_deref(), Some("b8:ce:f6:0a:1b:2c"));
+        assert_eq!(up.mtu, Some(9000));
+        assert_eq!(up.speed_mbps, Some(100_000));
+        assert_eq!(up.duplex.as_deref(), Some("full"));
+        assert_eq!(up.carrier_changes, Some(2));
+        assert_eq!(up.pci_address.as_deref(), Some("0000:3b:00.0"));
+        assert_eq!(up.numa_node, Some(1));
+        assert_eq!((up.rx_queues, up.tx_queues), (2, 1));
+        assert!(!up.is_virtual);
+
+        // Without a carrier the speed reads -1 and the duplex "unknown".
+        assert_eq!(down.speed_mbps, None);
+        assert_eq!(down.duplex, None);
+        assert_eq!(down.operstate, "down");
+        assert_eq!(down.pci_address.as_deref(), Some("0000:3b:00.1"));
+        assert!(!down.is_virtual);
+
+        // A virtio interface is not virtual and sits below its PCI function.
+        assert_eq!(virtio.driver.as_deref(), Some("virtio_net"));
+        assert_eq!(virtio.pci_address.as_deref(), Some("0000:00:03.0"));
+        assert_eq!(virtio.numa_node, None);
+        assert!(!virtio.is_virtual);
+
+        assert!(lo.is_virtual);
+        assert_eq!(lo.pci_address, None);
+        assert_eq!(lo.speed_mbps, None);
+        assert_eq!(lo.operstate, "unknown");
+
+        assert_eq!(
+            network.rdma_devices,
+            vec![RdmaDevice {
+                name: "mlx5_0".to_string(),
+                firmware: Some("22.36.1010".to_string()),
+                node_guid: Some("b8ce:f603:000a:1b2c".to_string()),
+                pci_address: Some("0000:3b:00.0".to_string()),
+                ports: vec![RdmaPort {
+                    port: 1,
+                    state: Some("ACTIVE".to_string()),
+                    physical_state: Some("LinkUp".to_string()),
+                    link_layer: Some("Ethernet".to_string()),
+                    rate: Some("100 Gb/sec (4X EDR)".to_string()),
+                    rate_gbps: Some(100.0),
+                }],
+            }]
+        );
+
+        let mut expectations = InventoryExpectations::default();
+        expectations
+            .link_speeds_mbps
+            .insert("ens1f*".to_string(), 100_000);
+        expectations
+            .link_speeds_mbps
+            .insert("mlx5_0/1".to_string(), 200_000);
+        let messages: Vec<String> = check_network(&network, &expectations)
+            .into_iter()
+            .map(|finding| finding.message)
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "ens1f1np1 reports no link speed (state down), expected 100G",
+                "mlx5_0/1 linked at 100G, expected 200G",
+            ]
+        );
+    }
+
+    #[test]
+    fn requires_the_network_class() {
+        let root = tempfile::tempdir().unwrap();
+        let error = discover_network(&SysfsReader::new(root.path())).unwrap_err();
+        assert_eq!(error, "/sys/class/net is not available");
+    }
+}
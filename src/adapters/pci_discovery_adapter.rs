@@ -0,0 +1,524 @@
+//! PCI Discovery Adapter
+//!
+//! This module enumerates the PCI functions under `/sys/bus/pci/devices` and
+//! resolves vendor, device and class names from a `pci.ids` database when one
+//! is installed.
+
+use std::collections::HashMap;
+
+use crate::adapters::discovery_adapter::SysfsReader;
+use crate::domain::inventory::{PciDevice, PcieLink};
+
+const PCI_DEVICES_DIR: &str = "/sys/bus/pci/devices";
+
+/// The locations distributions install `pci.ids` to, in order of preference.
+pub const PCI_IDS_PATHS: [&str; 3] = [
+    "/usr/share/hwdata/pci.ids",
+    "/usr/share/misc/pci.ids",
+    "/usr/share/pci.ids",
+];
+
+/// Discovers the PCI functions of the host.
+///
+/// # Arguments
+/// * `reader` - The reader used to access sysfs.
+/// * `ids` - The name database, empty if no `pci.ids` is installed.
+///
+/// # Returns
+/// A `Result` containing the devices ordered by address, or an error if the
+/// PCI bus is not exposed in sysfs.
+pub fn discover_pci(reader: &SysfsReader, ids: &PciIds) -> Result<Vec<PciDevice>, String> {
+    if !reader.path(PCI_DEVICES_DIR).is_dir() {
+        return Err(format!("{} is not available", PCI_DEVICES_DIR));
+    }
+
+    let devices = reader
+        .list_dir(PCI_DEVICES_DIR)
+        .into_iter()
+        .filter_map(|address| {
+            let dir = format!("{}/{}", PCI_DEVICES_DIR, address);
+            let read_hex = |name: &str| {
+                reader
+                    .read_string(format!("{}/{}", dir, name))
+                    .and_then(|value| parse_hex(&value))
+            };
+
+            let vendor_id = read_hex("vendor")? as u16;
+            let device_id = read_hex("device")? as u16;
+            let class = read_hex("class").unwrap_or(0);
+            let subsystem_vendor_id = read_hex("subsystem_vendor").map(|id| id as u16);
+            let subsystem_device_id = read_hex("subsystem_device").map(|id| id as u16);
+
+            let subsystem_name = match (subsystem_vendor_id, subsystem_device_id) {
+                (Some(sub_vendor), Some(sub_device)) => {
+                    ids.subsystem_name(vendor_id, device_id, sub_vendor, sub_device)
+                }
+                _ => None,
+            };
+
+            Some(PciDevice {
+                vendor_name: ids.vendor_name(vendor_id),
+                device_name: ids.device_name(vendor_id, device_id),
+                subsystem_name,
+                class_name: ids.class_name(class),
+                driver: reader.read_link_name(format!("{}/driver", dir)),
+                // The kernel reports -1 when the device has no NUMA affinity.
+                numa_node: reader
+                    .read_string(format!("{}/numa_node", dir))
+                    .and_then(|node| node.parse::<u32>().ok()),
+                link: read_link(reader, &dir),
+                address,
+                vendor_id,
+                device_id,
+                subsystem_vendor_id,
+                subsystem_device_id,
+                class,
+            })
+        })
+        .collect();
+
+    Ok(devices)
+}
+
+/// Returns the PCI address of the device behind a sysfs device link.
+///
+/// Devices such as network interfaces or NVMe controllers may sit below
+/// another bus like virtio, so the resolved path is searched for the closest
+/// component that is a PCI address.
+pub fn device_pci_address(reader: &SysfsReader, path: &str) -> Option<String> {
+    let resolved = reader.canonicalize(path)?;
+    resolved
+        .iter()
+        .rev()
+        .map(|component| component.to_string_lossy())
+        .find(|component| is_pci_address(component))
+        .map(|component| component.to_string())
+}
+
+/// Returns true for a PCI address such as "0000:3b:00.0".
+fn is_pci_address(name: &str) -> bool {
+    let bytes = name.as_bytes();
+    bytes.len() == 12
+        && bytes[4] == b':'
+        && bytes[7] == b':'
+        && bytes[10] == b'.'
+        && name
+            .chars()
+            .enumerate()
+            .all(|(i, c)| matches!(i, 4 | 7 | 10) || c.is_ascii_hexdigit())
+}
+
+/// Reads the PCI Express link attributes of a device, if it has any.
+fn read_link(reader: &SysfsReader, dir: &str) -> Option<PcieLink> {
+    // Speeds read "8.0 GT/s PCIe" or "Unknown"; widths of 0 are unknown.
+    let speed = |name: &str| {
+        reader
+            .read_string(format!("{}/{}", dir, name))
+            .and_then(|value| value.split_whitespace().next()?.parse::<f64>().ok())
+    };
+    let width = |name: &str| {
+        reader
+            .read_u64(format!("{}/{}", dir, name))
+            .filter(|&lanes| lanes > 0 && lanes <= 32)
+            .map(|lanes| lanes as u8)
+    };
+
+    let link = PcieLink {
+        current_speed_gts: speed("current_link_speed"),
+        max_speed_gts: speed("max_link_speed"),
+        current_width: width("current_link_width"),
+        max_width: width("max_link_width"),
+    };
+    if link.current_speed_gts.is_none()
+        && link.max_speed_gts.is_none()
+        && link.current_width.is_none()
+        && link.max_width.is_none()
+    {
+        None
+    } else {
+        Some(link)
+    }
+}
+
+/// Parses a sysfs hex value such as "0x8086".
+fn parse_hex(value: &str) -> Option<u32> {
+    let value = value.trim();
+    u32::from_str_radix(value.strip_prefix("0x").unwrap_or(value), 16).ok()
+}
+
+/// A vendor from `pci.ids` with its devices.
+#[derive(Debug, Default)]
+struct PciVendor {
+    name: String,
+    devices: HashMap<u16, PciDeviceName>,
+}
+
+/// A device from `pci.ids` with its subsystems.
+#[derive(Debug, Default)]
+struct PciDeviceName {
+    name: String,
+    subsystems: HashMap<(u16, u16), String>,
+}
+
+/// A device class from `pci.ids` with its subclass names.
+#[derive(Debug, Default)]
+struct PciClass {
+    name: String,
+    subclasses: HashMap<u8, String>,
+}
+
+/// The vendor, device and class names of the `pci.ids` database.
+#[derive(Debug, Default)]
+pub struct PciIds {
+    vendors: HashMap<u16, PciVendor>,
+    classes: HashMap<u8, PciClass>,
+}
+
+impl PciIds {
+    /// Loads the first `pci.ids` found below the reader's root.
+    ///
+    /// # Returns
+    /// The parsed database, or an empty one if no `pci.ids` is installed, in
+    /// which case devices are reported by their numeric identifiers.
+    pub fn load(reader: &SysfsReader) -> Self {
+        PCI_IDS_PATHS
+            .iter()
+            .find_map(|path| std::fs::read(reader.path(path)).ok())
+            .map(|contents| Self::parse(&String::from_utf8_lossy(&contents)))
+            .unwrap_or_default()
+    }
+
+    /// Parses the contents of a `pci.ids` file.
+    ///
+    /// Vendors start at column zero, devices are indented by one tab and
+    /// subsystems by two. Classes follow in the same layout behind a `C`.
+    pub fn parse(contents: &str) -> Self {
+        let mut ids = PciIds::default();
+        let mut vendor: Option<u16> = None;
+        let mut device: Option<u16> = None;
+        let mut class: Option<u8> = None;
+
+        for line in contents.lines() {
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let depth = line.chars().take_while(|&c| c == '\t').count();
+            let entry = line.trim_start_matches('\t');
+
+            if depth == 0 {
+                if let Some(rest) = entry.strip_prefix("C ") {
+                    vendor = None;
+                    class = split_entry(rest).and_then(|(id, name)| {
+                        let id = u8::from_str_radix(id, 16).ok()?;
+                        ids.classes.insert(
+                            id,
+                            PciClass {
+                                name,
+                                ..Default::default()
+                            },
+                        );
+                        Some(id)
+                    });
+                } else {
+                    class = None;
+                    // Other top-level sections, e.g. "X" or "AA", are not vendors.
+                    vendor = split_entry(entry).and_then(|(id, name)| {
+                        let id = u16::from_str_radix(id, 16).ok()?;
+                        ids.vendors.insert(
+                            id,
+                            PciVendor {
+                                name,
+                                ..Default::default()
+                            },
+                        );
+                        Some(id)
+                    });
+                }
+                device = None;
+                continue;
+            }
+
+            if let Some(class) = class {
+                if depth == 1 {
+                    if let Some((id, name)) = split_entry(entry) {
+                        if let (Ok(id), Some(class)) =
+                            (u8::from_str_radix(id, 16), ids.classes.get_mut(&class))
+                        {
+                            class.subclasses.insert(id, name);
+                        }
+                    }
+                }
+                continue;
+            }
+
+            let Some(current) = vendor.and_then(|id| ids.vendors.get_mut(&id)) else {
+                continue;
+            };
+            match depth {
+                1 => {
+                    device = split_entry(entry).and_then(|(id, name)| {
+                        let id = u16::from_str_radix(id, 16).ok()?;
+                        current.devices.insert(
+                            id,
+                            PciDeviceName {
+                                name,
+                                ..Default::default()
+                            },
+                        );
+                        Some(id)
+                    });
+                }
+                2 => {
+                    let Some(device) = device.and_then(|id| current.devices.get_mut(&id)) else {
+                        continue;
+                    };
+                    // Subsystem lines read "<vendor> <device>  <name>".
+                    let Some((sub_vendor, rest)) = entry.split_once(' ') else {
+                        continue;
+                    };
+                    if let Some((sub_device, name)) = split_entry(rest) {
+                        if let (Ok(sub_vendor), Ok(sub_device)) = (
+                            u16::from_str_radix(sub_vendor, 16),
+                            u16::from_str_radix(sub_device, 16),
+                        ) {
+                            device.subsystems.insert((sub_vendor, sub_device), name);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        ids
+    }
+
+    /// Returns the name of a vendor.
+    pub fn vendor_name(&self, vendor: u16) -> Option<String> {
+        self.vendors.get(&vendor).map(|v| v.name.clone())
+    }
+
+    /// Returns the name of a device.
+    pub fn device_name(&self, vendor: u16, device: u16) -> Option<String> {
+        Some(
+            self.vendors
+                .get(&vendor)?
+                .devices
+                .get(&device)?
+                .name
+                .clone(),
+        )
+    }
+
+    /// Returns the name of a subsystem.
+    pub fn subsystem_name(
+        &self,
+        vendor: u16,
+        device: u16,
+        sub_vendor: u16,
+        sub_device: u16,
+    ) -> Option<String> {
+        self.vendors
+            .get(&vendor)?
+            .devices
+            .get(&device)?
+            .subsystems
+            .get(&(sub_vendor, sub_device))
+            .cloned()
+    }
+
+    /// Returns the most specific name of a 24 bit class code.
+    ///
+    /// Falls back to the built-in base class names when the class is not in
+    /// the database.
+    pub fn class_name(&self, class: u32) -> String {
+        let base = (class >> 16) as u8;
+        let subclass = (class >> 8) as u8;
+        if let Some(entry) = self.classes.get(&base) {
+            return entry
+                .subclasses
+                .get(&subclass)
+                .cloned()
+                .unwrap_or_else(|| entry.name.clone());
+        }
+        BASE_CLASSES
+            .iter()
+            .find(|(code, _)| *code == base)
+            .map(|(_, name)| name.to_string())
+            .unwrap_or_else(|| format!("Class {:06x}", class))
+    }
+}
+
+/// Splits an entry such as "8086  Intel Corporation" into identifier and name.
+fn split_entry(entry: &str) -> Option<(&str, String)> {
+    let (id, name) = entry.split_once("  ")?;
+    Some((id.trim(), name.trim().to_string()))
+}
+
+/// The PCI base class names, used when no `pci.ids` is installed.
+const BASE_CLASSES: &[(u8, &str)] = &[
+    (0x00, "Unclassified device"),
+    (0x01, "Mass storage controller"),
+    (0x02, "Network controller"),
+    (0x03, "Display controller"),
+    (0x04, "Multimedia controller"),
+    (0x05, "Memory controller"),
+    (0x06, "Bridge"),
+    (0x07, "Communication controller"),
+    (0x08, "Generic system peripheral"),
+    (0x09, "Input device controller"),
+    (0x0A, "Docking station"),
+    (0x0B, "Processor"),
+    (0x0C, "Serial bus controller"),
+    (0x0D, "Wireless controller"),
+    (0x0E, "Intelligent controller"),
+    (0x0F, "Satellite communications controller"),
+    (0x10, "Encryption controller"),
+    (0x11, "Signal processing controller"),
+    (0x12, "Processing accelerators"),
+    (0x13, "Non-Essential Instrumentation"),
+    (0x40, "Coprocessor"),
+    (0xFF, "Unassigned class"),
+];
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use common::adapters::capture_log_adapter::CapturingLogger;
+
+    use super::*;
+    use crate::adapters::discovery_adapter::{write_tree, SysfsDiscoveryAdapter};
+    use crate::application::inventory_checks::check_pci;
+    use crate::ports::discovery_port::DiscoveryPort;
+
+    /// An excerpt of `pci.ids` with vendor, device, subsystem and class lines.
+    const PCI_IDS: &str = "\
+# List of PCI ID's
+15b3  Mellanox Technologies
+\t101b  MT28908 Family [ConnectX-6]
+\t\t15b3 0007  Mellanox ConnectX-6 VPI 100Gb/s
+8086  Intel Corporation
+\t2030  Sky Lake-E PCI Express Root Port A
+\ta0ed  Tiger Lake-LP USB 3.2 Gen 2x1 xHCI Host Controller
+C 02  Network controller
+\t07  Infiniband controller
+C 0c  Serial bus controller
+\t03  USB controller
+\t\t30  XHCI
+";
+
+    #[test]
+    fn parses_vendors_devices_subsystems_and_classes() {
+        let ids = PciIds::parse(PCI_IDS);
+        assert_eq!(
+            ids.vendor_name(0x15b3).as_deref(),
+            Some("Mellanox Technologies")
+        );
+        assert_eq!(
+            ids.device_name(0x15b3, 0x101b).as_deref(),
+            Some("MT28908 Family [ConnectX-6]")
+        );
+        assert_eq!(
+            ids.subsystem_name(0x15b3, 0x101b, 0x15b3, 0x0007)
+                .as_deref(),
+            Some("Mellanox ConnectX-6 VPI 100Gb/s")
+        );
+        assert_eq!(ids.device_name(0x8086, 0x101b), None);
+        assert_eq!(ids.subsystem_name(0x8086, 0xa0ed, 0x15b3, 0x0007), None);
+        assert_eq!(ids.class_name(0x020700), "Infiniband controller");
+        assert_eq!(ids.class_name(0x0c0330), "USB controller");
+        assert_eq!(ids.class_name(0x028000), "Network controller");
+        // Classes missing from the database fall back to the base class.
+        assert_eq!(ids.class_name(0x060400), "Bridge");
+        assert_eq!(ids.class_name(0x200000), "Class 200000");
+        assert_eq!(
+            PciIds::default().class_name(0x010802),
+            "Mass storage controller"
+        );
+    }
+
+    #[test]
+    fn recognizes_pci_addresses() {
+        assert!(is_pci_address("0000:3b:00.0"));
+        assert!(!is_pci_address("10000:00:00.0"));
+        assert!(!is_pci_address("0000:3b:00"));
+        assert!(!is_pci_address("0000:3g:00.0"));
+        assert!(!is_pci_address("0000-3b-00.0"));
+        assert!(!is_pci_address("virtio0"));
+        assert!(!is_pci_address("pci0000:00"));
+    }
+
+    #[test]
+    fn flags_a_device_linked_below_its_capability() {
+        let root = tempfile::tempdir().unwrap();
+        let nic = "sys/bus/pci/devices/0000:3b:00.0";
+        let port = "sys/bus/pci/devices/0000:3a:00.0";
+        let usb = "sys/bus/pci/devices/0000:00:14.0";
+        write_tree(
+            root.path(),
+            &[
+                ("usr/share/hwdata/pci.ids", PCI_IDS),
+                (&format!("{}/vendor", nic), "0x15b3\n"),
+                (&format!("{}/device", nic), "0x101b\n"),
+                (&format!("{}/subsystem_vendor", nic), "0x15b3\n"),
+                (&format!("{}/subsystem_device", nic), "0x0007\n"),
+                (&format!("{}/class", nic), "0x020700\n"),
+                (&format!("{}/numa_node", nic), "0\n"),
+                (&format!("{}/current_link_speed", nic), "8.0 GT/s PCIe\n"),
+                (&format!("{}/max_link_speed", nic), "16.0 GT/s PCIe\n"),
+                (&format!("{}/current_link_width", nic), "8\n"),
+                (&format!("{}/max_link_width", nic), "16\n"),
+                // Root ports run at the speed of their device, so are not flagged.
+                (&format!("{}/vendor", port), "0x8086\n"),
+                (&format!("{}/device", port), "0x2030\n"),
+                (&format!("{}/class", port), "0x060400\n"),
+                (&format!("{}/current_link_speed", port), "8.0 GT/s PCIe\n"),
+                (&format!("{}/max_link_speed", port), "8.0 GT/s PCIe\n"),
+                (&format!("{}/current_link_width", port), "8\n"),
+                (&format!("{}/max_link_width", port), "16\n"),
+                (&format!("{}/vendor", usb), "0x8086\n"),
+                (&format!("{}/device", usb), "0xa0ed\n"),
+                (&format!("{}/class", usb), "0x0c0330\n"),
+                (&format!("{}/numa_node", usb), "-1\n"),
+                (&format!("{}/current_link_speed", usb), "Unknown\n"),
+                (&format!("{}/current_link_width", usb), "0\n"),
+            ],
+        );
+
+        let logger = Arc::new(CapturingLogger::new());
+        let adapter = SysfsDiscoveryAdapter::with_root(logger, root.path());
+        let devices = adapter.discover_pci().unwrap();
+
+        let addresses: Vec<&str> = devices.iter().map(|d| d.address.as_str()).collect();
+        assert_eq!(
+            addresses,
+            vec!["0000:00:14.0", "0000:3a:00.0", "0000:3b:00.0"]
+        );
+        let (usb, nic) = (&devices[0], &devices[2]);
+        assert_eq!(usb.class_name, "USB controller");
+        assert_eq!(usb.numa_node, None);
+        assert_eq!(usb.link, None);
+        assert_eq!(
+            nic.subsystem_name.as_deref(),
+            Some("Mellanox ConnectX-6 VPI 100Gb/s")
+        );
+        assert_eq!(nic.numa_node, Some(0));
+        assert_eq!(
+            nic.link,
+            Some(PcieLink {
+                current_speed_gts: Some(8.0),
+                max_speed_gts: Some(16.0),
+                current_width: Some(8),
+                max_width: Some(16),
+            })
+        );
+
+        let findings = check_pci(&devices);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(
+            findings[0].message,
+            "0000:3b:00.0 (Mellanox Technologies MT28908 Family [ConnectX-6]) link running \
+             at 8 GT/s x8, capable of 16 GT/s x16"
+        );
+    }
+}
@@ -0,0 +1,243 @@
+//! Plotters Chart Adapter
+//!
+//! This module provides an adapter that renders run history charts with the
+//! `plotters` crate, either as SVG documents or as PNG images.
+
+use std::path::Path;
+
+use chrono::{DateTime, Duration, Utc};
+use plotters::coord::Shift;
+use plotters::prelude::*;
+
+use crate::ports::chart_port::{ChartPort, ChartSeries};
+
+/// The width of a rendered chart in pixels.
+const CHART_WIDTH: u32 = 1024;
+
+/// The height of a single panel in pixels.
+const PANEL_HEIGHT: u32 = 280;
+
+/// The colors used for consecutive panels.
+const PANEL_COLORS: [RGBColor; 3] = [BLUE, RED, GREEN];
+
+/// Renders charts with `plotters`, stacking one panel per series vertically.
+pub struct PlottersChartAdapter;
+
+impl PlottersChartAdapter {
+    /// Creates a new instance of `PlottersChartAdapter`.
+    pub fn new() -> Self {
+        PlottersChartAdapter
+    }
+
+    /// Returns the size of a chart with the given number of panels.
+    fn size(panels: usize) -> (u32, u32) {
+        (CHART_WIDTH, 40 + PANEL_HEIGHT * panels.max(1) as u32)
+    }
+}
+
+impl Default for PlottersChartAdapter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ChartPort for PlottersChartAdapter {
+    fn render_svg(&self, title: &str, series: &[ChartSeries]) -> Result<String, String> {
+        let mut svg = String::new();
+        {
+            let root = SVGBackend::with_string(&mut svg, Self::size(series.len())).into_drawing_area();
+            draw_chart(&root, title, series)?;
+            root.present().map_err(|e| e.to_string())?;
+        }
+        Ok(svg)
+    }
+
+    fn render_png(&self, title: &str, series: &[ChartSeries], path: &Path) -> Result<(), String> {
+        let root = BitMapBackend::new(path, Self::size(series.len())).into_drawing_area();
+        draw_chart(&root, title, series)?;
+        root.present().map_err(|e| e.to_string())
+    }
+}
+
+/// Draws the title and one line chart panel per series onto `root`.
+fn draw_chart<DB: DrawingBackend>(
+    root: &DrawingArea<DB, Shift>,
+    title: &str,
+    series: &[ChartSeries],
+) -> Result<(), String> {
+    root.fill(&WHITE).map_err(|e| e.to_string())?;
+    let root = root
+        .titled(title, ("sans-serif", 24))
+        .map_err(|e| e.to_string())?;
+
+    if series.is_empty() {
+        return Ok(());
+    }
+
+    let panels = root.split_evenly((series.len(), 1));
+    for (index, (panel, series)) in panels.iter().zip(series).enumerate() {
+        draw_panel(panel, series, PANEL_COLORS[index % PANEL_COLORS.len()])?;
+    }
+    Ok(())
+}
+
+/// Draws a single series as a line chart with a time x-axis.
+fn draw_panel<DB: DrawingBackend>(
+    area: &DrawingArea<DB, Shift>,
+    series: &ChartSeries,
+    color: RGBColor,
+) -> Result<(), String> {
+    if series.points.is_empty() {
+        area.draw(&Text::new(
+            format!("{}: no samples recorded", series.title),
+            (20, 20),
+            ("sans-serif", 16),
+        ))
+        .map_err(|e| e.to_string())?;
+        return Ok(());
+    }
+
+    let (start, end) = time_range(&series.points);
+    let (low, high) = value_range(&series.points);
+
+    let mut chart = ChartBuilder::on(area)
+        .caption(&series.title, ("sans-serif", 18))
+        .margin(10)
+        .x_label_area_size(30)
+        .y_label_area_size(60)
+        .build_cartesian_2d(start..end, low..high)
+        .map_err(|e| e.to_string())?;
+
+    chart
+        .configure_mesh()
+        .x_labels(8)
+        .x_label_formatter(&|time: &DateTime<Utc>| time.format("%m-%d %H:%M:%S").to_string())
+        .y_desc(series.unit.as_str())
+        .draw()
+        .map_err(|e| e.to_string())?;
+
+    chart
+        .draw_series(LineSeries::new(series.points.iter().copied(), &color))
+        .map_err(|e| e.to_string())?;
+    chart
+        .draw_series(
+            series
+                .points
+                .iter()
+                .map(|&point| Circle::new(point, 2, color.filled())),
+        )
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Returns the time range covered by the points, widened to at least a second.
+fn time_range(points: &[(DateTime<Utc>, f64)]) -> (DateTime<Utc>, DateTime<Utc>) {
+    let start = points.iter().map(|(time, _)| *time).min().unwrap_or_else(Utc::now);
+    let end = points.iter().map(|(time, _)| *time).max().unwrap_or(start);
+    if end <= start {
+        (start - Duration::seconds(1), start + Duration::seconds(1))
+    } else {
+        (start, end)
+    }
+}
+
+/// Returns the value range covered by the points with a little headroom.
+fn value_range(points: &[(DateTime<Utc>, f64)]) -> (f64, f64) {
+    let low = points.iter().map(|(_, value)| *value).fold(f64::INFINITY, f64::min);
+    let high = points.iter().map(|(_, value)| *value).fold(f64::NEG_INFINITY, f64::max);
+    let padding = ((high - low) * 0.1).max(1.0);
+    ((low - padding).max(0.0), high + padding)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates a series of values sampled five seconds apart.
+    fn series(title: &str, unit: &str, values: &[f64]) -> ChartSeries {
+        let start = DateTime::from_timestamp(1_714_521_600, 0).unwrap();
+        ChartSeries {
+            title: title.to_string(),
+            unit: unit.to_string(),
+            points: values
+                .iter()
+                .enumerate()
+                .map(|(index, value)| (start + Duration::seconds(5 * index as i64), *value))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn renders_a_panel_per_series() {
+        let svg = PlottersChartAdapter::new()
+            .render_svg(
+                "stress run on node1",
+                &[
+                    series("CPU utilization", "%", &[12.5, 80.0, 97.5]),
+                    series("Bogo ops/s", "ops/s", &[1200.0, 1350.0, 1300.0]),
+                ],
+            )
+            .unwrap();
+
+        assert!(svg.starts_with("<svg"), "{}", svg);
+        assert!(svg.contains("height=\"600\""));
+        for label in ["stress run on node1", "CPU utilization", "Bogo ops/s", "ops/s"] {
+            assert!(svg.contains(label), "missing {}", label);
+        }
+        // The x-axis is labelled with the sample times.
+        assert!(svg.contains("05-01 00:00:00"));
+        // One line per panel, in the color of the panel, and a marker per sample.
+        assert!(svg.contains("stroke=\"#0000FF\" stroke-width=\"1\" points="));
+        assert!(svg.contains("stroke=\"#FF0000\" stroke-width=\"1\" points="));
+        assert_eq!(svg.matches("<circle").count(), 6);
+    }
+
+    #[test]
+    fn renders_a_series_without_samples_as_a_note() {
+        let svg = PlottersChartAdapter::new()
+            .render_svg(
+                "node1 history",
+                &[
+                    series("CPU utilization", "%", &[50.0]),
+                    series("Temperature", "°C", &[]),
+                ],
+            )
+            .unwrap();
+        assert!(svg.contains("Temperature: no samples recorded"));
+        assert_eq!(svg.matches("<circle").count(), 1);
+
+        let svg = PlottersChartAdapter::new()
+            .render_svg("node1 history", &[])
+            .unwrap();
+        assert!(svg.contains("node1 history"));
+        assert!(!svg.contains("<polyline"));
+        assert!(!svg.contains("<circle"));
+    }
+
+    #[test]
+    fn widens_the_ranges_of_a_single_point() {
+        let points = series("CPU utilization", "%", &[0.0]).points;
+        let (start, end) = time_range(&points);
+        assert_eq!(end - start, Duration::seconds(2));
+        assert_eq!(value_range(&points), (0.0, 1.0));
+        assert_eq!(
+            value_range(&series("Load", "", &[40.0, 60.0]).points),
+            (38.0, 62.0)
+        );
+    }
+
+    #[test]
+    fn writes_a_png() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("chart.png");
+        PlottersChartAdapter::new()
+            .render_png(
+                "node1 history",
+                &[series("CPU utilization", "%", &[10.0, 20.0])],
+                &path,
+            )
+            .unwrap();
+        let png = std::fs::read(&path).unwrap();
+        assert!(png.starts_with(b"\x89PNG"));
+    }
+}
@@ -0,0 +1,424 @@
+//! Report Adapter
+//!
+//! This module provides an adapter that turns stored runs and metric samples
+//! into charts and JSON documents. It implements the `ReportPort` used by the
+//! web server and offers the same charts as PNG files for the `report` command.
+
+use std::path::Path;
+use std::sync::Arc;
+
+use chrono::{DateTime, Duration, Utc};
+
+use common::ports::log_port::LoggerPort;
+use common::ports::report_port::{ReportPort, RunFilter};
+
+use crate::domain::baseline::{Baseline, BaselineTolerances};
+use crate::domain::record::RecordQuery;
+use crate::domain::rollup::{HostHistory, MetricRollup, Resolution, RetentionPolicy, RollupStats};
+use crate::ports::chart_port::{ChartPort, ChartSeries};
+use crate::ports::repository_port::{
+    BaselineRepository, RollupRepository, RunRepository, SampleRepository,
+};
+
+/// The number of runs listed when a filter sets no limit.
+const DEFAULT_RUN_LIMIT: usize = 50;
+
+/// Renders run and host history charts from the database.
+pub struct ReportAdapter {
+    logger: Arc<dyn LoggerPort>,
+    runs: Arc<RunRepository>,
+    samples: Arc<SampleRepository>,
+    rollups: Arc<RollupRepository>,
+    baselines: Arc<BaselineRepository>,
+    charts: Arc<dyn ChartPort>,
+    tolerances: BaselineTolerances,
+    retention: RetentionPolicy,
+    sample_interval: Duration,
+}
+
+impl ReportAdapter {
+    /// Creates a new instance of `ReportAdapter`.
+    ///
+    /// # Arguments
+    /// * `logger` - Logger implementation for logging messages.
+    /// * `runs` - The repository runs are read from.
+    /// * `samples` - The repository samples are read from.
+    /// * `rollups` - The repository sample rollups are read from.
+    /// * `baselines` - The repository baselines are read from.
+    /// * `charts` - The renderer used to draw the charts.
+    pub fn new(
+        logger: Arc<dyn LoggerPort>,
+        runs: Arc<RunRepository>,
+        samples: Arc<SampleRepository>,
+        rollups: Arc<RollupRepository>,
+        baselines: Arc<BaselineRepository>,
+        charts: Arc<dyn ChartPort>,
+    ) -> Self {
+        ReportAdapter {
+            logger,
+            runs,
+            samples,
+            rollups,
+            baselines,
+            charts,
+            tolerances: BaselineTolerances::default(),
+            retention: RetentionPolicy::default(),
+            sample_interval: Duration::seconds(5),
+        }
+    }
+
+    /// Sets the tolerances runs are compared against their baseline with.
+    pub fn with_tolerances(mut self, tolerances: BaselineTolerances) -> Self {
+        self.tolerances = tolerances;
+        self
+    }
+
+    /// Sets the retention policy and sample interval the resolution of host
+    /// histories is picked with.
+    pub fn with_retention(mut self, retention: RetentionPolicy, sample_interval: Duration) -> Self {
+        self.retention = retention;
+        self.sample_interval = sample_interval;
+        self
+    }
+
+    /// Builds the chart title and series of a run.
+    fn run_series(&self, run_id: &str) -> Result<Option<(String, Vec<ChartSeries>)>, String> {
+        let Some(run) = self.runs.get(run_id)? else {
+            return Ok(None);
+        };
+        let query = RecordQuery::new().with_host(&run.host).with_run_id(&run.id);
+        let points: Vec<MetricRollup> = self
+            .samples
+            .list(&query)?
+            .items
+            .iter()
+            .map(MetricRollup::from_sample)
+            .collect();
+
+        let title = format!(
+            "{} run {} on {} ({})",
+            run.kind,
+            run.id,
+            run.host,
+            run.started_at.format("%Y-%m-%d %H:%M:%S UTC")
+        );
+        Ok(Some((title, series_from_points(&points))))
+    }
+
+    /// Reads the history of a host at the resolution picked for the time
+    /// range, falling back to raw samples while no rollups of that resolution
+    /// exist yet.
+    ///
+    /// # Arguments
+    /// * `host` - The name of the host.
+    /// * `hours` - Limits the history to the last `hours` hours, if given.
+    fn host_history(&self, host: &str, hours: Option<u64>) -> Result<HostHistory, String> {
+        let now = Utc::now();
+        let since = hours.map(|hours| now - Duration::hours(hours as i64));
+        let mut query = RecordQuery::new().with_host(host);
+        if let Some(since) = since {
+            query = query.since(since);
+        }
+
+        let resolution = Resolution::select(since, now, now, &self.retention, self.sample_interval);
+        if resolution != Resolution::Raw {
+            let points = self
+                .rollups
+                .list(&query.clone().with_kind(resolution.name()))?
+                .items;
+            if !points.is_empty() {
+                return Ok(HostHistory {
+                    host: host.to_string(),
+                    resolution,
+                    points,
+                });
+            }
+        }
+        Ok(HostHistory {
+            host: host.to_string(),
+            resolution: Resolution::Raw,
+            points: self
+                .samples
+                .list(&query)?
+                .items
+                .iter()
+                .map(MetricRollup::from_sample)
+                .collect(),
+        })
+    }
+
+    /// Builds the chart title and series of a host's history.
+    fn host_series(
+        &self,
+        host: &str,
+        hours: Option<u64>,
+    ) -> Result<Option<(String, Vec<ChartSeries>)>, String> {
+        let history = self.host_history(host, hours)?;
+        if history.points.is_empty() {
+            return Ok(None);
+        }
+
+        let mut details = Vec::new();
+        if let Some(hours) = hours {
+            details.push(format!("last {} h", hours));
+        }
+        if history.resolution != Resolution::Raw {
+            details.push(format!("{} averages", history.resolution.name()));
+        }
+        let title = if details.is_empty() {
+            format!("{} history", host)
+        } else {
+            format!("{} history ({})", host, details.join(", "))
+        };
+        Ok(Some((title, series_from_points(&history.points))))
+    }
+
+    /// Renders the chart of a run as a PNG file.
+    ///
+    /// # Returns
+    /// `Ok(false)` if the run does not exist, `Ok(true)` once the file is written.
+    pub fn render_run_png(&self, run_id: &str, path: &Path) -> Result<bool, String> {
+        match self.run_series(run_id)? {
+            Some((title, series)) => {
+                self.charts.render_png(&title, &series, path)?;
+                self.logger.log_info(&format!(
+                    "Wrote chart of run {} to {}",
+                    run_id,
+                    path.display()
+                ));
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Renders the history of a host as a PNG file.
+    ///
+    /// # Returns
+    /// `Ok(false)` if the host has no samples, `Ok(true)` once the file is written.
+    pub fn render_host_png(
+        &self,
+        host: &str,
+        hours: Option<u64>,
+        path: &Path,
+    ) -> Result<bool, String> {
+        match self.host_series(host, hours)? {
+            Some((title, series)) => {
+                self.charts.render_png(&title, &series, path)?;
+                self.logger.log_info(&format!(
+                    "Wrote history chart of {} to {}",
+                    host,
+                    path.display()
+                ));
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
+
+impl ReportPort for ReportAdapter {
+    fn run_chart_svg(&self, run_id: &str) -> Result<Option<String>, String> {
+        match self.run_series(run_id)? {
+            Some((title, series)) => self.charts.render_svg(&title, &series).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn host_history_svg(&self, host: &str, hours: Option<u64>) -> Result<Option<String>, String> {
+        match self.host_series(host, hours)? {
+            Some((title, series)) => self.charts.render_svg(&title, &series).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn host_history_json(&self, host: &str, hours: Option<u64>) -> Result<Option<String>, String> {
+        let history = self.host_history(host, hours)?;
+        if history.points.is_empty() {
+            return Ok(None);
+        }
+        serde_json::to_string(&history)
+            .map(Some)
+            .map_err(|e| e.to_string())
+    }
+
+    fn run_baseline_json(&self, run_id: &str) -> Result<Option<String>, String> {
+        let Some(run) = self.runs.get(run_id)? else {
+            return Ok(None);
+        };
+        let Some(sku) = &run.sku else {
+            return Ok(None);
+        };
+        // The baseline of the run's kind and profile, else of its kind, else of any run.
+        for key in Baseline::keys_for_run(sku, &run) {
+            if let Some(baseline) = self.baselines.get(&key)? {
+                return serde_json::to_string(&baseline.compare(&run, &self.tolerances))
+                    .map(Some)
+                    .map_err(|e| e.to_string());
+            }
+        }
+        Ok(None)
+    }
+
+    fn baseline_json(&self, sku: &str) -> Result<Option<String>, String> {
+        let baselines = self
+            .baselines
+            .list(&RecordQuery::new().with_sku(sku))?
+            .items;
+        if baselines.is_empty() {
+            return Ok(None);
+        }
+        serde_json::to_string(&baselines)
+            .map(Some)
+            .map_err(|e| e.to_string())
+    }
+
+    fn runs_json(&self, filter: &RunFilter) -> Result<String, String> {
+        let mut query = RecordQuery::new().newest_first().page(
+            filter.offset.unwrap_or(0),
+            filter.limit.unwrap_or(DEFAULT_RUN_LIMIT),
+        );
+        if let Some(host) = &filter.host {
+            query = query.with_host(host);
+        }
+        if let Some(kind) = &filter.kind {
+            query = query.with_kind(kind);
+        }
+        if let Some(since) = &filter.since {
+            query = query.since(parse_time(since)?);
+        }
+        if let Some(until) = &filter.until {
+            query = query.until(parse_time(until)?);
+        }
+        serde_json::to_string(&self.runs.list(&query)?).map_err(|e| e.to_string())
+    }
+}
+
+/// Parses an RFC 3339 time such as "2024-05-01T00:00:00Z".
+fn parse_time(time: &str) -> Result<DateTime<Utc>, String> {
+    DateTime::parse_from_rfc3339(time)
+        .map(|time| time.with_timezone(&Utc))
+        .map_err(|e| format!("Invalid time {}: {}", time, e))
+}
+
+/// Splits history points into the CPU utilization, temperature and bogo ops/s
+/// series, charting the average of every rollup.
+fn series_from_points(points: &[MetricRollup]) -> Vec<ChartSeries> {
+    let series =
+        |title: &str, unit: &str, value: fn(&MetricRollup) -> Option<RollupStats>| ChartSeries {
+            title: title.to_string(),
+            unit: unit.to_string(),
+            points: points
+                .iter()
+                .filter_map(|point| value(point).map(|stats| (point.start, stats.avg)))
+                .collect(),
+        };
+
+    vec![
+        series("CPU utilization", "%", |s| s.cpu_utilization),
+        series("Temperature", "°C", |s| s.temperature_c),
+        series("Bogo ops/s", "ops/s", |s| s.bogo_ops_per_sec),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use common::adapters::capture_log_adapter::CapturingLogger;
+
+    use super::*;
+    use crate::adapters::memory_database_adapter::MemoryDatabaseAdapter;
+    use crate::adapters::plotters_chart_adapter::PlottersChartAdapter;
+    use crate::adapters::repository_adapter::TreeRepository;
+    use crate::domain::run::{MetricSample, RunRecord};
+
+    /// Creates a report adapter over an in-memory database, returning the
+    /// repositories runs and samples are stored in.
+    fn reports() -> (ReportAdapter, Arc<RunRepository>, Arc<SampleRepository>) {
+        let logger = Arc::new(CapturingLogger::new());
+        let db = MemoryDatabaseAdapter::new();
+        let runs: Arc<RunRepository> = Arc::new(TreeRepository::open(logger.clone(), &db).unwrap());
+        let samples: Arc<SampleRepository> =
+            Arc::new(TreeRepository::open(logger.clone(), &db).unwrap());
+        let reports = ReportAdapter::new(
+            logger.clone(),
+            runs.clone(),
+            samples.clone(),
+            Arc::new(TreeRepository::open(logger.clone(), &db).unwrap()),
+            Arc::new(TreeRepository::open(logger, &db).unwrap()),
+            Arc::new(PlottersChartAdapter::new()),
+        );
+        (reports, runs, samples)
+    }
+
+    /// Stores samples of a run taken a second apart.
+    fn save_samples(samples: &Arc<SampleRepository>, run: &RunRecord, values: &[(f64, f64)]) {
+        for (index, (cpu_utilization, bogo_ops_per_sec)) in values.iter().enumerate() {
+            samples
+                .save(&MetricSample {
+                    host: run.host.clone(),
+                    run_id: Some(run.id.clone()),
+                    timestamp: run.started_at + Duration::seconds(index as i64),
+                    sequence: index as u32 + 1,
+                    cpu_utilization: Some(*cpu_utilization),
+                    temperature_c: None,
+                    bogo_ops_per_sec: Some(*bogo_ops_per_sec),
+                })
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn renders_the_chart_of_a_run() {
+        let (reports, runs, samples) = reports();
+        let run = RunRecord::start("node1", "stress");
+        runs.save(&run).unwrap();
+        save_samples(
+            &samples,
+            &run,
+            &[(45.0, 1200.0), (90.0, 1350.0), (95.0, 1300.0)],
+        );
+
+        let svg = reports.run_chart_svg(&run.id).unwrap().unwrap();
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains(&format!("stress run {} on node1", run.id)));
+        assert!(svg.contains("CPU utilization"));
+        assert!(svg.contains("Bogo ops/s"));
+        assert!(svg.contains("Temperature: no samples recorded"));
+        assert_eq!(svg.matches("<circle").count(), 6);
+
+        assert_eq!(reports.run_chart_svg("missing").unwrap(), None);
+    }
+
+    #[test]
+    fn renders_a_run_without_samples() {
+        let (reports, runs, _) = reports();
+        let run = RunRecord::start("node1", "stress");
+        runs.save(&run).unwrap();
+
+        let svg = reports.run_chart_svg(&run.id).unwrap().unwrap();
+        for title in ["CPU utilization", "Temperature", "Bogo ops/s"] {
+            assert!(svg.contains(&format!("{}: no samples recorded", title)));
+        }
+        assert!(!svg.contains("<circle"));
+    }
+
+    #[test]
+    fn renders_the_history_of_a_host() {
+        let (reports, runs, samples) = reports();
+        let run = RunRecord::start("node1", "stress");
+        runs.save(&run).unwrap();
+        save_samples(&samples, &run, &[(45.0, 1200.0), (90.0, 1350.0)]);
+
+        let svg = reports.host_history_svg("node1", Some(1)).unwrap().unwrap();
+        assert!(svg.contains("node1 history (last 1 h)"));
+        assert_eq!(svg.matches("<circle").count(), 4);
+        let json = reports.host_history_json("node1", None).unwrap().unwrap();
+        let history: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(history["host"], "node1");
+        assert_eq!(history["points"].as_array().unwrap().len(), 2);
+
+        // Hosts without samples have no history to chart.
+        assert_eq!(reports.host_history_svg("node2", None).unwrap(), None);
+        assert_eq!(reports.host_history_json("node2", Some(1)).unwrap(), None);
+    }
+}
@@ -0,0 +1,590 @@
+//! Repository Adapter
+//!
+//! This module provides an adapter that stores the records of every entity
+//! in their own tree of the database, encoded with the versioned encoding of
+//! the record domain module. Records whose keys do not sort by time are also
+//! indexed by time in a tree of their own, named after theirs, e.g.
+//! "runs_by_time".
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use common::ports::log_port::LoggerPort;
+
+use crate::domain::record::{
+    decode_record, encode_record, time_index_key, time_key_range, Page, Record, RecordQuery,
+};
+use crate::ports::database_port::{DatabasePort, KeyValue, WriteBatch};
+use crate::ports::repository_port::RepositoryPort;
+
+/// Stores the records of an entity in their own tree of the database.
+pub struct TreeRepository<T> {
+    logger: Arc<dyn LoggerPort>,
+    tree: Arc<dyn DatabasePort>,
+    /// The keys of the records ordered by time, for records indexed by time.
+    index: Option<Arc<dyn DatabasePort>>,
+    record: PhantomData<fn() -> T>,
+}
+
+impl<T: Record> TreeRepository<T> {
+    /// Opens the repository in the entity's tree of a database.
+    ///
+    /// # Arguments
+    /// * `logger` - Logger implementation for logging messages.
+    /// * `db` - The database the tree is opened in.
+    pub fn open(logger: Arc<dyn LoggerPort>, db: &dyn DatabasePort) -> Result<Self, String> {
+        let tree = db
+            .open_tree(T::TREE)
+            .map_err(|e| format!("Failed to open the {} tree: {}", T::TREE, e))?;
+        let index = if T::INDEXED_BY_TIME {
+            let name = format!("{}_by_time", T::TREE);
+            let index = db
+                .open_tree(&name)
+                .map_err(|e| format!("Failed to open the {} tree: {}", name, e))?;
+            Some(index)
+        } else {
+            None
+        };
+        Ok(TreeRepository {
+            logger,
+            tree,
+            index,
+            record: PhantomData,
+        })
+    }
+
+    /// Decodes a stored record, naming it in errors.
+    fn decode(&self, key: &[u8], value: &[u8]) -> Result<T, String> {
+        decode_record(value).map_err(|e| {
+            format!(
+                "Failed to decode record {} in {}: {}",
+                String::from_utf8_lossy(key),
+                T::TREE,
+                e
+            )
+        })
+    }
+
+    /// Removes the index entries of the records stored under some keys into a
+    /// batch. Undecodable records have no entry to remove.
+    fn remove_index_entries(&self, batch: &mut WriteBatch, keys: &[String]) -> Result<(), String> {
+        for key in keys {
+            let Some(value) = self.tree.get(key.as_bytes()).map_err(|e| e.to_string())? else {
+                continue;
+            };
+            if let Ok(stored) = self.decode(key.as_bytes(), &value) {
+                batch.remove(time_index_key(&stored).as_bytes());
+            }
+        }
+        Ok(())
+    }
+
+    /// Indexes records by time before they are stored, replacing the entries
+    /// of the records stored under the same keys in case their time changed.
+    fn index_records(&self, records: &[T]) -> Result<(), String> {
+        let Some(index) = &self.index else {
+            return Ok(());
+        };
+        let mut batch = WriteBatch::new();
+        let keys: Vec<String> = records.iter().map(Record::key).collect();
+        self.remove_index_entries(&mut batch, &keys)?;
+        for (record, key) in records.iter().zip(&keys) {
+            batch.insert(time_index_key(record).as_bytes(), key.as_bytes());
+        }
+        index
+            .apply_batch(&batch)
+            .map_err(|e| format!("Failed to index records of {} by time: {}", T::TREE, e))
+    }
+
+    /// Reads the entries of the time index, rebuilding it from the records
+    /// unless it holds exactly one entry for every record, e.g. when the tree
+    /// of the records was written before the index existed.
+    ///
+    /// # Arguments
+    /// * `index` - The tree of the time index.
+    /// * `values` - The stored records by key.
+    fn index_entries(
+        &self,
+        index: &dyn DatabasePort,
+        values: &HashMap<Vec<u8>, Vec<u8>>,
+    ) -> Result<Vec<KeyValue>, String> {
+        let entries = index.scan_prefix(b"").map_err(|e| e.to_string())?;
+        let keys: HashSet<&[u8]> = entries.iter().map(|(_, key)| key.as_slice()).collect();
+        if keys.len() == entries.len()
+            && keys.len() == values.len()
+            && keys.iter().all(|key| values.contains_key(*key))
+        {
+            return Ok(entries);
+        }
+
+        let mut rebuilt = BTreeMap::new();
+        for (key, value) in values {
+            match self.decode(key, value) {
+                Ok(record) => {
+                    rebuilt.insert(time_index_key(&record).into_bytes(), key.clone());
+                }
+                Err(e) => self.logger.log_warn(&format!("Skipping record: {}", e)),
+            }
+        }
+        // Only write the differences, so that an undecodable record, which
+        // is never indexed, does not rewrite the index every time.
+        let stored: BTreeMap<Vec<u8>, Vec<u8>> = entries.into_iter().collect();
+        let mut batch = WriteBatch::new();
+        for (entry, key) in &stored {
+            if rebuilt.get(entry) != Some(key) {
+                batch.remove(entry);
+            }
+        }
+        for (entry, key) in &rebuilt {
+            if stored.get(entry) != Some(key) {
+                batch.insert(entry, key);
+            }
+        }
+        if !batch.is_empty() {
+            index
+                .apply_batch(&batch)
+                .map_err(|e| format!("Failed to rebuild the time index of {}: {}", T::TREE, e))?;
+            self.logger
+                .log_info(&format!("Rebuilt the time index of {}", T::TREE));
+        }
+        Ok(rebuilt.into_iter().collect())
+    }
+
+    /// Lists records in the order of the time index, so that only the records
+    /// of the page are decoded, along with the records that only match the
+    /// query if their fields do, or their time within its bounding milliseconds.
+    fn list_by_time(
+        &self,
+        index: &dyn DatabasePort,
+        query: &RecordQuery,
+    ) -> Result<Page<T>, String> {
+        let values: HashMap<Vec<u8>, Vec<u8>> = self
+            .tree
+            .scan_prefix(b"")
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .collect();
+        let (start, end) = time_key_range("", query);
+        let mut entries: Vec<(i64, Vec<u8>)> = self
+            .index_entries(index, &values)?
+            .into_iter()
+            .filter(|(entry, _)| {
+                entry.as_slice() >= start.as_bytes() && entry.as_slice() < end.as_bytes()
+            })
+            .filter_map(|(entry, key)| Some((entry_millis(&entry)?, key)))
+            .collect();
+        if query.newest_first {
+            entries.reverse();
+        }
+
+        let certain = |millis: i64| {
+            !query.filters_beyond_time()
+                && query
+                    .since
+                    .is_none_or(|since| millis > since.timestamp_millis())
+                && query
+                    .until
+                    .is_none_or(|until| millis < until.timestamp_millis())
+        };
+        let limit = query.limit.unwrap_or(usize::MAX);
+        let mut items = Vec::new();
+        let mut total = 0;
+        for (millis, key) in entries {
+            let Some(value) = values.get(&key) else {
+                continue;
+            };
+            let in_page = total >= query.offset && items.len() < limit;
+            if certain(millis) && !in_page {
+                total += 1;
+                continue;
+            }
+            match self.decode(&key, value) {
+                Ok(record) if query.matches(&record) => {
+                    if in_page {
+                        items.push(record);
+                    }
+                    total += 1;
+                }
+                Ok(_) => {}
+                Err(e) => self.logger.log_warn(&format!("Skipping record: {}", e)),
+            }
+        }
+        Ok(Page {
+            items,
+            total,
+            offset: query.offset,
+        })
+    }
+}
+
+/// Reads the milliseconds an entry of a time index starts with.
+fn entry_millis(entry: &[u8]) -> Option<i64> {
+    std::str::from_utf8(entry.get(..20)?).ok()?.parse().ok()
+}
+
+impl<T: Record> RepositoryPort<T> for TreeRepository<T> {
+    fn save(&self, record: &T) -> Result<(), String> {
+        self.index_records(std::slice::from_ref(record))?;
+        let key = record.key();
+        let value = encode_record(record)?;
+        self.tree
+            .insert(key.as_bytes(), &value)
+            .map_err(|e| format!("Failed to store record {} in {}: {}", key, T::TREE, e))?;
+        Ok(())
+    }
+
+    fn save_all(&self, records: &[T]) -> Result<(), String> {
+        self.index_records(records)?;
+        let mut batch = WriteBatch::new();
+        for record in records {
+            batch.insert(record.key().as_bytes(), &encode_record(record)?);
+        }
+        self.tree.apply_batch(&batch).map_err(|e| {
+            format!(
+                "Failed to store {} records in {}: {}",
+                records.len(),
+                T::TREE,
+                e
+            )
+        })
+    }
+
+    fn get(&self, key: &str) -> Result<Option<T>, String> {
+        match self.tree.get(key.as_bytes()).map_err(|e| e.to_string())? {
+            Some(value) => self.decode(key.as_bytes(), &value).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn remove_all(&self, keys: &[String]) -> Result<(), String> {
+        // The entries are removed after the records, so that no record is
+        // ever left without one.
+        let mut index_batch = WriteBatch::new();
+        if self.index.is_some() {
+            self.remove_index_entries(&mut index_batch, keys)?;
+        }
+        let mut batch = WriteBatch::new();
+        for key in keys {
+            batch.remove(key.as_bytes());
+        }
+        self.tree.apply_batch(&batch).map_err(|e| {
+            format!(
+                "Failed to remove {} records from {}: {}",
+                keys.len(),
+                T::TREE,
+                e
+            )
+        })?;
+        match &self.index {
+            Some(index) => index
+                .apply_batch(&index_batch)
+                .map_err(|e| format!("Failed to unindex records of {}: {}", T::TREE, e)),
+            None => Ok(()),
+        }
+    }
+
+    /// Records that fail to decode are logged and skipped.
+    fn list(&self, query: &RecordQuery) -> Result<Page<T>, String> {
+        // Scan only the keys that can match when the query narrows them down.
+        let pairs = match (T::key_range(query), &self.index) {
+            (Some((start, end)), _) => self.tree.scan_range(start.as_bytes(), end.as_bytes()),
+            (None, Some(index)) => return self.list_by_time(index.as_ref(), query),
+            (None, None) => self.tree.scan_prefix(b""),
+        }
+        .map_err(|e| e.to_string())?;
+
+        let mut records = Vec::with_capacity(pairs.len());
+        for (key, value) in pairs {
+            match self.decode(&key, &value) {
+                Ok(record) if query.matches(&record) => records.push(record),
+                Ok(_) => {}
+                Err(e) => self.logger.log_warn(&format!("Skipping record: {}", e)),
+            }
+        }
+        records.sort_by_key(|record| record.timestamp());
+        if query.newest_first {
+            records.reverse();
+        }
+
+        let total = records.len();
+        Ok(Page {
+            items: records
+                .into_iter()
+                .skip(query.offset)
+                .take(query.limit.unwrap_or(usize::MAX))
+                .collect(),
+            total,
+            offset: query.offset,
+        })
+    }
+
+    /// Owners are found by skipping from the first key of one owner to the
+    /// first key after all of its records, so only one key is read per owner.
+    fn key_owners(&self) -> Result<Vec<String>, String> {
+        let mut owners = Vec::new();
+        let mut start = Vec::new();
+        // Keys are UTF-8, which never contains the byte 0xFF.
+        while let Some((key, _)) = self
+            .tree
+            .first_in_range(&start, &[0xFF])
+            .map_err(|e| e.to_string())?
+        {
+            match key.iter().position(|&byte| byte == b'/') {
+                Some(slash) => {
+                    owners.push(String::from_utf8_lossy(&key[..slash]).into_owned());
+                    // '0' is the byte after '/', so it ends the keys of the owner.
+                    start = [&key[..slash], b"0"].concat();
+                }
+                // A key without an owner; skip to the key right after it.
+                None => start = [key.as_slice(), &[0]].concat(),
+            }
+        }
+        Ok(owners)
+    }
+
+    fn last_in_key_range(&self, query: &RecordQuery) -> Result<Option<T>, String> {
+        let Some((start, end)) = T::key_range(query) else {
+            return Ok(None);
+        };
+        match self
+            .tree
+            .last_in_range(start.as_bytes(), end.as_bytes())
+            .map_err(|e| e.to_string())?
+        {
+            Some((key, value)) => self.decode(&key, &value).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Utc};
+    use common::adapters::capture_log_adapter::CapturingLogger;
+    use common::ports::log_port::LogLevel;
+
+    use super::*;
+    use crate::adapters::memory_database_adapter::MemoryDatabaseAdapter;
+    use crate::adapters::sled_database_adapter::SledDatabaseAdapter;
+    use crate::domain::run::{MetricSample, RunRecord};
+
+    /// Creates a sample of a host at a millisecond.
+    fn sample(host: &str, millis: i64) -> MetricSample {
+        MetricSample {
+            host: host.to_string(),
+            run_id: None,
+            timestamp: DateTime::from_timestamp_millis(millis).unwrap(),
+            sequence: 1,
+            cpu_utilization: Some(50.0),
+            temperature_c: None,
+            bogo_ops_per_sec: None,
+        }
+    }
+
+    /// Creates a run of a host started at a millisecond.
+    fn run(host: &str, millis: i64) -> RunRecord {
+        let mut run = RunRecord::start(host, "stress");
+        run.started_at = DateTime::from_timestamp_millis(millis).unwrap();
+        run
+    }
+
+    /// Opens a database on every backend.
+    fn backends(
+        logger: Arc<CapturingLogger>,
+        dir: &tempfile::TempDir,
+    ) -> [Box<dyn DatabasePort>; 2] {
+        let path = dir.path().join("db");
+        [
+            Box::new(MemoryDatabaseAdapter::new()),
+            Box::new(SledDatabaseAdapter::new(path.to_str().unwrap(), logger).unwrap()),
+        ]
+    }
+
+    /// Runs a check against a repository on every backend.
+    fn on_every_backend(check: impl Fn(&TreeRepository<MetricSample>)) {
+        let logger = Arc::new(CapturingLogger::new());
+        let dir = tempfile::tempdir().unwrap();
+        for db in backends(logger.clone(), &dir) {
+            check(&TreeRepository::open(logger.clone(), db.as_ref()).unwrap());
+        }
+    }
+
+    /// Runs a check against a repository of runs on every backend, with the
+    /// database it is stored in and the messages it logs.
+    fn runs_on_every_backend(
+        check: impl Fn(&TreeRepository<RunRecord>, &dyn DatabasePort, &CapturingLogger),
+    ) {
+        let logger = Arc::new(CapturingLogger::new());
+        let dir = tempfile::tempdir().unwrap();
+        for db in backends(logger.clone(), &dir) {
+            let runs = TreeRepository::open(logger.clone(), db.as_ref()).unwrap();
+            check(&runs, db.as_ref(), &logger);
+            logger.clear();
+        }
+    }
+
+    /// Lists the start of the runs matching a query, with their total.
+    fn started(runs: &TreeRepository<RunRecord>, query: RecordQuery) -> (Vec<i64>, usize) {
+        let page = runs.list(&query).unwrap();
+        let millis = page
+            .items
+            .iter()
+            .map(|run| run.started_at.timestamp_millis())
+            .collect();
+        (millis, page.total)
+    }
+
+    /// Returns the number of entries of the time index of runs.
+    fn index_len(db: &dyn DatabasePort) -> usize {
+        db.open_tree("runs_by_time")
+            .unwrap()
+            .scan_prefix(b"")
+            .unwrap()
+            .len()
+    }
+
+    #[test]
+    fn finds_every_owner_of_keys() {
+        on_every_backend(|samples| {
+            assert!(samples.key_owners().unwrap().is_empty());
+            // "-" sorts before the "/" ending an owner and "0" right after it.
+            for host in ["node1", "node10", "node1-b"] {
+                for millis in [1_000, 2_000, 3_000] {
+                    samples.save(&sample(host, millis)).unwrap();
+                }
+            }
+            assert_eq!(
+                samples.key_owners().unwrap(),
+                vec!["node1-b", "node1", "node10"]
+            );
+        });
+    }
+
+    #[test]
+    fn reads_the_last_record_of_a_key_range() {
+        on_every_backend(|samples| {
+            for (host, millis) in [("node1", 1_000), ("node1", 3_000), ("node2", 5_000)] {
+                samples.save(&sample(host, millis)).unwrap();
+            }
+            let last = |query: RecordQuery| {
+                samples
+                    .last_in_key_range(&query)
+                    .unwrap()
+                    .map(|sample| sample.timestamp.timestamp_millis())
+            };
+            let at = |millis| DateTime::<Utc>::from_timestamp_millis(millis).unwrap();
+
+            assert_eq!(last(RecordQuery::new().with_host("node1")), Some(3_000));
+            assert_eq!(
+                last(RecordQuery::new().with_host("node1").until(at(2_999))),
+                Some(1_000)
+            );
+            assert_eq!(last(RecordQuery::new().with_host("node3")), None);
+            // Queries that do not narrow down the keys have no range.
+            assert_eq!(last(RecordQuery::new()), None);
+        });
+    }
+
+    #[test]
+    fn pages_runs_in_time_order() {
+        runs_on_every_backend(|runs, _, _| {
+            runs.save_all(&[run("node1", 3_000), run("node2", 1_000)])
+                .unwrap();
+            for (host, millis) in [("node1", 5_000), ("node2", 2_000), ("node1", 4_000)] {
+                runs.save(&run(host, millis)).unwrap();
+            }
+            let at = |millis| DateTime::<Utc>::from_timestamp_millis(millis).unwrap();
+
+            assert_eq!(
+                started(runs, RecordQuery::new()),
+                (vec![1_000, 2_000, 3_000, 4_000, 5_000], 5)
+            );
+            let mut page = RecordQuery::new().newest_first();
+            page.offset = 1;
+            page.limit = Some(2);
+            assert_eq!(started(runs, page), (vec![4_000, 3_000], 5));
+            assert_eq!(
+                started(runs, RecordQuery::new().with_host("node2")),
+                (vec![1_000, 2_000], 2)
+            );
+            // `until` is exclusive, also within its millisecond.
+            let until = at(4_000) + chrono::Duration::microseconds(1);
+            assert_eq!(
+                started(runs, RecordQuery::new().since(at(2_000)).until(until)),
+                (vec![2_000, 3_000, 4_000], 3)
+            );
+            assert_eq!(
+                started(runs, RecordQuery::new().since(at(2_001)).until(at(4_000))),
+                (vec![3_000], 1)
+            );
+        });
+    }
+
+    #[test]
+    fn decodes_only_the_runs_of_the_page() {
+        runs_on_every_backend(|runs, db, logger| {
+            for millis in [1_000, 2_000, 3_000] {
+                runs.save(&run("node1", millis)).unwrap();
+            }
+            // The newest run is indexed but cannot be decoded.
+            db.open_tree("runs")
+                .unwrap()
+                .insert(b"broken", b"{")
+                .unwrap();
+            db.open_tree("runs_by_time")
+                .unwrap()
+                .insert(b"00000000000000009000/broken", b"broken")
+                .unwrap();
+
+            let mut page = RecordQuery::new();
+            page.limit = Some(2);
+            assert_eq!(started(runs, page), (vec![1_000, 2_000], 4));
+            assert!(!logger.contains(LogLevel::Warn, "Skipping record"));
+
+            let mut page = RecordQuery::new().newest_first();
+            page.limit = Some(1);
+            assert_eq!(started(runs, page).0, vec![3_000]);
+            assert!(logger.contains(LogLevel::Warn, "broken"));
+        });
+    }
+
+    #[test]
+    fn rebuilds_an_index_missing_runs() {
+        runs_on_every_backend(|runs, db, logger| {
+            runs.save(&run("node1", 2_000)).unwrap();
+            // A run stored before the index existed.
+            let legacy = run("node1", 1_000);
+            db.open_tree("runs")
+                .unwrap()
+                .insert(legacy.id.as_bytes(), &encode_record(&legacy).unwrap())
+                .unwrap();
+
+            assert_eq!(started(runs, RecordQuery::new()), (vec![1_000, 2_000], 2));
+            assert!(logger.contains(LogLevel::Info, "Rebuilt the time index of runs"));
+            assert_eq!(index_len(db), 2);
+
+            logger.clear();
+            started(runs, RecordQuery::new());
+            assert!(!logger.contains(LogLevel::Info, "Rebuilt"));
+        });
+    }
+
+    #[test]
+    fn keeps_one_index_entry_per_run() {
+        runs_on_every_backend(|runs, db, logger| {
+            let mut moved = run("node1", 1_000);
+            runs.save(&moved).unwrap();
+            moved.started_at = DateTime::from_timestamp_millis(3_000).unwrap();
+            runs.save_all(&[moved.clone(), run("node1", 2_000)])
+                .unwrap();
+            assert_eq!(index_len(db), 2);
+            assert_eq!(started(runs, RecordQuery::new()), (vec![2_000, 3_000], 2));
+
+            runs.remove_all(&[moved.id]).unwrap();
+            assert_eq!(index_len(db), 1);
+            assert_eq!(started(runs, RecordQuery::new()), (vec![2_000], 1));
+            assert!(!logger.contains(LogLevel::Info, "Rebuilt"));
+        });
+    }
+}
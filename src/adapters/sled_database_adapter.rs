@@ -0,0 +1,200 @@
+use std::cell::RefCell;
+use std::error::Error;
+use std::sync::Arc;
+
+use sled::transaction::{
+    ConflictableTransactionError, TransactionError, TransactionalTree, UnabortableTransactionError,
+};
+use sled::{Batch, Db, IVec, Tree};
+
+use common::ports::log_port::LoggerPort;
+
+use crate::domain::database::DEFAULT_TREE;
+use crate::ports::database_port::{
+    BatchOp, DatabasePort, KeyValue, Transaction, TransactionFn, WriteBatch,
+};
+
+/// A struct that serves as an adapter for the `DatabasePort` trait using the Sled embedded database.
+pub struct SledDatabaseAdapter {
+    db: Db,     // The sled database instance.
+    tree: Tree, // The tree of the database the adapter reads and writes.
+}
+
+/// Implement the SledDatabaseAdapter struct as a port.
+/// This allows the adapter to be used as a port in the application, and it
+/// also provides a concrete implementation of the `DatabasePort` interface.
+/// The `DatabasePort` trait is defined in the `database_port.rs` file.
+impl SledDatabaseAdapter {
+    /// Opens a new database or creates it if it doesn't exist, acting as an adapter.
+    pub fn new(path: &str, logger: Arc<dyn LoggerPort>) -> Result<Self, Box<dyn Error>> {
+        let db = sled::open(path)?;
+        logger.log_info(&format!("Database opened at path: {}", path));
+        let tree = Tree::clone(&db);
+        Ok(SledDatabaseAdapter { db, tree })
+    }
+}
+
+/// Copies the pairs read from sled into owned buffers.
+fn to_pairs(
+    pairs: impl Iterator<Item = sled::Result<(IVec, IVec)>>,
+) -> Result<Vec<KeyValue>, Box<dyn Error>> {
+    let pairs = pairs
+        .map(|pair| pair.map(|(key, value)| (key.to_vec(), value.to_vec())))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(pairs)
+}
+
+/// The reads and writes of a sled transaction. Conflicts are remembered so
+/// that sled retries the transaction instead of aborting it.
+struct SledTransaction<'a> {
+    tx: &'a TransactionalTree,
+    conflict: RefCell<Option<UnabortableTransactionError>>,
+}
+
+impl SledTransaction<'_> {
+    /// Remembers the error of a transactional read or write.
+    fn check(
+        &self,
+        result: Result<Option<IVec>, UnabortableTransactionError>,
+    ) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
+        match result {
+            Ok(value) => Ok(value.map(|value| value.to_vec())),
+            Err(e) => {
+                let message = e.to_string();
+                self.conflict.replace(Some(e));
+                Err(message.into())
+            }
+        }
+    }
+}
+
+impl Transaction for SledTransaction<'_> {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
+        self.check(self.tx.get(key))
+    }
+
+    fn insert(&self, key: &[u8], value: &[u8]) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
+        self.check(self.tx.insert(key, value))
+    }
+}
+
+/// Implement the `DatabasePort` trait for the `SledDatabaseAdapter` struct.
+/// This allows the adapter to be used as a port in the application, and it
+/// also provides a concrete implementation of the `DatabasePort` interface.
+impl DatabasePort for SledDatabaseAdapter {
+    /// Inserts a key-value pair
+    fn insert(&self, key: &[u8], value: &[u8]) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
+        let previous_value = self.tree.insert(key, value)?;
+        self.tree.flush()?; // Ensure that changes are written to disk through the adapter.
+        Ok(previous_value.map(|value| value.to_vec()))
+    }
+
+    /// Retrieves a value
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
+        let value = self.tree.get(key)?;
+        Ok(value.map(|value| value.to_vec()))
+    }
+
+    /// Returns all key-value pairs whose key starts with `prefix`
+    fn scan_prefix(&self, prefix: &[u8]) -> Result<Vec<KeyValue>, Box<dyn Error>> {
+        to_pairs(self.tree.scan_prefix(prefix))
+    }
+
+    /// Returns all key-value pairs with a key in `start..end`
+    fn scan_range(&self, start: &[u8], end: &[u8]) -> Result<Vec<KeyValue>, Box<dyn Error>> {
+        if start >= end {
+            return Ok(Vec::new());
+        }
+        to_pairs(self.tree.range(start..end))
+    }
+
+    /// Returns the pair with the first key in `start..end`
+    fn first_in_range(&self, start: &[u8], end: &[u8]) -> Result<Option<KeyValue>, Box<dyn Error>> {
+        if start >= end {
+            return Ok(None);
+        }
+        Ok(to_pairs(self.tree.range(start..end).take(1))?.pop())
+    }
+
+    /// Returns the pair with the last key in `start..end`
+    fn last_in_range(&self, start: &[u8], end: &[u8]) -> Result<Option<KeyValue>, Box<dyn Error>> {
+        if start >= end {
+            return Ok(None);
+        }
+        Ok(to_pairs(self.tree.range(start..end).rev().take(1))?.pop())
+    }
+
+    /// Applies a batch of writes atomically, flushing once
+    fn apply_batch(&self, batch: &WriteBatch) -> Result<(), Box<dyn Error>> {
+        let mut sled_batch = Batch::default();
+        for op in batch.ops() {
+            match op {
+                BatchOp::Insert(key, value) => sled_batch.insert(key.as_slice(), value.as_slice()),
+                BatchOp::Remove(key) => sled_batch.remove(key.as_slice()),
+            }
+        }
+        self.tree.apply_batch(sled_batch)?;
+        self.tree.flush()?; // Ensure that changes are written to disk through the adapter.
+        Ok(())
+    }
+
+    /// Runs a transaction, which sled retries on conflicts
+    fn transaction(&self, body: &TransactionFn) -> Result<(), Box<dyn Error>> {
+        let result = self.tree.transaction(|tx| {
+            let transaction = SledTransaction {
+                tx,
+                conflict: RefCell::new(None),
+            };
+            match body(&transaction) {
+                Ok(()) => Ok(()),
+                Err(e) => match transaction.conflict.into_inner() {
+                    Some(conflict) => Err(conflict.into()),
+                    None => Err(ConflictableTransactionError::Abort(e.to_string())),
+                },
+            }
+        });
+        match result {
+            Ok(()) => {
+                self.tree.flush()?; // Ensure that changes are written to disk through the adapter.
+                Ok(())
+            }
+            Err(TransactionError::Abort(e)) => Err(e.into()),
+            Err(TransactionError::Storage(e)) => Err(Box::new(e)),
+        }
+    }
+
+    /// Opens a named tree of the database
+    fn open_tree(&self, name: &str) -> Result<Arc<dyn DatabasePort>, Box<dyn Error>> {
+        let tree = if name == DEFAULT_TREE {
+            Tree::clone(&self.db)
+        } else {
+            self.db.open_tree(name)?
+        };
+        Ok(Arc::new(SledDatabaseAdapter {
+            db: self.db.clone(),
+            tree,
+        }))
+    }
+
+    /// Returns the names of every tree of the database
+    fn tree_names(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        let default = self.db.name();
+        Ok(self
+            .db
+            .tree_names()
+            .iter()
+            .map(|name| {
+                if *name == default {
+                    DEFAULT_TREE.to_string()
+                } else {
+                    String::from_utf8_lossy(name).to_string()
+                }
+            })
+            .collect())
+    }
+
+    /// Returns the size of the database on disk
+    fn size_on_disk(&self) -> Result<u64, Box<dyn Error>> {
+        Ok(self.db.size_on_disk()?)
+    }
+}
@@ -0,0 +1,521 @@
+//! SMBIOS Adapter
+//!
+//! This module provides a native parser for the SMBIOS structure table the
+//! kernel exposes at `/sys/firmware/dmi/tables/DMI`. It decodes the BIOS,
+//! system and baseboard information (types 0, 1 and 2) and the memory arrays
+//! and memory devices (types 16 and 17) without shelling out to `dmidecode`.
+
+use crate::adapters::discovery_adapter::SysfsReader;
+use crate::domain::inventory::{MemoryArray, MemoryDevice, MemoryInventory, PlatformInfo};
+
+/// The path of the raw SMBIOS structure table.
+pub const DMI_TABLE_PATH: &str = "/sys/firmware/dmi/tables/DMI";
+
+/// SMBIOS structure types decoded by this module.
+const TYPE_BIOS: u8 = 0;
+const TYPE_SYSTEM: u8 = 1;
+const TYPE_BASEBOARD: u8 = 2;
+const TYPE_MEMORY_ARRAY: u8 = 16;
+const TYPE_MEMORY_DEVICE: u8 = 17;
+const TYPE_END_OF_TABLE: u8 = 127;
+
+/// A single structure of the SMBIOS table: the formatted area and its strings.
+#[derive(Debug, Clone)]
+pub struct SmbiosStructure {
+    /// The structure type.
+    pub kind: u8,
+    /// The structure handle.
+    pub handle: u16,
+    /// The formatted area, including the four byte header.
+    pub data: Vec<u8>,
+    /// The strings referenced by index from the formatted area.
+    pub strings: Vec<String>,
+}
+
+impl SmbiosStructure {
+    /// Reads a byte at `offset`, if the formatted area is long enough.
+    fn byte(&self, offset: usize) -> Option<u8> {
+        self.data.get(offset).copied()
+    }
+
+    /// Reads a little-endian word at `offset`.
+    fn word(&self, offset: usize) -> Option<u16> {
+        let bytes = self.data.get(offset..offset + 2)?;
+        Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    /// Reads a little-endian double word at `offset`.
+    fn dword(&self, offset: usize) -> Option<u32> {
+        let bytes = self.data.get(offset..offset + 4)?;
+        Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// Reads a little-endian quad word at `offset`.
+    fn qword(&self, offset: usize) -> Option<u64> {
+        let bytes = self.data.get(offset..offset + 8)?;
+        let mut buffer = [0u8; 8];
+        buffer.copy_from_slice(bytes);
+        Some(u64::from_le_bytes(buffer))
+    }
+
+    /// Resolves the string whose 1-based index is stored at `offset`.
+    ///
+    /// Blank strings and common vendor placeholders are treated as absent.
+    fn string(&self, offset: usize) -> Option<String> {
+        let index = self.byte(offset)? as usize;
+        let value = self.strings.get(index.checked_sub(1)?)?.trim();
+        let placeholder = [
+            "",
+            "Not Specified",
+            "NO DIMM",
+            "Unknown",
+            "To Be Filled By O.E.M.",
+        ];
+        if placeholder.iter().any(|p| value.eq_ignore_ascii_case(p)) {
+            None
+        } else {
+            Some(value.to_string())
+        }
+    }
+}
+
+/// Reads and parses the SMBIOS table below the reader's root.
+///
+/// # Returns
+/// The parsed structures, or an error if the table cannot be read. The table
+/// is only readable by root on most distributions.
+pub fn read_dmi_table(reader: &SysfsReader) -> Result<Vec<SmbiosStructure>, String> {
+    let path = reader.path(DMI_TABLE_PATH);
+    let table = std::fs::read(&path).map_err(|e| {
+        format!(
+            "Unable to read SMBIOS table at {} (root is usually required): {}",
+            path.display(),
+            e
+        )
+    })?;
+    Ok(parse_dmi_table(&table))
+}
+
+/// Splits a raw SMBIOS table into its structures.
+///
+/// Parsing stops at the end-of-table structure or at the first truncated
+/// structure, returning everything decoded up to that point.
+pub fn parse_dmi_table(table: &[u8]) -> Vec<SmbiosStructure> {
+    let mut structures = Vec::new();
+    let mut offset = 0;
+
+    while offset + 4 <= table.len() {
+        let kind = table[offset];
+        let length = table[offset + 1] as usize;
+        let handle = u16::from_le_bytes([table[offset + 2], table[offset + 3]]);
+        if length < 4 || offset + length > table.len() {
+            break;
+        }
+        let data = table[offset..offset + length].to_vec();
+
+        // The string set follows the formatted area and ends with two NULs.
+        let strings_start = offset + length;
+        let mut end = strings_start;
+        while end + 1 < table.len() && !(table[end] == 0 && table[end + 1] == 0) {
+            end += 1;
+        }
+        let strings = table[strings_start..end]
+            .split(|&byte| byte == 0)
+            .filter(|bytes| !bytes.is_empty())
+            .map(|bytes| String::from_utf8_lossy(bytes).to_string())
+            .collect();
+
+        structures.push(SmbiosStructure {
+            kind,
+            handle,
+            data,
+            strings,
+        });
+
+        if kind == TYPE_END_OF_TABLE {
+            break;
+        }
+        offset = end + 2;
+    }
+
+    structures
+}
+
+/// Decodes the BIOS, system and baseboard information.
+pub fn platform_info(structures: &[SmbiosStructure]) -> PlatformInfo {
+    let mut info = PlatformInfo::default();
+
+    for structure in structures {
+        match structure.kind {
+            TYPE_BIOS => {
+                info.bios_vendor = structure.string(0x04);
+                info.bios_version = structure.string(0x05);
+                info.bios_release_date = structure.string(0x08);
+            }
+            TYPE_SYSTEM => {
+                info.system_manufacturer = structure.string(0x04);
+                info.system_product = structure.string(0x05);
+                info.system_serial = structure.string(0x07);
+                info.system_uuid = structure.data.get(0x08..0x18).and_then(format_uuid);
+            }
+            TYPE_BASEBOARD if info.board_product.is_none() => {
+                info.board_manufacturer = structure.string(0x04);
+                info.board_product = structure.string(0x05);
+            }
+            _ => {}
+        }
+    }
+
+    info
+}
+
+/// Decodes the memory arrays and memory devices.
+pub fn memory_inventory(structures: &[SmbiosStructure]) -> MemoryInventory {
+    let mut inventory = MemoryInventory::default();
+
+    for structure in structures {
+        match structure.kind {
+            TYPE_MEMORY_ARRAY => inventory.arrays.push(memory_array(structure)),
+            TYPE_MEMORY_DEVICE => inventory.devices.push(memory_device(structure)),
+            _ => {}
+        }
+    }
+
+    inventory
+}
+
+/// Decodes a type 16 physical memory array.
+fn memory_array(structure: &SmbiosStructure) -> MemoryArray {
+    // 0x80000000 means the capacity is stored in the extended field in bytes.
+    let max_capacity_kib = match structure.dword(0x07) {
+        Some(0x8000_0000) => structure.qword(0x0F).map(|bytes| bytes / 1024),
+        Some(kib) => Some(kib as u64),
+        None => None,
+    };
+
+    MemoryArray {
+        handle: structure.handle,
+        location: lookup(ARRAY_LOCATIONS, structure.byte(0x04)),
+        usage: lookup(ARRAY_USES, structure.byte(0x05)),
+        error_correction: lookup(ARRAY_ERROR_CORRECTION, structure.byte(0x06)),
+        max_capacity_kib,
+        slots: structure.word(0x0D).unwrap_or(0),
+    }
+}
+
+/// Decodes a type 17 memory device.
+fn memory_device(structure: &SmbiosStructure) -> MemoryDevice {
+    // Size: 0 is an empty slot, 0xFFFF is unknown, 0x7FFF means the size is in
+    // the extended size field in MiB, and bit 15 selects KiB instead of MiB.
+    let size_mib = match structure.word(0x0C) {
+        None | Some(0) | Some(0xFFFF) => None,
+        Some(0x7FFF) => structure.dword(0x1C).map(|mib| (mib & 0x7FFF_FFFF) as u64),
+        Some(size) if size & 0x8000 != 0 => Some((size & 0x7FFF) as u64 / 1024),
+        Some(size) => Some(size as u64),
+    };
+
+    MemoryDevice {
+        handle: structure.handle,
+        array_handle: structure.word(0x04).unwrap_or(0xFFFF),
+        locator: structure.string(0x10).unwrap_or_default(),
+        bank_locator: structure.string(0x11).unwrap_or_default(),
+        size_mib,
+        memory_type: lookup(MEMORY_TYPES, structure.byte(0x12)),
+        form_factor: lookup(FORM_FACTORS, structure.byte(0x0E)),
+        speed_mts: speed(structure, 0x15, 0x54),
+        configured_speed_mts: speed(structure, 0x20, 0x58),
+        manufacturer: structure.string(0x17),
+        part_number: structure.string(0x1A),
+        serial: structure.string(0x18),
+        rank: structure
+            .byte(0x1B)
+            .map(|attributes| attributes & 0x0F)
+            .filter(|&rank| rank != 0),
+        data_width: structure
+            .word(0x0A)
+            .filter(|&width| width != 0xFFFF && width != 0),
+    }
+}
+
+/// Reads a speed word, following the extended field when the word is 0xFFFF.
+fn speed(structure: &SmbiosStructure, offset: usize, extended_offset: usize) -> Option<u32> {
+    match structure.word(offset)? {
+        0 => None,
+        0xFFFF => structure.dword(extended_offset).filter(|&speed| speed != 0),
+        speed => Some(speed as u32),
+    }
+}
+
+/// Formats a 16 byte SMBIOS UUID, which stores its first three fields little-endian.
+fn format_uuid(bytes: &[u8]) -> Option<String> {
+    if bytes.iter().all(|&b| b == 0x00) || bytes.iter().all(|&b| b == 0xFF) {
+        return None;
+    }
+    Some(format!(
+        "{:02x}{:02x}{:02x}{:02x}-{:02x}{:02x}-{:02x}{:02x}-{:02x}{:02x}-{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}",
+        bytes[3], bytes[2], bytes[1], bytes[0], bytes[5], bytes[4], bytes[7], bytes[6],
+        bytes[8], bytes[9], bytes[10], bytes[11], bytes[12], bytes[13], bytes[14], bytes[15]
+    ))
+}
+
+/// Looks up an enumerated SMBIOS value, falling back to its hex code.
+fn lookup(table: &[(u8, &str)], value: Option<u8>) -> String {
+    match value {
+        Some(value) => table
+            .iter()
+            .find(|(code, _)| *code == value)
+            .map(|(_, name)| name.to_string())
+            .unwrap_or_else(|| format!("Unknown (0x{:02X})", value)),
+        None => "Unknown".to_string(),
+    }
+}
+
+const ARRAY_LOCATIONS: &[(u8, &str)] = &[
+    (0x01, "Other"),
+    (0x02, "Unknown"),
+    (0x03, "System board or motherboard"),
+    (0x04, "ISA add-on card"),
+    (0x05, "EISA add-on card"),
+    (0x06, "PCI add-on card"),
+    (0x07, "MCA add-on card"),
+    (0x08, "PCMCIA add-on card"),
+    (0x09, "Proprietary add-on card"),
+    (0x0A, "NuBus"),
+];
+
+const ARRAY_USES: &[(u8, &str)] = &[
+    (0x01, "Other"),
+    (0x02, "Unknown"),
+    (0x03, "System memory"),
+    (0x04, "Video memory"),
+    (0x05, "Flash memory"),
+    (0x06, "Non-volatile RAM"),
+    (0x07, "Cache memory"),
+];
+
+const ARRAY_ERROR_CORRECTION: &[(u8, &str)] = &[
+    (0x01, "Other"),
+    (0x02, "Unknown"),
+    (0x03, "None"),
+    (0x04, "Parity"),
+    (0x05, "Single-bit ECC"),
+    (0x06, "Multi-bit ECC"),
+    (0x07, "CRC"),
+];
+
+const FORM_FACTORS: &[(u8, &str)] = &[
+    (0x01, "Other"),
+    (0x02, "Unknown"),
+    (0x03, "SIMM"),
+    (0x04, "SIP"),
+    (0x05, "Chip"),
+    (0x06, "DIP"),
+    (0x07, "ZIP"),
+    (0x08, "Proprietary Card"),
+    (0x09, "DIMM"),
+    (0x0A, "TSOP"),
+    (0x0B, "Row of chips"),
+    (0x0C, "RIMM"),
+    (0x0D, "SODIMM"),
+    (0x0E, "SRIMM"),
+    (0x0F, "FB-DIMM"),
+    (0x10, "Die"),
+];
+
+const MEMORY_TYPES: &[(u8, &str)] = &[
+    (0x01, "Other"),
+    (0x02, "Unknown"),
+    (0x03, "DRAM"),
+    (0x0F, "SDRAM"),
+    (0x12, "DDR"),
+    (0x13, "DDR2"),
+    (0x14, "DDR2 FB-DIMM"),
+    (0x18, "DDR3"),
+    (0x19, "FBD2"),
+    (0x1A, "DDR4"),
+    (0x1B, "LPDDR"),
+    (0x1C, "LPDDR2"),
+    (0x1D, "LPDDR3"),
+    (0x1E, "LPDDR4"),
+    (0x1F, "Logical non-volatile device"),
+    (0x20, "HBM"),
+    (0x21, "HBM2"),
+    (0x22, "DDR5"),
+    (0x23, "LPDDR5"),
+    (0x24, "HBM3"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns a reader rooted at a captured sysfs tree below `tests/fixtures/dmi`.
+    fn fixture(name: &str) -> SysfsReader {
+        SysfsReader::new(format!(
+            "{}/tests/fixtures/dmi/{}",
+            env!("CARGO_MANIFEST_DIR"),
+            name
+        ))
+    }
+
+    #[test]
+    fn decodes_the_platform_of_a_server() {
+        let structures = read_dmi_table(&fixture("server")).unwrap();
+        let info = platform_info(&structures);
+
+        assert_eq!(
+            info.bios_vendor.as_deref(),
+            Some("American Megatrends Inc.")
+        );
+        assert_eq!(info.bios_version.as_deref(), Some("3.4"));
+        assert_eq!(info.bios_release_date.as_deref(), Some("10/21/2020"));
+        assert_eq!(info.system_manufacturer.as_deref(), Some("Supermicro"));
+        assert_eq!(info.system_product.as_deref(), Some("SYS-2029U-TR4"));
+        assert_eq!(info.system_serial.as_deref(), Some("S292394X0A12345"));
+        assert_eq!(
+            info.system_uuid.as_deref(),
+            Some("4c4c4544-0034-4d10-8052-b4c04f4a4a32")
+        );
+        // The second baseboard structure is an add-on card.
+        assert_eq!(info.board_manufacturer.as_deref(), Some("Supermicro"));
+        assert_eq!(info.board_product.as_deref(), Some("X11DPU"));
+    }
+
+    #[test]
+    fn decodes_populated_extended_and_empty_slots() {
+        let structures = read_dmi_table(&fixture("server")).unwrap();
+        let memory = memory_inventory(&structures);
+
+        assert_eq!(memory.arrays.len(), 1);
+        let array = &memory.arrays[0];
+        assert_eq!(array.location, "System board or motherboard");
+        assert_eq!(array.error_correction, "Multi-bit ECC");
+        assert_eq!(array.max_capacity_kib, Some(3 * 1024 * 1024 * 1024));
+        assert_eq!(array.slots, 3);
+
+        assert_eq!(memory.devices.len(), 3);
+        assert_eq!(memory.populated().count(), 2);
+        assert_eq!(memory.total_mib(), 16384 + 131072);
+
+        let dimm = &memory.devices[0];
+        assert_eq!(dimm.array_handle, array.handle);
+        assert_eq!(dimm.locator, "P1-DIMMA1");
+        assert_eq!(dimm.bank_locator, "P0_Node0_Channel0_Dimm0");
+        assert_eq!(dimm.size_mib, Some(16384));
+        assert_eq!(dimm.memory_type, "DDR4");
+        assert_eq!(dimm.form_factor, "DIMM");
+        assert_eq!(dimm.speed_mts, Some(2933));
+        assert_eq!(dimm.configured_speed_mts, Some(2666));
+        assert_eq!(dimm.manufacturer.as_deref(), Some("Samsung"));
+        assert_eq!(dimm.part_number.as_deref(), Some("M393A2K43CB2-CVF"));
+        assert_eq!(dimm.serial.as_deref(), Some("0x40A1B2C3"));
+        assert_eq!(dimm.rank, Some(2));
+        assert_eq!(dimm.data_width, Some(64));
+
+        // The size and the configured speed are stored in the extended fields.
+        let extended = &memory.devices[1];
+        assert_eq!(extended.size_mib, Some(131072));
+        assert_eq!(extended.configured_speed_mts, Some(2666));
+        assert_eq!(extended.rank, Some(4));
+
+        let empty = &memory.devices[2];
+        assert_eq!(empty.locator, "P1-DIMMB1");
+        assert!(!empty.is_populated());
+        assert_eq!(empty.memory_type, "Unknown");
+        assert_eq!(empty.speed_mts, None);
+        assert_eq!(empty.manufacturer, None);
+        assert_eq!(empty.part_number, None);
+        assert_eq!(empty.serial, None);
+        assert_eq!(empty.rank, None);
+        assert_eq!(empty.data_width, None);
+    }
+
+    #[test]
+    fn treats_placeholders_and_short_structures_as_missing() {
+        let structures = read_dmi_table(&fixture("desktop")).unwrap();
+
+        let info = platform_info(&structures);
+        assert_eq!(info.bios_vendor, None);
+        assert_eq!(info.bios_version.as_deref(), Some("P1.90"));
+        assert_eq!(info.bios_release_date, None);
+        assert_eq!(info.system_manufacturer, None);
+        assert_eq!(info.system_product.as_deref(), Some("Z170 Extreme4"));
+        assert_eq!(info.system_serial, None);
+        // SMBIOS 2.0 system structures end before the UUID.
+        assert_eq!(info.system_uuid, None);
+        assert_eq!(info.board_manufacturer.as_deref(), Some("ASRock"));
+
+        let memory = memory_inventory(&structures);
+        assert_eq!(memory.arrays[0].error_correction, "None");
+        assert_eq!(memory.arrays[0].max_capacity_kib, Some(64 * 1024 * 1024));
+        assert_eq!(memory.total_mib(), 8192);
+
+        // SMBIOS 2.3 memory devices end at the part number.
+        let dimm = &memory.devices[0];
+        assert_eq!(dimm.speed_mts, Some(2133));
+        assert_eq!(dimm.configured_speed_mts, None);
+        assert_eq!(dimm.rank, None);
+        assert_eq!(dimm.manufacturer.as_deref(), Some("Kingston"));
+        assert_eq!(dimm.part_number.as_deref(), Some("KHX2133C14D4/8G"));
+
+        let empty = &memory.devices[1];
+        assert!(!empty.is_populated());
+        assert_eq!(empty.form_factor, "Unknown");
+        assert_eq!(empty.manufacturer, None);
+        assert_eq!(empty.serial, None);
+    }
+
+    #[test]
+    fn stops_at_a_truncated_structure() {
+        let reader = fixture("server");
+        let table = std::fs::read(reader.path(DMI_TABLE_PATH)).unwrap();
+        let complete = parse_dmi_table(&table);
+        assert_eq!(complete.last().map(|s| s.kind), Some(TYPE_END_OF_TABLE));
+
+        // Cut the table in the middle of the formatted area of the second DIMM.
+        let second_dimm = complete
+            .iter()
+            .filter(|s| s.kind == TYPE_MEMORY_DEVICE)
+            .nth(1)
+            .unwrap()
+            .handle;
+        let cut = table
+            .windows(4)
+            .position(|w| w[0] == TYPE_MEMORY_DEVICE && w[2..] == second_dimm.to_le_bytes())
+            .unwrap()
+            + 0x10;
+        let structures = parse_dmi_table(&table[..cut]);
+
+        let kinds = structures.iter().map(|s| s.kind).collect::<Vec<_>>();
+        assert_eq!(kinds, vec![0, 1, 2, 2, 16, 17]);
+        assert_eq!(memory_inventory(&structures).devices.len(), 1);
+    }
+
+    #[test]
+    fn reports_a_missing_table() {
+        let root = tempfile::tempdir().unwrap();
+        let e = read_dmi_table(&SysfsReader::new(root.path())).unwrap_err();
+        assert!(e.contains("Unable to read SMBIOS table"), "{}", e);
+        assert!(e.contains("sys/firmware/dmi/tables/DMI"), "{}", e);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn reports_an_unreadable_table() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = tempfile::tempdir().unwrap();
+        let reader = SysfsReader::new(root.path());
+        let path = reader.path(DMI_TABLE_PATH);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::copy(fixture("server").path(DMI_TABLE_PATH), &path).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o000)).unwrap();
+        if std::fs::read(&path).is_ok() {
+            // Root reads the table regardless of its mode, like the kernel's.
+            return;
+        }
+
+        let e = read_dmi_table(&reader).unwrap_err();
+        assert!(e.contains("root is usually required"), "{}", e);
+    }
+}
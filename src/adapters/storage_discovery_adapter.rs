@@ -0,0 +1,216 @@
+//! Storage Discovery Adapter
+//!
+//! This module reads the block devices under `/sys/block` and joins them with
+//! the mount table from `/proc/mounts` and `/proc/self/mountinfo`.
+
+use std::collections::HashMap;
+
+use crate::adapters::discovery_adapter::SysfsReader;
+use crate::domain::inventory::{
+    BlockDevice, Filesystem, NvmeNamespace, Partition, StorageInventory,
+};
+
+const BLOCK_DIR: &str = "/sys/block";
+const MOUNTS_PATH: &str = "/proc/mounts";
+const MOUNTINFO_PATH: &str = "/proc/self/mountinfo";
+
+/// The unit of the `size` and `start` attributes in sysfs.
+const SECTOR_SIZE: u64 = 512;
+
+/// Discovers the block devices and the filesystems mounted from them.
+///
+/// # Arguments
+/// * `reader` - The reader used to access procfs and sysfs.
+///
+/// # Returns
+/// A `Result` containing the `StorageInventory`, or an error if `/sys/block`
+/// is not available.
+pub fn discover_storage(reader: &SysfsReader) -> Result<StorageInventory, String> {
+    if !reader.path(BLOCK_DIR).is_dir() {
+        return Err(format!("{} is not available", BLOCK_DIR));
+    }
+
+    let devices: Vec<BlockDevice> = reader
+        .list_dir(BLOCK_DIR)
+        .into_iter()
+        .filter_map(|name| read_block_device(reader, name))
+        .collect();
+
+    // Map device numbers to the names of devices and partitions.
+    let mut names = HashMap::new();
+    for device in &devices {
+        names.insert(device.dev.clone(), device.name.clone());
+        for partition in &device.partitions {
+            names.insert(partition.dev.clone(), partition.name.clone());
+        }
+    }
+
+    let filesystems = read_mounts(reader)
+        .into_iter()
+        .filter_map(|(mut filesystem, dev)| {
+            filesystem.device = dev.and_then(|dev| names.get(&dev).cloned()).or_else(|| {
+                let name = filesystem.source.strip_prefix("/dev/")?;
+                names.values().find(|known| known.as_str() == name).cloned()
+            });
+            (filesystem.device.is_some() || filesystem.source.starts_with("/dev/"))
+                .then_some(filesystem)
+        })
+        .collect();
+
+    Ok(StorageInventory {
+        devices,
+        filesystems,
+    })
+}
+
+/// Reads a whole block device, skipping devices without media such as
+/// unused loop devices.
+fn read_block_device(reader: &SysfsReader, name: String) -> Option<BlockDevice> {
+    let dir = format!("{}/{}", BLOCK_DIR, name);
+    let size_bytes = reader.read_u64(format!("{}/size", dir))? * SECTOR_SIZE;
+    if size_bytes == 0 {
+        return None;
+    }
+    let read = |path: &str| reader.read_string(format!("{}/{}", dir, path));
+    let read_u32 = |path: &str| {
+        reader
+            .read_u64(format!("{}/{}", dir, path))
+            .map(|value| value as u32)
+    };
+
+    // The scheduler file lists all schedulers with the active one in brackets.
+    let scheduler = read("queue/scheduler").and_then(|schedulers| {
+        schedulers
+            .split_whitespace()
+            .find_map(|s| s.strip_prefix('[')?.strip_suffix(']').map(str::to_string))
+    });
+
+    let nvme = name.starts_with("nvme").then(|| NvmeNamespace {
+        controller: reader.read_link_name(format!("{}/device", dir)),
+        nsid: read_u32("nsid"),
+        transport: read("device/transport"),
+        pci_address: read("device/address"),
+    });
+
+    let partitions = reader
+        .list_dir(&dir)
+        .into_iter()
+        .filter(|entry| reader.path(format!("{}/{}/partition", dir, entry)).exists())
+        .filter_map(|entry| {
+            let part_dir = format!("{}/{}", dir, entry);
+            Some(Partition {
+                dev: reader.read_string(format!("{}/dev", part_dir))?,
+                start_sector: reader.read_u64(format!("{}/start", part_dir)),
+                size_bytes: reader.read_u64(format!("{}/size", part_dir))? * SECTOR_SIZE,
+                name: entry,
+            })
+        })
+        .collect();
+
+    Some(BlockDevice {
+        dev: read("dev").unwrap_or_default(),
+        model: read("device/model").filter(|model| !model.is_empty()),
+        serial: read("serial")
+            .or_else(|| read("device/serial"))
+            .filter(|serial| !serial.is_empty()),
+        firmware: read("device/firmware_rev").or_else(|| read("device/rev")),
+        size_bytes,
+        rotational: read_u32("queue/rotational") == Some(1),
+        removable: read_u32("removable") == Some(1),
+        scheduler,
+        nr_requests: read_u32("queue/nr_requests"),
+        queue_depth: read_u32("device/queue_depth"),
+        logical_block_size: read_u32("queue/logical_block_size"),
+        physical_block_size: read_u32("queue/physical_block_size"),
+        nvme,
+        partitions,
+        name,
+    })
+}
+
+/// Reads the mount table with the device number of each mount.
+///
+/// `/proc/mounts` provides the mounts as the user sees them; the device
+/// numbers come from `/proc/self/mountinfo`, matched by mount point. When
+/// `/proc/mounts` is missing the mount table is taken from mountinfo alone.
+fn read_mounts(reader: &SysfsReader) -> Vec<(Filesystem, Option<String>)> {
+    let mountinfo: Vec<(Filesystem, String)> = reader
+        .read_string(MOUNTINFO_PATH)
+        .map(|contents| contents.lines().filter_map(parse_mountinfo_line).collect())
+        .unwrap_or_default();
+
+    let Some(mounts) = reader.read_string(MOUNTS_PATH) else {
+        return mountinfo
+            .into_iter()
+            .map(|(filesystem, dev)| (filesystem, Some(dev)))
+            .collect();
+    };
+
+    let devs: HashMap<&str, &str> = mountinfo
+        .iter()
+        .map(|(filesystem, dev)| (filesystem.mount_point.as_str(), dev.as_str()))
+        .collect();
+
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 4 {
+                return None;
+            }
+            let filesystem = Filesystem {
+                source: unescape(fields[0]),
+                mount_point: unescape(fields[1]),
+                fs_type: fields[2].to_string(),
+                options: fields[3].to_string(),
+                device: None,
+            };
+            let dev = devs
+                .get(filesystem.mount_point.as_str())
+                .map(|dev| dev.to_string());
+            Some((filesystem, dev))
+        })
+        .collect()
+}
+
+/// Parses a mountinfo line such as
+/// `28 1 254:0 / / rw,relatime shared:1 - ext4 /dev/vda rw,discard`.
+fn parse_mountinfo_line(line: &str) -> Option<(Filesystem, String)> {
+    let (mount, filesystem) = line.split_once(" - ")?;
+    let mount: Vec<&str> = mount.split_whitespace().collect();
+    let filesystem: Vec<&str> = filesystem.split_whitespace().collect();
+    if mount.len() < 6 || filesystem.len() < 2 {
+        return None;
+    }
+    Some((
+        Filesystem {
+            source: unescape(filesystem[1]),
+            mount_point: unescape(mount[4]),
+            fs_type: filesystem[0].to_string(),
+            options: mount[5].to_string(),
+            device: None,
+        },
+        mount[2].to_string(),
+    ))
+}
+
+/// Decodes the octal escapes the kernel uses for spaces and other
+/// whitespace in mount paths, e.g. `\040`.
+fn unescape(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'\\' && i + 4 <= bytes.len() {
+            let octal = std::str::from_utf8(&bytes[i + 1..i + 4]).unwrap_or("");
+            if let Ok(byte) = u8::from_str_radix(octal, 8) {
+                out.push(byte);
+                i += 4;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
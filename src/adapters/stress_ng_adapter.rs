@@ -6,11 +6,17 @@ use std::process::{Command, Stdio};
 use std::sync::Arc;
 use std::{fs, io, str};
 
-use common::ports::log_port::LoggerPort;
+use chrono::Utc;
 
+use common::ports::log_port::{LogLevel, LoggerPort};
+
+use crate::domain::run::{MetricDirection, RunMetric};
 use crate::adapters::stress_ng_manager_adapter::StressNgArch;
 use crate::adapters::stress_ng_manager_adapter::{STRESS_NG_LINUX, STRESS_NG_MACOS, STRESS_NG_APPLE};
 
+/// The file the output of the last stress-ng run is captured in.
+pub const STRESS_NG_OUTPUT_FILE: &str = "stress_ng_output.txt";
+
 pub struct StressNgAdapter {
     logger: Arc<dyn LoggerPort>,
 }
@@ -167,7 +173,7 @@ impl<'a> StressNgAdapter {
         }
 
         // Define the output file path
-        let output_file_path = "stress_ng_output.txt";
+        let output_file_path = STRESS_NG_OUTPUT_FILE;
 
         // Create or open the file to capture the command's output
         let output_file = match fs::File::create(output_file_path) {
@@ -198,7 +204,11 @@ impl<'a> StressNgAdapter {
         // Execute the stress-ng command
         match command.spawn() {
             Ok(mut child) => {
-                logger.log_debug("stress-ng command spawned, waiting for it to finish");
+                logger.log_with(
+                    LogLevel::Debug,
+                    "stress-ng command spawned, waiting for it to finish",
+                    &[("pid", &child.id().to_string())],
+                );
 
                 match child.wait() {
                     Ok(_) => {
@@ -302,4 +312,43 @@ impl<'a> StressNgAdapter {
 
         Ok(())
     }
+
+    /// Parses the bogo ops/s of each stressor from `--metrics-brief` output.
+    ///
+    /// stress-ng prints one `metrc` line per stressor below a two line header:
+    ///
+    /// ```text
+    /// stress-ng: metrc: [14411] stressor       bogo ops real time  usr time  sys time   bogo ops/s     bogo ops/s
+    /// stress-ng: metrc: [14411]                           (secs)    (secs)    (secs)   (real time) (usr+sys time)
+    /// stress-ng: metrc: [14411] cpu              658683     30.00    239.46      0.06     21954.51        2749.98
+    /// ```
+    ///
+    /// # Arguments
+    /// * `output` - The captured stress-ng output.
+    ///
+    /// # Returns
+    /// One `RunMetric` per stressor holding its bogo ops/s in real time.
+    pub fn parse_bogo_ops(output: &str) -> Vec<RunMetric> {
+        let recorded_at = Utc::now();
+        output
+            .lines()
+            .filter(|line| line.contains("metrc:"))
+            .filter_map(|line| {
+                // Skip the "stress-ng: metrc: [pid]" preamble.
+                let fields: Vec<&str> = line.split_whitespace().skip(3).collect();
+                // stressor, bogo ops, real, usr, sys, ops/s (real), ops/s (usr+sys)
+                if fields.len() < 7 || fields[1].parse::<f64>().is_err() {
+                    return None;
+                }
+                let value = fields[5].parse::<f64>().ok()?;
+                Some(RunMetric {
+                    name: format!("{} bogo ops/s", fields[0]),
+                    value,
+                    unit: "ops/s".to_string(),
+                    direction: MetricDirection::HigherIsBetter,
+                    recorded_at,
+                })
+            })
+            .collect()
+    }
 }
@@ -0,0 +1,150 @@
+//! System Metrics Adapter
+//!
+//! This module provides an adapter that reads CPU utilization from
+//! `/proc/stat` and temperatures from the thermal and hwmon sysfs classes.
+
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+
+use chrono::Utc;
+
+use common::ports::log_port::LoggerPort;
+
+use crate::adapters::host_info_adapter;
+use crate::domain::run::MetricSample;
+use crate::ports::system_metrics_port::SystemMetricsPort;
+
+/// Aggregate CPU time counters read from the first line of `/proc/stat`.
+#[derive(Debug, Clone, Copy)]
+struct CpuTimes {
+    /// Jiffies spent idle or waiting for I/O.
+    idle: u64,
+    /// Jiffies spent in all states.
+    total: u64,
+}
+
+/// Reads CPU utilization and temperature sensors from procfs and sysfs.
+pub struct SystemMetricsAdapter {
+    logger: Arc<dyn LoggerPort>,
+    host: String,
+    previous: Mutex<Option<CpuTimes>>,
+    /// The sequence number of the next sample.
+    next_sequence: AtomicU32,
+}
+
+impl SystemMetricsAdapter {
+    /// Creates a new instance of `SystemMetricsAdapter`.
+    ///
+    /// The CPU counters are read once on creation so that the first sample
+    /// reports utilization since the adapter was created.
+    pub fn new(logger: Arc<dyn LoggerPort>) -> Self {
+        SystemMetricsAdapter {
+            logger,
+            host: host_info_adapter::hostname(),
+            previous: Mutex::new(read_cpu_times()),
+            next_sequence: AtomicU32::new(1),
+        }
+    }
+
+    /// Computes CPU utilization since the previous call.
+    fn cpu_utilization(&self) -> Option<f64> {
+        let current = read_cpu_times()?;
+        let mut previous = self.previous.lock().ok()?;
+        let utilization = previous.and_then(|prev| {
+            let total = current.total.checked_sub(prev.total)?;
+            let idle = current.idle.checked_sub(prev.idle)?;
+            if total == 0 {
+                return None;
+            }
+            Some(100.0 * (total - idle.min(total)) as f64 / total as f64)
+        });
+        *previous = Some(current);
+        utilization
+    }
+}
+
+impl SystemMetricsPort for SystemMetricsAdapter {
+    fn hostname(&self) -> String {
+        self.host.clone()
+    }
+
+    fn sample(&self, run_id: Option<&str>) -> Result<MetricSample, String> {
+        let sample = MetricSample {
+            host: self.host.clone(),
+            run_id: run_id.map(str::to_string),
+            timestamp: Utc::now(),
+            sequence: self.next_sequence.fetch_add(1, Ordering::Relaxed),
+            cpu_utilization: self.cpu_utilization(),
+            temperature_c: max_temperature(),
+            bogo_ops_per_sec: None,
+        };
+        self.logger
+            .log_trace(&format!("Collected metric sample: {:?}", sample));
+        Ok(sample)
+    }
+}
+
+/// Reads the aggregate CPU counters from `/proc/stat`.
+///
+/// Returns `None` on systems without procfs.
+fn read_cpu_times() -> Option<CpuTimes> {
+    let stat = fs::read_to_string("/proc/stat").ok()?;
+    let line = stat.lines().find(|line| line.starts_with("cpu "))?;
+    let values: Vec<u64> = line
+        .split_whitespace()
+        .skip(1)
+        .filter_map(|field| field.parse().ok())
+        .collect();
+    if values.len() < 4 {
+        return None;
+    }
+    // Fields are user, nice, system, idle, iowait, irq, softirq, steal, ...
+    // guest time is already accounted for in user and nice.
+    let idle = values[3] + values.get(4).copied().unwrap_or(0);
+    let total = values.iter().take(8).sum();
+    Some(CpuTimes { idle, total })
+}
+
+/// Returns the hottest reading across all thermal zones and hwmon sensors.
+fn max_temperature() -> Option<f64> {
+    let mut readings = Vec::new();
+
+    if let Ok(zones) = fs::read_dir("/sys/class/thermal") {
+        for zone in zones.flatten() {
+            if zone
+                .file_name()
+                .to_string_lossy()
+                .starts_with("thermal_zone")
+            {
+                readings.extend(read_millidegrees(&zone.path().join("temp")));
+            }
+        }
+    }
+
+    if let Ok(hwmons) = fs::read_dir("/sys/class/hwmon") {
+        for hwmon in hwmons.flatten() {
+            let Ok(entries) = fs::read_dir(hwmon.path()) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if name.starts_with("temp") && name.ends_with("_input") {
+                    readings.extend(read_millidegrees(&entry.path()));
+                }
+            }
+        }
+    }
+
+    readings.into_iter().reduce(f64::max)
+}
+
+/// Reads a sysfs temperature file expressed in millidegrees Celsius.
+fn read_millidegrees(path: &Path) -> Option<f64> {
+    let raw = fs::read_to_string(path).ok()?;
+    let millidegrees: i64 = raw.trim().parse().ok()?;
+    // Sensors report implausible values when they are absent or faulty.
+    let celsius = millidegrees as f64 / 1000.0;
+    (-40.0..=150.0).contains(&celsius).then_some(celsius)
+}
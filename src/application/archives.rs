@@ -0,0 +1,378 @@
+//! Archives
+//!
+//! This module bundles a run with its samples, the files it produced and the
+//! inventory of its host into a single compressed archive, and imports such
+//! archives into the database, e.g. on another node for analysis. Imports
+//! verify the checksums of the manifest before anything is stored.
+
+use std::fmt::Write as _;
+use std::path::Path;
+use std::sync::Arc;
+
+use chrono::Utc;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use common::ports::log_port::LoggerPort;
+
+use crate::domain::archive::{
+    ArchiveEntry, ArchiveManifest, ManifestEntry, ARCHIVE_FORMAT_VERSION, ARTIFACTS_DIR,
+    INVENTORY_PATH, MANIFEST_PATH, RUN_PATH, SAMPLES_PATH,
+};
+use crate::domain::inventory::HostInventory;
+use crate::domain::record::{Record, RecordQuery};
+use crate::domain::run::{MetricSample, RunArtifact, RunRecord};
+use crate::ports::archive_port::ArchivePort;
+use crate::ports::repository_port::{
+    ArtifactRepository, InventoryRepository, RunRepository, SampleRepository,
+};
+
+/// Archives runs and imports run archives.
+pub struct Archives {
+    logger: Arc<dyn LoggerPort>,
+    archive: Arc<dyn ArchivePort>,
+    runs: Arc<RunRepository>,
+    samples: Arc<SampleRepository>,
+    inventories: Arc<InventoryRepository>,
+    artifacts: Arc<ArtifactRepository>,
+}
+
+impl Archives {
+    /// Creates a new instance of `Archives`.
+    ///
+    /// # Arguments
+    /// * `logger` - Logger implementation for logging messages.
+    /// * `archive` - Writes and reads the archive files.
+    /// * `runs`, `samples`, `inventories`, `artifacts` - The repositories runs
+    ///   are archived from and imported into.
+    pub fn new(
+        logger: Arc<dyn LoggerPort>,
+        archive: Arc<dyn ArchivePort>,
+        runs: Arc<RunRepository>,
+        samples: Arc<SampleRepository>,
+        inventories: Arc<InventoryRepository>,
+        artifacts: Arc<ArtifactRepository>,
+    ) -> Self {
+        Archives {
+            logger,
+            archive,
+            runs,
+            samples,
+            inventories,
+            artifacts,
+        }
+    }
+
+    /// Writes a run, its samples and artifacts and the latest inventory of
+    /// its host at the end of the run to an archive.
+    ///
+    /// # Arguments
+    /// * `run_id` - The run to archive.
+    /// * `path` - The archive file, replaced if it exists.
+    ///
+    /// # Returns
+    /// A `Result` containing the manifest of the archive, or an error message.
+    pub fn archive(&self, run_id: &str, path: &Path) -> Result<ArchiveManifest, String> {
+        let run = self
+            .runs
+            .get(run_id)?
+            .ok_or_else(|| format!("No run with id {}", run_id))?;
+        let samples = self
+            .samples
+            .list(
+                &RecordQuery::new()
+                    .with_host(&run.host)
+                    .with_run_id(&run.id)
+                    .since(run.started_at),
+            )?
+            .items;
+        let inventory = self
+            .inventories
+            .list(
+                &RecordQuery::new()
+                    .with_host(&run.host)
+                    .until(run.finished_at.unwrap_or_else(Utc::now))
+                    .newest_first()
+                    .page(0, 1),
+            )?
+            .items
+            .into_iter()
+            .next();
+        let artifacts = self
+            .artifacts
+            .list(&RecordQuery::new().with_run_id(&run.id))?
+            .items;
+
+        let mut entries = vec![ArchiveEntry::new(RUN_PATH, to_json(&run)?)];
+        let mut lines = Vec::new();
+        for sample in &samples {
+            serde_json::to_writer(&mut lines, sample).map_err(|e| e.to_string())?;
+            lines.push(b'\n');
+        }
+        entries.push(ArchiveEntry::new(SAMPLES_PATH, lines));
+        if let Some(inventory) = &inventory {
+            entries.push(ArchiveEntry::new(INVENTORY_PATH, to_json(inventory)?));
+        }
+        for artifact in &artifacts {
+            entries.push(ArchiveEntry::new(
+                &format!("{}{}", ARTIFACTS_DIR, artifact.name),
+                artifact.content.clone(),
+            ));
+        }
+
+        let manifest = ArchiveManifest {
+            format_version: ARCHIVE_FORMAT_VERSION,
+            created_by: format!("commandant-rs {}", env!("CARGO_PKG_VERSION")),
+            created_at: Utc::now(),
+            run_id: run.id.clone(),
+            host: run.host.clone(),
+            kind: run.kind.clone(),
+            samples: samples.len(),
+            inventory: inventory.as_ref().map(HostInventory::snapshot_id),
+            artifacts: artifacts
+                .iter()
+                .map(|artifact| artifact.name.clone())
+                .collect(),
+            entries: entries.iter().map(ManifestEntry::describe).collect(),
+        };
+        // The manifest comes first so that it can be read without the rest.
+        entries.insert(0, ArchiveEntry::new(MANIFEST_PATH, to_json(&manifest)?));
+        self.archive.write_archive(path, &entries)?;
+
+        self.logger.log_info(&format!(
+            "Archived run {} with {} samples and {} artifacts to {}",
+            run.id,
+            manifest.samples,
+            manifest.artifacts.len(),
+            path.display()
+        ));
+        Ok(manifest)
+    }
+
+    /// Imports the run of an archive with its samples, artifacts and
+    /// inventory. Nothing is stored unless every checksum matches, and runs
+    /// that are already in the database are not imported again. Samples are
+    /// keyed by host and time, so an archived sample may have the key of a
+    /// different local sample, e.g. when host names clash: such samples are
+    /// skipped and reported, keeping the local ones.
+    ///
+    /// # Returns
+    /// A `Result` containing the manifest of the archive, or an error message.
+    pub fn import(&self, path: &Path) -> Result<ArchiveManifest, String> {
+        let invalid = |e: String| format!("Invalid archive {}: {}", path.display(), e);
+        let mut entries = self.archive.read_archive(path)?;
+        let position = entries
+            .iter()
+            .position(|entry| entry.path == MANIFEST_PATH)
+            .ok_or_else(|| invalid(format!("{} is missing", MANIFEST_PATH)))?;
+        let manifest: ArchiveManifest = from_json(&entries.remove(position)).map_err(invalid)?;
+        manifest.verify(&entries).map_err(invalid)?;
+
+        let entry = |path: &str| entries.iter().find(|entry| entry.path == path);
+        let run: RunRecord = entry(RUN_PATH)
+            .ok_or_else(|| invalid(format!("{} is missing", RUN_PATH)))
+            .and_then(|entry| from_json(entry).map_err(invalid))?;
+        if self.runs.get(&run.id)?.is_some() {
+            return Err(format!("Run {} is already in the database", run.id));
+        }
+        let samples = match entry(SAMPLES_PATH) {
+            Some(entry) => String::from_utf8_lossy(&entry.data)
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(serde_json::from_str::<MetricSample>)
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| invalid(format!("{}: {}", SAMPLES_PATH, e)))?,
+            None => Vec::new(),
+        };
+        let inventory: Option<HostInventory> = entry(INVENTORY_PATH)
+            .map(|entry| from_json(entry).map_err(invalid))
+            .transpose()?;
+        // Artifacts keep the time the run finished, as their own is not archived.
+        let artifacts: Vec<RunArtifact> = entries
+            .iter()
+            .filter_map(|entry| {
+                let name = entry.path.strip_prefix(ARTIFACTS_DIR)?;
+                Some(RunArtifact {
+                    run_id: run.id.clone(),
+                    name: name.to_string(),
+                    created_at: run.finished_at.unwrap_or(run.started_at),
+                    content: entry.data.clone(),
+                })
+            })
+            .collect();
+
+        let mut new_samples = Vec::with_capacity(samples.len());
+        let mut conflicts = Vec::new();
+        for sample in &samples {
+            match self.samples.get(&sample.key())? {
+                None => new_samples.push(sample.clone()),
+                // Stored by an earlier, interrupted import of the run.
+                Some(local) if local == *sample => {}
+                Some(_) => conflicts.push(sample.key()),
+            }
+        }
+        if let Some(first) = conflicts.first() {
+            self.logger.log_warn(&format!(
+                "Skipped {} samples of run {} that differ from local samples with the same key, e.g. {}",
+                conflicts.len(),
+                run.id,
+                first
+            ));
+        }
+
+        // The run is stored last, so that an interrupted import can be retried.
+        self.samples.save_all(&new_samples)?;
+        if let Some(inventory) = &inventory {
+            self.inventories.save(inventory)?;
+        }
+        self.artifacts.save_all(&artifacts)?;
+        self.runs.save(&run)?;
+
+        self.logger.log_info(&format!(
+            "Imported run {} of {} with {} samples and {} artifacts from {}",
+            run.id,
+            run.host,
+            samples.len() - conflicts.len(),
+            artifacts.len(),
+            path.display()
+        ));
+        Ok(manifest)
+    }
+}
+
+/// Encodes a value as pretty JSON followed by a newline.
+fn to_json<T: Serialize>(value: &T) -> Result<Vec<u8>, String> {
+    let mut data = serde_json::to_vec_pretty(value).map_err(|e| e.to_string())?;
+    data.push(b'\n');
+    Ok(data)
+}
+
+/// Decodes an archive entry holding JSON.
+fn from_json<T: DeserializeOwned>(entry: &ArchiveEntry) -> Result<T, String> {
+    serde_json::from_slice(&entry.data).map_err(|e| format!("{}: {}", entry.path, e))
+}
+
+/// Renders the manifest of an archive as a human readable summary.
+pub fn render_manifest(manifest: &ArchiveManifest) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "Run {} ({} on {})",
+        manifest.run_id, manifest.kind, manifest.host
+    );
+    let _ = writeln!(out, "  Created:   {}", manifest.created_at.to_rfc3339());
+    let _ = writeln!(out, "  Samples:   {}", manifest.samples);
+    let _ = writeln!(
+        out,
+        "  Inventory: {}",
+        manifest.inventory.as_deref().unwrap_or("none")
+    );
+    let _ = writeln!(out, "  Artifacts: {}", manifest.artifacts.len());
+    for artifact in &manifest.artifacts {
+        let _ = writeln!(out, "    {}", artifact);
+    }
+    let _ = writeln!(
+        out,
+        "  Size:      {} entries, {:.1} KiB uncompressed",
+        manifest.entries.len(),
+        manifest.total_size() as f64 / 1024.0
+    );
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use common::adapters::capture_log_adapter::CapturingLogger;
+    use common::ports::log_port::LogLevel;
+
+    use super::*;
+    use crate::adapters::bzip2_archive_adapter::Bzip2ArchiveAdapter;
+    use crate::adapters::memory_database_adapter::MemoryDatabaseAdapter;
+    use crate::adapters::repository_adapter::TreeRepository;
+
+    /// Creates archives over an in-memory database.
+    fn archives(logger: Arc<CapturingLogger>) -> Archives {
+        let db = MemoryDatabaseAdapter::new();
+        Archives::new(
+            logger.clone(),
+            Arc::new(Bzip2ArchiveAdapter::new()),
+            Arc::new(TreeRepository::open(logger.clone(), &db).unwrap()),
+            Arc::new(TreeRepository::open(logger.clone(), &db).unwrap()),
+            Arc::new(TreeRepository::open(logger.clone(), &db).unwrap()),
+            Arc::new(TreeRepository::open(logger, &db).unwrap()),
+        )
+    }
+
+    /// Creates a sample of a run.
+    fn sample(run: &RunRecord, sequence: u32, cpu_utilization: f64) -> MetricSample {
+        MetricSample {
+            host: run.host.clone(),
+            run_id: Some(run.id.clone()),
+            timestamp: run.started_at + Duration::seconds(sequence as i64),
+            sequence,
+            cpu_utilization: Some(cpu_utilization),
+            temperature_c: None,
+            bogo_ops_per_sec: None,
+        }
+    }
+
+    #[test]
+    fn imports_artifacts_byte_for_byte() {
+        let source = archives(Arc::new(CapturingLogger::new()));
+        let run = RunRecord::start("node1", "stress");
+        let output = vec![b'o', b'k', 0xff, 0x00, 0xe2, 0x82];
+        source.runs.save(&run).unwrap();
+        source.samples.save(&sample(&run, 1, 50.0)).unwrap();
+        source
+            .artifacts
+            .save(&RunArtifact::new(&run.id, "out.bin", output.clone()))
+            .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("run.tar.bz2");
+        source.archive(&run.id, &path).unwrap();
+
+        let destination = archives(Arc::new(CapturingLogger::new()));
+        let manifest = destination.import(&path).unwrap();
+        assert_eq!(manifest.artifacts, vec!["out.bin".to_string()]);
+        let artifact = destination
+            .artifacts
+            .get(&format!("{}/out.bin", run.id))
+            .unwrap()
+            .unwrap();
+        assert_eq!(artifact.content, output);
+    }
+
+    #[test]
+    fn keeps_local_samples_with_the_key_of_an_imported_sample() {
+        let source = archives(Arc::new(CapturingLogger::new()));
+        let run = RunRecord::start("node1", "stress");
+        source.runs.save(&run).unwrap();
+        source
+            .samples
+            .save_all(&[sample(&run, 1, 50.0), sample(&run, 2, 60.0)])
+            .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("run.tar.bz2");
+        source.archive(&run.id, &path).unwrap();
+
+        let logger = Arc::new(CapturingLogger::new());
+        let destination = archives(logger.clone());
+        let local = MetricSample {
+            run_id: None,
+            ..sample(&run, 2, 99.0)
+        };
+        destination.samples.save(&local).unwrap();
+        destination.import(&path).unwrap();
+
+        let imported = destination
+            .samples
+            .get(&sample(&run, 1, 50.0).key())
+            .unwrap();
+        assert_eq!(imported, Some(sample(&run, 1, 50.0)));
+        let kept = destination.samples.get(&local.key()).unwrap();
+        assert_eq!(kept, Some(local.clone()));
+        assert!(logger.contains(LogLevel::Warn, "Skipped 1 samples"));
+        assert!(logger.contains(LogLevel::Info, "with 1 samples"));
+    }
+}
@@ -0,0 +1,446 @@
+//! Baselines
+//!
+//! This module stores the baselines of node SKUs in the database, derives
+//! them from the median of accepted runs or imports them from YAML, and
+//! compares runs against the baseline of their SKU, kind and profile.
+
+use std::fmt::Write;
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+
+use common::ports::log_port::LoggerPort;
+
+use crate::application::benchmark::format_rate;
+use crate::domain::baseline::{Baseline, BaselineComparison, BaselineTolerances};
+use crate::domain::record::{Record, RecordQuery};
+use crate::domain::run::{RunRecord, RunStatus};
+use crate::ports::discovery_port::DiscoveryPort;
+use crate::ports::repository_port::{BaselineRepository, RunRepository};
+
+/// Stores, derives and compares against baselines.
+pub struct Baselines {
+    logger: Arc<dyn LoggerPort>,
+    baselines: Arc<BaselineRepository>,
+    runs: Arc<RunRepository>,
+}
+
+impl Baselines {
+    /// Creates a new instance of `Baselines`.
+    pub fn new(
+        logger: Arc<dyn LoggerPort>,
+        baselines: Arc<BaselineRepository>,
+        runs: Arc<RunRepository>,
+    ) -> Self {
+        Baselines {
+            logger,
+            baselines,
+            runs,
+        }
+    }
+
+    /// Stores a baseline, replacing the previous one of the same SKU, kind
+    /// and profile.
+    pub fn save(&self, baseline: &Baseline) -> Result<(), String> {
+        self.baselines.save(baseline)?;
+        self.logger.log_info(&format!(
+            "Stored baseline {} with {} metrics",
+            baseline.key(),
+            baseline.metrics.len()
+        ));
+        Ok(())
+    }
+
+    /// Loads the baselines of a SKU for every kind and profile of runs,
+    /// oldest first.
+    pub fn list(&self, sku: &str) -> Result<Vec<Baseline>, String> {
+        Ok(self
+            .baselines
+            .list(&RecordQuery::new().with_sku(sku))?
+            .items)
+    }
+
+    /// Loads the baseline a run is compared against: the one of its SKU for
+    /// runs of its kind and profile, else of its kind, else of any run.
+    ///
+    /// # Returns
+    /// `Ok(None)` if the run has no SKU, or the SKU has no such baseline.
+    pub fn for_run(&self, run: &RunRecord) -> Result<Option<Baseline>, String> {
+        let Some(sku) = &run.sku else {
+            return Ok(None);
+        };
+        for key in Baseline::keys_for_run(sku, run) {
+            if let Some(baseline) = self.baselines.get(&key)? {
+                return Ok(Some(baseline));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Imports a baseline from a YAML file and stores it.
+    pub fn import(&self, path: &Path) -> Result<Baseline, String> {
+        let file = File::open(path)
+            .map_err(|e| format!("Unable to open baseline {}: {}", path.display(), e))?;
+        let baseline: Baseline = serde_yaml::from_reader(file)
+            .map_err(|e| format!("Invalid baseline {}: {}", path.display(), e))?;
+        if baseline.sku.trim().is_empty() {
+            return Err(format!("Baseline {} has no SKU", path.display()));
+        }
+        self.save(&baseline)?;
+        Ok(baseline)
+    }
+
+    /// Loads a run record by identifier.
+    pub fn load_run(&self, run_id: &str) -> Result<Option<RunRecord>, String> {
+        self.runs.get(run_id)
+    }
+
+    /// Derives the baseline of a SKU from the median of accepted runs and
+    /// stores it.
+    ///
+    /// # Arguments
+    /// * `sku` - The SKU of the baseline.
+    /// * `kind` - The kind of the accepted runs, e.g. "benchmark".
+    /// * `profile` - The profile of the accepted runs; `None` takes the
+    ///   profile of the latest passed run of the kind.
+    /// * `run_ids` - The accepted runs, which must be passed runs of the kind
+    ///   and profile made on the SKU, the profile defaulting to that of the
+    ///   first of them; empty accepts the latest such runs.
+    /// * `runs` - The number of latest passed runs to accept.
+    ///
+    /// # Returns
+    /// The baseline, or an error message if no run was accepted or naming
+    /// the first given run that cannot be accepted.
+    pub fn derive(
+        &self,
+        sku: &str,
+        kind: &str,
+        profile: Option<&str>,
+        run_ids: &[String],
+        runs: usize,
+    ) -> Result<Baseline, String> {
+        let accepted = if run_ids.is_empty() {
+            let mut passed = self
+                .runs
+                .list(&RecordQuery::new().with_kind(kind).newest_first())?
+                .items
+                .into_iter()
+                .filter(|run| run.sku.as_deref() == Some(sku) && run.status == RunStatus::Passed)
+                .peekable();
+            let profile = match profile {
+                Some(profile) => Some(profile.to_string()),
+                None => passed.peek().and_then(|run| run.profile.clone()),
+            };
+            passed
+                .filter(|run| run.profile == profile)
+                .take(runs.max(1))
+                .collect()
+        } else {
+            let given = run_ids
+                .iter()
+                .map(|id| {
+                    self.load_run(id)?
+                        .ok_or_else(|| format!("No run with id {} found", id))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            let profile = profile
+                .map(str::to_string)
+                .or_else(|| given.first().and_then(|run| run.profile.clone()));
+            for run in &given {
+                if let Some(reason) = rejection(run, sku, kind, profile.as_deref()) {
+                    return Err(format!("Run {} {}", run.id, reason));
+                }
+            }
+            given
+        };
+        if accepted.is_empty() {
+            return Err(format!(
+                "No passed {} runs of {} to derive a baseline from",
+                kind, sku
+            ));
+        }
+
+        let baseline = Baseline::from_runs(sku, &accepted)?;
+        self.save(&baseline)?;
+        Ok(baseline)
+    }
+
+    /// Compares a run against the baseline of its SKU, kind and profile.
+    ///
+    /// # Returns
+    /// `Ok(None)` if the run has no SKU, or the SKU has no baseline for runs
+    /// of its kind and profile.
+    pub fn compare(
+        &self,
+        run: &RunRecord,
+        tolerances: &BaselineTolerances,
+    ) -> Result<Option<BaselineComparison>, String> {
+        Ok(self
+            .for_run(run)?
+            .map(|baseline| baseline.compare(run, tolerances)))
+    }
+}
+
+/// Returns why a run given by id cannot be accepted for the baseline of a
+/// SKU derived from passed runs of a kind and profile, or `None` if it can.
+fn rejection(run: &RunRecord, sku: &str, kind: &str, profile: Option<&str>) -> Option<String> {
+    if run.kind != kind {
+        Some(format!("is a {} run, not a {} run", run.kind, kind))
+    } else if run.sku.as_deref() != Some(sku) {
+        Some(format!(
+            "was made on {}, not on {}",
+            run.sku.as_deref().unwrap_or("an unknown SKU"),
+            sku
+        ))
+    } else if run.status != RunStatus::Passed {
+        Some(format!("has not passed but is {:?}", run.status))
+    } else if run.profile.as_deref() != profile {
+        Some(format!(
+            "is a run of {}, not of {}",
+            run.profile.as_deref().unwrap_or("no profile"),
+            profile.unwrap_or("no profile")
+        ))
+    } else {
+        None
+    }
+}
+
+/// Returns the SKU of the host: the configured one, or else the system
+/// product name read from SMBIOS.
+pub fn resolve_sku(configured: Option<&str>, discovery: &dyn DiscoveryPort) -> Option<String> {
+    if let Some(sku) = configured {
+        return Some(sku.to_string());
+    }
+    discovery
+        .discover_platform()
+        .ok()?
+        .system_product
+        .map(|product| product.trim().to_string())
+        .filter(|product| !product.is_empty())
+}
+
+/// Renders a baseline as a human readable table.
+pub fn render_baseline(baseline: &Baseline) -> String {
+    let mut out = String::new();
+    let source = if baseline.run_ids.is_empty() {
+        "imported".to_string()
+    } else {
+        format!("median of {} runs", baseline.run_ids.len())
+    };
+    let runs = match (&baseline.kind, &baseline.profile) {
+        (Some(kind), Some(profile)) => format!(" for {} runs of {}", kind, profile),
+        (Some(kind), None) => format!(" for {} runs", kind),
+        (None, _) => String::new(),
+    };
+    let _ = writeln!(
+        out,
+        "Baseline of {}{} ({}, {})",
+        baseline.sku,
+        runs,
+        source,
+        baseline.created_at.format("%Y-%m-%d %H:%M:%S UTC")
+    );
+    for metric in &baseline.metrics {
+        let tolerance = metric
+            .tolerance_percent
+            .map(|tolerance| format!("  ±{:.1}%", tolerance))
+            .unwrap_or_default();
+        let _ = writeln!(
+            out,
+            "  {:<48} {:>16}{}",
+            metric.name,
+            format_rate(metric.value, &metric.unit),
+            tolerance
+        );
+    }
+    out
+}
+
+/// Renders the comparison of a run against its baseline, flagging every
+/// regressed metric.
+pub fn render_comparison(comparison: &BaselineComparison) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "Run {} compared with the baseline of {}",
+        comparison.run_id, comparison.sku
+    );
+    let _ = writeln!(
+        out,
+        "  {:<48} {:>16} {:>16} {:>9} {:>9}",
+        "Metric", "Baseline", "Value", "Delta", "Tolerance"
+    );
+    for metric in &comparison.comparisons {
+        let delta = metric
+            .delta_percent
+            .map(|delta| format!("{:+.1}%", delta))
+            .unwrap_or_else(|| "-".to_string());
+        let _ = writeln!(
+            out,
+            "  {:<48} {:>16} {:>16} {:>9} {:>8.1}%{}",
+            metric.name,
+            format_rate(metric.baseline, &metric.unit),
+            format_rate(metric.value, &metric.unit),
+            delta,
+            metric.tolerance_percent,
+            if metric.regressed { "  REGRESSION" } else { "" }
+        );
+    }
+    if !comparison.missing.is_empty() {
+        let _ = writeln!(
+            out,
+            "  Missing from the run: {}",
+            comparison.missing.join(", ")
+        );
+    }
+    let _ = writeln!(
+        out,
+        "{} of {} metrics regressed",
+        comparison.regressions,
+        comparison.comparisons.len()
+    );
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+    use common::adapters::capture_log_adapter::CapturingLogger;
+
+    use super::*;
+    use crate::adapters::memory_database_adapter::MemoryDatabaseAdapter;
+    use crate::adapters::repository_adapter::TreeRepository;
+    use crate::domain::run::{MetricDirection, RunMetric};
+
+    /// Stores passed runs of the SKU "gen3" with a metric, each given by
+    /// its start in minutes, kind, profile and metric value.
+    fn baselines_with_runs(
+        runs: &[(i64, &str, &str, f64)],
+    ) -> (Baselines, Arc<RunRepository>, Vec<RunRecord>) {
+        let logger = Arc::new(CapturingLogger::new());
+        let db = MemoryDatabaseAdapter::new();
+        let repository: Arc<RunRepository> =
+            Arc::new(TreeRepository::open(logger.clone(), &db).unwrap());
+        let baselines = Baselines::new(
+            logger.clone(),
+            Arc::new(TreeRepository::open(logger, &db).unwrap()),
+            repository.clone(),
+        );
+
+        let started = Utc::now() - Duration::hours(1);
+        let mut stored = Vec::new();
+        for (minutes, kind, profile, value) in runs {
+            let mut run = RunRecord::start("node1", kind);
+            run.started_at = started + Duration::minutes(*minutes);
+            run.sku = Some("gen3".to_string());
+            run.profile = Some(profile.to_string());
+            run.metrics.push(RunMetric {
+                name: "cpu sha256 x1".to_string(),
+                value: *value,
+                unit: "B/s".to_string(),
+                direction: MetricDirection::HigherIsBetter,
+                recorded_at: run.started_at,
+            });
+            run.finish(RunStatus::Passed);
+            repository.save(&run).unwrap();
+            stored.push(run);
+        }
+        (baselines, repository, stored)
+    }
+
+    #[test]
+    fn derives_baselines_from_runs_of_one_kind_and_profile() {
+        // The newest run comes last and sets the profile derived from.
+        let (baselines, _, _) = baselines_with_runs(&[
+            (0, "benchmark", "cpu", 1.0),
+            (1, "benchmark", "cpu,memory", 2.0),
+            (2, "stress", "--cpu 4", 100.0),
+            (3, "benchmark", "cpu,memory", 4.0),
+        ]);
+
+        let baseline = baselines.derive("gen3", "benchmark", None, &[], 5).unwrap();
+        assert_eq!(baseline.profile.as_deref(), Some("cpu,memory"));
+        assert_eq!(baseline.run_ids.len(), 2);
+        assert_eq!(baseline.metrics[0].value, 3.0);
+
+        let baseline = baselines
+            .derive("gen3", "benchmark", Some("cpu"), &[], 5)
+            .unwrap();
+        assert_eq!(baseline.metrics[0].value, 1.0);
+        assert!(baselines
+            .derive("gen3", "benchmark", Some("disk"), &[], 5)
+            .is_err());
+
+        let stress = baselines.derive("gen3", "stress", None, &[], 5).unwrap();
+        assert_eq!(stress.kind.as_deref(), Some("stress"));
+    }
+
+    #[test]
+    fn keeps_and_compares_the_baselines_of_every_kind_of_a_sku() {
+        let (baselines, _, runs) = baselines_with_runs(&[
+            (0, "benchmark", "cpu", 10.0),
+            (1, "stress", "--cpu 4", 100.0),
+        ]);
+        baselines.derive("gen3", "benchmark", None, &[], 5).unwrap();
+        baselines.derive("gen3", "stress", None, &[], 5).unwrap();
+
+        let keys: Vec<String> = baselines
+            .list("gen3")
+            .unwrap()
+            .iter()
+            .map(Baseline::key)
+            .collect();
+        assert_eq!(keys, vec!["gen3/benchmark/cpu", "gen3/stress/--cpu 4"]);
+
+        // Each run is compared against the baseline of its own kind.
+        let tolerances = BaselineTolerances::default();
+        for (run, baseline) in runs.iter().zip([10.0, 100.0]) {
+            let comparison = baselines.compare(run, &tolerances).unwrap().unwrap();
+            assert_eq!(comparison.comparisons[0].baseline, baseline);
+            assert_eq!(comparison.regressions, 0);
+        }
+
+        // Runs of another profile fall back to a baseline of any profile.
+        let mut other = runs[0].clone();
+        other.profile = Some("memory".to_string());
+        assert_eq!(baselines.compare(&other, &tolerances).unwrap(), None);
+        let mut any_profile = baselines.list("gen3").unwrap().remove(0);
+        any_profile.profile = None;
+        baselines.save(&any_profile).unwrap();
+        assert!(baselines.compare(&other, &tolerances).unwrap().is_some());
+        assert_eq!(baselines.list("gen3").unwrap().len(), 3);
+    }
+
+    #[test]
+    fn rejects_runs_given_by_id_that_would_not_be_accepted() {
+        let (baselines, repository, runs) = baselines_with_runs(&[
+            (0, "benchmark", "cpu", 1.0),
+            (1, "benchmark", "cpu", 2.0),
+            (2, "benchmark", "cpu,memory", 3.0),
+        ]);
+        let mut failed = runs[0].clone();
+        failed.id = "failed".to_string();
+        failed.status = RunStatus::Failed;
+        let mut elsewhere = runs[0].clone();
+        elsewhere.id = "elsewhere".to_string();
+        elsewhere.sku = Some("gen2".to_string());
+        repository.save_all(&[failed, elsewhere]).unwrap();
+        let derive = |ids: &[&str]| {
+            let ids: Vec<String> = ids.iter().map(|id| id.to_string()).collect();
+            baselines.derive("gen3", "benchmark", None, &ids, 5)
+        };
+
+        let baseline = derive(&[&runs[0].id, &runs[1].id]).unwrap();
+        assert_eq!(baseline.metrics[0].value, 1.5);
+        let error = derive(&[&runs[0].id, "failed", "elsewhere"]).unwrap_err();
+        assert_eq!(error, "Run failed has not passed but is Failed");
+        let error = derive(&[&runs[0].id, "elsewhere"]).unwrap_err();
+        assert_eq!(error, "Run elsewhere was made on gen2, not on gen3");
+        let error = derive(&[&runs[0].id, &runs[2].id]).unwrap_err();
+        assert!(
+            error.ends_with("is a run of cpu,memory, not of cpu"),
+            "{}",
+            error
+        );
+    }
+}
@@ -0,0 +1,384 @@
+//! Benchmark
+//!
+//! This module is the harness every benchmark suite is run with: each case
+//! runs warmup iterations that are discarded, then a number of measured
+//! iterations that are summarized with their variance and the confidence
+//! interval of the mean, leaving out outliers. The CPU frequency and thermal
+//! throttling counters are read alongside the cases of CPU and memory bound
+//! suites, and such a case measured while the CPUs throttled is marked
+//! untrustworthy.
+
+use std::fmt::Write;
+use std::sync::Arc;
+use std::time::Duration;
+
+use common::ports::log_port::LoggerPort;
+
+use crate::application::run_recorder::RunRecorder;
+use crate::domain::benchmark::{
+    assess_throttling, BenchmarkResult, BenchmarkSettings, CpuFrequencyReading,
+};
+use crate::domain::run::{RunRecord, RunStatus};
+use crate::ports::benchmark_port::BenchmarkPort;
+use crate::ports::cpu_frequency_port::CpuFrequencyPort;
+
+/// Runs every case of a suite.
+///
+/// # Arguments
+/// * `port` - The suite to run.
+/// * `settings` - The number and duration of the iterations of every case.
+/// * `frequency` - Reads the CPU frequency to detect throttling; `None` skips
+///   it, as do suites that are not CPU bound.
+/// * `logger` - The logger progress is reported to.
+///
+/// # Returns
+/// A `Result` containing the result of every case, or the error of the first
+/// case that failed.
+pub fn run_suite(
+    port: &dyn BenchmarkPort,
+    settings: &BenchmarkSettings,
+    frequency: Option<&dyn CpuFrequencyPort>,
+    logger: &dyn LoggerPort,
+) -> Result<Vec<BenchmarkResult>, String> {
+    let frequency = frequency.filter(|_| port.cpu_bound());
+    let duration = port.iteration_duration(Duration::from_millis(settings.iteration_ms));
+    if duration.as_millis() != settings.iteration_ms as u128 {
+        logger.log_info(&format!(
+            "Running {} benchmark iterations of {} ms",
+            port.suite(),
+            duration.as_millis()
+        ));
+    }
+    let mut results = Vec::new();
+    for case in port.cases() {
+        logger.log_info(&format!(
+            "Running {} benchmark {} on {} thread(s)",
+            port.suite(),
+            case.name,
+            case.threads
+        ));
+        let run = || {
+            port.run_iteration(&case, duration)
+                .map_err(|e| format!("{} benchmark {} failed: {}", port.suite(), case.name, e))
+        };
+
+        for iteration in 1..=settings.warmup_iterations {
+            let measurement = run()?;
+            logger.log_debug(&format!(
+                "{} benchmark {} warmup iteration {}: {}",
+                port.suite(),
+                case.name,
+                iteration,
+                format_rate(measurement.value, &case.unit)
+            ));
+        }
+
+        let before = read_frequency(frequency, logger);
+        let mut readings = Vec::with_capacity(settings.iterations);
+        let mut measurements = Vec::with_capacity(settings.iterations);
+        for iteration in 1..=settings.iterations {
+            let measurement = run()?;
+            readings.extend(read_frequency(frequency, logger));
+            logger.log_debug(&format!(
+                "{} benchmark {} iteration {}: {}",
+                port.suite(),
+                case.name,
+                iteration,
+                format_rate(measurement.value, &case.unit)
+            ));
+            measurements.push(measurement);
+        }
+
+        let mut result =
+            BenchmarkResult::new(port.suite(), &case, &measurements, settings.reject_outliers);
+        if !result.outliers.is_empty() {
+            logger.log_info(&format!(
+                "Rejected {} outlier iteration(s) of {} benchmark {}",
+                result.outliers.len(),
+                port.suite(),
+                case.name
+            ));
+        }
+        if let Some(before) = before {
+            result.untrustworthy = assess_throttling(&before, &readings, case.threads);
+        }
+        if let Some(reason) = &result.untrustworthy {
+            logger.log_warn(&format!(
+                "{} benchmark {} on {} thread(s): metrics untrustworthy, {}",
+                port.suite(),
+                case.name,
+                case.threads,
+                reason
+            ));
+        }
+        results.push(result);
+    }
+    Ok(results)
+}
+
+/// Takes a reading of the CPU frequency, logging failures.
+fn read_frequency(
+    frequency: Option<&dyn CpuFrequencyPort>,
+    logger: &dyn LoggerPort,
+) -> Option<CpuFrequencyReading> {
+    match frequency?.read() {
+        Ok(reading) => Some(reading),
+        Err(e) => {
+            logger.log_debug(&format!("Unable to read the CPU frequency: {}", e));
+            None
+        }
+    }
+}
+
+/// Runs every suite as a recorded benchmark run, sampling the host while the
+/// suites are running.
+///
+/// # Arguments
+/// * `suites` - The suites to run.
+/// * `sku` - The SKU of the host, if known.
+/// * `settings` - The number and duration of the iterations of every case.
+/// * `frequency` - Reads the CPU frequency to detect throttling; `None` skips it.
+/// * `recorder` - The recorder the run and its samples are stored with.
+/// * `sample_interval` - The interval between samples of the host.
+/// * `logger` - The logger progress and errors are reported to.
+///
+/// # Returns
+/// The finished run with a metric per result, which failed if any suite
+/// failed, and the results of every suite that completed.
+pub fn run_recorded(
+    suites: &[Box<dyn BenchmarkPort>],
+    sku: Option<String>,
+    settings: &BenchmarkSettings,
+    frequency: Option<&dyn CpuFrequencyPort>,
+    recorder: &Arc<RunRecorder>,
+    sample_interval: Duration,
+    logger: &dyn LoggerPort,
+) -> (RunRecord, Vec<BenchmarkResult>) {
+    let mut run = RunRecord::start(&recorder.hostname(), "benchmark");
+    run.sku = sku;
+    run.profile = Some(
+        suites
+            .iter()
+            .map(|suite| suite.suite())
+            .collect::<Vec<_>>()
+            .join(","),
+    );
+    recorder.start_run_log(&mut run);
+    if let Err(e) = recorder.save_run(&run) {
+        logger.log_error(&e);
+    }
+    logger.log_info(&format!("Recording benchmark run {}", run.id));
+    let sampling = recorder.start_sampling(Some(run.id.clone()), sample_interval);
+
+    let mut results = Vec::new();
+    let mut status = RunStatus::Passed;
+    for suite in suites {
+        match run_suite(suite.as_ref(), settings, frequency, logger) {
+            Ok(suite_results) => results.extend(suite_results),
+            Err(e) => {
+                logger.log_error(&e);
+                status = RunStatus::Failed;
+            }
+        }
+    }
+
+    sampling.stop();
+    run.metrics = results
+        .iter()
+        .flat_map(BenchmarkResult::to_run_metrics)
+        .collect();
+    run.finish(status);
+    if let Err(e) = recorder.save_run(&run) {
+        logger.log_error(&e);
+    }
+    if let Err(e) = recorder.finish_run_log(&run) {
+        logger.log_error(&e);
+    }
+    (run, results)
+}
+
+/// Renders benchmark results as a human readable table.
+pub fn render_results(
+    host: &str,
+    settings: &BenchmarkSettings,
+    results: &[BenchmarkResult],
+) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "Benchmarks on {} ({} warmup and {} measured iterations of {} ms)",
+        host, settings.warmup_iterations, settings.iterations, settings.iteration_ms
+    );
+    let _ = writeln!(
+        out,
+        "  {:<28} {:>7} {:>16} {:>16} {:>16} {:>16} {:>7} {:>16} {:>16}",
+        "Benchmark", "Threads", "Mean", "95% CI", "Median", "Stddev", "CV", "Min", "Max"
+    );
+    for result in results {
+        let stats = &result.stats;
+        let _ = writeln!(
+            out,
+            "  {:<28} {:>7} {:>16} {:>16} {:>16} {:>16} {:>6.1}% {:>16} {:>16}",
+            format!("{} {}", result.suite, result.name),
+            result.threads,
+            format_rate(stats.mean, &result.unit),
+            format!("±{}", format_rate(stats.ci95, &result.unit)),
+            format_rate(stats.median, &result.unit),
+            format_rate(stats.stddev, &result.unit),
+            stats.cv_percent(),
+            format_rate(stats.min, &result.unit),
+            format_rate(stats.max, &result.unit)
+        );
+        for detail in &result.details {
+            let stats = &detail.stats;
+            let _ = writeln!(
+                out,
+                "    {:<26} {:>7} {:>16} {:>16} {:>16} {:>16} {:>6.1}% {:>16} {:>16}",
+                detail.name,
+                "",
+                format_rate(stats.mean, &detail.unit),
+                format!("±{}", format_rate(stats.ci95, &detail.unit)),
+                format_rate(stats.median, &detail.unit),
+                format_rate(stats.stddev, &detail.unit),
+                stats.cv_percent(),
+                format_rate(stats.min, &detail.unit),
+                format_rate(stats.max, &detail.unit)
+            );
+        }
+        if !result.outliers.is_empty() {
+            let outliers: Vec<String> = result
+                .outliers
+                .iter()
+                .map(|value| format_rate(*value, &result.unit))
+                .collect();
+            let _ = writeln!(out, "    rejected outliers: {}", outliers.join(", "));
+        }
+        if let Some(reason) = &result.untrustworthy {
+            let _ = writeln!(out, "    metrics untrustworthy: {}", reason);
+        }
+    }
+    out
+}
+
+/// Formats a measured value in its unit. Rates get a decimal SI prefix, e.g.
+/// "12.34 Gops/s" or "1.50 GB/s", latencies in "ns" are scaled to "us" or
+/// "ms" when large, and other units are shown as they are.
+pub fn format_rate(rate: f64, unit: &str) -> String {
+    if unit == "ns" {
+        return match rate.abs() {
+            ns if ns >= 1e6 => format!("{:.2} ms", rate / 1e6),
+            ns if ns >= 1e3 => format!("{:.2} us", rate / 1e3),
+            _ => format!("{:.2} ns", rate),
+        };
+    }
+    const PREFIXES: [(f64, &str); 4] = [(1e12, "T"), (1e9, "G"), (1e6, "M"), (1e3, "k")];
+    for (scale, prefix) in PREFIXES {
+        if unit.ends_with("/s") && rate.abs() >= scale {
+            return format!("{:.2} {}{}", rate / scale, prefix, unit);
+        }
+    }
+    format!("{:.2} {}", rate, unit)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Mutex;
+
+    use common::adapters::capture_log_adapter::CapturingLogger;
+
+    use super::*;
+    use crate::domain::benchmark::{BenchmarkCase, BenchmarkMeasurement};
+    use crate::domain::run::MetricDirection;
+
+    /// A suite of a single case measuring the given values in turn.
+    struct FakeSuite {
+        cpu_bound: bool,
+        values: Mutex<VecDeque<f64>>,
+    }
+
+    impl BenchmarkPort for FakeSuite {
+        fn suite(&self) -> &str {
+            "fake"
+        }
+
+        fn cases(&self) -> Vec<BenchmarkCase> {
+            vec![BenchmarkCase {
+                name: "spin".to_string(),
+                threads: 1,
+                unit: "ops/s".to_string(),
+                direction: MetricDirection::HigherIsBetter,
+            }]
+        }
+
+        fn cpu_bound(&self) -> bool {
+            self.cpu_bound
+        }
+
+        fn run_iteration(
+            &self,
+            _case: &BenchmarkCase,
+            _duration: Duration,
+        ) -> Result<BenchmarkMeasurement, String> {
+            let value = self.values.lock().unwrap().pop_front();
+            value
+                .map(Into::into)
+                .ok_or_else(|| "no value left".to_string())
+        }
+    }
+
+    /// CPUs counting another throttling event at every reading.
+    #[derive(Default)]
+    struct ThrottlingCpus {
+        reads: AtomicU64,
+    }
+
+    impl CpuFrequencyPort for ThrottlingCpus {
+        fn read(&self) -> Result<CpuFrequencyReading, String> {
+            Ok(CpuFrequencyReading {
+                cpu_mhz: Vec::new(),
+                throttle_events: Some(self.reads.fetch_add(1, Ordering::SeqCst)),
+            })
+        }
+    }
+
+    /// Runs a suite measuring a warmup spike then three equal values.
+    fn run_fake_suite(cpu_bound: bool, cpus: &ThrottlingCpus) -> BenchmarkResult {
+        let suite = FakeSuite {
+            cpu_bound,
+            values: Mutex::new(VecDeque::from([1000.0, 10.0, 10.0, 10.0])),
+        };
+        let settings = BenchmarkSettings {
+            iterations: 3,
+            iteration_ms: 1,
+            warmup_iterations: 1,
+            reject_outliers: true,
+        };
+        let mut results =
+            run_suite(&suite, &settings, Some(cpus), &CapturingLogger::new()).unwrap();
+        results.remove(0)
+    }
+
+    #[test]
+    fn discards_warmup_iterations() {
+        let result = run_fake_suite(true, &ThrottlingCpus::default());
+        assert_eq!(result.samples, vec![10.0, 10.0, 10.0]);
+        assert_eq!(result.stats.mean, 10.0);
+    }
+
+    #[test]
+    fn checks_only_cpu_bound_suites_for_throttling() {
+        let cpus = ThrottlingCpus::default();
+        let result = run_fake_suite(true, &cpus);
+        assert_eq!(
+            result.untrustworthy.as_deref(),
+            Some("3 thermal throttling events")
+        );
+
+        let cpus = ThrottlingCpus::default();
+        let result = run_fake_suite(false, &cpus);
+        assert_eq!(result.untrustworthy, None);
+        assert_eq!(cpus.reads.load(Ordering::SeqCst), 0);
+    }
+}
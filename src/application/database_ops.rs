@@ -0,0 +1,455 @@
+//! Database Operations
+//!
+//! This module lists, reads, exports, imports and prunes the contents of the
+//! database, and reports its size and the number of entries in every tree.
+//! Exports hold every key-value pair of every tree byte for byte, so that
+//! importing an export restores the database as it was, and compaction
+//! copies every pair into a new database.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Arc;
+
+use chrono::{Duration, Utc};
+
+use common::ports::log_port::LoggerPort;
+
+use crate::application::migrations::read_schema_version;
+use crate::domain::database::{DatabaseEntry, DatabaseStats, ExportFormat};
+use crate::domain::inventory::HostInventory;
+use crate::domain::record::{Record, RecordQuery};
+use crate::domain::run::{MetricSample, RunArtifact, RunRecord};
+use crate::ports::database_port::{DatabasePort, WriteBatch};
+use crate::ports::repository_port::{
+    ArtifactRepository, InventoryRepository, RepositoryPort, RunRepository, SampleRepository,
+};
+
+/// Lists, exports, imports and prunes the contents of the database.
+pub struct DatabaseOps {
+    logger: Arc<dyn LoggerPort>,
+    db: Arc<dyn DatabasePort>,
+    runs: Arc<RunRepository>,
+    samples: Arc<SampleRepository>,
+    inventories: Arc<InventoryRepository>,
+    artifacts: Arc<ArtifactRepository>,
+}
+
+impl DatabaseOps {
+    /// Creates a new instance of `DatabaseOps`.
+    ///
+    /// # Arguments
+    /// * `logger` - Logger implementation for logging messages.
+    /// * `db` - The database whose trees are listed, exported and imported.
+    /// * `runs`, `samples`, `inventories`, `artifacts` - The repositories
+    ///   pruned by age.
+    pub fn new(
+        logger: Arc<dyn LoggerPort>,
+        db: Arc<dyn DatabasePort>,
+        runs: Arc<RunRepository>,
+        samples: Arc<SampleRepository>,
+        inventories: Arc<InventoryRepository>,
+        artifacts: Arc<ArtifactRepository>,
+    ) -> Self {
+        DatabaseOps {
+            logger,
+            db,
+            runs,
+            samples,
+            inventories,
+            artifacts,
+        }
+    }
+
+    /// Returns the names of the trees that are read: the given tree, which
+    /// must exist, or every tree of the database.
+    fn tree_names(&self, tree: Option<&str>) -> Result<Vec<String>, String> {
+        let mut names = self.db.tree_names().map_err(|e| e.to_string())?;
+        names.sort();
+        match tree {
+            Some(tree) if names.iter().any(|name| name == tree) => Ok(vec![tree.to_string()]),
+            Some(tree) => Err(format!(
+                "No tree named {}, the trees are {}",
+                tree,
+                names.join(", ")
+            )),
+            None => Ok(names),
+        }
+    }
+
+    /// Reads the key-value pairs of the trees whose key starts with a prefix.
+    fn entries(&self, tree: Option<&str>, prefix: &str) -> Result<Vec<DatabaseEntry>, String> {
+        let mut entries = Vec::new();
+        for name in self.tree_names(tree)? {
+            let pairs = self
+                .db
+                .open_tree(&name)
+                .and_then(|tree| tree.scan_prefix(prefix.as_bytes()))
+                .map_err(|e| format!("Failed to read the {} tree: {}", name, e))?;
+            entries.extend(
+                pairs
+                    .into_iter()
+                    .map(|(key, value)| DatabaseEntry::new(&name, &key, &value)),
+            );
+        }
+        Ok(entries)
+    }
+
+    /// Lists the keys of a tree, or of every tree, that start with a prefix.
+    ///
+    /// # Returns
+    /// A `Result` containing the tree and key of every matching pair, or an
+    /// error message.
+    pub fn list(&self, tree: Option<&str>, prefix: &str) -> Result<Vec<(String, String)>, String> {
+        Ok(self
+            .entries(tree, prefix)?
+            .into_iter()
+            .map(|entry| (entry.tree, entry.key))
+            .collect())
+    }
+
+    /// Reads the value of a key in a tree, or in every tree that has it.
+    pub fn get(&self, tree: Option<&str>, key: &str) -> Result<Vec<DatabaseEntry>, String> {
+        let mut entries = Vec::new();
+        for name in self.tree_names(tree)? {
+            let value = self
+                .db
+                .open_tree(&name)
+                .and_then(|tree| tree.get(key.as_bytes()))
+                .map_err(|e| format!("Failed to read the {} tree: {}", name, e))?;
+            if let Some(value) = value {
+                entries.push(DatabaseEntry::new(&name, key.as_bytes(), &value));
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Exports every key-value pair of every tree to a file.
+    ///
+    /// # Arguments
+    /// * `format` - A JSON array, or one JSON entry per line.
+    /// * `path` - The file the export is written to.
+    ///
+    /// # Returns
+    /// A `Result` containing the number of exported pairs, or an error message.
+    pub fn export(&self, format: ExportFormat, path: &Path) -> Result<usize, String> {
+        let entries = self.entries(None, "")?;
+        let mut file = fs::File::create(path)
+            .map_err(|e| format!("Unable to create {}: {}", path.display(), e))?;
+        let written = match format {
+            ExportFormat::Json => serde_json::to_writer_pretty(&mut file, &entries)
+                .map_err(|e| e.to_string())
+                .and_then(|_| writeln!(file).map_err(|e| e.to_string())),
+            ExportFormat::Ndjson => entries.iter().try_for_each(|entry| {
+                serde_json::to_writer(&mut file, entry).map_err(|e| e.to_string())?;
+                writeln!(file).map_err(|e| e.to_string())
+            }),
+        };
+        written.map_err(|e| format!("Unable to write {}: {}", path.display(), e))?;
+
+        self.logger.log_info(&format!(
+            "Exported {} entries to {}",
+            entries.len(),
+            path.display()
+        ));
+        Ok(entries.len())
+    }
+
+    /// Imports the key-value pairs of an export, in either format, replacing
+    /// the pairs with the same keys.
+    ///
+    /// # Returns
+    /// A `Result` containing the number of imported pairs in every tree, or
+    /// an error message.
+    pub fn import(&self, path: &Path) -> Result<BTreeMap<String, usize>, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Unable to read {}: {}", path.display(), e))?;
+        let entries: Vec<DatabaseEntry> = if contents.trim_start().starts_with('[') {
+            serde_json::from_str(&contents)
+                .map_err(|e| format!("Invalid export {}: {}", path.display(), e))?
+        } else {
+            contents
+                .lines()
+                .enumerate()
+                .filter(|(_, line)| !line.trim().is_empty())
+                .map(|(number, line)| {
+                    serde_json::from_str(line).map_err(|e| {
+                        format!(
+                            "Invalid export {} line {}: {}",
+                            path.display(),
+                            number + 1,
+                            e
+                        )
+                    })
+                })
+                .collect::<Result<_, _>>()?
+        };
+
+        // Write every tree in one batch rather than flushing after every entry.
+        let mut batches: BTreeMap<String, WriteBatch> = BTreeMap::new();
+        for entry in entries {
+            let key = entry.key_bytes()?;
+            batches
+                .entry(entry.tree)
+                .or_default()
+                .insert(&key, &entry.value.to_bytes());
+        }
+        let mut imported = BTreeMap::new();
+        for (name, batch) in batches {
+            self.db
+                .open_tree(&name)
+                .and_then(|tree| tree.apply_batch(&batch))
+                .map_err(|e| format!("Failed to import into the {} tree: {}", name, e))?;
+            imported.insert(name, batch.len());
+        }
+
+        self.logger.log_info(&format!(
+            "Imported {} entries from {}",
+            imported.values().sum::<usize>(),
+            path.display()
+        ));
+        Ok(imported)
+    }
+
+    /// Removes the runs, samples, inventory snapshots and run artifacts older
+    /// than an age.
+    /// Baselines are kept however old they are.
+    ///
+    /// # Returns
+    /// A `Result` containing the number of removed records in every tree, or
+    /// an error message.
+    pub fn prune(&self, older_than: Duration) -> Result<BTreeMap<String, usize>, String> {
+        let cutoff = Utc::now()
+            .checked_sub_signed(older_than)
+            .ok_or_else(|| format!("The age {} reaches before any date", older_than))?;
+        let query = RecordQuery::new().until(cutoff);
+        let pruned = BTreeMap::from([
+            (
+                RunRecord::TREE.to_string(),
+                prune_repository(self.runs.as_ref(), &query)?,
+            ),
+            (
+                MetricSample::TREE.to_string(),
+                prune_repository(self.samples.as_ref(), &query)?,
+            ),
+            (
+                HostInventory::TREE.to_string(),
+                prune_repository(self.inventories.as_ref(), &query)?,
+            ),
+            (
+                RunArtifact::TREE.to_string(),
+                prune_repository(self.artifacts.as_ref(), &query)?,
+            ),
+        ]);
+
+        self.logger.log_info(&format!(
+            "Pruned {} records from before {}",
+            pruned.values().sum::<usize>(),
+            cutoff.to_rfc3339()
+        ));
+        Ok(pruned)
+    }
+
+    /// Returns the size of the database and the number of entries in every tree.
+    ///
+    /// # Arguments
+    /// * `path` - The path the database was opened at.
+    pub fn stats(&self, path: &str) -> Result<DatabaseStats, String> {
+        let mut trees = BTreeMap::new();
+        for name in self.tree_names(None)? {
+            let count = self
+                .db
+                .open_tree(&name)
+                .and_then(|tree| tree.scan_prefix(b""))
+                .map_err(|e| format!("Failed to read the {} tree: {}", name, e))?
+                .len();
+            trees.insert(name, count);
+        }
+        Ok(DatabaseStats {
+            path: path.to_string(),
+            size_on_disk: self.db.size_on_disk().map_err(|e| e.to_string())?,
+            schema_version: read_schema_version(self.db.as_ref())?,
+            trees,
+        })
+    }
+}
+
+/// Copies every key-value pair of every tree of a database into another
+/// database byte for byte, a tree at a time. Copying into a new database
+/// compacts it, as it leaves behind the space kept for removed and
+/// overwritten pairs.
+///
+/// # Returns
+/// A `Result` containing the number of copied pairs in every tree, or an
+/// error message.
+pub fn copy_database(
+    source: &dyn DatabasePort,
+    target: &dyn DatabasePort,
+) -> Result<BTreeMap<String, usize>, String> {
+    let mut copied = BTreeMap::new();
+    for name in source.tree_names().map_err(|e| e.to_string())? {
+        let pairs = source
+            .open_tree(&name)
+            .and_then(|tree| tree.scan_prefix(b""))
+            .map_err(|e| format!("Failed to read the {} tree: {}", name, e))?;
+        let mut batch = WriteBatch::new();
+        for (key, value) in &pairs {
+            batch.insert(key, value);
+        }
+        target
+            .open_tree(&name)
+            .and_then(|tree| tree.apply_batch(&batch))
+            .map_err(|e| format!("Failed to write the {} tree: {}", name, e))?;
+        copied.insert(name, pairs.len());
+    }
+    Ok(copied)
+}
+
+/// Removes the records of a repository matching a query.
+fn prune_repository<T: Record>(
+    repository: &dyn RepositoryPort<T>,
+    query: &RecordQuery,
+) -> Result<usize, String> {
+    let keys: Vec<String> = repository
+        .list(query)?
+        .items
+        .iter()
+        .map(|record| record.key())
+        .collect();
+    repository.remove_all(&keys)?;
+    Ok(keys.len())
+}
+
+/// Parses an age such as "30d", "12h", "90m", "45s" or "2w".
+pub fn parse_age(age: &str) -> Result<Duration, String> {
+    let age = age.trim();
+    let split = age.find(|c: char| !c.is_ascii_digit()).unwrap_or(age.len());
+    let (amount, unit) = age.split_at(split);
+    let amount: i64 = amount
+        .parse()
+        .map_err(|_| format!("Invalid age {}, expected e.g. 30d", age))?;
+    let unit_seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" | "" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => {
+            return Err(format!(
+                "Invalid age unit {} in {}, expected s, m, h, d or w",
+                unit, age
+            ))
+        }
+    };
+    amount
+        .checked_mul(unit_seconds)
+        .and_then(Duration::try_seconds)
+        .ok_or_else(|| format!("Invalid age {}, it is too long", age))
+}
+
+/// Renders database statistics as human readable text.
+pub fn render_stats(stats: &DatabaseStats) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "Database {}", stats.path);
+    let _ = writeln!(
+        out,
+        "  Size on disk:   {:.1} MiB",
+        stats.size_on_disk as f64 / (1024.0 * 1024.0)
+    );
+    let _ = writeln!(out, "  Schema version: {}", stats.schema_version);
+    let _ = writeln!(out, "  Entries:        {}", stats.total_entries());
+    for (tree, count) in &stats.trees {
+        let _ = writeln!(out, "    {:<16} {:>10}", tree, count);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use common::adapters::capture_log_adapter::CapturingLogger;
+
+    use super::*;
+    use crate::adapters::memory_database_adapter::MemoryDatabaseAdapter;
+    use crate::adapters::repository_adapter::TreeRepository;
+    use crate::ports::database_port::KeyValue;
+
+    /// Creates the database operations on an in-memory database.
+    fn database_ops(db: Arc<dyn DatabasePort>) -> DatabaseOps {
+        let logger = Arc::new(CapturingLogger::new());
+        DatabaseOps::new(
+            logger.clone(),
+            db.clone(),
+            Arc::new(TreeRepository::open(logger.clone(), db.as_ref()).unwrap()),
+            Arc::new(TreeRepository::open(logger.clone(), db.as_ref()).unwrap()),
+            Arc::new(TreeRepository::open(logger.clone(), db.as_ref()).unwrap()),
+            Arc::new(TreeRepository::open(logger, db.as_ref()).unwrap()),
+        )
+    }
+
+    /// Fills a database with pairs of every kind of key and value.
+    fn fill(db: &dyn DatabasePort) {
+        let runs = db.open_tree("runs").unwrap();
+        runs.insert(
+            b"run1",
+            br#"{"version":1,"record":{"id":"run1","z":1,"a":2.5}}"#,
+        )
+        .unwrap();
+        db.insert(b"cpu_stats.txt", b"USER PID\n  root 1").unwrap();
+        db.insert(&[0x6b, 0xff], &[0x00, 0xfe]).unwrap();
+        db.insert(b"spaced", b"{ \"a\": [1, 2] }").unwrap();
+    }
+
+    /// Returns every pair of every tree of a database.
+    fn dump(db: &dyn DatabasePort) -> BTreeMap<String, Vec<KeyValue>> {
+        db.tree_names()
+            .unwrap()
+            .into_iter()
+            .map(|name| {
+                let pairs = db.open_tree(&name).unwrap().scan_prefix(b"").unwrap();
+                (name, pairs)
+            })
+            .filter(|(_, pairs)| !pairs.is_empty())
+            .collect()
+    }
+
+    #[test]
+    fn restores_exports_byte_for_byte() {
+        let source: Arc<dyn DatabasePort> = Arc::new(MemoryDatabaseAdapter::new());
+        fill(source.as_ref());
+        let dir = tempfile::tempdir().unwrap();
+
+        for format in [ExportFormat::Json, ExportFormat::Ndjson] {
+            let path = dir.path().join("export");
+            database_ops(source.clone()).export(format, &path).unwrap();
+
+            let target: Arc<dyn DatabasePort> = Arc::new(MemoryDatabaseAdapter::new());
+            database_ops(target.clone()).import(&path).unwrap();
+            assert_eq!(dump(target.as_ref()), dump(source.as_ref()));
+        }
+    }
+
+    #[test]
+    fn copies_every_tree_into_another_database() {
+        let source = MemoryDatabaseAdapter::new();
+        fill(&source);
+        let target = MemoryDatabaseAdapter::new();
+
+        let copied = copy_database(&source, &target).unwrap();
+        assert_eq!(copied.get("runs"), Some(&1));
+        assert_eq!(dump(&target), dump(&source));
+    }
+
+    #[test]
+    fn rejects_ages_that_overflow() {
+        assert_eq!(parse_age("2w").unwrap(), Duration::days(14));
+        assert_eq!(parse_age("90").unwrap(), Duration::days(90));
+        assert!(parse_age("9223372036854775807d").is_err());
+        assert!(parse_age("99999999999999999999s").is_err());
+        assert!(parse_age("10y").is_err());
+
+        let ops = database_ops(Arc::new(MemoryDatabaseAdapter::new()));
+        assert!(ops.prune(parse_age("106751991167d").unwrap()).is_err());
+    }
+}
@@ -0,0 +1,679 @@
+//! Discovery
+//!
+//! This module runs hardware discovery through a `DiscoveryPort` and renders
+//! the resulting inventory for the terminal.
+
+use std::fmt::Write;
+
+use chrono::Utc;
+
+use crate::application::inventory_checks::{self, InventoryExpectations};
+use crate::domain::inventory::{
+    ChangeKind, CpuInventory, FindingSeverity, HostInventory, InventoryChange, InventoryFinding,
+    MemoryInventory, NetworkInventory, PciDevice, PlatformInfo, StorageInventory,
+};
+use crate::ports::discovery_port::DiscoveryPort;
+
+/// Feature flags worth calling out when summarizing a CPU.
+const NOTABLE_FLAGS: [&str; 14] = [
+    "sse4_2",
+    "avx",
+    "avx2",
+    "fma",
+    "avx512f",
+    "avx512bw",
+    "avx512vl",
+    "avx512_vnni",
+    "avx512_bf16",
+    "amx_tile",
+    "aes",
+    "sha_ni",
+    "vmx",
+    "svm",
+];
+
+/// The areas of the inventory to discover.
+#[derive(Debug, Clone, Copy)]
+pub struct DiscoverySections {
+    /// Discover processors, caches, NUMA nodes and frequencies.
+    pub cpu: bool,
+    /// Discover the BIOS, system and baseboard identification.
+    pub platform: bool,
+    /// Discover the memory arrays and DIMM slots.
+    pub memory: bool,
+    /// Discover the PCI devices and their links.
+    pub pci: bool,
+    /// Discover the block devices and mounted filesystems.
+    pub storage: bool,
+    /// Discover the network interfaces and RDMA devices.
+    pub network: bool,
+    /// Whether the sections were requested explicitly rather than defaulted.
+    pub explicit: bool,
+}
+
+impl DiscoverySections {
+    /// Selects every section.
+    pub fn all() -> Self {
+        DiscoverySections {
+            cpu: true,
+            platform: true,
+            memory: true,
+            pci: true,
+            storage: true,
+            network: true,
+            explicit: false,
+        }
+    }
+
+    /// Selects the requested sections, or all of them if none was requested.
+    pub fn select(
+        cpu: bool,
+        platform: bool,
+        memory: bool,
+        pci: bool,
+        storage: bool,
+        network: bool,
+    ) -> Self {
+        let requested = DiscoverySections {
+            cpu,
+            platform,
+            memory,
+            pci,
+            storage,
+            network,
+            explicit: true,
+        };
+        if requested.is_empty() {
+            Self::all()
+        } else {
+            requested
+        }
+    }
+
+    /// Returns true if no section is selected.
+    fn is_empty(&self) -> bool {
+        !(self.cpu || self.platform || self.memory || self.pci || self.storage || self.network)
+    }
+}
+
+/// Discovers the requested sections of the host inventory.
+///
+/// # Arguments
+/// * `port` - The discovery implementation to use.
+/// * `host` - The name of the host being discovered.
+/// * `sections` - The areas of the inventory to discover.
+/// * `expectations` - The expected properties the inventory is checked against.
+///
+/// The SMBIOS backed sections are only readable by root. Unless they were
+/// requested explicitly, failing to discover them is reported as a finding
+/// instead of failing the whole run.
+///
+/// # Returns
+/// A `Result` containing the `HostInventory` with its findings, or the first
+/// error encountered.
+pub fn discover(
+    port: &dyn DiscoveryPort,
+    host: &str,
+    sections: DiscoverySections,
+    expectations: &InventoryExpectations,
+) -> Result<HostInventory, String> {
+    let mut inventory = HostInventory {
+        host: host.to_string(),
+        collected_at: Utc::now(),
+        cpu: None,
+        platform: None,
+        memory: None,
+        pci: None,
+        storage: None,
+        network: None,
+        findings: Vec::new(),
+    };
+
+    if sections.cpu {
+        inventory.cpu = Some(port.discover_cpu()?);
+    }
+    let mut skipped = Vec::new();
+    if sections.platform {
+        match port.discover_platform() {
+            Ok(platform) => inventory.platform = Some(platform),
+            Err(e) if !sections.explicit => skipped.push(("platform", e)),
+            Err(e) => return Err(e),
+        }
+    }
+    if sections.memory {
+        match port.discover_memory() {
+            Ok(memory) => inventory.memory = Some(memory),
+            Err(e) if !sections.explicit => skipped.push(("memory", e)),
+            Err(e) => return Err(e),
+        }
+    }
+    if sections.pci {
+        inventory.pci = Some(port.discover_pci()?);
+    }
+    if sections.storage {
+        inventory.storage = Some(port.discover_storage()?);
+    }
+    if sections.network {
+        inventory.network = Some(port.discover_network()?);
+    }
+
+    inventory.findings = skipped
+        .into_iter()
+        .map(|(component, e)| InventoryFinding {
+            severity: FindingSeverity::Info,
+            component: component.to_string(),
+            message: format!("Not discovered: {}", e),
+        })
+        .collect();
+    inventory
+        .findings
+        .extend(inventory_checks::check_inventory(&inventory, expectations));
+    Ok(inventory)
+}
+
+/// Renders the inventory as a human readable report.
+pub fn render_inventory(inventory: &HostInventory) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "Host {} (collected {})",
+        inventory.host,
+        inventory.collected_at.format("%Y-%m-%d %H:%M:%S UTC")
+    );
+
+    if let Some(cpu) = &inventory.cpu {
+        render_cpu(&mut out, cpu);
+    }
+    if let Some(platform) = &inventory.platform {
+        render_platform(&mut out, platform);
+    }
+    if let Some(memory) = &inventory.memory {
+        render_memory(&mut out, memory);
+    }
+    if let Some(devices) = &inventory.pci {
+        render_pci(&mut out, devices);
+    }
+    if let Some(storage) = &inventory.storage {
+        render_storage(&mut out, storage);
+    }
+    if let Some(network) = &inventory.network {
+        render_network(&mut out, network);
+    }
+
+    if !inventory.findings.is_empty() {
+        let _ = writeln!(out, "\nFindings");
+        for finding in &inventory.findings {
+            let severity = match finding.severity {
+                FindingSeverity::Info => "INFO",
+                FindingSeverity::Warning => "WARN",
+            };
+            let _ = writeln!(
+                out,
+                "  [{}] {}: {}",
+                severity, finding.component, finding.message
+            );
+        }
+    }
+    out
+}
+
+/// Renders the changes between two snapshots as a human readable report.
+pub fn render_changes(
+    older: &HostInventory,
+    newer: &HostInventory,
+    changes: &[InventoryChange],
+) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "Changes on {} from snapshot {} to {}",
+        newer.host,
+        older.snapshot_id(),
+        newer.snapshot_id()
+    );
+    if changes.is_empty() {
+        let _ = writeln!(out, "  No changes");
+    }
+    for change in changes {
+        let kind = match change.kind {
+            ChangeKind::Added => "added",
+            ChangeKind::Removed => "removed",
+            ChangeKind::Changed => "changed",
+        };
+        let _ = writeln!(out, "  [{}] {}: {}", kind, change.component, change.detail);
+    }
+    out
+}
+
+/// Renders the CPU section of the report.
+fn render_cpu(out: &mut String, cpu: &CpuInventory) {
+    let _ = writeln!(out, "\nCPU");
+    let _ = writeln!(out, "  Model:      {} ({})", cpu.model, cpu.vendor);
+    let _ = writeln!(
+        out,
+        "  Topology:   {} socket(s), {} core(s), {} thread(s), SMT {}",
+        cpu.sockets,
+        cpu.cores,
+        cpu.threads,
+        if cpu.smt_active { "on" } else { "off" }
+    );
+
+    for cache in &cpu.caches {
+        let _ = writeln!(
+            out,
+            "  L{} {:<11} {} KiB x {} = {} KiB",
+            cache.level,
+            cache.cache_type,
+            cache.size_kib,
+            cache.instances,
+            cache.total_kib()
+        );
+    }
+
+    for node in &cpu.numa_nodes {
+        let memory = node
+            .memory_total_kib
+            .map(|kib| format!("{:.1} GiB", kib as f64 / (1024.0 * 1024.0)))
+            .unwrap_or_else(|| "unknown".to_string());
+        let _ = writeln!(
+            out,
+            "  NUMA node {}: {} CPU(s), {} memory",
+            node.id,
+            node.cpus.len(),
+            memory
+        );
+    }
+
+    let mhz = |value: Option<f64>| {
+        value
+            .map(|mhz| format!("{:.0} MHz", mhz))
+            .unwrap_or_else(|| "n/a".to_string())
+    };
+    let _ = writeln!(
+        out,
+        "  Frequency:  min {}, max {}, current {}, governor {}",
+        mhz(cpu.frequency.min_mhz),
+        mhz(cpu.frequency.max_mhz),
+        mhz(cpu.frequency.current_mhz),
+        if cpu.frequency.governors.is_empty() {
+            "n/a".to_string()
+        } else {
+            cpu.frequency.governors.join(", ")
+        }
+    );
+
+    let notable: Vec<&str> = NOTABLE_FLAGS
+        .iter()
+        .copied()
+        .filter(|flag| cpu.flags.iter().any(|f| f == flag))
+        .collect();
+    let _ = writeln!(
+        out,
+        "  Flags:      {} ({} total)",
+        if notable.is_empty() {
+            "none notable".to_string()
+        } else {
+            notable.join(" ")
+        },
+        cpu.flags.len()
+    );
+}
+
+/// Renders the platform section of the report.
+fn render_platform(out: &mut String, platform: &PlatformInfo) {
+    let value = |value: &Option<String>| value.clone().unwrap_or_else(|| "n/a".to_string());
+    let _ = writeln!(out, "\nPlatform");
+    let _ = writeln!(
+        out,
+        "  System:     {} {} (serial {})",
+        value(&platform.system_manufacturer),
+        value(&platform.system_product),
+        value(&platform.system_serial)
+    );
+    let _ = writeln!(
+        out,
+        "  Board:      {} {}",
+        value(&platform.board_manufacturer),
+        value(&platform.board_product)
+    );
+    let _ = writeln!(
+        out,
+        "  BIOS:       {} {} ({})",
+        value(&platform.bios_vendor),
+        value(&platform.bios_version),
+        value(&platform.bios_release_date)
+    );
+    let _ = writeln!(out, "  UUID:       {}", value(&platform.system_uuid));
+}
+
+/// Renders the memory section of the report with one line per slot.
+fn render_memory(out: &mut String, memory: &MemoryInventory) {
+    let _ = writeln!(out, "\nMemory");
+    let _ = writeln!(
+        out,
+        "  Installed:  {} MiB in {} of {} slot(s)",
+        memory.total_mib(),
+        memory.populated().count(),
+        memory.devices.len()
+    );
+    for array in &memory.arrays {
+        let capacity = array
+            .max_capacity_kib
+            .map(|kib| format!("{} GiB", kib / (1024 * 1024)))
+            .unwrap_or_else(|| "unknown".to_string());
+        let _ = writeln!(
+            out,
+            "  Array 0x{:04X}: {}, {} slot(s), max {}, {}",
+            array.handle, array.usage, array.slots, capacity, array.error_correction
+        );
+    }
+
+    let _ = writeln!(
+        out,
+        "  {:<16} {:<24} {:>9} {:<6} {:>12} {:>5} {:<14} Part number",
+        "Slot", "Bank", "Size", "Type", "Speed MT/s", "Rank", "Manufacturer"
+    );
+    for device in &memory.devices {
+        let Some(size_mib) = device.size_mib else {
+            let _ = writeln!(
+                out,
+                "  {:<16} {:<24} {:>9}",
+                device.locator, device.bank_locator, "empty"
+            );
+            continue;
+        };
+        let speed = match (device.configured_speed_mts, device.speed_mts) {
+            (Some(configured), Some(rated)) => format!("{}/{}", configured, rated),
+            (Some(speed), None) | (None, Some(speed)) => speed.to_string(),
+            (None, None) => "n/a".to_string(),
+        };
+        let _ = writeln!(
+            out,
+            "  {:<16} {:<24} {:>5} MiB {:<6} {:>12} {:>5} {:<14} {}",
+            device.locator,
+            device.bank_locator,
+            size_mib,
+            device.memory_type,
+            speed,
+            device
+                .rank
+                .map(|rank| rank.to_string())
+                .unwrap_or_else(|| "n/a".to_string()),
+            device.manufacturer.as_deref().unwrap_or("n/a"),
+            device.part_number.as_deref().unwrap_or("n/a")
+        );
+    }
+}
+
+/// Renders the PCI section of the report with one line per device.
+fn render_pci(out: &mut String, devices: &[PciDevice]) {
+    let _ = writeln!(out, "\nPCI devices");
+    for device in devices {
+        let link = device
+            .link
+            .as_ref()
+            .map(|link| {
+                let value = |current: Option<String>, max: Option<String>| {
+                    format!(
+                        "{}/{}",
+                        current.unwrap_or_else(|| "?".to_string()),
+                        max.unwrap_or_else(|| "?".to_string())
+                    )
+                };
+                format!(
+                    " link {} GT/s x{}",
+                    value(
+                        link.current_speed_gts.map(|gts| gts.to_string()),
+                        link.max_speed_gts.map(|gts| gts.to_string())
+                    ),
+                    value(
+                        link.current_width.map(|lanes| lanes.to_string()),
+                        link.max_width.map(|lanes| lanes.to_string())
+                    )
+                )
+            })
+            .unwrap_or_default();
+        let numa = device
+            .numa_node
+            .map(|node| format!(" node {}", node))
+            .unwrap_or_default();
+        let _ = writeln!(
+            out,
+            "  {} {}: {} [{}]{}{}",
+            device.address,
+            device.class_name,
+            device.display_name(),
+            device.driver.as_deref().unwrap_or("no driver"),
+            numa,
+            link
+        );
+    }
+}
+
+/// Renders the storage section of the report.
+fn render_storage(out: &mut String, storage: &StorageInventory) {
+    let gib = |bytes: u64| bytes as f64 / (1024.0 * 1024.0 * 1024.0);
+    let _ = writeln!(out, "\nStorage");
+    for device in &storage.devices {
+        let _ = writeln!(
+            out,
+            "  {:<10} {:>9.1} GiB {:<4} {} (serial {})",
+            device.name,
+            gib(device.size_bytes),
+            if device.rotational { "HDD" } else { "SSD" },
+            device.model.as_deref().unwrap_or("unknown model"),
+            device.serial.as_deref().unwrap_or("n/a")
+        );
+        let _ = writeln!(
+            out,
+            "             scheduler {}, nr_requests {}, queue depth {}, blocks {}/{} B",
+            device.scheduler.as_deref().unwrap_or("n/a"),
+            device
+                .nr_requests
+                .map(|n| n.to_string())
+                .unwrap_or_else(|| "n/a".to_string()),
+            device
+                .queue_depth
+                .map(|n| n.to_string())
+                .unwrap_or_else(|| "n/a".to_string()),
+            device
+                .logical_block_size
+                .map(|n| n.to_string())
+                .unwrap_or_else(|| "?".to_string()),
+            device
+                .physical_block_size
+                .map(|n| n.to_string())
+                .unwrap_or_else(|| "?".to_string())
+        );
+        if let Some(nvme) = &device.nvme {
+            let _ = writeln!(
+                out,
+                "             NVMe controller {}, namespace {}, transport {}{}",
+                nvme.controller.as_deref().unwrap_or("?"),
+                nvme.nsid
+                    .map(|nsid| nsid.to_string())
+                    .unwrap_or_else(|| "?".to_string()),
+                nvme.transport.as_deref().unwrap_or("?"),
+                nvme.pci_address
+                    .as_ref()
+                    .map(|address| format!(" at {}", address))
+                    .unwrap_or_default()
+            );
+        }
+        for partition in &device.partitions {
+            let _ = writeln!(
+                out,
+                "    {:<12} {:>9.1} GiB",
+                partition.name,
+                gib(partition.size_bytes)
+            );
+        }
+    }
+
+    if !storage.filesystems.is_empty() {
+        let _ = writeln!(out, "  Mounted filesystems");
+    }
+    for filesystem in &storage.filesystems {
+        let _ = writeln!(
+            out,
+            "    {:<24} {:<8} {} ({})",
+            filesystem.mount_point, filesystem.fs_type, filesystem.source, filesystem.options
+        );
+    }
+}
+
+/// Renders the network section of the report.
+fn render_network(out: &mut String, network: &NetworkInventory) {
+    let _ = writeln!(out, "\nNetwork");
+    for interface in network.interfaces.iter().filter(|i| !i.is_virtual) {
+        let _ = writeln!(
+            out,
+            "  {:<12} {:<5} {:>6} {:<5} mtu {} {} [{}]{}{}",
+            interface.name,
+            interface.operstate,
+            interface
+                .speed_mbps
+                .map(inventory_checks::format_link_speed)
+                .unwrap_or_else(|| "-".to_string()),
+            interface.duplex.as_deref().unwrap_or("-"),
+            interface
+                .mtu
+                .map(|mtu| mtu.to_string())
+                .unwrap_or_else(|| "?".to_string()),
+            interface.mac.as_deref().unwrap_or("no mac"),
+            interface.driver.as_deref().unwrap_or("no driver"),
+            interface
+                .pci_address
+                .as_ref()
+                .map(|address| format!(" at {}", address))
+                .unwrap_or_default(),
+            interface
+                .numa_node
+                .map(|node| format!(" node {}", node))
+                .unwrap_or_default()
+        );
+        let _ = writeln!(
+            out,
+            "               {} rx / {} tx queue(s), {} carrier change(s)",
+            interface.rx_queues,
+            interface.tx_queues,
+            interface
+                .carrier_changes
+                .map(|changes| changes.to_string())
+                .unwrap_or_else(|| "?".to_string())
+        );
+    }
+    let virtual_names: Vec<&str> = network
+        .interfaces
+        .iter()
+        .filter(|i| i.is_virtual)
+        .map(|i| i.name.as_str())
+        .collect();
+    if !virtual_names.is_empty() {
+        let _ = writeln!(out, "  Virtual: {}", virtual_names.join(" "));
+    }
+
+    for device in &network.rdma_devices {
+        let _ = writeln!(
+            out,
+            "  RDMA {} firmware {}{}",
+            device.name,
+            device.firmware.as_deref().unwrap_or("?"),
+            device
+                .pci_address
+                .as_ref()
+                .map(|address| format!(" at {}", address))
+                .unwrap_or_default()
+        );
+        for port in &device.ports {
+            let _ = writeln!(
+                out,
+                "    port {}: {} {} {} {}",
+                port.port,
+                port.link_layer.as_deref().unwrap_or("?"),
+                port.state.as_deref().unwrap_or("?"),
+                port.physical_state.as_deref().unwrap_or("?"),
+                port.rate.as_deref().unwrap_or("?")
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use common::adapters::capture_log_adapter::CapturingLogger;
+
+    use super::*;
+    use crate::adapters::discovery_adapter::SysfsDiscoveryAdapter;
+
+    /// Selects the SMBIOS backed sections.
+    fn smbios_sections(explicit: bool) -> DiscoverySections {
+        DiscoverySections {
+            cpu: false,
+            platform: true,
+            memory: true,
+            pci: false,
+            storage: false,
+            network: false,
+            explicit,
+        }
+    }
+
+    #[test]
+    fn discovers_memory_from_a_captured_table() {
+        let port = SysfsDiscoveryAdapter::with_root(
+            Arc::new(CapturingLogger::new()),
+            format!("{}/tests/fixtures/dmi/server", env!("CARGO_MANIFEST_DIR")),
+        );
+        let inventory = discover(
+            &port,
+            "node1",
+            smbios_sections(true),
+            &InventoryExpectations::default(),
+        )
+        .unwrap();
+
+        let memory = inventory.memory.unwrap();
+        assert_eq!(memory.populated().count(), 2);
+        assert_eq!(memory.total_mib(), 147456);
+        assert_eq!(
+            inventory.platform.unwrap().board_product.as_deref(),
+            Some("X11DPU")
+        );
+    }
+
+    #[test]
+    fn reports_unreadable_smbios_sections_as_findings() {
+        let root = tempfile::tempdir().unwrap();
+        let port = SysfsDiscoveryAdapter::with_root(Arc::new(CapturingLogger::new()), root.path());
+
+        let inventory = discover(
+            &port,
+            "node1",
+            smbios_sections(false),
+            &InventoryExpectations::default(),
+        )
+        .unwrap();
+        assert!(inventory.platform.is_none());
+        assert!(inventory.memory.is_none());
+        let skipped = inventory
+            .findings
+            .iter()
+            .filter(|f| f.message.starts_with("Not discovered"))
+            .map(|f| f.component.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(skipped, vec!["platform", "memory"]);
+
+        // Sections requested explicitly fail the discovery instead.
+        let e = discover(
+            &port,
+            "node1",
+            smbios_sections(true),
+            &InventoryExpectations::default(),
+        )
+        .unwrap_err();
+        assert!(e.contains("Unable to read SMBIOS table"), "{}", e);
+    }
+}
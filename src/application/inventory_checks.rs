@@ -0,0 +1,600 @@
+//! Inventory Checks
+//!
+//! This module inspects a discovered inventory for configurations that are
+//! likely to cost performance or reliability, such as mixed DIMMs or memory
+//! channels populated unevenly.
+
+use std::collections::BTreeMap;
+
+use crate::domain::inventory::{
+    FindingSeverity, HostInventory, InventoryFinding, MemoryDevice, MemoryInventory,
+    NetworkInventory, PciDevice,
+};
+
+/// The PCI base class of bridges, whose links mirror the devices behind them.
+const PCI_CLASS_BRIDGE: u32 = 0x06;
+
+/// The expected properties of an inventory, taken from the configuration.
+#[derive(Debug, Clone, Default)]
+pub struct InventoryExpectations {
+    /// The expected link speed in Mb/s per network interface, or per RDMA
+    /// port written as "mlx5_0/1". Names ending in `*` match by prefix.
+    pub link_speeds_mbps: BTreeMap<String, u64>,
+}
+
+impl InventoryExpectations {
+    /// Returns the expected link speed of an interface or port.
+    ///
+    /// An exact name wins over patterns; among patterns the longest prefix wins.
+    pub fn link_speed_mbps(&self, name: &str) -> Option<u64> {
+        if let Some(speed) = self.link_speeds_mbps.get(name) {
+            return Some(*speed);
+        }
+        self.link_speeds_mbps
+            .iter()
+            .filter_map(|(pattern, speed)| {
+                let prefix = pattern.strip_suffix('*')?;
+                name.starts_with(prefix).then_some((prefix.len(), *speed))
+            })
+            .max_by_key(|(length, _)| *length)
+            .map(|(_, speed)| speed)
+    }
+}
+
+/// Returns true if `name` matches `pattern`, where a pattern ending in `*`
+/// matches by prefix.
+pub fn name_matches(pattern: &str, name: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => pattern == name,
+    }
+}
+
+/// Runs every check applicable to the discovered sections of the inventory.
+pub fn check_inventory(
+    inventory: &HostInventory,
+    expectations: &InventoryExpectations,
+) -> Vec<InventoryFinding> {
+    let mut findings = Vec::new();
+    if let Some(memory) = &inventory.memory {
+        findings.extend(check_memory(memory));
+    }
+    if let Some(devices) = &inventory.pci {
+        findings.extend(check_pci(devices));
+    }
+    if let Some(network) = &inventory.network {
+        findings.extend(check_network(network, expectations));
+    }
+    findings
+}
+
+/// Checks the memory configuration integrity.
+///
+/// Flags mixed DIMM types, sizes, speeds and part numbers, DIMMs configured
+/// below their rated speed, and memory channels that are populated unevenly.
+pub fn check_memory(memory: &MemoryInventory) -> Vec<InventoryFinding> {
+    let populated: Vec<&MemoryDevice> = memory.populated().collect();
+    if populated.is_empty() {
+        return Vec::new();
+    }
+
+    let mut findings = Vec::new();
+    let mut finding = |severity: FindingSeverity, message: String| {
+        findings.push(InventoryFinding {
+            severity,
+            component: "memory".to_string(),
+            message,
+        })
+    };
+
+    let types = count_by(&populated, |device| Some(device.memory_type.clone()));
+    if types.len() > 1 {
+        finding(
+            FindingSeverity::Warning,
+            format!("Mixed memory types installed: {}", describe_counts(&types)),
+        );
+    }
+
+    let sizes = count_by(&populated, |device| {
+        device.size_mib.map(|mib| format!("{} MiB", mib))
+    });
+    if sizes.len() > 1 {
+        finding(
+            FindingSeverity::Warning,
+            format!("Mixed DIMM sizes installed: {}", describe_counts(&sizes)),
+        );
+    }
+
+    let speeds = count_by(&populated, |device| {
+        device
+            .configured_speed_mts
+            .or(device.speed_mts)
+            .map(|mts| format!("{} MT/s", mts))
+    });
+    if speeds.len() > 1 {
+        finding(
+            FindingSeverity::Warning,
+            format!("Mixed DIMM speeds configured: {}", describe_counts(&speeds)),
+        );
+    }
+
+    let parts = count_by(&populated, |device| device.part_number.clone());
+    if parts.len() > 1 {
+        finding(
+            FindingSeverity::Info,
+            format!(
+                "Mixed DIMM part numbers installed: {}",
+                describe_counts(&parts)
+            ),
+        );
+    }
+
+    let slowed: Vec<String> = populated
+        .iter()
+        .filter_map(
+            |device| match (device.speed_mts, device.configured_speed_mts) {
+                (Some(rated), Some(configured)) if configured < rated => Some(format!(
+                    "{} ({} of {} MT/s)",
+                    device.locator, configured, rated
+                )),
+                _ => None,
+            },
+        )
+        .collect();
+    if !slowed.is_empty() {
+        finding(
+            FindingSeverity::Info,
+            format!(
+                "DIMMs configured below their rated speed: {}",
+                slowed.join(", ")
+            ),
+        );
+    }
+
+    // (array handle, channel) -> (populated DIMMs, capacity in MiB)
+    let mut channels: BTreeMap<(u16, String), (usize, u64)> = BTreeMap::new();
+    for device in &memory.devices {
+        let entry = channels
+            .entry((device.array_handle, channel_key(device)))
+            .or_default();
+        if device.is_populated() {
+            entry.0 += 1;
+            entry.1 += device.size_mib.unwrap_or(0);
+        }
+    }
+    let mut layouts: Vec<(usize, u64)> = channels.values().copied().collect();
+    layouts.sort_unstable();
+    layouts.dedup();
+    if channels.len() > 1 && layouts.len() > 1 {
+        let summary: Vec<String> = channels
+            .iter()
+            .map(|((_, channel), (dimms, mib))| {
+                format!("{} {} DIMM(s) {} MiB", channel, dimms, mib)
+            })
+            .collect();
+        finding(
+            FindingSeverity::Warning,
+            format!(
+                "Memory channels are populated unevenly: {}",
+                summary.join(", ")
+            ),
+        );
+    }
+
+    findings
+}
+
+/// Checks the PCI Express links of the devices.
+///
+/// Flags endpoints whose negotiated link speed or width is below what the link
+/// supports, e.g. a x16 GPU trained at x8 or a Gen4 NIC running at Gen3.
+/// Bridges are skipped since their links are reported again by the endpoint
+/// behind them. Some devices lower the link speed while idle to save power,
+/// so speed findings on idle accelerators should be re-checked under load.
+pub fn check_pci(devices: &[PciDevice]) -> Vec<InventoryFinding> {
+    devices
+        .iter()
+        .filter(|device| device.class >> 16 != PCI_CLASS_BRIDGE)
+        .filter_map(|device| {
+            let link = device.link.as_ref()?;
+            if !link.is_speed_degraded() && !link.is_width_degraded() {
+                return None;
+            }
+            let gts = |speed: Option<f64>| {
+                speed
+                    .map(|gts| format!("{} GT/s", gts))
+                    .unwrap_or_else(|| "unknown speed".to_string())
+            };
+            let lanes = |width: Option<u8>| {
+                width
+                    .map(|lanes| format!("x{}", lanes))
+                    .unwrap_or_else(|| "unknown width".to_string())
+            };
+            Some(InventoryFinding {
+                severity: FindingSeverity::Warning,
+                component: "pci".to_string(),
+                message: format!(
+                    "{} ({}) link running at {} {}, capable of {} {}",
+                    device.address,
+                    device.display_name(),
+                    gts(link.current_speed_gts),
+                    lanes(link.current_width),
+                    gts(link.max_speed_gts),
+                    lanes(link.max_width)
+                ),
+            })
+        })
+        .collect()
+}
+
+/// Checks the negotiated link speeds against the expected speeds.
+///
+/// Reports links that came up slower than expected, e.g. a 100G port linked
+/// at 25G, links without a speed although one is expected, and links that
+/// are faster than expected, which usually means the expectation is stale.
+pub fn check_network(
+    network: &NetworkInventory,
+    expectations: &InventoryExpectations,
+) -> Vec<InventoryFinding> {
+    let links = network
+        .interfaces
+        .iter()
+        .map(|interface| {
+            (
+                interface.name.clone(),
+                interface.speed_mbps,
+                interface.operstate.clone(),
+            )
+        })
+        .chain(network.rdma_devices.iter().flat_map(|device| {
+            device.ports.iter().map(move |port| {
+                (
+                    format!("{}/{}", device.name, port.port),
+                    port.rate_gbps.map(|gbps| (gbps * 1000.0).round() as u64),
+                    port.state.clone().unwrap_or_else(|| "unknown".to_string()),
+                )
+            })
+        }));
+
+    links
+        .filter_map(|(name, speed, state)| {
+            let expected = expectations.link_speed_mbps(&name)?;
+            let (severity, message) = match speed {
+                None => (
+                    FindingSeverity::Warning,
+                    format!(
+                        "{} reports no link speed (state {}), expected {}",
+                        name,
+                        state,
+                        format_link_speed(expected)
+                    ),
+                ),
+                Some(speed) if speed < expected => (
+                    FindingSeverity::Warning,
+                    format!(
+                        "{} linked at {}, expected {}",
+                        name,
+                        format_link_speed(speed),
+                        format_link_speed(expected)
+                    ),
+                ),
+                Some(speed) if speed > expected => (
+                    FindingSeverity::Info,
+                    format!(
+                        "{} linked at {}, faster than the expected {}",
+                        name,
+                        format_link_speed(speed),
+                        format_link_speed(expected)
+                    ),
+                ),
+                Some(_) => return None,
+            };
+            Some(InventoryFinding {
+                severity,
+                component: "network".to_string(),
+                message,
+            })
+        })
+        .collect()
+}
+
+/// Formats a link speed in Mb/s as "25G" or "100M".
+pub fn format_link_speed(mbps: u64) -> String {
+    if mbps >= 1000 && mbps.is_multiple_of(1000) {
+        format!("{}G", mbps / 1000)
+    } else if mbps >= 1000 {
+        format!("{:.1}G", mbps as f64 / 1000.0)
+    } else {
+        format!("{}M", mbps)
+    }
+}
+
+/// Derives the memory channel a slot belongs to from its labels.
+///
+/// Labels such as "P0_Node0_Channel0_Dimm1" or "ChannelA-DIMM0" name the
+/// channel explicitly; otherwise the trailing slot number is stripped from the
+/// locator, turning "DIMM_A1" and "DIMM_A2" into channel "DIMM_A".
+pub fn channel_key(device: &MemoryDevice) -> String {
+    for label in [&device.bank_locator, &device.locator] {
+        let Some(position) = label.to_ascii_lowercase().find("channel") else {
+            continue;
+        };
+        let rest = &label[position + "channel".len()..];
+        let id: String = rest
+            .trim_start_matches([' ', '_', '-'])
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect();
+        return format!("{}Channel{}", &label[..position], id);
+    }
+    device
+        .locator
+        .trim_end_matches(|c: char| c.is_ascii_digit())
+        .to_string()
+}
+
+/// Counts the devices by a property, skipping devices where it is unknown.
+fn count_by(
+    devices: &[&MemoryDevice],
+    property: impl Fn(&MemoryDevice) -> Option<String>,
+) -> BTreeMap<String, usize> {
+    let mut counts = BTreeMap::new();
+    for device in devices {
+        if let Some(value) = property(device) {
+            *counts.entry(value).or_insert(0) += 1;
+        }
+    }
+    counts
+}
+
+/// Formats counts as "DDR4 x 4, DDR5 x 2".
+fn describe_counts(counts: &BTreeMap<String, usize>) -> String {
+    counts
+        .iter()
+        .map(|(value, count)| format!("{} x {}", value, count))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::inventory::NetworkInterface;
+
+    /// Creates a DIMM slot of the first array, populated with a DDR4 DIMM of
+    /// a size, rated and configured speed and part number, or empty.
+    fn dimm(locator: &str, dimm: Option<(u64, u32, u32, &str)>) -> MemoryDevice {
+        MemoryDevice {
+            handle: 0x1100,
+            array_handle: 0x1000,
+            locator: locator.to_string(),
+            bank_locator: "P0_Node0".to_string(),
+            size_mib: dimm.map(|(mib, _, _, _)| mib),
+            memory_type: "DDR4".to_string(),
+            form_factor: "DIMM".to_string(),
+            speed_mts: dimm.map(|(_, rated, _, _)| rated),
+            configured_speed_mts: dimm.map(|(_, _, configured, _)| configured),
+            manufacturer: dimm.map(|_| "Samsung".to_string()),
+            part_number: dimm.map(|(_, _, _, part)| part.to_string()),
+            serial: None,
+            rank: Some(2),
+            data_width: Some(64),
+        }
+    }
+
+    /// A 32 GiB DDR4-3200 DIMM running at its rated speed.
+    const RDIMM_32G: (u64, u32, u32, &str) = (32768, 3200, 3200, "M393A4K40DB3-CWE");
+
+    /// Checks the memory of DIMM slots, returning the severity and message
+    /// of every finding.
+    fn check(devices: Vec<MemoryDevice>) -> Vec<(FindingSeverity, String)> {
+        let memory = MemoryInventory {
+            arrays: Vec::new(),
+            devices,
+        };
+        check_memory(&memory)
+            .into_iter()
+            .map(|finding| (finding.severity, finding.message))
+            .collect()
+    }
+
+    #[test]
+    fn accepts_matching_dimms_populated_evenly() {
+        let findings = check(vec![
+            dimm("DIMM_A1", Some(RDIMM_32G)),
+            dimm("DIMM_A2", None),
+            dimm("DIMM_B1", Some(RDIMM_32G)),
+            dimm("DIMM_B2", None),
+        ]);
+        assert_eq!(findings, vec![]);
+        assert_eq!(check(vec![dimm("DIMM_A1", None)]), vec![]);
+    }
+
+    #[test]
+    fn flags_mixed_speeds() {
+        let findings = check(vec![
+            dimm("DIMM_A1", Some(RDIMM_32G)),
+            dimm("DIMM_B1", Some((32768, 2933, 2933, "M393A4K40DB3-CWE"))),
+        ]);
+        assert_eq!(
+            findings,
+            vec![(
+                FindingSeverity::Warning,
+                "Mixed DIMM speeds configured: 2933 MT/s x 1, 3200 MT/s x 1".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn flags_mixed_sizes_without_unbalancing_the_channels() {
+        let rdimm_16g = (16384, 3200, 3200, "M393A4K40DB3-CWE");
+        let findings = check(vec![
+            dimm("DIMM_A1", Some(RDIMM_32G)),
+            dimm("DIMM_A2", Some(rdimm_16g)),
+            dimm("DIMM_B1", Some(RDIMM_32G)),
+            dimm("DIMM_B2", Some(rdimm_16g)),
+        ]);
+        assert_eq!(
+            findings,
+            vec![(
+                FindingSeverity::Warning,
+                "Mixed DIMM sizes installed: 16384 MiB x 2, 32768 MiB x 2".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn flags_mixed_part_numbers_and_dimms_slowed_down() {
+        let findings = check(vec![
+            dimm("DIMM_A1", Some((32768, 3200, 2933, "M393A4K40DB3-CWE"))),
+            dimm("DIMM_B1", Some((32768, 2933, 2933, "HMA84GR7CJR4N-WM"))),
+        ]);
+        assert_eq!(
+            findings,
+            vec![
+                (
+                    FindingSeverity::Info,
+                    "Mixed DIMM part numbers installed: HMA84GR7CJR4N-WM x 1, \
+                     M393A4K40DB3-CWE x 1"
+                        .to_string()
+                ),
+                (
+                    FindingSeverity::Info,
+                    "DIMMs configured below their rated speed: DIMM_A1 (2933 of 3200 MT/s)"
+                        .to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn flags_channels_populated_unevenly() {
+        let findings = check(vec![
+            dimm("DIMM_A1", Some(RDIMM_32G)),
+            dimm("DIMM_A2", Some(RDIMM_32G)),
+            dimm("DIMM_B1", Some(RDIMM_32G)),
+            dimm("DIMM_B2", None),
+        ]);
+        assert_eq!(
+            findings,
+            vec![(
+                FindingSeverity::Warning,
+                "Memory channels are populated unevenly: DIMM_A 2 DIMM(s) 65536 MiB, \
+                 DIMM_B 1 DIMM(s) 32768 MiB"
+                    .to_string()
+            )]
+        );
+    }
+
+    /// Creates expectations from pairs of names or patterns and speeds.
+    fn expectations(speeds: &[(&str, u64)]) -> InventoryExpectations {
+        InventoryExpectations {
+            link_speeds_mbps: speeds
+                .iter()
+                .map(|(name, mbps)| (name.to_string(), *mbps))
+                .collect(),
+        }
+    }
+
+    /// Creates a physical interface linked at a speed.
+    fn interface(name: &str, speed_mbps: Option<u64>) -> NetworkInterface {
+        NetworkInterface {
+            name: name.to_string(),
+            driver: None,
+            mac: None,
+            mtu: None,
+            speed_mbps,
+            duplex: None,
+            operstate: if speed_mbps.is_some() { "up" } else { "down" }.to_string(),
+            carrier_changes: None,
+            numa_node: None,
+            rx_queues: 0,
+            tx_queues: 0,
+            pci_address: None,
+            is_virtual: false,
+        }
+    }
+
+    #[test]
+    fn prefers_exact_names_then_the_longest_pattern() {
+        let expectations = expectations(&[
+            ("eth*", 10_000),
+            ("eth1*", 25_000),
+            ("eth1", 100_000),
+            ("mlx5_*", 200_000),
+        ]);
+        assert_eq!(expectations.link_speed_mbps("eth1"), Some(100_000));
+        // "eth1*" also matches eth10 and is longer than "eth*".
+        assert_eq!(expectations.link_speed_mbps("eth10"), Some(25_000));
+        assert_eq!(expectations.link_speed_mbps("eth2"), Some(10_000));
+        assert_eq!(expectations.link_speed_mbps("eth"), Some(10_000));
+        assert_eq!(expectations.link_speed_mbps("mlx5_0/1"), Some(200_000));
+        assert_eq!(expectations.link_speed_mbps("ens1f0"), None);
+
+        assert!(name_matches("eth*", "eth10"));
+        assert!(name_matches("eth1", "eth1"));
+        assert!(!name_matches("eth1", "eth10"));
+    }
+
+    #[test]
+    fn flags_links_slower_faster_or_without_a_speed() {
+        let network = NetworkInventory {
+            interfaces: vec![
+                interface("ens1f0", Some(25_000)),
+                interface("ens1f1", None),
+                interface("ens2f0", Some(100_000)),
+                interface("ens3f0", Some(200_000)),
+                interface("eno1", Some(1000)),
+            ],
+            rdma_devices: Vec::new(),
+        };
+        let findings: Vec<(FindingSeverity, String)> = check_network(
+            &network,
+            &expectations(&[("ens*", 100_000), ("eno1", 10_000)]),
+        )
+        .into_iter()
+        .map(|finding| (finding.severity, finding.message))
+        .collect();
+        assert_eq!(
+            findings,
+            vec![
+                (
+                    FindingSeverity::Warning,
+                    "ens1f0 linked at 25G, expected 100G".to_string()
+                ),
+                (
+                    FindingSeverity::Warning,
+                    "ens1f1 reports no link speed (state down), expected 100G".to_string()
+                ),
+                (
+                    FindingSeverity::Info,
+                    "ens3f0 linked at 200G, faster than the expected 100G".to_string()
+                ),
+                (
+                    FindingSeverity::Warning,
+                    "eno1 linked at 1G, expected 10G".to_string()
+                ),
+            ]
+        );
+        assert_eq!(check_network(&network, &expectations(&[])), vec![]);
+    }
+
+    #[test]
+    fn formats_link_speeds() {
+        assert_eq!(format_link_speed(100), "100M");
+        assert_eq!(format_link_speed(1000), "1G");
+        assert_eq!(format_link_speed(2500), "2.5G");
+        assert_eq!(format_link_speed(400_000), "400G");
+    }
+
+    #[test]
+    fn derives_channels_from_slot_labels() {
+        let mut device = dimm("DIMM_A1", None);
+        assert_eq!(channel_key(&device), "DIMM_A");
+        device.bank_locator = "P0_Node0_Channel0_Dimm1".to_string();
+        assert_eq!(channel_key(&device), "P0_Node0_Channel0");
+        device.bank_locator = "BANK 0".to_string();
+        device.locator = "ChannelA-DIMM0".to_string();
+        assert_eq!(channel_key(&device), "ChannelA");
+    }
+}
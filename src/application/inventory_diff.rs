@@ -0,0 +1,389 @@
+//! Inventory Diff
+//!
+//! This module compares two inventory snapshots of a host and reports the
+//! components that were added, removed or changed, e.g. a DIMM disappearing
+//! after an RMA, a BIOS update, or a NIC renegotiating a slower link.
+//!
+//! Each section is flattened into named components with a list of properties
+//! and the flattened forms are compared. The components of a section that was
+//! discovered in only one of the snapshots are reported as added or removed.
+
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+use crate::domain::inventory::{
+    ChangeKind, CpuInventory, HostInventory, InventoryChange, MemoryInventory, NetworkInventory,
+    PciDevice, PlatformInfo, StorageInventory,
+};
+
+/// Components by name, each with its properties in display order.
+type Components = BTreeMap<String, Vec<(&'static str, String)>>;
+
+/// Compares two snapshots of a host.
+///
+/// # Returns
+/// The changes from `older` to `newer`, grouped by section.
+pub fn diff_inventories(older: &HostInventory, newer: &HostInventory) -> Vec<InventoryChange> {
+    let mut changes = Vec::new();
+    changes.extend(diff_section(
+        older.platform.as_ref(),
+        newer.platform.as_ref(),
+        platform_components,
+    ));
+    changes.extend(diff_section(
+        older.cpu.as_ref(),
+        newer.cpu.as_ref(),
+        cpu_components,
+    ));
+    changes.extend(diff_section(
+        older.memory.as_ref(),
+        newer.memory.as_ref(),
+        memory_components,
+    ));
+    changes.extend(diff_section(
+        older.pci.as_deref(),
+        newer.pci.as_deref(),
+        pci_components,
+    ));
+    changes.extend(diff_section(
+        older.storage.as_ref(),
+        newer.storage.as_ref(),
+        storage_components,
+    ));
+    changes.extend(diff_section(
+        older.network.as_ref(),
+        newer.network.as_ref(),
+        network_components,
+    ));
+    changes
+}
+
+/// Compares a section, treating a section that was not discovered as one
+/// without components.
+fn diff_section<T: ?Sized>(
+    older: Option<&T>,
+    newer: Option<&T>,
+    components: fn(&T) -> Components,
+) -> Vec<InventoryChange> {
+    diff_components(
+        &older.map(components).unwrap_or_default(),
+        &newer.map(components).unwrap_or_default(),
+    )
+}
+
+/// Compares two sets of flattened components.
+fn diff_components(older: &Components, newer: &Components) -> Vec<InventoryChange> {
+    let mut changes = Vec::new();
+
+    for (name, old_properties) in older {
+        let Some(new_properties) = newer.get(name) else {
+            changes.push(InventoryChange {
+                kind: ChangeKind::Removed,
+                component: name.clone(),
+                detail: summarize(old_properties),
+            });
+            continue;
+        };
+
+        let value = |properties: &[(&'static str, String)], key: &str| {
+            properties
+                .iter()
+                .find(|(property, _)| *property == key)
+                .map(|(_, value)| value.clone())
+                .unwrap_or_else(|| "none".to_string())
+        };
+        let mut keys: Vec<&'static str> = old_properties.iter().map(|(key, _)| *key).collect();
+        for (key, _) in new_properties {
+            if !keys.contains(key) {
+                keys.push(key);
+            }
+        }
+        for key in keys {
+            let (old_value, new_value) = (value(old_properties, key), value(new_properties, key));
+            if old_value != new_value {
+                changes.push(InventoryChange {
+                    kind: ChangeKind::Changed,
+                    component: name.clone(),
+                    detail: format!("{}: {} -> {}", key, old_value, new_value),
+                });
+            }
+        }
+    }
+
+    for (name, properties) in newer {
+        if !older.contains_key(name) {
+            changes.push(InventoryChange {
+                kind: ChangeKind::Added,
+                component: name.clone(),
+                detail: summarize(properties),
+            });
+        }
+    }
+
+    changes
+}
+
+/// Summarizes the properties of an added or removed component.
+fn summarize(properties: &[(&'static str, String)]) -> String {
+    if properties.is_empty() {
+        return "present".to_string();
+    }
+    properties
+        .iter()
+        .map(|(key, value)| format!("{} {}", key, value))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Formats an optional value, showing "none" when it is absent.
+fn opt<T: Display>(value: &Option<T>) -> String {
+    value
+        .as_ref()
+        .map(|value| value.to_string())
+        .unwrap_or_else(|| "none".to_string())
+}
+
+/// Flattens the platform section into components.
+fn platform_components(platform: &PlatformInfo) -> Components {
+    let mut components = Components::new();
+    components.insert(
+        "platform".to_string(),
+        vec![
+            ("BIOS vendor", opt(&platform.bios_vendor)),
+            ("BIOS version", opt(&platform.bios_version)),
+            ("BIOS date", opt(&platform.bios_release_date)),
+            ("system manufacturer", opt(&platform.system_manufacturer)),
+            ("system product", opt(&platform.system_product)),
+            ("system serial", opt(&platform.system_serial)),
+            ("system UUID", opt(&platform.system_uuid)),
+            ("board manufacturer", opt(&platform.board_manufacturer)),
+            ("board product", opt(&platform.board_product)),
+        ],
+    );
+    components
+}
+
+/// Flattens the cpu section into components.
+fn cpu_components(cpu: &CpuInventory) -> Components {
+    let mut components = Components::new();
+    components.insert(
+        "cpu".to_string(),
+        vec![
+            ("model", cpu.model.clone()),
+            ("sockets", cpu.sockets.to_string()),
+            ("cores", cpu.cores.to_string()),
+            ("threads", cpu.threads.to_string()),
+            ("SMT", if cpu.smt_active { "on" } else { "off" }.to_string()),
+            (
+                "max frequency",
+                opt(&cpu.frequency.max_mhz.map(|mhz| format!("{:.0} MHz", mhz))),
+            ),
+            ("governors", cpu.frequency.governors.join(", ")),
+            ("NUMA nodes", cpu.numa_nodes.len().to_string()),
+        ],
+    );
+    for cache in &cpu.caches {
+        components.insert(
+            format!(
+                "cpu L{} {} cache of {} KiB",
+                cache.level, cache.cache_type, cache.size_kib
+            ),
+            vec![("instances", cache.instances.to_string())],
+        );
+    }
+    for flag in &cpu.flags {
+        components.insert(format!("cpu flag {}", flag), Vec::new());
+    }
+    components
+}
+
+/// Flattens the memory section into components.
+fn memory_components(memory: &MemoryInventory) -> Components {
+    memory
+        .populated()
+        .map(|device| {
+            let name = format!("memory {} {}", device.locator, device.bank_locator);
+            (
+                name.trim_end().to_string(),
+                vec![
+                    (
+                        "size",
+                        opt(&device.size_mib.map(|mib| format!("{} MiB", mib))),
+                    ),
+                    ("type", device.memory_type.clone()),
+                    (
+                        "speed",
+                        opt(&device.speed_mts.map(|s| format!("{} MT/s", s))),
+                    ),
+                    (
+                        "configured speed",
+                        opt(&device.configured_speed_mts.map(|s| format!("{} MT/s", s))),
+                    ),
+                    ("manufacturer", opt(&device.manufacturer)),
+                    ("part number", opt(&device.part_number)),
+                    ("serial", opt(&device.serial)),
+                    ("rank", opt(&device.rank)),
+                ],
+            )
+        })
+        .collect()
+}
+
+/// Flattens the pci section into components.
+fn pci_components(devices: &[PciDevice]) -> Components {
+    devices
+        .iter()
+        .map(|device| {
+            let link = device.link.as_ref();
+            (
+                format!("pci {}", device.address),
+                vec![
+                    (
+                        "device",
+                        format!("{:04x}:{:04x}", device.vendor_id, device.device_id),
+                    ),
+                    ("name", device.display_name()),
+                    ("driver", opt(&device.driver)),
+                    ("NUMA node", opt(&device.numa_node)),
+                    (
+                        "link speed",
+                        opt(&link
+                            .and_then(|l| l.current_speed_gts)
+                            .map(|gts| format!("{} GT/s", gts))),
+                    ),
+                    (
+                        "link width",
+                        opt(&link
+                            .and_then(|l| l.current_width)
+                            .map(|w| format!("x{}", w))),
+                    ),
+                ],
+            )
+        })
+        .collect()
+}
+
+/// Flattens the storage section into components.
+fn storage_components(storage: &StorageInventory) -> Components {
+    storage
+        .devices
+        .iter()
+        .map(|device| {
+            // Kernel names can move between boots, so prefer the serial number.
+            let id = device.serial.clone().unwrap_or_else(|| device.name.clone());
+            (
+                format!("storage {}", id),
+                vec![
+                    ("name", device.name.clone()),
+                    ("model", opt(&device.model)),
+                    ("size", format!("{} bytes", device.size_bytes)),
+                    ("firmware", opt(&device.firmware)),
+                    ("scheduler", opt(&device.scheduler)),
+                    ("logical block size", opt(&device.logical_block_size)),
+                    ("physical block size", opt(&device.physical_block_size)),
+                ],
+            )
+        })
+        .collect()
+}
+
+/// Flattens the network section into components.
+fn network_components(network: &NetworkInventory) -> Components {
+    let mut components: Components = network
+        .interfaces
+        .iter()
+        .filter(|interface| !interface.is_virtual)
+        .map(|interface| {
+            (
+                format!("network {}", interface.name),
+                vec![
+                    ("driver", opt(&interface.driver)),
+                    ("MAC", opt(&interface.mac)),
+                    ("MTU", opt(&interface.mtu)),
+                    (
+                        "speed",
+                        opt(&interface.speed_mbps.map(|mbps| format!("{} Mb/s", mbps))),
+                    ),
+                    ("duplex", opt(&interface.duplex)),
+                    ("state", interface.operstate.clone()),
+                    ("PCI address", opt(&interface.pci_address)),
+                ],
+            )
+        })
+        .collect();
+
+    for device in &network.rdma_devices {
+        components.insert(
+            format!("rdma {}", device.name),
+            vec![
+                ("firmware", opt(&device.firmware)),
+                ("PCI address", opt(&device.pci_address)),
+            ],
+        );
+        for port in &device.ports {
+            components.insert(
+                format!("rdma {}/{}", device.name, port.port),
+                vec![
+                    ("state", opt(&port.state)),
+                    ("physical state", opt(&port.physical_state)),
+                    ("link layer", opt(&port.link_layer)),
+                    ("rate", opt(&port.rate)),
+                ],
+            );
+        }
+    }
+    components
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+
+    /// Creates an inventory with only the platform section.
+    fn inventory(bios_version: &str) -> HostInventory {
+        HostInventory {
+            host: "node1".to_string(),
+            collected_at: Utc::now(),
+            cpu: None,
+            platform: Some(PlatformInfo {
+                bios_version: Some(bios_version.to_string()),
+                ..Default::default()
+            }),
+            memory: None,
+            pci: None,
+            storage: None,
+            network: None,
+            findings: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn reports_sections_discovered_in_one_snapshot_only() {
+        let older = inventory("1.0");
+        let mut newer = inventory("1.1");
+        newer.pci = Some(Vec::new());
+        newer.storage = Some(StorageInventory::default());
+        let changes = diff_inventories(&older, &newer);
+        assert_eq!(
+            changes,
+            vec![InventoryChange {
+                kind: ChangeKind::Changed,
+                component: "platform".to_string(),
+                detail: "BIOS version: 1.0 -> 1.1".to_string(),
+            }]
+        );
+
+        newer.platform = None;
+        let changes = diff_inventories(&older, &newer);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].kind, ChangeKind::Removed);
+        assert_eq!(changes[0].component, "platform");
+        assert!(changes[0].detail.contains("BIOS version 1.0"));
+
+        let changes = diff_inventories(&newer, &older);
+        assert_eq!(changes[0].kind, ChangeKind::Added);
+        assert_eq!(changes[0].component, "platform");
+    }
+}
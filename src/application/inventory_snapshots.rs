@@ -0,0 +1,175 @@
+//! Inventory Snapshots
+//!
+//! This module stores every discovered inventory in the database, keyed by
+//! host and collection time, so that later runs can be compared against it.
+//! Every discovered section is stored with it, e.g. the block devices and
+//! filesystems of `discover --storage`.
+
+use std::sync::Arc;
+
+use common::ports::log_port::LoggerPort;
+
+use crate::domain::inventory::HostInventory;
+use crate::domain::record::RecordQuery;
+use crate::ports::repository_port::InventoryRepository;
+
+/// Stores and loads inventory snapshots.
+pub struct InventorySnapshots {
+    logger: Arc<dyn LoggerPort>,
+    repository: Arc<InventoryRepository>,
+}
+
+impl InventorySnapshots {
+    /// Creates a new instance of `InventorySnapshots`.
+    pub fn new(logger: Arc<dyn LoggerPort>, repository: Arc<InventoryRepository>) -> Self {
+        InventorySnapshots { logger, repository }
+    }
+
+    /// Stores an inventory as a snapshot.
+    ///
+    /// # Returns
+    /// The snapshot identifier of the stored inventory.
+    pub fn save(&self, inventory: &HostInventory) -> Result<String, String> {
+        let id = inventory.snapshot_id();
+        self.repository.save(inventory)?;
+        self.logger.log_info(&format!(
+            "Stored inventory snapshot {} of {}",
+            id, inventory.host
+        ));
+        Ok(id)
+    }
+
+    /// Loads a snapshot of a host by identifier.
+    pub fn get(&self, host: &str, id: &str) -> Result<Option<HostInventory>, String> {
+        self.repository.get(&HostInventory::key_for(host, id))
+    }
+
+    /// Loads all snapshots of a host, oldest first.
+    ///
+    /// Snapshots that fail to decode are logged and skipped.
+    pub fn list(&self, host: &str) -> Result<Vec<HostInventory>, String> {
+        Ok(self
+            .repository
+            .list(&RecordQuery::new().with_host(host))?
+            .items)
+    }
+
+    /// Loads the most recent snapshot of the host of `newer` taken before it
+    /// that discovered the same sections, so that partial discovery runs are
+    /// not compared with snapshots they cannot be compared with.
+    pub fn previous(&self, newer: &HostInventory) -> Result<Option<HostInventory>, String> {
+        let (id, sections) = (newer.snapshot_id(), newer.sections());
+        Ok(self
+            .list(&newer.host)?
+            .into_iter()
+            .rev()
+            .find(|snapshot| snapshot.snapshot_id() < id && snapshot.sections() == sections))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+    use common::adapters::capture_log_adapter::CapturingLogger;
+
+    use super::*;
+    use crate::adapters::discovery_adapter::{write_tree, SysfsDiscoveryAdapter};
+    use crate::adapters::memory_database_adapter::MemoryDatabaseAdapter;
+    use crate::adapters::repository_adapter::TreeRepository;
+    use crate::application::discovery::{self, DiscoverySections};
+    use crate::application::inventory_checks::InventoryExpectations;
+    use crate::domain::inventory::PlatformInfo;
+
+    #[test]
+    fn compares_against_the_previous_snapshot_of_the_same_sections() {
+        let logger = Arc::new(CapturingLogger::new());
+        let db = MemoryDatabaseAdapter::new();
+        let snapshots = InventorySnapshots::new(
+            logger.clone(),
+            Arc::new(TreeRepository::open(logger, &db).unwrap()),
+        );
+
+        let snapshot = |minutes: i64, pci: bool| HostInventory {
+            host: "node1".to_string(),
+            collected_at: Utc::now() - Duration::minutes(60 - minutes),
+            cpu: None,
+            platform: Some(PlatformInfo::default()),
+            memory: None,
+            pci: pci.then(Vec::new),
+            storage: None,
+            network: None,
+            findings: Vec::new(),
+        };
+        let complete = snapshot(0, true);
+        let partial = snapshot(10, false);
+        let newer = snapshot(20, true);
+        for inventory in [&complete, &partial, &newer] {
+            snapshots.save(inventory).unwrap();
+        }
+
+        let previous = snapshots.previous(&newer).unwrap().unwrap();
+        assert_eq!(previous.snapshot_id(), complete.snapshot_id());
+        let previous = snapshots.previous(&snapshot(30, false)).unwrap().unwrap();
+        assert_eq!(previous.snapshot_id(), partial.snapshot_id());
+        assert!(snapshots.previous(&complete).unwrap().is_none());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn stores_the_storage_section_with_the_inventory() {
+        let root = tempfile::tempdir().unwrap();
+        write_tree(
+            root.path(),
+            &[
+                ("sys/class/nvme/nvme0/transport", "pcie\n"),
+                ("sys/class/nvme/nvme0/address", "0000:3b:00.0\n"),
+                ("sys/class/nvme/nvme0/model", "SAMSUNG MZQL2960HCJR\n"),
+                ("sys/block/nvme0n1/size", "1875385008\n"),
+                ("sys/block/nvme0n1/dev", "259:0\n"),
+                ("sys/block/nvme0n1/nsid", "1\n"),
+                ("sys/block/nvme0n1/queue/scheduler", "[none] mq-deadline\n"),
+                ("sys/block/nvme0n1/queue/rotational", "0\n"),
+                ("sys/block/nvme0n1/queue/logical_block_size", "512\n"),
+                ("sys/block/nvme0n1/queue/physical_block_size", "4096\n"),
+                ("sys/block/nvme0n1/nvme0n1p1/partition", "1\n"),
+                ("sys/block/nvme0n1/nvme0n1p1/dev", "259:1\n"),
+                ("sys/block/nvme0n1/nvme0n1p1/start", "2048\n"),
+                ("sys/block/nvme0n1/nvme0n1p1/size", "1875380912\n"),
+                (
+                    "proc/self/mountinfo",
+                    "28 1 259:1 / /data rw,relatime shared:1 - xfs /dev/nvme0n1p1 rw\n",
+                ),
+            ],
+        );
+        std::os::unix::fs::symlink(
+            root.path().join("sys/class/nvme/nvme0"),
+            root.path().join("sys/block/nvme0n1/device"),
+        )
+        .unwrap();
+
+        let logger = Arc::new(CapturingLogger::new());
+        let db = MemoryDatabaseAdapter::new();
+        let snapshots = InventorySnapshots::new(
+            logger.clone(),
+            Arc::new(TreeRepository::open(logger.clone(), &db).unwrap()),
+        );
+        let inventory = discovery::discover(
+            &SysfsDiscoveryAdapter::with_root(logger, root.path()),
+            "node1",
+            DiscoverySections::select(false, false, false, false, true, false),
+            &InventoryExpectations::default(),
+        )
+        .unwrap();
+        let id = snapshots.save(&inventory).unwrap();
+
+        let stored = snapshots.get("node1", &id).unwrap().unwrap();
+        assert_eq!(stored.storage, inventory.storage);
+        assert_eq!(stored.sections(), vec!["storage"]);
+        let storage = stored.storage.unwrap();
+        let nvme = storage.devices[0].nvme.as_ref().unwrap();
+        assert_eq!(nvme.controller.as_deref(), Some("nvme0"));
+        assert_eq!(nvme.pci_address.as_deref(), Some("0000:3b:00.0"));
+        assert_eq!(storage.devices[0].partitions[0].start_sector, Some(2048));
+        assert_eq!(storage.filesystems[0].device.as_deref(), Some("nvme0n1p1"));
+    }
+}
@@ -0,0 +1,459 @@
+//! Migrations
+//!
+//! This module upgrades the layout of the database to the current schema
+//! version, one migration at a time, and imports the records of databases
+//! written in the legacy layout, such as the OneForAll database, whose
+//! default tree holds the raw `ps` listings of the overwatch command. The
+//! schema version is kept in the metadata tree so that every migration runs
+//! once.
+
+use std::fmt::Write;
+use std::sync::Arc;
+
+use chrono::Utc;
+
+use common::ports::log_port::LoggerPort;
+
+use crate::domain::database::{MigrationReport, META_TREE, SCHEMA_VERSION, SCHEMA_VERSION_KEY};
+use crate::domain::process::ProcessSnapshot;
+use crate::domain::record::{encode_record, Record};
+use crate::ports::database_port::{DatabasePort, WriteBatch};
+
+/// A step upgrading the layout from one version to the next.
+struct Migration {
+    /// The version the migration upgrades from.
+    from: u32,
+    /// What the migration does.
+    description: &'static str,
+    /// Applies the migration to a database, filling in the report.
+    apply: fn(&dyn DatabasePort, &mut MigrationReport) -> Result<(), String>,
+}
+
+/// Every migration in the order they are applied.
+const MIGRATIONS: &[Migration] = &[Migration {
+    from: 0,
+    description: "Move the ps listings of the default tree into the processes tree",
+    apply: |db, report| move_legacy_records(db, db, true, report),
+}];
+
+/// Upgrades the database layout and imports legacy databases.
+pub struct Migrator {
+    logger: Arc<dyn LoggerPort>,
+    db: Arc<dyn DatabasePort>,
+}
+
+impl Migrator {
+    /// Creates a new instance of `Migrator`.
+    pub fn new(logger: Arc<dyn LoggerPort>, db: Arc<dyn DatabasePort>) -> Self {
+        Migrator { logger, db }
+    }
+
+    /// Returns the schema version of the database; databases without one
+    /// are in the legacy layout of version 0.
+    pub fn schema_version(&self) -> Result<u32, String> {
+        read_schema_version(self.db.as_ref())
+    }
+
+    /// Applies every migration the database has not had yet.
+    ///
+    /// # Arguments
+    /// * `dry_run` - Only reports what the migrations would do.
+    ///
+    /// # Returns
+    /// A `Result` containing the report of every pending migration, or the
+    /// error of the first migration that failed.
+    pub fn migrate(&self, dry_run: bool) -> Result<Vec<MigrationReport>, String> {
+        let version = self.schema_version()?;
+        if version > SCHEMA_VERSION {
+            return Err(format!(
+                "Database schema version {} is newer than the supported version {}",
+                version, SCHEMA_VERSION
+            ));
+        }
+
+        let mut reports = Vec::new();
+        for migration in MIGRATIONS.iter().filter(|m| m.from >= version) {
+            let mut report = MigrationReport {
+                from: migration.from,
+                to: migration.from + 1,
+                description: migration.description.to_string(),
+                dry_run,
+                ..Default::default()
+            };
+            (migration.apply)(self.db.as_ref(), &mut report)?;
+            if !dry_run {
+                self.set_schema_version(report.from, report.to)?;
+                self.logger.log_info(&format!(
+                    "Migrated the database from schema version {} to {}, moving {} records",
+                    report.from,
+                    report.to,
+                    report.total_moved()
+                ));
+            }
+            reports.push(report);
+        }
+        Ok(reports)
+    }
+
+    /// Imports the records of a database in the legacy layout, leaving the
+    /// legacy database as it is. Every legacy database is imported once.
+    ///
+    /// # Arguments
+    /// * `legacy` - The legacy database.
+    /// * `name` - The name the legacy database is remembered as imported by,
+    ///   e.g. its path.
+    /// * `dry_run` - Only reports what the import would do.
+    ///
+    /// # Returns
+    /// A `Result` containing the report of the import, `None` if the database
+    /// was imported before, or an error message.
+    pub fn import_legacy(
+        &self,
+        legacy: &dyn DatabasePort,
+        name: &str,
+        dry_run: bool,
+    ) -> Result<Option<MigrationReport>, String> {
+        if self.is_imported(name)? {
+            return Ok(None);
+        }
+        let version = read_schema_version(legacy)?;
+        if version != 0 {
+            return Err(format!(
+                "{} has schema version {} and is not a legacy database",
+                name, version
+            ));
+        }
+
+        let mut report = MigrationReport {
+            from: 0,
+            to: SCHEMA_VERSION,
+            description: format!("Import the legacy database {}", name),
+            dry_run,
+            ..Default::default()
+        };
+        move_legacy_records(legacy, self.db.as_ref(), false, &mut report)?;
+        if !dry_run {
+            self.meta()?
+                .insert(
+                    imported_key(name).as_bytes(),
+                    Utc::now().to_rfc3339().as_bytes(),
+                )
+                .map_err(|e| format!("Failed to record the import of {}: {}", name, e))?;
+            self.logger.log_info(&format!(
+                "Imported {} records from the legacy database {}",
+                report.total_moved(),
+                name
+            ));
+        }
+        Ok(Some(report))
+    }
+
+    /// Returns whether the legacy database remembered as `name` was imported,
+    /// so that it need not be opened again.
+    pub fn is_imported(&self, name: &str) -> Result<bool, String> {
+        Ok(self
+            .meta()?
+            .get(imported_key(name).as_bytes())
+            .map_err(|e| e.to_string())?
+            .is_some())
+    }
+
+    /// Opens the metadata tree.
+    fn meta(&self) -> Result<Arc<dyn DatabasePort>, String> {
+        self.db
+            .open_tree(META_TREE)
+            .map_err(|e| format!("Failed to open the {} tree: {}", META_TREE, e))
+    }
+
+    /// Stores the schema version of the database if it is still `from`, so
+    /// that a process migrating the same database concurrently is noticed.
+    fn set_schema_version(&self, from: u32, to: u32) -> Result<(), String> {
+        self.meta()?
+            .transaction(&|tx| {
+                let version = match tx.get(SCHEMA_VERSION_KEY.as_bytes())? {
+                    Some(value) => String::from_utf8_lossy(&value).parse()?,
+                    None => 0,
+                };
+                if version != from {
+                    return Err(format!(
+                        "the schema version changed from {} to {} during the migration",
+                        from, version
+                    )
+                    .into());
+                }
+                tx.insert(SCHEMA_VERSION_KEY.as_bytes(), to.to_string().as_bytes())?;
+                Ok(())
+            })
+            .map_err(|e| format!("Failed to store the schema version: {}", e))
+    }
+}
+
+/// Returns the key of the metadata tree remembering the import of a legacy database.
+fn imported_key(name: &str) -> String {
+    format!("imported/{}", name)
+}
+
+/// Reads the schema version of a database, 0 if it has none.
+pub fn read_schema_version(db: &dyn DatabasePort) -> Result<u32, String> {
+    let meta = db
+        .open_tree(META_TREE)
+        .map_err(|e| format!("Failed to open the {} tree: {}", META_TREE, e))?;
+    match meta
+        .get(SCHEMA_VERSION_KEY.as_bytes())
+        .map_err(|e| e.to_string())?
+    {
+        Some(value) => String::from_utf8_lossy(&value)
+            .parse()
+            .map_err(|e| format!("Invalid schema version: {}", e)),
+        None => Ok(0),
+    }
+}
+
+/// Moves the `ps` listings stored as raw text under any key of the default
+/// tree of `source` into the processes tree of `target`, keyed by the key they
+/// were stored under.
+///
+/// Values that hold no `ps` listing are left in place and listed as skipped.
+///
+/// # Arguments
+/// * `source` - The database in the legacy layout.
+/// * `target` - The database the records are moved to; may be `source`.
+/// * `remove` - Whether moved records are removed from `source`.
+/// * `report` - The report the moved and skipped records are added to;
+///   nothing is written in a dry run.
+fn move_legacy_records(
+    source: &dyn DatabasePort,
+    target: &dyn DatabasePort,
+    remove: bool,
+    report: &mut MigrationReport,
+) -> Result<(), String> {
+    let pairs = source.scan_prefix(b"").map_err(|e| e.to_string())?;
+    if pairs.is_empty() {
+        return Ok(());
+    }
+    // Write the moved records before removing them, so a migration that is
+    // interrupted in between moves them again when it reruns.
+    let migrated_at = Utc::now();
+    let mut inserts = WriteBatch::new();
+    let mut removals = WriteBatch::new();
+    for (key, value) in pairs {
+        let source_key = String::from_utf8_lossy(&key).to_string();
+        let snapshot = std::str::from_utf8(&value)
+            .map_err(|e| e.to_string())
+            .and_then(|text| ProcessSnapshot::parse(&source_key, migrated_at, text));
+        let Ok(snapshot) = snapshot else {
+            report.skipped.push(source_key);
+            continue;
+        };
+        inserts.insert(snapshot.key().as_bytes(), &encode_record(&snapshot)?);
+        removals.remove(&key);
+    }
+    if !inserts.is_empty() {
+        report
+            .moved
+            .insert(ProcessSnapshot::TREE.to_string(), inserts.len());
+    }
+    if report.dry_run {
+        return Ok(());
+    }
+
+    target
+        .open_tree(ProcessSnapshot::TREE)
+        .and_then(|tree| tree.apply_batch(&inserts))
+        .map_err(|e| {
+            format!(
+                "Failed to move records into {}: {}",
+                ProcessSnapshot::TREE,
+                e
+            )
+        })?;
+    if remove {
+        source
+            .apply_batch(&removals)
+            .map_err(|e| format!("Failed to remove moved records: {}", e))?;
+    }
+    Ok(())
+}
+
+/// Renders migration reports as human readable text.
+pub fn render_reports(reports: &[MigrationReport]) -> String {
+    let mut out = String::new();
+    for report in reports {
+        let _ = writeln!(
+            out,
+            "{} ({} -> {}){}",
+            report.description,
+            report.from,
+            report.to,
+            if report.dry_run { ", dry run" } else { "" }
+        );
+        if report.moved.is_empty() {
+            let _ = writeln!(out, "  no records to move");
+        }
+        for (tree, count) in &report.moved {
+            let verb = if report.dry_run {
+                "would move"
+            } else {
+                "moved"
+            };
+            let _ = writeln!(out, "  {} {} records into {}", verb, count, tree);
+        }
+        for key in &report.skipped {
+            let _ = writeln!(out, "  skipped undecodable record {}", key);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    use common::adapters::capture_log_adapter::CapturingLogger;
+
+    use crate::adapters::memory_database_adapter::MemoryDatabaseAdapter;
+    use crate::adapters::repository_adapter::TreeRepository;
+    use crate::adapters::sled_database_adapter::SledDatabaseAdapter;
+    use crate::domain::record::RecordQuery;
+    use crate::ports::repository_port::RepositoryPort;
+
+    /// A `ps aux | sort -nrk 3,3 | head` listing as the legacy overwatch stored it.
+    const PS_AUX: &str = include_str!("../../tests/fixtures/legacy/ps_aux.txt");
+
+    fn logger() -> Arc<dyn LoggerPort> {
+        Arc::new(CapturingLogger::new())
+    }
+
+    /// Opens a sled database in a temporary directory holding the legacy layout:
+    /// a ps listing under the key of the overwatch command and a value that is
+    /// not a ps listing.
+    fn legacy_database(dir: &tempfile::TempDir) -> Arc<dyn DatabasePort> {
+        let path = dir.path().join("OneForAll_database_file.db");
+        let db = SledDatabaseAdapter::new(path.to_str().unwrap(), logger()).unwrap();
+        db.insert(b"cpu_stats.txt", PS_AUX.as_bytes()).unwrap();
+        db.insert(b"notes", &[0xff, 0xfe, 0x00]).unwrap();
+        Arc::new(db)
+    }
+
+    fn snapshots(db: &dyn DatabasePort) -> Vec<ProcessSnapshot> {
+        TreeRepository::<ProcessSnapshot>::open(logger(), db)
+            .unwrap()
+            .list(&RecordQuery::new())
+            .unwrap()
+            .items
+    }
+
+    #[test]
+    fn migrates_legacy_ps_listings_in_place() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = legacy_database(&dir);
+        let migrator = Migrator::new(logger(), db.clone());
+        assert_eq!(migrator.schema_version().unwrap(), 0);
+
+        let reports = migrator.migrate(false).unwrap();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].moved.get("processes"), Some(&1));
+        assert_eq!(reports[0].skipped, vec!["notes".to_string()]);
+        assert_eq!(migrator.schema_version().unwrap(), SCHEMA_VERSION);
+
+        let snapshots = snapshots(db.as_ref());
+        assert_eq!(snapshots.len(), 1);
+        let snapshot = &snapshots[0];
+        assert_eq!(snapshot.source, "cpu_stats.txt");
+        // The header line is not a process.
+        assert_eq!(snapshot.processes.len(), 9);
+        let busiest = &snapshot.processes[0];
+        assert_eq!(busiest.user, "root");
+        assert_eq!(busiest.pid, 1842);
+        assert_eq!(busiest.cpu_percent, 98.7);
+        assert_eq!(busiest.memory_percent, 0.1);
+        assert_eq!(busiest.command, "stress-ng --cpu 8 --timeout 60s");
+
+        // Moved listings leave the default tree, undecodable values stay.
+        assert!(db.get(b"cpu_stats.txt").unwrap().is_none());
+        assert!(db.get(b"notes").unwrap().is_some());
+    }
+
+    #[test]
+    fn rerunning_migrations_changes_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = legacy_database(&dir);
+        let migrator = Migrator::new(logger(), db.clone());
+        migrator.migrate(false).unwrap();
+        let migrated = snapshots(db.as_ref());
+
+        assert!(migrator.migrate(false).unwrap().is_empty());
+        assert!(migrator.migrate(true).unwrap().is_empty());
+        assert_eq!(snapshots(db.as_ref()), migrated);
+        assert_eq!(migrator.schema_version().unwrap(), SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn dry_run_writes_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = legacy_database(&dir);
+        let migrator = Migrator::new(logger(), db.clone());
+
+        let reports = migrator.migrate(true).unwrap();
+        assert_eq!(reports[0].moved.get("processes"), Some(&1));
+        assert!(reports[0].dry_run);
+        assert_eq!(migrator.schema_version().unwrap(), 0);
+        assert!(snapshots(db.as_ref()).is_empty());
+        assert!(db.get(b"cpu_stats.txt").unwrap().is_some());
+    }
+
+    #[test]
+    fn imports_a_legacy_database_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let legacy = legacy_database(&dir);
+        let db: Arc<dyn DatabasePort> = Arc::new(MemoryDatabaseAdapter::new());
+        let migrator = Migrator::new(logger(), db.clone());
+        migrator.migrate(false).unwrap();
+        assert!(!migrator.is_imported("legacy").unwrap());
+
+        let report = migrator
+            .import_legacy(legacy.as_ref(), "legacy", false)
+            .unwrap()
+            .unwrap();
+        assert_eq!(report.moved.get("processes"), Some(&1));
+        assert!(migrator.is_imported("legacy").unwrap());
+        assert_eq!(snapshots(db.as_ref()).len(), 1);
+        // The legacy database is left as it is.
+        assert!(legacy.get(b"cpu_stats.txt").unwrap().is_some());
+
+        assert!(migrator
+            .import_legacy(legacy.as_ref(), "legacy", false)
+            .unwrap()
+            .is_none());
+        assert_eq!(snapshots(db.as_ref()).len(), 1);
+    }
+
+    #[test]
+    fn imports_the_shipped_legacy_database() {
+        // The OneForAll database of the repository holds an empty default tree.
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("OneForAll_database_file.db");
+        std::fs::create_dir(&path).unwrap();
+        let shipped = Path::new(env!("CARGO_MANIFEST_DIR")).join("OneForAll_database_file.db");
+        for file in std::fs::read_dir(shipped).unwrap() {
+            let file = file.unwrap();
+            std::fs::copy(file.path(), path.join(file.file_name())).unwrap();
+        }
+        let legacy = SledDatabaseAdapter::new(path.to_str().unwrap(), logger()).unwrap();
+        let db: Arc<dyn DatabasePort> = Arc::new(MemoryDatabaseAdapter::new());
+        let migrator = Migrator::new(logger(), db.clone());
+
+        let report = migrator
+            .import_legacy(&legacy, "OneForAll_database_file.db", false)
+            .unwrap()
+            .unwrap();
+        assert_eq!(report.total_moved(), 0);
+        assert!(report.skipped.is_empty());
+        assert!(migrator
+            .import_legacy(&legacy, "OneForAll_database_file.db", false)
+            .unwrap()
+            .is_none());
+    }
+}
@@ -1 +1,13 @@
-pub mod ai_prediction_use_case;
+// pub mod ai_prediction_use_case;
+pub mod archives;
+pub mod baselines;
+pub mod benchmark;
+pub mod database_ops;
+pub mod discovery;
+pub mod inventory_checks;
+pub mod inventory_diff;
+pub mod inventory_snapshots;
+pub mod migrations;
+pub mod retention;
+pub mod run_recorder;
+pub mod spec_check;
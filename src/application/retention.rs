@@ -0,0 +1,278 @@
+//! Retention
+//!
+//! This module keeps the sample history of overwatch from growing without
+//! bound: it rolls samples up into 1-minute and hourly rollups and removes
+//! raw samples and 1-minute rollups once the retention policy expires them.
+//! Samples taken during a run are kept with their run, so that run charts
+//! stay complete; they are removed with `database-ops prune`.
+
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+
+use common::ports::log_port::LoggerPort;
+
+use crate::domain::record::{Record, RecordQuery};
+use crate::domain::rollup::{MetricRollup, Resolution, RetentionPolicy, RetentionReport};
+use crate::ports::repository_port::{RollupRepository, SampleRepository};
+
+/// Rolls up and expires the sample history of hosts.
+pub struct Retention {
+    logger: Arc<dyn LoggerPort>,
+    samples: Arc<SampleRepository>,
+    rollups: Arc<RollupRepository>,
+    policy: RetentionPolicy,
+}
+
+impl Retention {
+    /// Creates a new instance of `Retention`.
+    ///
+    /// # Arguments
+    /// * `logger` - Logger implementation for logging messages.
+    /// * `samples` - The repository raw samples are rolled up from and expired in.
+    /// * `rollups` - The repository rollups are stored in.
+    /// * `policy` - How long every resolution is kept.
+    pub fn new(
+        logger: Arc<dyn LoggerPort>,
+        samples: Arc<SampleRepository>,
+        rollups: Arc<RollupRepository>,
+        policy: RetentionPolicy,
+    ) -> Self {
+        Retention {
+            logger,
+            samples,
+            rollups,
+            policy,
+        }
+    }
+
+    /// Rolls up every complete bucket that has not been rolled up yet, then
+    /// removes the raw samples and 1-minute rollups the policy expires.
+    ///
+    /// Hosts are handled one at a time, so that every scan only covers the
+    /// keys of one host within the time range it needs.
+    pub fn apply(&self, now: DateTime<Utc>) -> Result<RetentionReport, String> {
+        let mut report = RetentionReport::default();
+        for resolution in Resolution::ROLLUPS {
+            report.rolled_up.insert(resolution.name().to_string(), 0);
+        }
+
+        for host in self.samples.key_owners()? {
+            for resolution in Resolution::ROLLUPS {
+                let count = self.roll_up(&host, resolution, now)?;
+                *report
+                    .rolled_up
+                    .entry(resolution.name().to_string())
+                    .or_default() += count;
+            }
+
+            // Raw samples expire after at least an hour, so every expired
+            // sample has been rolled up into the buckets completed above.
+            if let Some(cutoff) = self.policy.cutoff(Resolution::Raw, now) {
+                let expired: Vec<String> = self
+                    .samples
+                    .list(&RecordQuery::new().with_host(&host).until(cutoff))?
+                    .items
+                    .iter()
+                    .filter(|sample| sample.run_id.is_none())
+                    .map(|sample| sample.key())
+                    .collect();
+                self.samples.remove_all(&expired)?;
+                report.expired_samples += expired.len();
+            }
+        }
+
+        // Rollups outlive the samples they were rolled up from.
+        if let Some(cutoff) = self.policy.cutoff(Resolution::Minute, now) {
+            for host in self.rollups.key_owners()? {
+                let query = RecordQuery::new()
+                    .with_host(&host)
+                    .with_kind(Resolution::Minute.name())
+                    .until(cutoff);
+                let expired: Vec<String> = self
+                    .rollups
+                    .list(&query)?
+                    .items
+                    .iter()
+                    .map(|rollup| rollup.key())
+                    .collect();
+                self.rollups.remove_all(&expired)?;
+                report.expired_rollups += expired.len();
+            }
+        }
+
+        if report.rolled_up.values().any(|count| *count > 0)
+            || report.expired_samples > 0
+            || report.expired_rollups > 0
+        {
+            self.logger.log_info(&format!(
+                "Rolled up {} buckets, expired {} samples and {} 1-minute rollups",
+                report.rolled_up.values().sum::<usize>(),
+                report.expired_samples,
+                report.expired_rollups
+            ));
+        }
+        Ok(report)
+    }
+
+    /// Rolls up the samples of a host in the buckets of a resolution
+    /// completed since the newest rollup of that host and resolution.
+    ///
+    /// # Returns
+    /// A `Result` containing the number of stored rollups, or an error message.
+    fn roll_up(
+        &self,
+        host: &str,
+        resolution: Resolution,
+        now: DateTime<Utc>,
+    ) -> Result<usize, String> {
+        let Some(bucket) = resolution.bucket() else {
+            return Ok(0);
+        };
+        // The rollups of a host and resolution are keyed by time, so the
+        // last key of their range is the newest one.
+        let newest = self.rollups.last_in_key_range(
+            &RecordQuery::new()
+                .with_host(host)
+                .with_kind(resolution.name()),
+        )?;
+
+        // Only complete buckets are rolled up, so rollups are never revised.
+        let mut query = RecordQuery::new()
+            .with_host(host)
+            .until(resolution.bucket_start(now));
+        if let Some(newest) = newest {
+            query = query.since(newest.start + bucket);
+        }
+        let rollups = MetricRollup::roll_up(resolution, &self.samples.list(&query)?.items);
+        self.rollups.save_all(&rollups)?;
+        Ok(rollups.len())
+    }
+
+    /// Starts a background thread that applies the retention policy every
+    /// `interval` for as long as the application is running.
+    pub fn start(self: &Arc<Self>, interval: Duration) {
+        let interval = interval.max(Duration::from_secs(1));
+        let retention = Arc::clone(self);
+        thread::spawn(move || loop {
+            if let Err(e) = retention.apply(Utc::now()) {
+                retention
+                    .logger
+                    .log_error(&format!("Failed to apply the retention policy: {}", e));
+            }
+            thread::sleep(interval);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use common::adapters::capture_log_adapter::CapturingLogger;
+
+    use super::*;
+    use crate::adapters::memory_database_adapter::MemoryDatabaseAdapter;
+    use crate::adapters::repository_adapter::TreeRepository;
+    use crate::domain::run::MetricSample;
+
+    /// Returns a time on 2024-06-10.
+    fn at(hour: u32, minute: u32, second: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 6, 10, hour, minute, second)
+            .unwrap()
+    }
+
+    /// Creates a sample of a host.
+    fn sample(host: &str, timestamp: DateTime<Utc>, run_id: Option<&str>) -> MetricSample {
+        MetricSample {
+            host: host.to_string(),
+            run_id: run_id.map(str::to_string),
+            timestamp,
+            sequence: 1,
+            cpu_utilization: Some(50.0),
+            temperature_c: None,
+            bogo_ops_per_sec: None,
+        }
+    }
+
+    /// Creates a retention over an in-memory database.
+    fn retention(
+        policy: RetentionPolicy,
+    ) -> (Retention, Arc<SampleRepository>, Arc<RollupRepository>) {
+        let logger = Arc::new(CapturingLogger::new());
+        let db = MemoryDatabaseAdapter::new();
+        let samples: Arc<SampleRepository> =
+            Arc::new(TreeRepository::open(logger.clone(), &db).unwrap());
+        let rollups: Arc<RollupRepository> =
+            Arc::new(TreeRepository::open(logger.clone(), &db).unwrap());
+        let retention = Retention::new(logger, samples.clone(), rollups.clone(), policy);
+        (retention, samples, rollups)
+    }
+
+    #[test]
+    fn rolls_up_every_host_from_its_own_newest_rollup() {
+        let (retention, samples, rollups) = retention(RetentionPolicy::default());
+        samples
+            .save_all(&[
+                sample("node-a", at(11, 58, 10), None),
+                sample("node-a", at(11, 59, 10), None),
+                sample("node-b", at(11, 30, 10), None),
+            ])
+            .unwrap();
+        // node-a was rolled up up to 11:58 before node-b's samples arrived.
+        rollups
+            .save_all(&MetricRollup::roll_up(
+                Resolution::Minute,
+                &[sample("node-a", at(11, 58, 10), None)],
+            ))
+            .unwrap();
+
+        let report = retention.apply(at(12, 0, 30)).unwrap();
+        assert_eq!(report.rolled_up["1m"], 2);
+        assert_eq!(report.rolled_up["1h"], 2);
+
+        let node_b = rollups
+            .list(
+                &RecordQuery::new()
+                    .with_host("node-b")
+                    .with_kind(Resolution::Minute.name()),
+            )
+            .unwrap();
+        assert_eq!(node_b.items.len(), 1);
+        assert_eq!(node_b.items[0].start, at(11, 30, 0));
+
+        // Rolling up again finds nothing new.
+        let report = retention.apply(at(12, 0, 40)).unwrap();
+        assert_eq!(report.rolled_up.values().sum::<usize>(), 0);
+    }
+
+    #[test]
+    fn expires_samples_and_rollups_of_every_host() {
+        let policy = RetentionPolicy {
+            raw_hours: 1,
+            minute_days: 1,
+        };
+        let (retention, samples, rollups) = retention(policy);
+        samples
+            .save_all(&[
+                sample("node-a", at(10, 0, 10), None),
+                sample("node-a", at(10, 0, 20), Some("run-1")),
+                sample("node-b", at(11, 30, 10), None),
+            ])
+            .unwrap();
+        let old = sample("node-b", at(10, 0, 10) - chrono::Duration::days(2), None);
+        rollups
+            .save_all(&MetricRollup::roll_up(Resolution::Minute, &[old]))
+            .unwrap();
+
+        let report = retention.apply(at(12, 0, 30)).unwrap();
+        assert_eq!(report.expired_samples, 1);
+        assert_eq!(report.expired_rollups, 1);
+
+        let remaining = samples.list(&RecordQuery::new()).unwrap().items;
+        assert_eq!(remaining.len(), 2);
+        assert_eq!(remaining[0].run_id.as_deref(), Some("run-1"));
+        assert_eq!(remaining[1].host, "node-b");
+    }
+}
@@ -0,0 +1,156 @@
+//! Run Recorder
+//!
+//! This module records runs, metric samples and the files runs produce in
+//! the database so that they can later be charted, compared and archived.
+
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use common::ports::log_port::LoggerPort;
+
+use crate::domain::run::{MetricSample, RunArtifact, RunRecord};
+use crate::ports::repository_port::{ArtifactRepository, RunRepository, SampleRepository};
+use crate::ports::system_metrics_port::SystemMetricsPort;
+
+/// Stores run records and metric samples taken from a `SystemMetricsPort`.
+pub struct RunRecorder {
+    logger: Arc<dyn LoggerPort>,
+    runs: Arc<RunRepository>,
+    samples: Arc<SampleRepository>,
+    artifacts: Arc<ArtifactRepository>,
+    metrics: Arc<dyn SystemMetricsPort>,
+}
+
+/// A handle to a background sampling thread started by `RunRecorder::start_sampling`.
+pub struct SamplingHandle {
+    stop: Arc<AtomicBool>,
+    thread: JoinHandle<()>,
+}
+
+impl SamplingHandle {
+    /// Stops the sampling thread and waits for it to exit.
+    pub fn stop(self) {
+        self.stop.store(true, Ordering::SeqCst);
+        let _ = self.thread.join();
+    }
+}
+
+impl RunRecorder {
+    /// Creates a new instance of `RunRecorder`.
+    pub fn new(
+        logger: Arc<dyn LoggerPort>,
+        runs: Arc<RunRepository>,
+        samples: Arc<SampleRepository>,
+        artifacts: Arc<ArtifactRepository>,
+        metrics: Arc<dyn SystemMetricsPort>,
+    ) -> Self {
+        RunRecorder {
+            logger,
+            runs,
+            samples,
+            artifacts,
+            metrics,
+        }
+    }
+
+    /// Returns the name of the host samples are taken on.
+    pub fn hostname(&self) -> String {
+        self.metrics.hostname()
+    }
+
+    /// Stores a run record, replacing any earlier state of the run.
+    pub fn save_run(&self, run: &RunRecord) -> Result<(), String> {
+        self.runs.save(run)
+    }
+
+    /// Stores a metric sample.
+    pub fn save_sample(&self, sample: &MetricSample) -> Result<(), String> {
+        self.samples.save(sample)
+    }
+
+    /// Stores a file produced by a run, replacing any earlier artifact of the
+    /// run with the same name.
+    pub fn save_artifact(&self, artifact: &RunArtifact) -> Result<(), String> {
+        self.artifacts.save(artifact)
+    }
+
+    /// Starts writing every logged message to a log file of the run and links
+    /// the file from the run record. Call before the run is first saved.
+    pub fn start_run_log(&self, run: &mut RunRecord) {
+        run.log_file = self.logger.start_run_log(&run.id);
+    }
+
+    /// Stops writing messages to the log file of a run and stores the file
+    /// as an artifact of the run, named "logs/<file>". Files the log was
+    /// rotated to during the run, e.g. "<file>.1", are stored first.
+    pub fn finish_run_log(&self, run: &RunRecord) -> Result<(), String> {
+        self.logger.finish_run_log();
+        let Some(log_file) = &run.log_file else {
+            return Ok(());
+        };
+        let path = Path::new(log_file);
+        let rotated = (1..)
+            .map(|index| format!("{}.{}", log_file, index))
+            .take_while(|rotated| Path::new(rotated).exists())
+            .collect::<Vec<_>>();
+        let mut content = Vec::new();
+        for part in rotated.iter().rev().map(Path::new).chain([path]) {
+            let mut data =
+                fs::read(part).map_err(|e| format!("Unable to read {}: {}", part.display(), e))?;
+            content.append(&mut data);
+        }
+        let name = format!(
+            "logs/{}",
+            path.file_name().unwrap_or_default().to_string_lossy()
+        );
+        self.save_artifact(&RunArtifact::new(&run.id, &name, content))
+    }
+
+    /// Takes a sample of the host without storing it, so that it can be
+    /// completed, e.g. with the rate of a stressor, before `save_sample`.
+    pub fn take_sample(&self, run_id: Option<&str>) -> Result<MetricSample, String> {
+        self.metrics.sample(run_id)
+    }
+
+    /// Takes a sample of the host and stores it.
+    pub fn record_sample(&self, run_id: Option<&str>) -> Result<MetricSample, String> {
+        let sample = self.take_sample(run_id)?;
+        self.save_sample(&sample)?;
+        Ok(sample)
+    }
+
+    /// Starts a background thread that records a sample every `interval`.
+    ///
+    /// The thread keeps running until `SamplingHandle::stop` is called.
+    pub fn start_sampling(
+        self: &Arc<Self>,
+        run_id: Option<String>,
+        interval: Duration,
+    ) -> SamplingHandle {
+        let interval = interval.max(Duration::from_millis(100));
+        let stop = Arc::new(AtomicBool::new(false));
+        let recorder = Arc::clone(self);
+        let thread_stop = Arc::clone(&stop);
+
+        let thread = thread::spawn(move || {
+            // Sleep in short steps so that stopping does not wait a full interval.
+            let step = Duration::from_millis(100).min(interval);
+            while !thread_stop.load(Ordering::SeqCst) {
+                if let Err(e) = recorder.record_sample(run_id.as_deref()) {
+                    recorder.logger.log_error(&e);
+                }
+                let mut waited = Duration::ZERO;
+                while waited < interval && !thread_stop.load(Ordering::SeqCst) {
+                    thread::sleep(step);
+                    waited += step;
+                }
+            }
+        });
+
+        SamplingHandle { stop, thread }
+    }
+}
@@ -0,0 +1,588 @@
+//! Spec Check
+//!
+//! This module loads golden specs and checks discovered inventories against
+//! them, producing a pass/fail report per requirement.
+
+use std::fmt::Write;
+use std::fs::File;
+use std::path::Path;
+
+use crate::application::discovery::DiscoverySections;
+use crate::application::inventory_checks::{format_link_speed, name_matches};
+use crate::domain::golden_spec::{GoldenSpec, SpecCheck, SpecReport};
+use crate::domain::inventory::HostInventory;
+
+/// The value reported when a section needed by a requirement is missing.
+const NOT_DISCOVERED: &str = "not discovered";
+
+/// Loads a golden spec from a YAML file.
+pub fn load_spec(path: &Path) -> Result<GoldenSpec, String> {
+    let file =
+        File::open(path).map_err(|e| format!("Unable to open spec {}: {}", path.display(), e))?;
+    serde_yaml::from_reader(file).map_err(|e| format!("Invalid spec {}: {}", path.display(), e))
+}
+
+/// Returns the inventory sections needed to check a spec.
+///
+/// The sections are marked as explicitly requested, so that a section that
+/// cannot be discovered fails the check rather than being skipped.
+pub fn required_sections(spec: &GoldenSpec) -> DiscoverySections {
+    DiscoverySections {
+        cpu: spec.cpu.is_some(),
+        platform: spec.bios.is_some(),
+        memory: spec.memory.is_some(),
+        pci: false,
+        storage: spec.nvme.is_some(),
+        network: !spec.network.is_empty(),
+        explicit: true,
+    }
+}
+
+/// Checks an inventory against a spec.
+pub fn check_spec(spec: &GoldenSpec, inventory: &HostInventory) -> SpecReport {
+    let mut checks = Vec::new();
+    let mut check = |requirement: &str, expected: String, actual: String, passed: bool| {
+        checks.push(SpecCheck {
+            requirement: requirement.to_string(),
+            expected,
+            actual,
+            passed,
+        })
+    };
+
+    if let Some(cpu_spec) = &spec.cpu {
+        let cpu = inventory.cpu.as_ref();
+        if let Some(model) = &cpu_spec.model {
+            let actual = cpu.map(|cpu| cpu.model.clone());
+            let passed = actual
+                .as_deref()
+                .is_some_and(|actual| normalize(actual) == normalize(model));
+            check("cpu model", model.clone(), found(actual), passed);
+        }
+        let counts = [
+            ("cpu sockets", cpu_spec.sockets, cpu.map(|cpu| cpu.sockets)),
+            ("cpu cores", cpu_spec.cores, cpu.map(|cpu| cpu.cores)),
+            ("cpu threads", cpu_spec.threads, cpu.map(|cpu| cpu.threads)),
+        ];
+        for (requirement, expected, actual) in counts {
+            if let Some(expected) = expected {
+                check(
+                    requirement,
+                    expected.to_string(),
+                    found(actual),
+                    actual == Some(expected),
+                );
+            }
+        }
+    }
+
+    if let Some(memory_spec) = &spec.memory {
+        let memory = inventory.memory.as_ref();
+        if let Some(total_gib) = memory_spec.total_gib {
+            let actual = memory.map(|memory| memory.total_mib() / 1024);
+            check(
+                "memory total",
+                format!("{} GiB", total_gib),
+                found(actual.map(|gib| format!("{} GiB", gib))),
+                actual == Some(total_gib),
+            );
+        }
+        if let Some(dimm_count) = memory_spec.dimm_count {
+            let actual = memory.map(|memory| memory.populated().count());
+            check(
+                "memory DIMM count",
+                dimm_count.to_string(),
+                found(actual),
+                actual == Some(dimm_count),
+            );
+        }
+        if let Some(speed_mts) = memory_spec.speed_mts {
+            // The slowest DIMM decides, since all channels run in lockstep.
+            let actual = memory.and_then(|memory| {
+                memory
+                    .populated()
+                    .filter_map(|device| device.configured_speed_mts.or(device.speed_mts))
+                    .min()
+            });
+            check(
+                "memory speed",
+                format!("at least {} MT/s", speed_mts),
+                found(actual.map(|mts| format!("{} MT/s", mts))),
+                actual.is_some_and(|actual| actual >= speed_mts),
+            );
+        }
+        if let Some(memory_type) = &memory_spec.memory_type {
+            let types: Option<Vec<String>> = memory.map(|memory| {
+                let mut types: Vec<String> = memory
+                    .populated()
+                    .map(|device| device.memory_type.clone())
+                    .collect();
+                types.sort();
+                types.dedup();
+                types
+            });
+            let passed = types.as_ref().is_some_and(|types| {
+                !types.is_empty() && types.iter().all(|t| t.eq_ignore_ascii_case(memory_type))
+            });
+            check(
+                "memory type",
+                memory_type.clone(),
+                found(types.map(|types| types.join(", "))),
+                passed,
+            );
+        }
+    }
+
+    for nic_spec in &spec.network {
+        let interfaces: Option<Vec<_>> = inventory.network.as_ref().map(|network| {
+            network
+                .interfaces
+                .iter()
+                .filter(|interface| !interface.is_virtual)
+                .filter(|interface| name_matches(&nic_spec.name, &interface.name))
+                .collect()
+        });
+        if let Some(count) = nic_spec.count {
+            let actual = interfaces.as_ref().map(|interfaces| interfaces.len());
+            check(
+                &format!("network {} count", nic_spec.name),
+                count.to_string(),
+                found(actual),
+                actual == Some(count),
+            );
+        }
+        if let Some(speed_mbps) = nic_spec.speed_mbps {
+            let requirement = format!("network {} speed", nic_spec.name);
+            match &interfaces {
+                Some(interfaces) if interfaces.is_empty() => check(
+                    &requirement,
+                    format_link_speed(speed_mbps),
+                    "no matching interface".to_string(),
+                    false,
+                ),
+                Some(interfaces) => {
+                    for interface in interfaces {
+                        check(
+                            &format!("network {} speed", interface.name),
+                            format_link_speed(speed_mbps),
+                            interface
+                                .speed_mbps
+                                .map(format_link_speed)
+                                .unwrap_or_else(|| format!("no link ({})", interface.operstate)),
+                            interface.speed_mbps == Some(speed_mbps),
+                        );
+                    }
+                }
+                None => check(
+                    &requirement,
+                    format_link_speed(speed_mbps),
+                    NOT_DISCOVERED.to_string(),
+                    false,
+                ),
+            }
+        }
+    }
+
+    if let Some(nvme_spec) = &spec.nvme {
+        let namespaces: Option<Vec<_>> = inventory.storage.as_ref().map(|storage| {
+            storage
+                .devices
+                .iter()
+                .filter(|device| device.nvme.is_some())
+                .collect()
+        });
+        if let Some(count) = nvme_spec.count {
+            let actual = namespaces.as_ref().map(|namespaces| namespaces.len());
+            check(
+                "nvme count",
+                count.to_string(),
+                found(actual),
+                actual == Some(count),
+            );
+        }
+        if let Some(capacity_gb) = nvme_spec.capacity_gb {
+            let expected = format!("at least {} GB", capacity_gb);
+            match &namespaces {
+                Some(namespaces) if !namespaces.is_empty() => {
+                    for device in namespaces {
+                        check(
+                            &format!("nvme {} capacity", device.name),
+                            expected.clone(),
+                            format!("{} GB", device.size_bytes / 1_000_000_000),
+                            capacity_gb
+                                .checked_mul(1_000_000_000)
+                                .is_some_and(|bytes| device.size_bytes >= bytes),
+                        );
+                    }
+                }
+                Some(_) => check(
+                    "nvme capacity",
+                    expected,
+                    "no NVMe namespace".to_string(),
+                    false,
+                ),
+                None => check("nvme capacity", expected, NOT_DISCOVERED.to_string(), false),
+            }
+        }
+    }
+
+    if let Some(bios_spec) = &spec.bios {
+        let platform = inventory.platform.as_ref();
+        let fields = [
+            (
+                "bios vendor",
+                &bios_spec.vendor,
+                platform.map(|p| p.bios_vendor.clone()),
+            ),
+            (
+                "bios version",
+                &bios_spec.version,
+                platform.map(|p| p.bios_version.clone()),
+            ),
+        ];
+        for (requirement, expected, actual) in fields {
+            if let Some(expected) = expected {
+                let passed =
+                    matches!(&actual, Some(Some(actual)) if actual.trim() == expected.trim());
+                let actual = match actual {
+                    Some(Some(actual)) => actual,
+                    Some(None) => "unknown".to_string(),
+                    None => NOT_DISCOVERED.to_string(),
+                };
+                check(requirement, expected.clone(), actual, passed);
+            }
+        }
+    }
+
+    SpecReport {
+        spec: spec
+            .name
+            .clone()
+            .unwrap_or_else(|| "unnamed spec".to_string()),
+        host: inventory.host.clone(),
+        snapshot_id: inventory.snapshot_id(),
+        passed: checks.iter().all(|check| check.passed),
+        checks,
+    }
+}
+
+/// Renders a spec report as a human readable report.
+pub fn render_report(report: &SpecReport) -> String {
+    let mut out = String::new();
+    let passed = report.checks.iter().filter(|check| check.passed).count();
+    let _ = writeln!(
+        out,
+        "Spec {} on {}: {} ({} of {} checks passed)",
+        report.spec,
+        report.host,
+        if report.passed { "PASS" } else { "FAIL" },
+        passed,
+        report.checks.len()
+    );
+    for check in &report.checks {
+        let _ = writeln!(
+            out,
+            "  [{}] {}: expected {}, found {}",
+            if check.passed { "PASS" } else { "FAIL" },
+            check.requirement,
+            check.expected,
+            check.actual
+        );
+    }
+    out
+}
+
+/// Formats a discovered value, or "not discovered" if the section is missing.
+fn found<T: ToString>(value: Option<T>) -> String {
+    value
+        .map(|value| value.to_string())
+        .unwrap_or_else(|| NOT_DISCOVERED.to_string())
+}
+
+/// Lowercases a model name and collapses repeated whitespace.
+fn normalize(model: &str) -> String {
+    model
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+    use crate::domain::golden_spec::{BiosSpec, CpuSpec, MemorySpec, NicSpec, NvmeSpec};
+    use crate::domain::inventory::{
+        BlockDevice, CpuFrequency, CpuInventory, MemoryDevice, MemoryInventory, NetworkInterface,
+        NetworkInventory, NvmeNamespace, PlatformInfo, StorageInventory,
+    };
+
+    /// Creates a spec requiring every kind of requirement.
+    fn spec() -> GoldenSpec {
+        GoldenSpec {
+            name: Some("compute-gen3".to_string()),
+            cpu: Some(CpuSpec {
+                // Differs from the discovered model in case and whitespace.
+                model: Some("amd epyc  9654 96-Core Processor".to_string()),
+                sockets: Some(2),
+                cores: Some(192),
+                threads: Some(384),
+            }),
+            memory: Some(MemorySpec {
+                total_gib: Some(64),
+                dimm_count: Some(2),
+                speed_mts: Some(4800),
+                memory_type: Some("DDR5".to_string()),
+            }),
+            network: vec![NicSpec {
+                name: "ens1f*".to_string(),
+                count: Some(2),
+                speed_mbps: Some(200_000),
+            }],
+            nvme: Some(NvmeSpec {
+                count: Some(2),
+                capacity_gb: Some(3840),
+            }),
+            bios: Some(BiosSpec {
+                vendor: None,
+                version: Some("1.7.3".to_string()),
+            }),
+        }
+    }
+
+    /// Creates a populated DDR5 DIMM slot.
+    fn dimm(locator: &str, configured_speed_mts: u32) -> MemoryDevice {
+        MemoryDevice {
+            handle: 0x1100,
+            array_handle: 0x1000,
+            locator: locator.to_string(),
+            bank_locator: "P0".to_string(),
+            size_mib: Some(32768),
+            memory_type: "DDR5".to_string(),
+            form_factor: "DIMM".to_string(),
+            speed_mts: Some(4800),
+            configured_speed_mts: Some(configured_speed_mts),
+            manufacturer: None,
+            part_number: None,
+            serial: None,
+            rank: None,
+            data_width: None,
+        }
+    }
+
+    /// Creates a physical interface linked at a speed.
+    fn interface(name: &str, speed_mbps: Option<u64>) -> NetworkInterface {
+        NetworkInterface {
+            name: name.to_string(),
+            driver: Some("mlx5_core".to_string()),
+            mac: None,
+            mtu: Some(9000),
+            speed_mbps,
+            duplex: None,
+            operstate: if speed_mbps.is_some() { "up" } else { "down" }.to_string(),
+            carrier_changes: None,
+            numa_node: None,
+            rx_queues: 1,
+            tx_queues: 1,
+            pci_address: None,
+            is_virtual: false,
+        }
+    }
+
+    /// Creates an NVMe namespace of a size.
+    fn namespace(name: &str, size_bytes: u64) -> BlockDevice {
+        BlockDevice {
+            name: name.to_string(),
+            dev: "259:0".to_string(),
+            model: None,
+            serial: None,
+            firmware: None,
+            size_bytes,
+            rotational: false,
+            removable: false,
+            scheduler: None,
+            nr_requests: None,
+            queue_depth: None,
+            logical_block_size: None,
+            physical_block_size: None,
+            nvme: Some(NvmeNamespace {
+                controller: None,
+                nsid: Some(1),
+                transport: Some("pcie".to_string()),
+                pci_address: None,
+            }),
+            partitions: Vec::new(),
+        }
+    }
+
+    /// Creates an inventory meeting every requirement of the spec.
+    fn inventory() -> HostInventory {
+        let mut bridge = interface("br0", Some(200_000));
+        bridge.is_virtual = true;
+        HostInventory {
+            host: "node1".to_string(),
+            collected_at: Utc::now(),
+            cpu: Some(CpuInventory {
+                vendor: "AuthenticAMD".to_string(),
+                model: "AMD EPYC 9654 96-Core Processor".to_string(),
+                sockets: 2,
+                cores: 192,
+                threads: 384,
+                smt_active: true,
+                caches: Vec::new(),
+                numa_nodes: Vec::new(),
+                frequency: CpuFrequency::default(),
+                flags: Vec::new(),
+            }),
+            platform: Some(PlatformInfo {
+                bios_vendor: Some("AMI".to_string()),
+                bios_version: Some("1.7.3 ".to_string()),
+                ..Default::default()
+            }),
+            memory: Some(MemoryInventory {
+                arrays: Vec::new(),
+                devices: vec![dimm("DIMM_A1", 4800), dimm("DIMM_B1", 4800)],
+            }),
+            pci: None,
+            storage: Some(StorageInventory {
+                devices: vec![
+                    namespace("nvme0n1", 3_840_755_982_336),
+                    namespace("nvme1n1", 3_840_755_982_336),
+                ],
+                filesystems: Vec::new(),
+            }),
+            network: Some(NetworkInventory {
+                interfaces: vec![
+                    interface("ens1f0np0", Some(200_000)),
+                    interface("ens1f1np1", Some(200_000)),
+                    interface("eno1", Some(1000)),
+                    bridge,
+                ],
+                rdma_devices: Vec::new(),
+            }),
+            findings: Vec::new(),
+        }
+    }
+
+    /// Returns the requirement and found value of every failed check.
+    fn failures(report: &SpecReport) -> Vec<(&str, &str)> {
+        report
+            .checks
+            .iter()
+            .filter(|check| !check.passed)
+            .map(|check| (check.requirement.as_str(), check.actual.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn passes_a_host_meeting_the_spec() {
+        let report = check_spec(&spec(), &inventory());
+        assert_eq!(failures(&report), vec![]);
+        assert!(report.passed);
+        assert_eq!(report.checks.len(), 15);
+
+        let rendered = render_report(&report);
+        assert!(rendered.starts_with("Spec compute-gen3 on node1: PASS (15 of 15 checks passed)\n"));
+        assert!(rendered.contains(
+            "  [PASS] cpu model: expected amd epyc  9654 96-Core Processor, \
+             found AMD EPYC 9654 96-Core Processor\n"
+        ));
+        assert!(rendered.contains(
+            "  [PASS] nvme nvme1n1 capacity: expected at least 3840 GB, found 3840 GB\n"
+        ));
+    }
+
+    #[test]
+    fn fails_every_requirement_the_host_does_not_meet() {
+        let mut inventory = inventory();
+        inventory.cpu.as_mut().unwrap().model = "AMD EPYC 9554 64-Core Processor".to_string();
+        inventory.platform.as_mut().unwrap().bios_version = Some("1.6.0".to_string());
+        inventory.memory.as_mut().unwrap().devices = vec![dimm("DIMM_A1", 4400)];
+        let network = inventory.network.as_mut().unwrap();
+        network.interfaces[0].speed_mbps = Some(100_000);
+        network.interfaces.remove(1);
+        inventory.storage.as_mut().unwrap().devices[1].size_bytes = 1_920_383_410_176;
+
+        let report = check_spec(&spec(), &inventory);
+        assert!(!report.passed);
+        assert_eq!(
+            failures(&report),
+            vec![
+                ("cpu model", "AMD EPYC 9554 64-Core Processor"),
+                ("memory total", "32 GiB"),
+                ("memory DIMM count", "1"),
+                ("memory speed", "4400 MT/s"),
+                ("network ens1f* count", "1"),
+                ("network ens1f0np0 speed", "100G"),
+                ("nvme nvme1n1 capacity", "1920 GB"),
+                ("bios version", "1.6.0"),
+            ]
+        );
+        assert!(render_report(&report)
+            .starts_with("Spec compute-gen3 on node1: FAIL (6 of 14 checks passed)\n"));
+    }
+
+    #[test]
+    fn fails_requirements_of_sections_not_discovered() {
+        let mut inventory = inventory();
+        inventory.cpu = None;
+        inventory.platform = None;
+        inventory.memory = None;
+        inventory.storage = None;
+        inventory.network = None;
+
+        let report = check_spec(&spec(), &inventory);
+        assert!(!report.passed);
+        assert_eq!(report.checks.len(), 13);
+        assert!(report
+            .checks
+            .iter()
+            .all(|check| !check.passed && check.actual == NOT_DISCOVERED));
+        assert!(render_report(&report)
+            .contains("  [FAIL] memory total: expected 64 GiB, found not discovered\n"));
+    }
+
+    #[test]
+    fn fails_a_capacity_larger_than_any_drive() {
+        let mut spec = spec();
+        spec.nvme = Some(NvmeSpec {
+            count: None,
+            capacity_gb: Some(u64::MAX),
+        });
+        let report = check_spec(&spec, &inventory());
+        assert_eq!(
+            failures(&report),
+            vec![
+                ("nvme nvme0n1 capacity", "3840 GB"),
+                ("nvme nvme1n1 capacity", "3840 GB"),
+            ]
+        );
+    }
+
+    #[test]
+    fn loads_a_spec_from_a_yaml_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("gen3.yaml");
+        std::fs::write(
+            &path,
+            "name: compute-gen3\nmemory:\n  dimm_count: 2\nnetwork:\n  - name: \"ens1f*\"\n    speed_mbps: 200000\n",
+        )
+        .unwrap();
+        let spec = load_spec(&path).unwrap();
+        assert_eq!(spec.name.as_deref(), Some("compute-gen3"));
+        assert_eq!(spec.memory.as_ref().unwrap().dimm_count, Some(2));
+
+        let sections = required_sections(&spec);
+        assert!(sections.memory && sections.network && sections.explicit);
+        assert!(!sections.cpu && !sections.storage && !sections.platform);
+
+        std::fs::write(&path, "memory:\n  dimms: 2\n").unwrap();
+        let error = load_spec(&path).unwrap_err();
+        assert!(error.starts_with("Invalid spec "), "{}", error);
+        assert!(load_spec(&dir.path().join("missing.yaml"))
+            .unwrap_err()
+            .starts_with("Unable to open spec "));
+    }
+}
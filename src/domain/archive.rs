@@ -0,0 +1,141 @@
+//! Archive Domain Entities
+//!
+//! This module provides the entities of run archives: a run bundled with its
+//! samples, the files it produced and the inventory of its host into a single
+//! file, so that it can be moved to another node and analyzed there. Every
+//! archive starts with a manifest describing the run and listing the size and
+//! SHA-256 checksum of every other entry, which are verified on import.
+//!
+//! Entries of an archive:
+//! * `manifest.json` - The `ArchiveManifest`.
+//! * `run.json` - The `RunRecord`.
+//! * `samples.ndjson` - The `MetricSample`s of the run, one per line.
+//! * `inventory.json` - The latest `HostInventory` of the host at the end of
+//!   the run, if there is one.
+//! * `artifacts/<name>` - The `RunArtifact`s of the run, e.g. raw tool output
+//!   and logs, as plain files.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// The version of the layout archives are written with.
+pub const ARCHIVE_FORMAT_VERSION: u32 = 1;
+
+/// The path of the manifest in an archive.
+pub const MANIFEST_PATH: &str = "manifest.json";
+
+/// The path of the run record in an archive.
+pub const RUN_PATH: &str = "run.json";
+
+/// The path of the samples in an archive.
+pub const SAMPLES_PATH: &str = "samples.ndjson";
+
+/// The path of the inventory snapshot in an archive.
+pub const INVENTORY_PATH: &str = "inventory.json";
+
+/// The directory of the artifacts in an archive.
+pub const ARTIFACTS_DIR: &str = "artifacts/";
+
+/// A file of an archive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveEntry {
+    /// The relative path of the file within the archive.
+    pub path: String,
+    /// The contents of the file.
+    pub data: Vec<u8>,
+}
+
+impl ArchiveEntry {
+    /// Creates an entry from its path and contents.
+    pub fn new(path: &str, data: Vec<u8>) -> Self {
+        Self {
+            path: path.to_string(),
+            data,
+        }
+    }
+}
+
+/// The size and checksum of an entry listed in the manifest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// The relative path of the entry within the archive.
+    pub path: String,
+    /// The size of the entry in bytes.
+    pub size: u64,
+    /// The SHA-256 checksum of the entry as lowercase hex.
+    pub sha256: String,
+}
+
+impl ManifestEntry {
+    /// Describes an entry of an archive.
+    pub fn describe(entry: &ArchiveEntry) -> Self {
+        Self {
+            path: entry.path.clone(),
+            size: entry.data.len() as u64,
+            sha256: format!("{:x}", Sha256::digest(&entry.data)),
+        }
+    }
+}
+
+/// Describes an archive and the entries it holds.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArchiveManifest {
+    /// The version of the layout of the archive.
+    pub format_version: u32,
+    /// The version of commandant-rs that created the archive.
+    pub created_by: String,
+    /// The time at which the archive was created.
+    pub created_at: DateTime<Utc>,
+    /// The archived run.
+    pub run_id: String,
+    /// The host the run was executed on.
+    pub host: String,
+    /// The kind of run, e.g. "stress".
+    pub kind: String,
+    /// The number of archived samples.
+    pub samples: usize,
+    /// The snapshot identifier of the archived inventory, if any.
+    pub inventory: Option<String>,
+    /// The names of the archived artifacts.
+    pub artifacts: Vec<String>,
+    /// Every other entry of the archive, in the order they were written.
+    pub entries: Vec<ManifestEntry>,
+}
+
+impl ArchiveManifest {
+    /// Checks that an archive has the supported format version and that its
+    /// entries are exactly the ones listed, with matching sizes and checksums.
+    ///
+    /// # Arguments
+    /// * `entries` - The entries of the archive other than the manifest.
+    pub fn verify(&self, entries: &[ArchiveEntry]) -> Result<(), String> {
+        if self.format_version != ARCHIVE_FORMAT_VERSION {
+            return Err(format!(
+                "unsupported archive format version {} (supported: {})",
+                self.format_version, ARCHIVE_FORMAT_VERSION
+            ));
+        }
+        for listed in &self.entries {
+            let entry = entries
+                .iter()
+                .find(|entry| entry.path == listed.path)
+                .ok_or_else(|| format!("{} is missing", listed.path))?;
+            if ManifestEntry::describe(entry) != *listed {
+                return Err(format!("{} does not match its checksum", listed.path));
+            }
+        }
+        if let Some(entry) = entries
+            .iter()
+            .find(|entry| !self.entries.iter().any(|listed| listed.path == entry.path))
+        {
+            return Err(format!("{} is not listed in the manifest", entry.path));
+        }
+        Ok(())
+    }
+
+    /// Returns the total size of the listed entries in bytes.
+    pub fn total_size(&self) -> u64 {
+        self.entries.iter().map(|entry| entry.size).sum()
+    }
+}
@@ -0,0 +1,419 @@
+//! Baseline Domain Entities
+//!
+//! This module provides the baseline of a node SKU: the value every benchmark
+//! and stress metric is expected to reach on that hardware, either derived
+//! from the median of accepted runs or imported from YAML. A SKU keeps a
+//! baseline for every kind and profile of runs, and runs are compared against
+//! the one of their SKU, kind and profile with a tolerance per metric. Metrics
+//! that fall outside their tolerance in the bad direction are regressions.
+//!
+//! A baseline is imported from YAML:
+//!
+//! ```yaml
+//! sku: compute-gen3
+//! kind: benchmark
+//! metrics:
+//!   - name: "cpu sha256 x1"
+//!     unit: "B/s"
+//!     direction: higher_is_better
+//!     value: 2.1e9
+//!   - name: "memory latency 65536KiB x1"
+//!     unit: "ns"
+//!     direction: lower_is_better
+//!     value: 95.0
+//!     tolerance_percent: 10
+//! ```
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::domain::record::{prefix_key_range, Record, RecordQuery};
+use crate::domain::run::{MetricDirection, RunMetric, RunRecord};
+
+/// The expected metrics of a node SKU.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Baseline {
+    /// The SKU the baseline applies to.
+    pub sku: String,
+    /// The kind of runs the baseline applies to, e.g. "benchmark"; `None`
+    /// applies it to runs of every kind.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    /// The profile of the runs the baseline was derived from, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    /// The time at which the baseline was created.
+    #[serde(default = "Utc::now")]
+    pub created_at: DateTime<Utc>,
+    /// The runs the baseline was derived from; empty for imported baselines.
+    #[serde(default)]
+    pub run_ids: Vec<String>,
+    /// The expected value of every metric.
+    pub metrics: Vec<BaselineMetric>,
+}
+
+/// The expected value of a metric.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BaselineMetric {
+    /// The name of the metric, e.g. "cpu sha256 x1".
+    pub name: String,
+    /// The unit of the metric, e.g. "B/s".
+    pub unit: String,
+    /// Whether higher or lower values are better; `None` takes the direction
+    /// the run recorded for the metric.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub direction: Option<MetricDirection>,
+    /// The expected value.
+    pub value: f64,
+    /// The tolerance of the metric in percent, overriding the configured ones.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tolerance_percent: Option<f64>,
+}
+
+/// The tolerances runs are compared against a baseline with.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct BaselineTolerances {
+    /// The tolerance of metrics without one of their own, in percent.
+    pub default_percent: f64,
+    /// Tolerances in percent by metric name; names ending in "*" match by prefix.
+    pub metrics: BTreeMap<String, f64>,
+}
+
+impl BaselineTolerances {
+    /// Returns the tolerance of a metric in percent: the baseline's own,
+    /// then an exact name, then the longest matching prefix, then the default.
+    pub fn for_metric(&self, metric: &BaselineMetric) -> f64 {
+        if let Some(tolerance) = metric.tolerance_percent {
+            return tolerance;
+        }
+        if let Some(tolerance) = self.metrics.get(&metric.name) {
+            return *tolerance;
+        }
+        self.metrics
+            .iter()
+            .filter_map(|(pattern, tolerance)| {
+                let prefix = pattern.strip_suffix('*')?;
+                metric
+                    .name
+                    .starts_with(prefix)
+                    .then_some((prefix.len(), *tolerance))
+            })
+            .max_by_key(|(length, _)| *length)
+            .map(|(_, tolerance)| tolerance)
+            .unwrap_or(self.default_percent)
+    }
+}
+
+impl Baseline {
+    /// Derives a baseline from the median of every metric over runs.
+    ///
+    /// # Arguments
+    /// * `sku` - The SKU the runs were made on.
+    /// * `runs` - The accepted runs, which must share their kind and profile.
+    ///
+    /// # Returns
+    /// The baseline, or an error message if the runs differ in kind or profile.
+    pub fn from_runs(sku: &str, runs: &[RunRecord]) -> Result<Self, String> {
+        let first = runs.first();
+        if let Some(first) = first {
+            let other = runs
+                .iter()
+                .find(|run| run.kind != first.kind || run.profile != first.profile);
+            if let Some(other) = other {
+                return Err(format!(
+                    "Run {} is not comparable with run {}: a {} differs from a {}",
+                    other.id,
+                    first.id,
+                    describe(other),
+                    describe(first)
+                ));
+            }
+        }
+
+        // Group the values by name, keeping the order of first appearance.
+        let mut values: Vec<(&RunMetric, Vec<f64>)> = Vec::new();
+        for metric in runs.iter().flat_map(|run| &run.metrics) {
+            match values
+                .iter_mut()
+                .find(|(first, _)| first.name == metric.name)
+            {
+                Some((_, metric_values)) => metric_values.push(metric.value),
+                None => values.push((metric, vec![metric.value])),
+            }
+        }
+
+        Ok(Baseline {
+            sku: sku.to_string(),
+            kind: first.map(|run| run.kind.clone()),
+            profile: first.and_then(|run| run.profile.clone()),
+            created_at: Utc::now(),
+            run_ids: runs.iter().map(|run| run.id.clone()).collect(),
+            metrics: values
+                .into_iter()
+                .map(|(metric, values)| BaselineMetric {
+                    name: metric.name.clone(),
+                    unit: metric.unit.clone(),
+                    direction: Some(metric.direction),
+                    value: median(values),
+                    tolerance_percent: None,
+                })
+                .collect(),
+        })
+    }
+
+    /// Returns the database key of the baseline of a SKU for runs of a kind
+    /// and profile, "*" standing for a baseline of runs of any of them, e.g.
+    /// "compute-gen3/benchmark/cpu,memory" or "compute-gen3/stress/*".
+    pub fn key_for(sku: &str, kind: Option<&str>, profile: Option<&str>) -> String {
+        format!("{}/{}/{}", sku, kind.unwrap_or("*"), profile.unwrap_or("*"))
+    }
+
+    /// Returns the keys of the baselines of a SKU a run may be compared
+    /// against, most specific first: the one of its kind and profile, the
+    /// one of its kind, then the one of runs of any kind.
+    pub fn keys_for_run(sku: &str, run: &RunRecord) -> Vec<String> {
+        let mut keys = vec![
+            Self::key_for(sku, Some(&run.kind), run.profile.as_deref()),
+            Self::key_for(sku, Some(&run.kind), None),
+            Self::key_for(sku, None, None),
+        ];
+        keys.dedup();
+        keys
+    }
+
+    /// Compares the metrics of a run against the baseline.
+    ///
+    /// Metrics of the run without a baseline are left out, and baseline
+    /// metrics the run did not produce are listed as missing.
+    pub fn compare(&self, run: &RunRecord, tolerances: &BaselineTolerances) -> BaselineComparison {
+        let mut comparisons = Vec::new();
+        let mut missing = Vec::new();
+        for expected in &self.metrics {
+            let Some(metric) = run.metrics.iter().find(|m| m.name == expected.name) else {
+                missing.push(expected.name.clone());
+                continue;
+            };
+            let tolerance_percent = tolerances.for_metric(expected);
+            let direction = expected.direction.unwrap_or(metric.direction);
+            let delta_percent = (expected.value != 0.0)
+                .then(|| (metric.value - expected.value) / expected.value.abs() * 100.0);
+            let regressed = match (delta_percent, direction) {
+                (Some(delta), MetricDirection::HigherIsBetter) => delta < -tolerance_percent,
+                (Some(delta), MetricDirection::LowerIsBetter) => delta > tolerance_percent,
+                // Anything above a baseline of zero, e.g. lost packets, is worse.
+                (None, MetricDirection::LowerIsBetter) => metric.value > 0.0,
+                (None, MetricDirection::HigherIsBetter) => false,
+            };
+            comparisons.push(MetricComparison {
+                name: expected.name.clone(),
+                unit: expected.unit.clone(),
+                baseline: expected.value,
+                value: metric.value,
+                delta_percent,
+                tolerance_percent,
+                direction,
+                regressed,
+            });
+        }
+
+        BaselineComparison {
+            sku: self.sku.clone(),
+            run_id: run.id.clone(),
+            regressions: comparisons.iter().filter(|c| c.regressed).count(),
+            comparisons,
+            missing,
+        }
+    }
+}
+
+impl Record for Baseline {
+    const TREE: &'static str = "baselines";
+
+    /// Baselines are keyed by their SKU, kind and profile, so that a SKU
+    /// keeps the baselines of every kind and profile of runs side by side.
+    fn key(&self) -> String {
+        Self::key_for(&self.sku, self.kind.as_deref(), self.profile.as_deref())
+    }
+
+    fn timestamp(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+
+    fn sku(&self) -> Option<&str> {
+        Some(&self.sku)
+    }
+
+    fn kind(&self) -> Option<&str> {
+        self.kind.as_deref()
+    }
+
+    fn key_range(query: &RecordQuery) -> Option<(String, String)> {
+        query.sku.as_deref().map(prefix_key_range)
+    }
+}
+
+/// Describes the kind and profile of a run, e.g. "benchmark run of cpu,memory".
+fn describe(run: &RunRecord) -> String {
+    match &run.profile {
+        Some(profile) => format!("{} run of {}", run.kind, profile),
+        None => format!("{} run", run.kind),
+    }
+}
+
+/// Returns the median of values, or zero if there are none.
+fn median(mut values: Vec<f64>) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    values.sort_by(f64::total_cmp);
+    let middle = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[middle - 1] + values[middle]) / 2.0
+    } else {
+        values[middle]
+    }
+}
+
+/// A metric of a run compared against its baseline.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetricComparison {
+    /// The name of the metric.
+    pub name: String,
+    /// The unit of the metric.
+    pub unit: String,
+    /// The expected value.
+    pub baseline: f64,
+    /// The value measured in the run.
+    pub value: f64,
+    /// The change from the baseline in percent, or `None` for a baseline of zero.
+    pub delta_percent: Option<f64>,
+    /// The tolerance the metric was compared with in percent.
+    pub tolerance_percent: f64,
+    /// Whether higher or lower values are better.
+    pub direction: MetricDirection,
+    /// Whether the metric is worse than its baseline by more than its tolerance.
+    pub regressed: bool,
+}
+
+/// The metrics of a run compared against the baseline of its SKU.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BaselineComparison {
+    /// The SKU of the baseline.
+    pub sku: String,
+    /// The run that was compared.
+    pub run_id: String,
+    /// The number of regressed metrics.
+    pub regressions: usize,
+    /// Every metric of the run that has a baseline.
+    pub comparisons: Vec<MetricComparison>,
+    /// The baseline metrics the run did not produce.
+    pub missing: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::run::{RunMetric, RunStatus};
+
+    /// Creates a passed run of a kind and profile with a metric.
+    fn run(kind: &str, profile: &str, metric: RunMetric) -> RunRecord {
+        let mut run = RunRecord::start("node1", kind);
+        run.profile = Some(profile.to_string());
+        run.metrics.push(metric);
+        run.finish(RunStatus::Passed);
+        run
+    }
+
+    /// Creates a metric of a direction.
+    fn metric(name: &str, unit: &str, direction: MetricDirection, value: f64) -> RunMetric {
+        RunMetric {
+            name: name.to_string(),
+            value,
+            unit: unit.to_string(),
+            direction,
+            recorded_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn compares_metrics_in_the_direction_they_were_recorded_with() {
+        let hit_rate = |value| {
+            metric(
+                "cache hit rate",
+                "%",
+                MetricDirection::HigherIsBetter,
+                value,
+            )
+        };
+        let baseline =
+            Baseline::from_runs("gen3", &[run("benchmark", "cpu", hit_rate(90.0))]).unwrap();
+        assert_eq!(baseline.kind.as_deref(), Some("benchmark"));
+        assert_eq!(baseline.profile.as_deref(), Some("cpu"));
+        assert_eq!(
+            baseline.metrics[0].direction,
+            Some(MetricDirection::HigherIsBetter)
+        );
+
+        let tolerances = BaselineTolerances {
+            default_percent: 5.0,
+            ..Default::default()
+        };
+        let better = baseline.compare(&run("benchmark", "cpu", hit_rate(99.0)), &tolerances);
+        assert_eq!(better.regressions, 0);
+        let worse = baseline.compare(&run("benchmark", "cpu", hit_rate(80.0)), &tolerances);
+        assert_eq!(worse.regressions, 1);
+        assert_eq!(
+            worse.comparisons[0].direction,
+            MetricDirection::HigherIsBetter
+        );
+
+        // The direction of an imported baseline overrides the run's.
+        let mut imported = baseline.clone();
+        imported.metrics[0].direction = Some(MetricDirection::LowerIsBetter);
+        let comparison = imported.compare(&run("benchmark", "cpu", hit_rate(99.0)), &tolerances);
+        assert_eq!(comparison.regressions, 1);
+        assert_eq!(
+            comparison.comparisons[0].direction,
+            MetricDirection::LowerIsBetter
+        );
+    }
+
+    #[test]
+    fn looks_up_the_baseline_of_the_kind_and_profile_of_a_run_first() {
+        let stress = run(
+            "stress",
+            "--cpu 4",
+            metric("bogo ops", "ops/s", MetricDirection::HigherIsBetter, 1.0),
+        );
+        assert_eq!(
+            Baseline::keys_for_run("gen3", &stress),
+            vec!["gen3/stress/--cpu 4", "gen3/stress/*", "gen3/*/*"]
+        );
+    }
+
+    #[test]
+    fn rejects_runs_of_different_profiles() {
+        let rate = || metric("cpu sha256 x1", "B/s", MetricDirection::HigherIsBetter, 1e9);
+        let error = Baseline::from_runs(
+            "gen3",
+            &[
+                run("benchmark", "cpu", rate()),
+                run("benchmark", "cpu,memory", rate()),
+            ],
+        )
+        .unwrap_err();
+        assert!(error.contains("benchmark run of cpu,memory"), "{}", error);
+        assert!(Baseline::from_runs(
+            "gen3",
+            &[
+                run("benchmark", "cpu", rate()),
+                run("stress", "cpu", rate())
+            ]
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,489 @@
+//! Benchmark Domain Entities
+//!
+//! This module provides the domain entities of a benchmark run: the cases a
+//! benchmark suite offers, the settings they are run with, and the rates
+//! measured over repeated iterations with their variance and confidence
+//! interval. Iterations far from the others are rejected as outliers, and
+//! results measured while the CPUs throttled are marked untrustworthy.
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use crate::domain::baseline::BaselineComparison;
+use crate::domain::run::{MetricDirection, RunMetric, RunStatus};
+
+/// A single case of a benchmark suite, e.g. SHA-256 hashing on every CPU.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BenchmarkCase {
+    /// The name of the case, e.g. "sha256".
+    pub name: String,
+    /// The number of threads the case runs on.
+    pub threads: usize,
+    /// The unit of the measured rate, e.g. "ops/s" or "B/s".
+    pub unit: String,
+    /// Whether higher or lower rates are better.
+    pub direction: MetricDirection,
+}
+
+/// The engines the storage benchmarks can be run with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageEngine {
+    /// fio, failing if it is not installed.
+    Fio,
+    /// The native storage benchmark.
+    Native,
+    /// fio when it is installed, and the native benchmark otherwise.
+    #[default]
+    Auto,
+}
+
+/// The settings benchmark cases are run with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BenchmarkSettings {
+    /// The number of measured iterations of every case.
+    pub iterations: usize,
+    /// The duration of a single iteration in milliseconds.
+    pub iteration_ms: u64,
+    /// The number of unmeasured iterations run before the measured ones.
+    pub warmup_iterations: usize,
+    /// Whether iterations far from the others are left out of the statistics.
+    pub reject_outliers: bool,
+}
+
+/// The measurement of a single iteration of a case.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchmarkMeasurement {
+    /// The headline value in the unit of the case.
+    pub value: f64,
+    /// Further values measured alongside, e.g. latency percentiles.
+    pub details: Vec<BenchmarkDetail>,
+}
+
+impl From<f64> for BenchmarkMeasurement {
+    /// Creates a measurement of a headline value without details.
+    fn from(value: f64) -> Self {
+        BenchmarkMeasurement {
+            value,
+            details: Vec::new(),
+        }
+    }
+}
+
+/// A named value measured alongside the headline value of a case.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchmarkDetail {
+    /// The name of the value, e.g. "read p99 latency".
+    pub name: String,
+    /// The unit of the value, e.g. "ns".
+    pub unit: String,
+    /// Whether higher or lower values are better.
+    pub direction: MetricDirection,
+    /// The measured value.
+    pub value: f64,
+}
+
+/// Summary statistics over the values measured in every iteration.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BenchmarkStats {
+    /// The mean rate.
+    pub mean: f64,
+    /// The median rate.
+    pub median: f64,
+    /// The sample standard deviation of the rates.
+    pub stddev: f64,
+    /// The half-width of the 95% confidence interval of the mean.
+    pub ci95: f64,
+    /// The lowest rate.
+    pub min: f64,
+    /// The highest rate.
+    pub max: f64,
+}
+
+impl BenchmarkStats {
+    /// Summarizes the rates measured in every iteration.
+    ///
+    /// The standard deviation and confidence interval are zero when fewer
+    /// than two rates were measured.
+    pub fn from_samples(samples: &[f64]) -> Self {
+        if samples.is_empty() {
+            return BenchmarkStats {
+                mean: 0.0,
+                median: 0.0,
+                stddev: 0.0,
+                ci95: 0.0,
+                min: 0.0,
+                max: 0.0,
+            };
+        }
+        let n = samples.len() as f64;
+        let mean = samples.iter().sum::<f64>() / n;
+        let stddev = if samples.len() > 1 {
+            let sum_sq: f64 = samples.iter().map(|x| (x - mean).powi(2)).sum();
+            (sum_sq / (n - 1.0)).sqrt()
+        } else {
+            0.0
+        };
+        BenchmarkStats {
+            mean,
+            median: median(samples),
+            stddev,
+            ci95: t_critical_95(samples.len() - 1) * stddev / n.sqrt(),
+            min: samples.iter().copied().fold(f64::INFINITY, f64::min),
+            max: samples.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+        }
+    }
+
+    /// Returns the coefficient of variation, the standard deviation relative
+    /// to the mean, as a percentage.
+    pub fn cv_percent(&self) -> f64 {
+        if self.mean == 0.0 {
+            0.0
+        } else {
+            self.stddev / self.mean * 100.0
+        }
+    }
+}
+
+/// Summary statistics of a detail over every iteration.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchmarkDetailStats {
+    /// The name of the detail.
+    pub name: String,
+    /// The unit of the detail.
+    pub unit: String,
+    /// Whether higher or lower values of the detail are better.
+    pub direction: MetricDirection,
+    /// Summary statistics over the detail's values.
+    pub stats: BenchmarkStats,
+}
+
+/// The rates measured for a benchmark case.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchmarkResult {
+    /// The suite the case belongs to, e.g. "cpu".
+    pub suite: String,
+    /// The name of the case, e.g. "sha256".
+    pub name: String,
+    /// The number of threads the case ran on.
+    pub threads: usize,
+    /// The unit of the measured rates.
+    pub unit: String,
+    /// Whether higher or lower rates are better.
+    pub direction: MetricDirection,
+    /// The rate measured in every iteration that was not rejected.
+    pub samples: Vec<f64>,
+    /// The rates of the iterations rejected as outliers.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub outliers: Vec<f64>,
+    /// Summary statistics over the measured rates.
+    pub stats: BenchmarkStats,
+    /// Summary statistics of every detail, in the order they were measured.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub details: Vec<BenchmarkDetailStats>,
+    /// Why the result is untrustworthy, e.g. the CPUs throttled while it was
+    /// measured.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub untrustworthy: Option<String>,
+}
+
+impl BenchmarkResult {
+    /// Creates a result from the measurements of every iteration of a case.
+    ///
+    /// # Arguments
+    /// * `suite` - The suite the case belongs to.
+    /// * `case` - The case that was measured.
+    /// * `measurements` - The measurement of every iteration.
+    /// * `reject_outliers` - Whether iterations whose headline value is an
+    ///   outlier are left out of the statistics, details included.
+    pub fn new(
+        suite: &str,
+        case: &BenchmarkCase,
+        measurements: &[BenchmarkMeasurement],
+        reject_outliers: bool,
+    ) -> Self {
+        let values: Vec<f64> = measurements.iter().map(|m| m.value).collect();
+        let rejected = if reject_outliers {
+            outlier_indices(&values)
+        } else {
+            Vec::new()
+        };
+        let (kept, outliers): (Vec<_>, Vec<_>) = measurements
+            .iter()
+            .enumerate()
+            .partition(|(i, _)| !rejected.contains(i));
+        let samples: Vec<f64> = kept.iter().map(|(_, m)| m.value).collect();
+
+        // Group the details by name, keeping the order of first appearance.
+        let mut details: Vec<(BenchmarkDetail, Vec<f64>)> = Vec::new();
+        for detail in kept.iter().flat_map(|(_, m)| &m.details) {
+            match details.iter_mut().find(|(d, _)| d.name == detail.name) {
+                Some((_, values)) => values.push(detail.value),
+                None => details.push((detail.clone(), vec![detail.value])),
+            }
+        }
+
+        BenchmarkResult {
+            suite: suite.to_string(),
+            name: case.name.clone(),
+            threads: case.threads,
+            unit: case.unit.clone(),
+            direction: case.direction,
+            stats: BenchmarkStats::from_samples(&samples),
+            samples,
+            outliers: outliers.iter().map(|(_, m)| m.value).collect(),
+            details: details
+                .into_iter()
+                .map(|(detail, values)| BenchmarkDetailStats {
+                    name: detail.name,
+                    unit: detail.unit,
+                    direction: detail.direction,
+                    stats: BenchmarkStats::from_samples(&values),
+                })
+                .collect(),
+            untrustworthy: None,
+        }
+    }
+
+    /// Returns the name the result is recorded under in a run, e.g.
+    /// "cpu sha256 x1" for the single-threaded case.
+    pub fn metric_name(&self) -> String {
+        format!("{} {} x{}", self.suite, self.name, self.threads)
+    }
+
+    /// Converts the mean value and the mean of every detail into run metrics.
+    pub fn to_run_metrics(&self) -> Vec<RunMetric> {
+        let recorded_at = Utc::now();
+        let mut metrics = vec![RunMetric {
+            name: self.metric_name(),
+            value: self.stats.mean,
+            unit: self.unit.clone(),
+            direction: self.direction,
+            recorded_at,
+        }];
+        for detail in &self.details {
+            metrics.push(RunMetric {
+                name: format!("{} {}", self.metric_name(), detail.name),
+                value: detail.stats.mean,
+                unit: detail.unit.clone(),
+                direction: detail.direction,
+                recorded_at,
+            });
+        }
+        metrics
+    }
+}
+
+/// The results of a recorded benchmark run as printed in JSON.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchmarkReport {
+    /// The run the results were recorded as.
+    pub run_id: String,
+    /// The status the run finished with.
+    pub status: RunStatus,
+    /// The result of every case that completed.
+    pub results: Vec<BenchmarkResult>,
+    /// The run compared against the baseline of its SKU, if it has one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comparison: Option<BaselineComparison>,
+}
+
+/// Modified z-score above which a value is an outlier, as recommended by
+/// Iglewicz and Hoaglin.
+const OUTLIER_Z_SCORE: f64 = 3.5;
+
+/// Returns the indices of the outliers among values.
+///
+/// Values are compared with the median using the median absolute deviation,
+/// which a single outlier cannot skew the way it skews the standard
+/// deviation. Fewer than three values, or values of which most are equal,
+/// have no outliers.
+pub fn outlier_indices(values: &[f64]) -> Vec<usize> {
+    if values.len() < 3 {
+        return Vec::new();
+    }
+    let center = median(values);
+    let deviations: Vec<f64> = values.iter().map(|v| (v - center).abs()).collect();
+    let mad = median(&deviations);
+    if mad == 0.0 {
+        return Vec::new();
+    }
+    deviations
+        .iter()
+        .enumerate()
+        .filter(|(_, deviation)| 0.6745 * *deviation / mad > OUTLIER_Z_SCORE)
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// Returns the median of values, or zero if there are none.
+fn median(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let middle = sorted.len() / 2;
+    if sorted.len().is_multiple_of(2) {
+        (sorted[middle - 1] + sorted[middle]) / 2.0
+    } else {
+        sorted[middle]
+    }
+}
+
+/// Returns the two-sided 95% critical value of Student's t-distribution with
+/// the degrees of freedom, or zero without any.
+fn t_critical_95(degrees_of_freedom: usize) -> f64 {
+    const TABLE: [f64; 30] = [
+        12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228, 2.201, 2.179, 2.160,
+        2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086, 2.080, 2.074, 2.069, 2.064, 2.060, 2.056,
+        2.052, 2.048, 2.045, 2.042,
+    ];
+    match degrees_of_freedom {
+        0 => 0.0,
+        df if df <= TABLE.len() => TABLE[df - 1],
+        df if df <= 60 => 2.000,
+        df if df <= 120 => 1.980,
+        _ => 1.960,
+    }
+}
+
+/// Percentage by which the CPU frequency may fall below the highest one read
+/// during the measured iterations before a result is untrustworthy.
+const FREQUENCY_DROP_PERCENT: f64 = 10.0;
+
+/// A reading of the CPU frequency and throttling counters.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct CpuFrequencyReading {
+    /// The current frequency of every CPU in MHz, fastest first, or none if
+    /// cpufreq is unavailable.
+    pub cpu_mhz: Vec<f64>,
+    /// The thermal throttling events counted over every CPU and package since
+    /// boot, if the counters are available.
+    pub throttle_events: Option<u64>,
+}
+
+impl CpuFrequencyReading {
+    /// Returns the mean frequency in MHz of the `cpus` fastest CPUs. These are
+    /// the CPUs a benchmark on as many threads keeps busy, while idle CPUs
+    /// clock down and would drag a mean over every CPU along with them.
+    pub fn busiest_mhz(&self, cpus: usize) -> Option<f64> {
+        let busiest = &self.cpu_mhz[..cpus.max(1).min(self.cpu_mhz.len())];
+        (!busiest.is_empty()).then(|| busiest.iter().sum::<f64>() / busiest.len() as f64)
+    }
+}
+
+/// Returns why results measured between readings are untrustworthy: the CPUs
+/// throttled, or the frequency of the CPUs the case kept busy fell well below
+/// the highest one read.
+///
+/// # Arguments
+/// * `before` - The reading taken before the first measured iteration.
+/// * `during` - The readings taken after every measured iteration.
+/// * `threads` - The number of threads of the case, and so of busy CPUs.
+pub fn assess_throttling(
+    before: &CpuFrequencyReading,
+    during: &[CpuFrequencyReading],
+    threads: usize,
+) -> Option<String> {
+    let mut reasons = Vec::new();
+
+    if let (Some(start), Some(end)) = (
+        before.throttle_events,
+        during.iter().rev().find_map(|r| r.throttle_events),
+    ) {
+        if end > start {
+            reasons.push(format!("{} thermal throttling events", end - start));
+        }
+    }
+
+    let frequencies: Vec<f64> = during
+        .iter()
+        .filter_map(|r| r.busiest_mhz(threads))
+        .collect();
+    let highest = frequencies.iter().copied().fold(f64::NAN, f64::max);
+    let lowest = frequencies.iter().copied().fold(f64::NAN, f64::min);
+    if highest > 0.0 {
+        let drop_percent = (highest - lowest) / highest * 100.0;
+        if drop_percent > FREQUENCY_DROP_PERCENT {
+            reasons.push(format!(
+                "CPU frequency dropped {:.1}% from {:.0} MHz to {:.0} MHz",
+                drop_percent, highest, lowest
+            ));
+        }
+    }
+
+    (!reasons.is_empty()).then(|| reasons.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Asserts that the confidence interval of values is the one computed by
+    /// hand with a critical value of the t-distribution.
+    fn assert_ci95(values: &[f64], t: f64) {
+        let stats = BenchmarkStats::from_samples(values);
+        let expected = t * stats.stddev / (values.len() as f64).sqrt();
+        assert!(
+            (stats.ci95 - expected).abs() < 1e-9,
+            "{} != {}",
+            stats.ci95,
+            expected
+        );
+    }
+
+    #[test]
+    fn rejects_a_single_spike() {
+        let values = [100.0, 101.0, 99.0, 100.0, 102.0, 500.0];
+        assert_eq!(outlier_indices(&values), vec![5]);
+
+        let case = BenchmarkCase {
+            name: "sha256".to_string(),
+            threads: 1,
+            unit: "B/s".to_string(),
+            direction: MetricDirection::HigherIsBetter,
+        };
+        let measurements: Vec<BenchmarkMeasurement> =
+            values.iter().map(|value| (*value).into()).collect();
+        let result = BenchmarkResult::new("cpu", &case, &measurements, true);
+        assert_eq!(result.outliers, vec![500.0]);
+        assert_eq!(result.stats.max, 102.0);
+        let kept = BenchmarkResult::new("cpu", &case, &measurements, false);
+        assert!(kept.outliers.is_empty());
+        assert_eq!(kept.stats.max, 500.0);
+    }
+
+    #[test]
+    fn rejects_nothing_without_a_deviation() {
+        assert!(outlier_indices(&[7.0; 6]).is_empty());
+        // Most values are equal, so the median absolute deviation is zero too.
+        assert!(outlier_indices(&[7.0, 7.0, 7.0, 7.0, 9.0]).is_empty());
+        assert!(outlier_indices(&[1.0, 1000.0]).is_empty());
+    }
+
+    #[test]
+    fn computes_the_confidence_interval_with_students_t() {
+        let stats = BenchmarkStats::from_samples(&[1.0, 3.0]);
+        assert_eq!(stats.mean, 2.0);
+        // The standard error is sqrt(2) / sqrt(2) = 1.
+        assert!((stats.ci95 - 12.706).abs() < 1e-9);
+
+        assert_ci95(&[1.0, 2.0, 3.0, 4.0, 5.0], 2.776);
+        let thirty_one: Vec<f64> = (1..=31).map(f64::from).collect();
+        assert_ci95(&thirty_one, 2.042);
+        let forty: Vec<f64> = (1..=40).map(f64::from).collect();
+        assert_ci95(&forty, 2.000);
+        let thousand: Vec<f64> = (1..=1000).map(f64::from).collect();
+        assert_ci95(&thousand, 1.960);
+    }
+
+    #[test]
+    fn summarizes_a_single_value_without_a_confidence_interval() {
+        let stats = BenchmarkStats::from_samples(&[42.0]);
+        assert_eq!((stats.mean, stats.median), (42.0, 42.0));
+        assert_eq!((stats.stddev, stats.ci95), (0.0, 0.0));
+        assert_eq!((stats.min, stats.max), (42.0, 42.0));
+        assert_eq!(BenchmarkStats::from_samples(&[]).ci95, 0.0);
+    }
+}
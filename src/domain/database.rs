@@ -0,0 +1,251 @@
+//! Database Domain Entities
+//!
+//! This module provides the schema version of the database layout, the
+//! reports of the migrations that upgrade older layouts to it, and the
+//! entries, formats and statistics of the database operations that list,
+//! export, import and prune the raw contents of the database.
+//!
+//! Layouts by version:
+//! * 0 - The legacy OneForAll layout: the raw text of the latest `ps`
+//!   listing of the overwatch command under a key of the default tree, e.g.
+//!   "cpu_stats.txt".
+//! * 1 - Records stored in a tree per entity with versioned encodings, the
+//!   `ps` listings as `ProcessSnapshot`s in the processes tree.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+/// The version of the layout the database is upgraded to.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// The tree holding the schema version and other database metadata.
+pub const META_TREE: &str = "meta";
+
+/// The key of the schema version in the metadata tree.
+pub const SCHEMA_VERSION_KEY: &str = "schema_version";
+
+/// The name the default tree of the database is listed and opened by.
+pub const DEFAULT_TREE: &str = "default";
+
+/// The storage backends the database can be kept in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DatabaseBackend {
+    /// The sled embedded database on disk.
+    #[default]
+    Sled,
+    /// Memory only, for ephemeral runs and tests; nothing outlives the process.
+    Memory,
+}
+
+/// The outcome of a migration, or of what it would do in a dry run.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct MigrationReport {
+    /// The version the migration upgrades from.
+    pub from: u32,
+    /// The version the migration upgrades to.
+    pub to: u32,
+    /// What the migration does.
+    pub description: String,
+    /// The number of records moved into each tree.
+    pub moved: BTreeMap<String, usize>,
+    /// The keys of records that could not be decoded and were left in place.
+    pub skipped: Vec<String>,
+    /// Whether the migration was only planned and nothing was written.
+    pub dry_run: bool,
+}
+
+impl MigrationReport {
+    /// Returns the number of records moved into every tree.
+    pub fn total_moved(&self) -> usize {
+        self.moved.values().sum()
+    }
+}
+
+/// A key-value pair of a tree, as it is listed, exported and imported.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DatabaseEntry {
+    /// The tree the pair is stored in.
+    pub tree: String,
+    /// The key of the pair, with invalid UTF-8 replaced.
+    pub key: String,
+    /// The bytes of the key as hex digits, for keys that are not UTF-8.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_hex: Option<String>,
+    /// The value of the pair.
+    #[serde(flatten)]
+    pub value: EntryValue,
+}
+
+impl DatabaseEntry {
+    /// Wraps a stored key-value pair of a tree.
+    pub fn new(tree: &str, key: &[u8], value: &[u8]) -> Self {
+        DatabaseEntry {
+            tree: tree.to_string(),
+            key: String::from_utf8_lossy(key).into_owned(),
+            key_hex: std::str::from_utf8(key).is_err().then(|| to_hex(key)),
+            value: EntryValue::from_bytes(value),
+        }
+    }
+
+    /// Returns the bytes the key is stored as.
+    pub fn key_bytes(&self) -> Result<Vec<u8>, String> {
+        match &self.key_hex {
+            Some(hex) => from_hex(hex).map_err(|e| format!("Invalid key_hex {}: {}", hex, e)),
+            None => Ok(self.key.as_bytes().to_vec()),
+        }
+    }
+}
+
+/// The value of a key-value pair, kept as readable as its bytes allow.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryValue {
+    /// A JSON document, such as an encoded record.
+    #[serde(rename = "value")]
+    Json(serde_json::Value),
+    /// UTF-8 text that is not JSON.
+    Text(String),
+    /// Any other bytes.
+    Bytes(Vec<u8>),
+}
+
+impl EntryValue {
+    /// Wraps stored bytes, preferring JSON, then text. Values are JSON only
+    /// if they are stored exactly as `to_bytes` encodes them again, e.g.
+    /// without whitespace, so that importing an export restores them byte
+    /// for byte.
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        match std::str::from_utf8(bytes) {
+            Ok(text) => serde_json::from_str(text)
+                .ok()
+                .map(EntryValue::Json)
+                .filter(|value| value.to_bytes() == bytes)
+                .unwrap_or_else(|| EntryValue::Text(text.to_string())),
+            Err(_) => EntryValue::Bytes(bytes.to_vec()),
+        }
+    }
+
+    /// Returns the bytes the value is stored as.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            EntryValue::Json(value) => value.to_string().into_bytes(),
+            EntryValue::Text(text) => text.as_bytes().to_vec(),
+            EntryValue::Bytes(bytes) => bytes.clone(),
+        }
+    }
+}
+
+impl fmt::Display for EntryValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EntryValue::Json(value) => match serde_json::to_string_pretty(value) {
+                Ok(json) => write!(f, "{}", json),
+                Err(_) => write!(f, "{}", value),
+            },
+            EntryValue::Text(text) => write!(f, "{}", text),
+            EntryValue::Bytes(bytes) => write!(f, "{:?}", bytes),
+        }
+    }
+}
+
+/// Encodes bytes as lowercase hex digits, two per byte.
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Decodes hex digits, two per byte.
+fn from_hex(hex: &str) -> Result<Vec<u8>, String> {
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return Err("expected two hex digits per byte".to_string());
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|e| e.to_string()))
+        .collect()
+}
+
+/// The formats the database is exported in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExportFormat {
+    /// A single JSON array of entries.
+    Json,
+    /// One JSON entry per line.
+    Ndjson,
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format.to_ascii_lowercase().as_str() {
+            "json" => Ok(ExportFormat::Json),
+            "ndjson" | "jsonl" => Ok(ExportFormat::Ndjson),
+            _ => Err(format!(
+                "unknown export format {}, expected json or ndjson",
+                format
+            )),
+        }
+    }
+}
+
+/// The size and contents of the database.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct DatabaseStats {
+    /// The path of the database.
+    pub path: String,
+    /// The size of the database on disk in bytes.
+    pub size_on_disk: u64,
+    /// The schema version of the layout.
+    pub schema_version: u32,
+    /// The number of key-value pairs in every tree.
+    pub trees: BTreeMap<String, usize>,
+}
+
+impl DatabaseStats {
+    /// Returns the number of key-value pairs in every tree.
+    pub fn total_entries(&self) -> usize {
+        self.trees.values().sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_the_bytes_of_exported_entries() {
+        let pairs: [(&[u8], &[u8]); 5] = [
+            (
+                b"run1",
+                br#"{"version":1,"record":{"id":"run1","b":2,"a":1.0}}"#,
+            ),
+            (b"pretty", b"{ \"a\": 1 }"),
+            (b"text", b"cpu_stats"),
+            (&[0x6b, 0xff, 0x00], &[0xc3, 0x28]),
+            (b"number", b"1e3"),
+        ];
+        for (key, value) in pairs {
+            let entry = DatabaseEntry::new("tree", key, value);
+            let json = serde_json::to_string(&entry).unwrap();
+            let imported: DatabaseEntry = serde_json::from_str(&json).unwrap();
+            assert_eq!(imported.key_bytes().unwrap(), key, "{}", json);
+            assert_eq!(imported.value.to_bytes(), value, "{}", json);
+        }
+
+        // Records are exported as JSON, anything else that parses as JSON as text.
+        let record = DatabaseEntry::new("runs", b"run1", br#"{"version":1}"#);
+        assert!(matches!(record.value, EntryValue::Json(_)));
+        let pretty = DatabaseEntry::new("default", b"pretty", b"{ \"a\": 1 }");
+        assert!(matches!(pretty.value, EntryValue::Text(_)));
+        assert_eq!(
+            DatabaseEntry::new("default", &[0xff], b"")
+                .key_hex
+                .as_deref(),
+            Some("ff")
+        );
+    }
+}
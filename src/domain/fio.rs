@@ -0,0 +1,134 @@
+//! Fio Domain Entities
+//!
+//! This module provides the profiles fio storage benchmarks are generated
+//! from, and the typed results parsed from fio's JSON output.
+
+use serde::{Deserialize, Serialize};
+
+/// A fio job profile, e.g. random 4k reads at queue depth 32.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FioProfile {
+    /// The name of the profile, used as the fio job name.
+    pub name: String,
+    /// The fio I/O pattern: read, write, randread, randwrite, rw or randrw.
+    pub rw: String,
+    /// The block size, e.g. "4k" or "1m".
+    pub block_size: String,
+    /// The percentage of reads in mixed patterns.
+    #[serde(default)]
+    pub rwmixread: Option<u32>,
+    /// The number of I/Os in flight per job.
+    #[serde(default = "default_iodepth")]
+    pub iodepth: u32,
+    /// The number of jobs issuing I/O in parallel.
+    #[serde(default = "default_numjobs")]
+    pub numjobs: u32,
+    /// Whether to bypass the page cache with O_DIRECT.
+    #[serde(default = "default_direct")]
+    pub direct: bool,
+}
+
+/// Default number of I/Os in flight per job
+fn default_iodepth() -> u32 {
+    32
+}
+
+/// Default number of jobs
+fn default_numjobs() -> u32 {
+    1
+}
+
+/// Default for bypassing the page cache
+fn default_direct() -> bool {
+    true
+}
+
+impl FioProfile {
+    /// Creates a profile with the default queue depth, job count and direct I/O.
+    pub fn new(name: &str, rw: &str, block_size: &str, rwmixread: Option<u32>) -> Self {
+        FioProfile {
+            name: name.to_string(),
+            rw: rw.to_string(),
+            block_size: block_size.to_string(),
+            rwmixread,
+            iodepth: default_iodepth(),
+            numjobs: default_numjobs(),
+            direct: default_direct(),
+        }
+    }
+
+    /// Returns the default profiles: sequential 1 MiB reads and writes, random
+    /// 4 KiB reads and writes, and a 70/30 random 4 KiB read/write mix.
+    pub fn defaults() -> Vec<FioProfile> {
+        vec![
+            FioProfile::new("seq-read", "read", "1m", None),
+            FioProfile::new("seq-write", "write", "1m", None),
+            FioProfile::new("rand-read", "randread", "4k", None),
+            FioProfile::new("rand-write", "randwrite", "4k", None),
+            FioProfile::new("mixed", "randrw", "4k", Some(70)),
+        ]
+    }
+
+    /// Returns whether the profile issues random I/O, which is measured in
+    /// IOPS rather than bandwidth.
+    pub fn is_random(&self) -> bool {
+        self.rw.starts_with("rand")
+    }
+
+    /// Returns whether the profile writes to its target.
+    pub fn writes(&self) -> bool {
+        self.rw.contains("write") || self.rw.ends_with("rw")
+    }
+}
+
+/// The results of a fio job.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FioJobResult {
+    /// The name of the job.
+    pub job: String,
+    /// The read results, if the job issued reads.
+    pub read: Option<FioIoResult>,
+    /// The write results, if the job issued writes.
+    pub write: Option<FioIoResult>,
+}
+
+impl FioJobResult {
+    /// Returns the bandwidth of reads and writes combined in bytes per second.
+    pub fn bandwidth_bytes_per_sec(&self) -> f64 {
+        self.directions()
+            .map(|(_, io)| io.bandwidth_bytes_per_sec)
+            .sum()
+    }
+
+    /// Returns the IOPS of reads and writes combined.
+    pub fn iops(&self) -> f64 {
+        self.directions().map(|(_, io)| io.iops).sum()
+    }
+
+    /// Iterates over the directions the job issued I/O in, with their names.
+    pub fn directions(&self) -> impl Iterator<Item = (&'static str, &FioIoResult)> {
+        [("read", self.read.as_ref()), ("write", self.write.as_ref())]
+            .into_iter()
+            .filter_map(|(name, io)| Some((name, io?)))
+    }
+}
+
+/// The results of a fio job in one direction.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FioIoResult {
+    /// The bandwidth in bytes per second.
+    pub bandwidth_bytes_per_sec: f64,
+    /// The I/O operations per second.
+    pub iops: f64,
+    /// The mean completion latency in nanoseconds.
+    pub mean_latency_ns: f64,
+    /// The median completion latency in nanoseconds.
+    pub p50_latency_ns: Option<f64>,
+    /// The 99th percentile completion latency in nanoseconds.
+    pub p99_latency_ns: Option<f64>,
+    /// The 99.9th percentile completion latency in nanoseconds.
+    pub p99_9_latency_ns: Option<f64>,
+    /// The 99.99th percentile completion latency in nanoseconds.
+    pub p99_99_latency_ns: Option<f64>,
+}
@@ -0,0 +1,206 @@
+//! Golden Spec Domain Entities
+//!
+//! This module provides the "golden spec" of a node SKU: the configuration a
+//! node is expected to have, e.g. as specified in a procurement contract, and
+//! the report produced by checking a discovered inventory against it.
+//!
+//! Every requirement is optional so that a spec only needs to list what it
+//! cares about. A spec is written in YAML:
+//!
+//! ```yaml
+//! name: compute-gen3
+//! cpu:
+//!   model: AMD EPYC 9654 96-Core Processor
+//!   sockets: 2
+//!   cores: 192
+//! memory:
+//!   total_gib: 1536
+//!   dimm_count: 24
+//!   speed_mts: 4800
+//! network:
+//!   - name: "ens1f*"
+//!     count: 2
+//!     speed_mbps: 200000
+//! nvme:
+//!   count: 4
+//!   capacity_gb: 3840
+//! bios:
+//!   version: "1.7.3"
+//! ```
+
+use serde::{Deserialize, Serialize};
+
+/// The expected configuration of a node SKU.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GoldenSpec {
+    /// The name of the spec, e.g. the SKU.
+    #[serde(default)]
+    pub name: Option<String>,
+    /// The expected processors.
+    #[serde(default)]
+    pub cpu: Option<CpuSpec>,
+    /// The expected memory configuration.
+    #[serde(default)]
+    pub memory: Option<MemorySpec>,
+    /// The expected network interfaces.
+    #[serde(default)]
+    pub network: Vec<NicSpec>,
+    /// The expected NVMe drives.
+    #[serde(default)]
+    pub nvme: Option<NvmeSpec>,
+    /// The expected BIOS.
+    #[serde(default)]
+    pub bios: Option<BiosSpec>,
+}
+
+/// The expected processors.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CpuSpec {
+    /// The model name; compared ignoring case and repeated whitespace.
+    pub model: Option<String>,
+    /// The number of sockets.
+    pub sockets: Option<usize>,
+    /// The number of physical cores across all sockets.
+    pub cores: Option<usize>,
+    /// The number of hardware threads across all sockets.
+    pub threads: Option<usize>,
+}
+
+/// The expected memory configuration.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MemorySpec {
+    /// The total installed memory in GiB.
+    pub total_gib: Option<u64>,
+    /// The number of populated DIMM slots.
+    pub dimm_count: Option<usize>,
+    /// The speed every DIMM must be configured to run at, at least, in MT/s.
+    pub speed_mts: Option<u32>,
+    /// The memory type, e.g. "DDR5".
+    #[serde(rename = "type")]
+    pub memory_type: Option<String>,
+}
+
+/// The expected interfaces matching a name or name pattern.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NicSpec {
+    /// The interface name; names ending in `*` match by prefix.
+    pub name: String,
+    /// The number of matching interfaces.
+    pub count: Option<usize>,
+    /// The negotiated link speed every matching interface must have, in Mb/s.
+    pub speed_mbps: Option<u64>,
+}
+
+/// The expected NVMe drives.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NvmeSpec {
+    /// The number of NVMe namespaces.
+    pub count: Option<usize>,
+    /// The capacity every namespace must have, at least, in decimal GB.
+    pub capacity_gb: Option<u64>,
+}
+
+/// The expected BIOS.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BiosSpec {
+    /// The BIOS vendor.
+    pub vendor: Option<String>,
+    /// The BIOS version.
+    pub version: Option<String>,
+}
+
+/// The outcome of checking one requirement of a spec.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpecCheck {
+    /// The requirement, e.g. "memory DIMM count".
+    pub requirement: String,
+    /// The expected value.
+    pub expected: String,
+    /// The value found on the host.
+    pub actual: String,
+    /// Whether the host meets the requirement.
+    pub passed: bool,
+}
+
+/// The outcome of checking an inventory against a spec.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpecReport {
+    /// The name of the spec.
+    pub spec: String,
+    /// The host that was checked.
+    pub host: String,
+    /// The inventory snapshot that was checked.
+    pub snapshot_id: String,
+    /// Whether every requirement was met.
+    pub passed: bool,
+    /// The outcome of each requirement.
+    pub checks: Vec<SpecCheck>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_a_spec_from_yaml() {
+        let yaml = r#"
+name: compute-gen3
+cpu:
+  model: AMD EPYC 9654 96-Core Processor
+  sockets: 2
+memory:
+  total_gib: 1536
+  type: DDR5
+network:
+  - name: "ens1f*"
+    count: 2
+    speed_mbps: 200000
+nvme:
+  capacity_gb: 3840
+bios:
+  version: "1.7.3"
+"#;
+        let spec: GoldenSpec = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(spec.name.as_deref(), Some("compute-gen3"));
+        assert_eq!(
+            spec.cpu,
+            Some(CpuSpec {
+                model: Some("AMD EPYC 9654 96-Core Processor".to_string()),
+                sockets: Some(2),
+                cores: None,
+                threads: None,
+            })
+        );
+        let memory = spec.memory.unwrap();
+        assert_eq!(memory.total_gib, Some(1536));
+        assert_eq!(memory.memory_type.as_deref(), Some("DDR5"));
+        assert_eq!(memory.dimm_count, None);
+        assert_eq!(
+            spec.network,
+            vec![NicSpec {
+                name: "ens1f*".to_string(),
+                count: Some(2),
+                speed_mbps: Some(200_000),
+            }]
+        );
+        assert_eq!(spec.nvme.unwrap().capacity_gb, Some(3840));
+        assert_eq!(spec.bios.unwrap().version.as_deref(), Some("1.7.3"));
+
+        let empty: GoldenSpec = serde_yaml::from_str("{}").unwrap();
+        assert_eq!(empty, GoldenSpec::default());
+    }
+
+    #[test]
+    fn rejects_unknown_requirements() {
+        let error = serde_yaml::from_str::<GoldenSpec>("memory:\n  total_gb: 1536\n")
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("total_gb"), "{}", error);
+    }
+}
@@ -0,0 +1,538 @@
+//! Hardware Inventory Domain Entities
+//!
+//! This module provides the domain entities produced by hardware discovery.
+//! A `HostInventory` bundles everything discovered on a host at one point in
+//! time; each section is optional so that partial discovery runs (e.g. only
+//! `--cpu`) can still be represented.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::domain::record::{prefix_key_range, Record, RecordQuery};
+
+/// Everything discovered about a host at one point in time.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HostInventory {
+    /// The host the inventory was collected on.
+    pub host: String,
+    /// The time at which the inventory was collected.
+    pub collected_at: DateTime<Utc>,
+    /// The CPU inventory, if it was discovered.
+    #[serde(default)]
+    pub cpu: Option<CpuInventory>,
+    /// The BIOS and system identification, if it was discovered.
+    #[serde(default)]
+    pub platform: Option<PlatformInfo>,
+    /// The memory inventory, if it was discovered.
+    #[serde(default)]
+    pub memory: Option<MemoryInventory>,
+    /// The PCI devices, if they were discovered.
+    #[serde(default)]
+    pub pci: Option<Vec<PciDevice>>,
+    /// The block devices and mounted filesystems, if they were discovered.
+    #[serde(default)]
+    pub storage: Option<StorageInventory>,
+    /// The network interfaces and RDMA devices, if they were discovered.
+    #[serde(default)]
+    pub network: Option<NetworkInventory>,
+    /// The problems found while checking the discovered sections.
+    #[serde(default)]
+    pub findings: Vec<InventoryFinding>,
+}
+
+impl HostInventory {
+    /// Returns the snapshot identifier, the collection time as
+    /// "YYYYMMDDTHHMMSSmmmZ" so that identifiers sort chronologically.
+    pub fn snapshot_id(&self) -> String {
+        self.collected_at.format("%Y%m%dT%H%M%S%3fZ").to_string()
+    }
+
+    /// Returns the names of the sections that were discovered, e.g. "cpu".
+    pub fn sections(&self) -> Vec<&'static str> {
+        [
+            ("platform", self.platform.is_some()),
+            ("cpu", self.cpu.is_some()),
+            ("memory", self.memory.is_some()),
+            ("pci", self.pci.is_some()),
+            ("storage", self.storage.is_some()),
+            ("network", self.network.is_some()),
+        ]
+        .into_iter()
+        .filter(|(_, discovered)| *discovered)
+        .map(|(name, _)| name)
+        .collect()
+    }
+
+    /// Returns the database key of a snapshot of a host.
+    pub fn key_for(host: &str, snapshot_id: &str) -> String {
+        format!("{}/{}", host, snapshot_id)
+    }
+}
+
+impl Record for HostInventory {
+    const TREE: &'static str = "inventory";
+
+    fn key(&self) -> String {
+        Self::key_for(&self.host, &self.snapshot_id())
+    }
+
+    fn timestamp(&self) -> DateTime<Utc> {
+        self.collected_at
+    }
+
+    fn host(&self) -> Option<&str> {
+        Some(&self.host)
+    }
+
+    fn key_range(query: &RecordQuery) -> Option<(String, String)> {
+        query.host.as_deref().map(prefix_key_range)
+    }
+}
+
+/// How a component changed between two inventory snapshots.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum ChangeKind {
+    /// The component appeared.
+    Added,
+    /// The component disappeared.
+    Removed,
+    /// A property of the component changed.
+    Changed,
+}
+
+/// A difference between two inventory snapshots.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InventoryChange {
+    /// How the component changed.
+    pub kind: ChangeKind,
+    /// The component, e.g. "memory DIMM_A1" or "pci 0000:3b:00.0".
+    pub component: String,
+    /// What changed, e.g. "configured speed: 3200 MT/s -> 2933 MT/s".
+    pub detail: String,
+}
+
+/// How severe an inventory finding is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum FindingSeverity {
+    /// Worth knowing but not necessarily a problem.
+    Info,
+    /// A configuration that is likely to cost performance or reliability.
+    Warning,
+}
+
+/// A problem or notable property found while checking an inventory.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InventoryFinding {
+    /// How severe the finding is.
+    pub severity: FindingSeverity,
+    /// The component the finding is about, e.g. "memory".
+    pub component: String,
+    /// A human readable description of the finding.
+    pub message: String,
+}
+
+/// The processors of a host, their topology, caches and frequencies.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CpuInventory {
+    /// The CPU vendor, e.g. "GenuineIntel".
+    pub vendor: String,
+    /// The CPU model name, e.g. "Intel(R) Xeon(R) Gold 6338 CPU @ 2.00GHz".
+    pub model: String,
+    /// The number of populated sockets.
+    pub sockets: usize,
+    /// The number of physical cores across all sockets.
+    pub cores: usize,
+    /// The number of hardware threads (logical CPUs) across all sockets.
+    pub threads: usize,
+    /// Whether simultaneous multithreading is active.
+    pub smt_active: bool,
+    /// The caches of the processors, one entry per level, type and size.
+    pub caches: Vec<CacheInfo>,
+    /// The NUMA nodes of the host.
+    pub numa_nodes: Vec<NumaNode>,
+    /// The frequency limits and scaling governor.
+    pub frequency: CpuFrequency,
+    /// The feature flags reported by the processor, e.g. "avx512f".
+    pub flags: Vec<String>,
+}
+
+/// One cache level, type and size, e.g. the L1 data cache.
+///
+/// Hybrid processors report one entry per size, e.g. for the L2 caches of
+/// their performance and of their efficiency cores.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CacheInfo {
+    /// The cache level, 1 for L1.
+    pub level: u8,
+    /// The cache type: "Data", "Instruction" or "Unified".
+    pub cache_type: String,
+    /// The size of a single instance in KiB.
+    pub size_kib: u64,
+    /// The number of distinct instances across the host.
+    pub instances: usize,
+}
+
+impl CacheInfo {
+    /// Returns the combined size of all instances in KiB.
+    pub fn total_kib(&self) -> u64 {
+        self.size_kib * self.instances as u64
+    }
+}
+
+/// A NUMA node with its CPUs and memory.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NumaNode {
+    /// The node number.
+    pub id: u32,
+    /// The logical CPUs attached to the node.
+    pub cpus: Vec<u32>,
+    /// The total memory attached to the node in KiB.
+    pub memory_total_kib: Option<u64>,
+}
+
+/// Frequency limits and scaling configuration of the processors.
+///
+/// Values are aggregated over all logical CPUs: the lowest minimum, the
+/// highest maximum, and the mean current frequency.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CpuFrequency {
+    /// The lowest frequency the hardware supports in MHz.
+    pub min_mhz: Option<f64>,
+    /// The highest frequency the hardware supports in MHz.
+    pub max_mhz: Option<f64>,
+    /// The mean current frequency in MHz.
+    pub current_mhz: Option<f64>,
+    /// The scaling governors in use, e.g. "performance".
+    pub governors: Vec<String>,
+}
+
+/// BIOS and system identification read from SMBIOS types 0, 1 and 2.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PlatformInfo {
+    /// The BIOS vendor.
+    pub bios_vendor: Option<String>,
+    /// The BIOS version string.
+    pub bios_version: Option<String>,
+    /// The BIOS release date as reported, usually MM/DD/YYYY.
+    pub bios_release_date: Option<String>,
+    /// The system manufacturer.
+    pub system_manufacturer: Option<String>,
+    /// The system product name.
+    pub system_product: Option<String>,
+    /// The system serial number.
+    pub system_serial: Option<String>,
+    /// The system UUID.
+    pub system_uuid: Option<String>,
+    /// The baseboard manufacturer.
+    pub board_manufacturer: Option<String>,
+    /// The baseboard product name.
+    pub board_product: Option<String>,
+}
+
+/// The memory arrays and memory devices (DIMM slots) of a host.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MemoryInventory {
+    /// The physical memory arrays (SMBIOS type 16).
+    pub arrays: Vec<MemoryArray>,
+    /// The memory device slots, populated or not (SMBIOS type 17).
+    pub devices: Vec<MemoryDevice>,
+}
+
+impl MemoryInventory {
+    /// Returns the populated memory devices.
+    pub fn populated(&self) -> impl Iterator<Item = &MemoryDevice> {
+        self.devices.iter().filter(|device| device.is_populated())
+    }
+
+    /// Returns the total installed memory in MiB.
+    pub fn total_mib(&self) -> u64 {
+        self.populated().filter_map(|device| device.size_mib).sum()
+    }
+}
+
+/// A physical memory array, usually one per socket.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MemoryArray {
+    /// The SMBIOS handle of the array.
+    pub handle: u16,
+    /// Where the array is located, e.g. "System board or motherboard".
+    pub location: String,
+    /// What the array is used for, e.g. "System memory".
+    pub usage: String,
+    /// The error correction in use, e.g. "Multi-bit ECC".
+    pub error_correction: String,
+    /// The maximum capacity the array supports in KiB.
+    pub max_capacity_kib: Option<u64>,
+    /// The number of slots in the array.
+    pub slots: u16,
+}
+
+/// A memory device slot and the DIMM installed in it, if any.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MemoryDevice {
+    /// The SMBIOS handle of the device.
+    pub handle: u16,
+    /// The handle of the array the device belongs to.
+    pub array_handle: u16,
+    /// The slot label, e.g. "DIMM_A1".
+    pub locator: String,
+    /// The bank label, e.g. "P0_Node0_Channel0_Dimm0".
+    pub bank_locator: String,
+    /// The size of the installed DIMM in MiB, `None` if the slot is empty.
+    pub size_mib: Option<u64>,
+    /// The memory type, e.g. "DDR5".
+    pub memory_type: String,
+    /// The form factor, e.g. "DIMM".
+    pub form_factor: String,
+    /// The rated speed in MT/s.
+    pub speed_mts: Option<u32>,
+    /// The speed the DIMM is configured to run at in MT/s.
+    pub configured_speed_mts: Option<u32>,
+    /// The DIMM manufacturer.
+    pub manufacturer: Option<String>,
+    /// The DIMM part number.
+    pub part_number: Option<String>,
+    /// The DIMM serial number.
+    pub serial: Option<String>,
+    /// The number of ranks.
+    pub rank: Option<u8>,
+    /// The data width in bits.
+    pub data_width: Option<u16>,
+}
+
+impl MemoryDevice {
+    /// Returns true if a DIMM is installed in the slot.
+    pub fn is_populated(&self) -> bool {
+        self.size_mib.is_some()
+    }
+}
+
+/// A PCI function and, for PCI Express devices, its link state.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PciDevice {
+    /// The PCI address, e.g. "0000:3b:00.0".
+    pub address: String,
+    /// The vendor identifier.
+    pub vendor_id: u16,
+    /// The device identifier.
+    pub device_id: u16,
+    /// The subsystem vendor identifier.
+    pub subsystem_vendor_id: Option<u16>,
+    /// The subsystem device identifier.
+    pub subsystem_device_id: Option<u16>,
+    /// The 24 bit class code: base class, subclass and programming interface.
+    pub class: u32,
+    /// The class name, e.g. "Ethernet controller".
+    pub class_name: String,
+    /// The vendor name from `pci.ids`, if known.
+    pub vendor_name: Option<String>,
+    /// The device name from `pci.ids`, if known.
+    pub device_name: Option<String>,
+    /// The subsystem name from `pci.ids`, if known.
+    pub subsystem_name: Option<String>,
+    /// The kernel driver bound to the device.
+    pub driver: Option<String>,
+    /// The NUMA node the device is attached to.
+    pub numa_node: Option<u32>,
+    /// The PCI Express link state, if the device has a link.
+    pub link: Option<PcieLink>,
+}
+
+impl PciDevice {
+    /// Returns a readable name, falling back to the numeric identifiers.
+    pub fn display_name(&self) -> String {
+        match (&self.vendor_name, &self.device_name) {
+            (Some(vendor), Some(device)) => format!("{} {}", vendor, device),
+            (Some(vendor), None) => format!("{} device {:04x}", vendor, self.device_id),
+            _ => format!("{:04x}:{:04x}", self.vendor_id, self.device_id),
+        }
+    }
+}
+
+/// The negotiated and maximum PCI Express link speed and width.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PcieLink {
+    /// The negotiated link speed in GT/s.
+    pub current_speed_gts: Option<f64>,
+    /// The maximum link speed in GT/s.
+    pub max_speed_gts: Option<f64>,
+    /// The negotiated link width in lanes.
+    pub current_width: Option<u8>,
+    /// The maximum link width in lanes.
+    pub max_width: Option<u8>,
+}
+
+impl PcieLink {
+    /// Returns true if the link is running below its maximum speed.
+    pub fn is_speed_degraded(&self) -> bool {
+        matches!(
+            (self.current_speed_gts, self.max_speed_gts),
+            (Some(current), Some(max)) if current < max
+        )
+    }
+
+    /// Returns true if the link is running below its maximum width.
+    pub fn is_width_degraded(&self) -> bool {
+        matches!(
+            (self.current_width, self.max_width),
+            (Some(current), Some(max)) if current < max
+        )
+    }
+}
+
+/// The block devices of a host and the filesystems mounted from them.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StorageInventory {
+    /// The block devices with a non-zero size.
+    pub devices: Vec<BlockDevice>,
+    /// The filesystems mounted from block devices.
+    pub filesystems: Vec<Filesystem>,
+}
+
+/// A whole block device such as a disk or an NVMe namespace.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockDevice {
+    /// The kernel name, e.g. "nvme0n1" or "sda".
+    pub name: String,
+    /// The device number as "major:minor".
+    pub dev: String,
+    /// The device model.
+    pub model: Option<String>,
+    /// The device serial number.
+    pub serial: Option<String>,
+    /// The firmware revision.
+    pub firmware: Option<String>,
+    /// The capacity in bytes.
+    pub size_bytes: u64,
+    /// Whether the device is rotational, i.e. a spinning disk.
+    pub rotational: bool,
+    /// Whether the media is removable.
+    pub removable: bool,
+    /// The active I/O scheduler, e.g. "none" or "mq-deadline".
+    pub scheduler: Option<String>,
+    /// The number of requests the block layer queues for the device.
+    pub nr_requests: Option<u32>,
+    /// The command queue depth of the device, for SCSI and SATA devices.
+    pub queue_depth: Option<u32>,
+    /// The logical block size in bytes.
+    pub logical_block_size: Option<u32>,
+    /// The physical block size in bytes.
+    pub physical_block_size: Option<u32>,
+    /// The NVMe namespace details, for NVMe devices.
+    pub nvme: Option<NvmeNamespace>,
+    /// The partitions of the device.
+    pub partitions: Vec<Partition>,
+}
+
+/// The NVMe controller and namespace a block device belongs to.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NvmeNamespace {
+    /// The controller name, e.g. "nvme0".
+    pub controller: Option<String>,
+    /// The namespace identifier.
+    pub nsid: Option<u32>,
+    /// The transport to the controller, e.g. "pcie", "tcp" or "rdma".
+    pub transport: Option<String>,
+    /// The PCI address of the controller, for PCIe attached controllers.
+    pub pci_address: Option<String>,
+}
+
+/// A partition of a block device.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Partition {
+    /// The kernel name, e.g. "nvme0n1p1".
+    pub name: String,
+    /// The device number as "major:minor".
+    pub dev: String,
+    /// The first sector of the partition.
+    pub start_sector: Option<u64>,
+    /// The size in bytes.
+    pub size_bytes: u64,
+}
+
+/// A mounted filesystem.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Filesystem {
+    /// The mount source, e.g. "/dev/nvme0n1p2".
+    pub source: String,
+    /// The mount point.
+    pub mount_point: String,
+    /// The filesystem type, e.g. "ext4".
+    pub fs_type: String,
+    /// The mount options.
+    pub options: String,
+    /// The block device or partition the filesystem lives on, if known.
+    pub device: Option<String>,
+}
+
+/// The network interfaces and RDMA devices of a host.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct NetworkInventory {
+    /// The network interfaces, physical and virtual.
+    pub interfaces: Vec<NetworkInterface>,
+    /// The RDMA devices, e.g. InfiniBand or RoCE adapters.
+    pub rdma_devices: Vec<RdmaDevice>,
+}
+
+/// A network interface and its link state.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NetworkInterface {
+    /// The interface name, e.g. "ens1f0".
+    pub name: String,
+    /// The kernel driver, e.g. "mlx5_core".
+    pub driver: Option<String>,
+    /// The hardware address.
+    pub mac: Option<String>,
+    /// The maximum transmission unit in bytes.
+    pub mtu: Option<u32>,
+    /// The negotiated speed in Mb/s, `None` without a link.
+    pub speed_mbps: Option<u64>,
+    /// The negotiated duplex, e.g. "full".
+    pub duplex: Option<String>,
+    /// The operational state, e.g. "up" or "down".
+    pub operstate: String,
+    /// How often the carrier was lost or regained.
+    pub carrier_changes: Option<u64>,
+    /// The NUMA node of the adapter.
+    pub numa_node: Option<u32>,
+    /// The number of receive queues.
+    pub rx_queues: usize,
+    /// The number of transmit queues.
+    pub tx_queues: usize,
+    /// The PCI address of the adapter.
+    pub pci_address: Option<String>,
+    /// Whether the interface is virtual, i.e. not backed by a device.
+    pub is_virtual: bool,
+}
+
+/// An RDMA device with its ports.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RdmaDevice {
+    /// The device name, e.g. "mlx5_0".
+    pub name: String,
+    /// The firmware version.
+    pub firmware: Option<String>,
+    /// The node GUID.
+    pub node_guid: Option<String>,
+    /// The PCI address of the adapter.
+    pub pci_address: Option<String>,
+    /// The ports of the device.
+    pub ports: Vec<RdmaPort>,
+}
+
+/// A port of an RDMA device.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RdmaPort {
+    /// The port number, starting at 1.
+    pub port: u32,
+    /// The logical port state, e.g. "ACTIVE".
+    pub state: Option<String>,
+    /// The physical port state, e.g. "LinkUp".
+    pub physical_state: Option<String>,
+    /// The link layer, "InfiniBand" or "Ethernet".
+    pub link_layer: Option<String>,
+    /// The rate as reported, e.g. "100 Gb/sec (4X EDR)".
+    pub rate: Option<String>,
+    /// The rate in Gb/s.
+    pub rate_gbps: Option<f64>,
+}
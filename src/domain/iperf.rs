@@ -0,0 +1,94 @@
+//! Iperf Domain Entities
+//!
+//! This module provides the profiles iperf3 network benchmarks are run with,
+//! and the typed results parsed from iperf3's JSON output.
+
+use serde::{Deserialize, Serialize};
+
+/// The transport protocol of an iperf3 test.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IperfProtocol {
+    /// TCP streams.
+    #[default]
+    #[serde(alias = "TCP")]
+    Tcp,
+    /// UDP datagrams.
+    #[serde(alias = "UDP")]
+    Udp,
+}
+
+/// An iperf3 test profile, e.g. TCP with four parallel streams.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct IperfProfile {
+    /// The name of the profile.
+    pub name: String,
+    /// The transport protocol, "tcp" or "udp"; other values are rejected.
+    #[serde(default)]
+    pub protocol: IperfProtocol,
+    /// The number of parallel streams.
+    #[serde(default = "default_parallel")]
+    pub parallel: u32,
+    /// The target bitrate, e.g. "10G"; unset is unlimited for TCP and 1 Mbit/s
+    /// for UDP.
+    #[serde(default)]
+    pub bitrate: Option<String>,
+    /// Whether the server sends and the client receives.
+    #[serde(default)]
+    pub reverse: bool,
+}
+
+/// Default number of parallel streams
+fn default_parallel() -> u32 {
+    1
+}
+
+impl IperfProfile {
+    /// Creates a profile sending from the client to the server.
+    pub fn new(name: &str, protocol: IperfProtocol, parallel: u32, bitrate: Option<&str>) -> Self {
+        IperfProfile {
+            name: name.to_string(),
+            protocol,
+            parallel,
+            bitrate: bitrate.map(str::to_string),
+            reverse: false,
+        }
+    }
+
+    /// Returns the default profiles: TCP with a single stream and with four
+    /// parallel streams, and UDP at 1 Gbit/s.
+    pub fn defaults() -> Vec<IperfProfile> {
+        vec![
+            IperfProfile::new("tcp", IperfProtocol::Tcp, 1, None),
+            IperfProfile::new("tcp-parallel", IperfProtocol::Tcp, 4, None),
+            IperfProfile::new("udp", IperfProtocol::Udp, 1, Some("1G")),
+        ]
+    }
+
+    /// Returns whether the profile sends UDP datagrams.
+    pub fn is_udp(&self) -> bool {
+        self.protocol == IperfProtocol::Udp
+    }
+}
+
+/// The results of an iperf3 test.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IperfResult {
+    /// The rate the sender sent at in bits per second.
+    pub sent_bits_per_sec: f64,
+    /// The rate the receiver received at in bits per second.
+    pub received_bits_per_sec: f64,
+    /// The TCP segments retransmitted by the sender.
+    pub retransmits: Option<u64>,
+    /// The UDP jitter seen by the receiver in milliseconds.
+    pub jitter_ms: Option<f64>,
+    /// The UDP datagrams lost.
+    pub lost_packets: Option<u64>,
+    /// The UDP datagrams lost as a percentage of those sent.
+    pub lost_percent: Option<f64>,
+    /// The CPU utilization of the client in percent.
+    pub host_cpu_percent: Option<f64>,
+    /// The CPU utilization of the server in percent.
+    pub remote_cpu_percent: Option<f64>,
+}
@@ -0,0 +1,232 @@
+//! Latency Histogram Domain Entity
+//!
+//! This module provides a log-linear latency histogram: every power of two
+//! is split into 16 linear buckets, so percentiles are accurate to within
+//! about 6% at any scale while recording stays a constant-time increment.
+
+use std::time::Duration;
+
+/// The number of bits of each value that select a linear sub-bucket.
+const SUB_BUCKET_BITS: u32 = 4;
+
+/// The number of linear sub-buckets per power of two.
+const SUB_BUCKETS: u64 = 1 << SUB_BUCKET_BITS;
+
+/// The number of buckets needed to cover every `u64` value.
+const BUCKETS: usize = (SUB_BUCKETS + (64 - SUB_BUCKET_BITS as u64) * SUB_BUCKETS) as usize;
+
+/// A histogram of latencies in nanoseconds.
+#[derive(Debug, Clone)]
+pub struct LatencyHistogram {
+    counts: Vec<u64>,
+    count: u64,
+    sum_ns: u128,
+    min_ns: u64,
+    max_ns: u64,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        LatencyHistogram::new()
+    }
+}
+
+impl LatencyHistogram {
+    /// Creates an empty histogram.
+    pub fn new() -> Self {
+        LatencyHistogram {
+            counts: vec![0; BUCKETS],
+            count: 0,
+            sum_ns: 0,
+            min_ns: u64::MAX,
+            max_ns: 0,
+        }
+    }
+
+    /// Records a latency.
+    pub fn record(&mut self, latency: Duration) {
+        self.record_ns(u64::try_from(latency.as_nanos()).unwrap_or(u64::MAX));
+    }
+
+    /// Records a latency in nanoseconds.
+    pub fn record_ns(&mut self, ns: u64) {
+        self.counts[bucket_index(ns)] += 1;
+        self.count += 1;
+        self.sum_ns += u128::from(ns);
+        self.min_ns = self.min_ns.min(ns);
+        self.max_ns = self.max_ns.max(ns);
+    }
+
+    /// Adds every latency recorded in another histogram.
+    pub fn merge(&mut self, other: &LatencyHistogram) {
+        for (count, other_count) in self.counts.iter_mut().zip(&other.counts) {
+            *count += other_count;
+        }
+        self.count += other.count;
+        self.sum_ns += other.sum_ns;
+        self.min_ns = self.min_ns.min(other.min_ns);
+        self.max_ns = self.max_ns.max(other.max_ns);
+    }
+
+    /// Returns the number of latencies recorded.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Returns the mean latency in nanoseconds, or `None` if empty.
+    pub fn mean_ns(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.sum_ns as f64 / self.count as f64)
+    }
+
+    /// Returns the highest latency in nanoseconds, or `None` if empty.
+    pub fn max_ns(&self) -> Option<u64> {
+        (self.count > 0).then_some(self.max_ns)
+    }
+
+    /// Returns the latency below which `percentile` percent of the recorded
+    /// latencies fall, in nanoseconds, or `None` if empty.
+    ///
+    /// The value is the midpoint of the bucket holding the percentile,
+    /// clamped to the lowest and highest latency recorded.
+    pub fn percentile_ns(&self, percentile: f64) -> Option<f64> {
+        if self.count == 0 {
+            return None;
+        }
+        let rank = ((percentile / 100.0) * self.count as f64).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (index, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                let (lower, width) = bucket_range(index);
+                // The middle of the whole nanoseconds in the bucket, so that
+                // the exact buckets below 16 ns report their value.
+                let midpoint = lower as f64 + (width - 1) as f64 / 2.0;
+                return Some(midpoint.clamp(self.min_ns as f64, self.max_ns as f64));
+            }
+        }
+        Some(self.max_ns as f64)
+    }
+}
+
+/// Returns the bucket of a value.
+fn bucket_index(ns: u64) -> usize {
+    if ns < SUB_BUCKETS {
+        return ns as usize;
+    }
+    let exponent = 63 - ns.leading_zeros();
+    let shift = exponent - SUB_BUCKET_BITS;
+    let sub_bucket = (ns >> shift) - SUB_BUCKETS;
+    (SUB_BUCKETS + u64::from(shift) * SUB_BUCKETS + sub_bucket) as usize
+}
+
+/// Returns the lowest value and the width of a bucket.
+fn bucket_range(index: usize) -> (u64, u64) {
+    let index = index as u64;
+    if index < SUB_BUCKETS {
+        return (index, 1);
+    }
+    let shift = (index - SUB_BUCKETS) / SUB_BUCKETS;
+    let sub_bucket = (index - SUB_BUCKETS) % SUB_BUCKETS;
+    ((SUB_BUCKETS + sub_bucket) << shift, 1 << shift)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates a histogram of the latencies in a range of nanoseconds.
+    fn uniform(latencies: std::ops::RangeInclusive<u64>) -> LatencyHistogram {
+        let mut histogram = LatencyHistogram::new();
+        for ns in latencies {
+            histogram.record_ns(ns);
+        }
+        histogram
+    }
+
+    /// Asserts that a value is within 6% of the expected value.
+    fn assert_close(actual: Option<f64>, expected: f64) {
+        let actual = actual.unwrap();
+        assert!(
+            (actual - expected).abs() <= expected * 0.06,
+            "{} is not within 6% of {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn buckets_small_values_exactly_and_powers_of_two_at_their_start() {
+        for ns in 0..SUB_BUCKETS {
+            assert_eq!(bucket_index(ns), ns as usize);
+            assert_eq!(bucket_range(ns as usize), (ns, 1));
+        }
+        for exponent in SUB_BUCKET_BITS..64 {
+            let index = bucket_index(1 << exponent);
+            assert_eq!(index, ((exponent - SUB_BUCKET_BITS + 1) * 16) as usize);
+            assert_eq!(bucket_range(index).0, 1 << exponent);
+            // The value just below a power of two ends the previous bucket.
+            assert_eq!(bucket_index((1 << exponent) - 1), index - 1);
+        }
+        for ns in [17, 100, 1_000, 123_456_789, u64::MAX / 3] {
+            let (lower, width) = bucket_range(bucket_index(ns));
+            assert!(lower <= ns && ns - lower < width, "{} not in bucket", ns);
+        }
+    }
+
+    #[test]
+    fn records_the_largest_latency() {
+        assert_eq!(bucket_index(u64::MAX), BUCKETS - 1);
+
+        let mut histogram = LatencyHistogram::new();
+        histogram.record(Duration::from_secs(u64::MAX));
+        histogram.record_ns(u64::MAX);
+        assert_eq!(histogram.count(), 2);
+        assert_eq!(histogram.max_ns(), Some(u64::MAX));
+        assert_eq!(histogram.percentile_ns(100.0), Some(u64::MAX as f64));
+    }
+
+    #[test]
+    fn estimates_percentiles_within_6_percent() {
+        let histogram = uniform(1..=100_000);
+        assert_eq!(histogram.count(), 100_000);
+        assert_eq!(histogram.mean_ns(), Some(50_000.5));
+        assert_eq!(histogram.max_ns(), Some(100_000));
+        for percentile in [1.0, 10.0, 50.0, 90.0, 99.0, 99.9] {
+            assert_close(histogram.percentile_ns(percentile), percentile * 1_000.0);
+        }
+        // Percentiles are clamped to the latencies recorded.
+        assert_eq!(histogram.percentile_ns(0.0), Some(1.0));
+        assert_eq!(histogram.percentile_ns(100.0), Some(100_000.0));
+    }
+
+    #[test]
+    fn merges_histograms() {
+        let mut histogram = uniform(1..=50_000);
+        histogram.merge(&uniform(50_001..=100_000));
+        assert_eq!(histogram.count(), 100_000);
+        assert_eq!(histogram.mean_ns(), Some(50_000.5));
+        assert_close(histogram.percentile_ns(50.0), 50_000.0);
+        assert_close(histogram.percentile_ns(99.0), 99_000.0);
+        assert_eq!(histogram.percentile_ns(0.0), Some(1.0));
+        assert_eq!(histogram.max_ns(), Some(100_000));
+
+        // Merging an empty histogram changes nothing.
+        let before = histogram.clone();
+        histogram.merge(&LatencyHistogram::new());
+        assert_eq!(histogram.counts, before.counts);
+        assert_eq!(histogram.percentile_ns(0.0), Some(1.0));
+
+        let mut empty = LatencyHistogram::new();
+        empty.merge(&before);
+        assert_eq!(empty.percentile_ns(50.0), before.percentile_ns(50.0));
+    }
+
+    #[test]
+    fn reports_nothing_when_empty() {
+        let histogram = LatencyHistogram::default();
+        assert_eq!(histogram.count(), 0);
+        assert_eq!(histogram.mean_ns(), None);
+        assert_eq!(histogram.max_ns(), None);
+        assert_eq!(histogram.percentile_ns(50.0), None);
+    }
+}
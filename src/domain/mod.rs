@@ -1,5 +1,17 @@
 // mod.rs
 
-pub mod logging;
+pub mod archive;
+pub mod baseline;
+pub mod benchmark;
+pub mod database;
+pub mod fio;
+pub mod golden_spec;
+pub mod inventory;
+pub mod iperf;
+pub mod latency_histogram;
+pub mod process;
+pub mod record;
+pub mod rollup;
+pub mod run;
 pub mod stress_ng;
-pub mod ai_model;
+// pub mod ai_model;
@@ -0,0 +1,100 @@
+//! Process Domain Entities
+//!
+//! This module provides the listings of the busiest processes of a host, as
+//! collected with `ps aux` by the overwatch command of the legacy OneForAll
+//! database, which stored the raw text of the latest listing under a key of
+//! its default tree, e.g. "cpu_stats.txt".
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::domain::record::Record;
+
+/// The usage of a process in a `ps aux` listing.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProcessUsage {
+    /// The user the process runs as.
+    pub user: String,
+    /// The process id.
+    pub pid: u32,
+    /// The share of a CPU used by the process, in percent.
+    pub cpu_percent: f32,
+    /// The share of the memory used by the process, in percent.
+    pub memory_percent: f32,
+    /// The command line of the process.
+    pub command: String,
+}
+
+impl ProcessUsage {
+    /// Parses a line of `ps aux` output: USER PID %CPU %MEM VSZ RSS TTY STAT
+    /// START TIME COMMAND, where the command may contain spaces.
+    ///
+    /// # Returns
+    /// The usage of the process, or `None` for the header and other lines
+    /// that do not describe a process.
+    pub fn parse(line: &str) -> Option<Self> {
+        let fields = line.split_whitespace().collect::<Vec<_>>();
+        if fields.len() < 11 {
+            return None;
+        }
+        Some(ProcessUsage {
+            user: fields[0].to_string(),
+            pid: fields[1].parse().ok()?,
+            cpu_percent: fields[2].parse().ok()?,
+            memory_percent: fields[3].parse().ok()?,
+            command: fields[10..].join(" "),
+        })
+    }
+}
+
+/// A listing of the busiest processes, stored in the "processes" tree.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProcessSnapshot {
+    /// The key the listing was collected under, e.g. "cpu_stats.txt". Every
+    /// listing replaces the previous one of its source.
+    pub source: String,
+    /// The time the listing was stored. Legacy listings carry no time of
+    /// their own and keep the time they were migrated.
+    pub taken_at: DateTime<Utc>,
+    /// The processes of the listing, busiest first.
+    pub processes: Vec<ProcessUsage>,
+}
+
+impl ProcessSnapshot {
+    /// Parses the raw text of a `ps aux` listing.
+    ///
+    /// # Arguments
+    /// * `source` - The key the listing was collected under.
+    /// * `taken_at` - The time the listing is stored with.
+    /// * `text` - The output of `ps aux`, with or without its header.
+    ///
+    /// # Returns
+    /// The snapshot, or an error message if no line describes a process.
+    pub fn parse(source: &str, taken_at: DateTime<Utc>, text: &str) -> Result<Self, String> {
+        let processes = text
+            .lines()
+            .filter_map(ProcessUsage::parse)
+            .collect::<Vec<_>>();
+        if processes.is_empty() {
+            return Err(format!("{} holds no ps listing", source));
+        }
+        Ok(ProcessSnapshot {
+            source: source.to_string(),
+            taken_at,
+            processes,
+        })
+    }
+}
+
+impl Record for ProcessSnapshot {
+    const TREE: &'static str = "processes";
+
+    /// Snapshots are keyed by their source.
+    fn key(&self) -> String {
+        self.source.clone()
+    }
+
+    fn timestamp(&self) -> DateTime<Utc> {
+        self.taken_at
+    }
+}
@@ -0,0 +1,241 @@
+//! Record Domain Entities
+//!
+//! This module provides what every entity stored in the database has in
+//! common: the tree it is stored in, its key, and the time, host, kind and
+//! run it is listed and filtered by. It also provides the query records are listed with
+//! and the page of records a query returns, and the encoding records are
+//! stored with: JSON wrapped in an envelope tagged with the version of the
+//! encoding, so that records written by other versions are recognized.
+
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+/// The version of the encoding records are written with.
+pub const RECORD_VERSION: u32 = 1;
+
+/// A record as it is stored, tagged with the version of its encoding.
+#[derive(Serialize, Deserialize)]
+struct Envelope<T> {
+    version: u32,
+    record: T,
+}
+
+/// An entity stored in its own tree of the database.
+pub trait Record: Serialize + DeserializeOwned + Send + Sync {
+    /// The name of the tree the records are stored in, e.g. "runs".
+    const TREE: &'static str;
+
+    /// Whether the repository keeps an index of the keys ordered by time, for
+    /// records whose keys do not narrow queries down, such as runs keyed by
+    /// uuid, so that a page is picked before the records are decoded.
+    const INDEXED_BY_TIME: bool = false;
+
+    /// Returns the key of the record within its tree.
+    fn key(&self) -> String;
+
+    /// Returns the time the record is ordered and filtered by.
+    fn timestamp(&self) -> DateTime<Utc>;
+
+    /// Returns the host the record belongs to, if any.
+    fn host(&self) -> Option<&str> {
+        None
+    }
+
+    /// Returns the node SKU the record belongs to, if any.
+    fn sku(&self) -> Option<&str> {
+        None
+    }
+
+    /// Returns the kind of the record, e.g. "benchmark" for runs.
+    fn kind(&self) -> Option<&str> {
+        None
+    }
+
+    /// Returns the run the record belongs to, if any.
+    fn run_id(&self) -> Option<&str> {
+        None
+    }
+
+    /// Returns the range of keys `start..end` holding every record that can
+    /// match a query, if keys are ordered so that the query narrows them
+    /// down, so that listing scans only that range instead of the whole tree.
+    fn key_range(_query: &RecordQuery) -> Option<(String, String)> {
+        None
+    }
+}
+
+/// Returns the range of keys holding every record of an owner such as a host,
+/// for keys that start with the owner followed by a slash.
+pub fn prefix_key_range(owner: &str) -> (String, String) {
+    // '0' is the character after '/', so it ends the keys of the owner.
+    (format!("{}/", owner), format!("{}0", owner))
+}
+
+/// Returns the range of keys holding the records of a query's time range, for
+/// keys made of a prefix and a zero-padded timestamp in milliseconds, e.g.
+/// "host/00000001700000000000".
+pub fn time_key_range(prefix: &str, query: &RecordQuery) -> (String, String) {
+    let key = |millis: i64| format!("{}{:020}", prefix, millis.max(0));
+    let start = match query.since {
+        Some(since) => key(since.timestamp_millis()),
+        None => prefix.to_string(),
+    };
+    // Keys are truncated to milliseconds, so the end includes the millisecond
+    // of `until`, and ':' is the character after the digits of the timestamp.
+    let end = match query.until {
+        Some(until) => key(until.timestamp_millis() + 1),
+        None => format!("{}:", prefix),
+    };
+    (start, end)
+}
+
+/// Returns the key of a record in the index of records by time, made of its
+/// zero-padded timestamp in milliseconds and its key, e.g.
+/// "00000001700000000000/run1", so that `time_key_range` applies to it.
+pub fn time_index_key<T: Record>(record: &T) -> String {
+    format!(
+        "{:020}/{}",
+        record.timestamp().timestamp_millis().max(0),
+        record.key()
+    )
+}
+
+/// Filters and pagination records are listed with. Unset filters match every
+/// record, while a host, SKU, kind or run filter only matches records that
+/// have one.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RecordQuery {
+    /// Only records of this host.
+    pub host: Option<String>,
+    /// Only records of this node SKU.
+    pub sku: Option<String>,
+    /// Only records of this kind, e.g. "stress".
+    pub kind: Option<String>,
+    /// Only records of this run.
+    pub run_id: Option<String>,
+    /// Only records at or after this time.
+    pub since: Option<DateTime<Utc>>,
+    /// Only records before this time.
+    pub until: Option<DateTime<Utc>>,
+    /// Whether the newest records come first instead of the oldest.
+    pub newest_first: bool,
+    /// The number of matching records skipped.
+    pub offset: usize,
+    /// The maximum number of records returned; unset returns all.
+    pub limit: Option<usize>,
+}
+
+impl RecordQuery {
+    /// Creates a query matching every record, oldest first.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only matches records of a host.
+    pub fn with_host(mut self, host: &str) -> Self {
+        self.host = Some(host.to_string());
+        self
+    }
+
+    /// Only matches records of a node SKU.
+    pub fn with_sku(mut self, sku: &str) -> Self {
+        self.sku = Some(sku.to_string());
+        self
+    }
+
+    /// Only matches records of a kind.
+    pub fn with_kind(mut self, kind: &str) -> Self {
+        self.kind = Some(kind.to_string());
+        self
+    }
+
+    /// Only matches records of a run.
+    pub fn with_run_id(mut self, run_id: &str) -> Self {
+        self.run_id = Some(run_id.to_string());
+        self
+    }
+
+    /// Only matches records at or after a time.
+    pub fn since(mut self, since: DateTime<Utc>) -> Self {
+        self.since = Some(since);
+        self
+    }
+
+    /// Only matches records before a time.
+    pub fn until(mut self, until: DateTime<Utc>) -> Self {
+        self.until = Some(until);
+        self
+    }
+
+    /// Returns whether the query filters on anything but the time of the
+    /// records, which can only be matched once they are decoded.
+    pub fn filters_beyond_time(&self) -> bool {
+        self.host.is_some() || self.sku.is_some() || self.kind.is_some() || self.run_id.is_some()
+    }
+
+    /// Lists the newest records first.
+    pub fn newest_first(mut self) -> Self {
+        self.newest_first = true;
+        self
+    }
+
+    /// Returns a page of at most `limit` records after skipping `offset`.
+    pub fn page(mut self, offset: usize, limit: usize) -> Self {
+        self.offset = offset;
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Returns whether a record matches the filters of the query.
+    pub fn matches<T: Record>(&self, record: &T) -> bool {
+        let timestamp = record.timestamp();
+        self.host
+            .as_deref()
+            .is_none_or(|host| record.host() == Some(host))
+            && self
+                .sku
+                .as_deref()
+                .is_none_or(|sku| record.sku() == Some(sku))
+            && self
+                .kind
+                .as_deref()
+                .is_none_or(|kind| record.kind() == Some(kind))
+            && self
+                .run_id
+                .as_deref()
+                .is_none_or(|run_id| record.run_id() == Some(run_id))
+            && self.since.is_none_or(|since| timestamp >= since)
+            && self.until.is_none_or(|until| timestamp < until)
+    }
+}
+
+/// A page of the records matching a query.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Page<T> {
+    /// The records of the page, in the order of the query.
+    pub items: Vec<T>,
+    /// The number of records matching the query over every page.
+    pub total: usize,
+    /// The number of matching records before the page.
+    pub offset: usize,
+}
+
+/// Encodes a record as it is stored.
+pub fn encode_record<T: Record>(record: &T) -> Result<Vec<u8>, String> {
+    serde_json::to_vec(&Envelope {
+        version: RECORD_VERSION,
+        record,
+    })
+    .map_err(|e| e.to_string())
+}
+
+/// Decodes a stored record, rejecting encodings of other versions.
+pub fn decode_record<T: Record>(value: &[u8]) -> Result<T, String> {
+    let envelope: Envelope<serde_json::Value> =
+        serde_json::from_slice(value).map_err(|e| e.to_string())?;
+    if envelope.version != RECORD_VERSION {
+        return Err(format!("unsupported version {}", envelope.version));
+    }
+    serde_json::from_value(envelope.record).map_err(|e| e.to_string())
+}
@@ -0,0 +1,294 @@
+//! Rollup Domain Entities
+//!
+//! This module provides the downsampled history of a host: metric samples are
+//! rolled up into 1-minute and hourly buckets holding the minimum, average,
+//! maximum and 95th percentile of every metric. Raw samples are kept for a
+//! number of hours, 1-minute rollups for a number of days and hourly rollups
+//! forever, and history queries pick the finest resolution that is still
+//! retained for their time range and keeps the number of points chartable.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::domain::record::{prefix_key_range, time_key_range, Record, RecordQuery};
+use crate::domain::run::MetricSample;
+
+/// The maximum number of points a history query aims to return.
+pub const MAX_HISTORY_POINTS: i64 = 2000;
+
+/// The resolution of a host's history.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Resolution {
+    /// Every sample as it was taken.
+    Raw,
+    /// 1-minute rollups.
+    Minute,
+    /// Hourly rollups.
+    Hour,
+}
+
+impl Resolution {
+    /// The resolutions samples are rolled up into, finest first.
+    pub const ROLLUPS: [Resolution; 2] = [Resolution::Minute, Resolution::Hour];
+
+    /// Returns the name of the resolution, e.g. "1m".
+    pub fn name(&self) -> &'static str {
+        match self {
+            Resolution::Raw => "raw",
+            Resolution::Minute => "1m",
+            Resolution::Hour => "1h",
+        }
+    }
+
+    /// Returns the width of the buckets of the resolution, or `None` for raw samples.
+    pub fn bucket(&self) -> Option<Duration> {
+        match self {
+            Resolution::Raw => None,
+            Resolution::Minute => Some(Duration::minutes(1)),
+            Resolution::Hour => Some(Duration::hours(1)),
+        }
+    }
+
+    /// Returns the start of the bucket a time falls into.
+    pub fn bucket_start(&self, time: DateTime<Utc>) -> DateTime<Utc> {
+        let Some(bucket) = self.bucket() else {
+            return time;
+        };
+        let seconds = bucket.num_seconds();
+        let start = time.timestamp().div_euclid(seconds) * seconds;
+        Utc.timestamp_opt(start, 0).single().unwrap_or(time)
+    }
+
+    /// Picks the resolution of a history query: the finest one that is still
+    /// retained at `since` and returns at most `MAX_HISTORY_POINTS` points.
+    /// Unbounded queries use hourly rollups.
+    ///
+    /// # Arguments
+    /// * `since` - The start of the time range, if any.
+    /// * `until` - The end of the time range.
+    /// * `now` - The current time.
+    /// * `policy` - How long every resolution is retained.
+    /// * `sample_interval` - The interval raw samples are taken at.
+    pub fn select(
+        since: Option<DateTime<Utc>>,
+        until: DateTime<Utc>,
+        now: DateTime<Utc>,
+        policy: &RetentionPolicy,
+        sample_interval: Duration,
+    ) -> Resolution {
+        let Some(since) = since else {
+            return Resolution::Hour;
+        };
+        let span = (until - since).num_seconds().max(0);
+        [Resolution::Raw, Resolution::Minute]
+            .into_iter()
+            .find(|resolution| {
+                let step = resolution.bucket().unwrap_or(sample_interval).num_seconds();
+                policy.retains(*resolution, since, now) && span / step.max(1) <= MAX_HISTORY_POINTS
+            })
+            .unwrap_or(Resolution::Hour)
+    }
+}
+
+/// How long the history of a host is kept at every resolution. Hourly
+/// rollups are kept forever.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    /// The number of hours raw samples are kept.
+    pub raw_hours: u64,
+    /// The number of days 1-minute rollups are kept.
+    pub minute_days: u64,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        RetentionPolicy {
+            raw_hours: 24,
+            minute_days: 30,
+        }
+    }
+}
+
+impl RetentionPolicy {
+    /// Returns the time before which a resolution is no longer kept, or
+    /// `None` if it is kept forever. Raw samples are kept for at least an
+    /// hour so that they are rolled up before they expire.
+    pub fn cutoff(&self, resolution: Resolution, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match resolution {
+            Resolution::Raw => Some(now - Duration::hours(self.raw_hours.max(1) as i64)),
+            Resolution::Minute => Some(now - Duration::days(self.minute_days as i64)),
+            Resolution::Hour => None,
+        }
+    }
+
+    /// Returns whether a resolution is still retained at a time.
+    pub fn retains(&self, resolution: Resolution, time: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+        self.cutoff(resolution, now)
+            .is_none_or(|cutoff| time >= cutoff)
+    }
+}
+
+/// The minimum, average, maximum and 95th percentile of a metric over a bucket.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RollupStats {
+    /// The lowest value.
+    pub min: f64,
+    /// The mean of the values.
+    pub avg: f64,
+    /// The highest value.
+    pub max: f64,
+    /// The nearest-rank 95th percentile.
+    pub p95: f64,
+}
+
+impl RollupStats {
+    /// Summarizes values, or returns `None` if there are none.
+    pub fn from_values(mut values: Vec<f64>) -> Option<Self> {
+        if values.is_empty() {
+            return None;
+        }
+        values.sort_by(f64::total_cmp);
+        let rank = (values.len() as f64 * 0.95).ceil() as usize;
+        Some(RollupStats {
+            min: values[0],
+            avg: values.iter().sum::<f64>() / values.len() as f64,
+            max: values[values.len() - 1],
+            p95: values[rank.clamp(1, values.len()) - 1],
+        })
+    }
+
+    /// Summarizes a single value.
+    pub fn from_value(value: f64) -> Self {
+        RollupStats {
+            min: value,
+            avg: value,
+            max: value,
+            p95: value,
+        }
+    }
+}
+
+/// The metric samples of a host over one bucket of a resolution.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetricRollup {
+    /// The host the samples were taken on.
+    pub host: String,
+    /// The resolution of the bucket.
+    pub resolution: Resolution,
+    /// The start of the bucket.
+    pub start: DateTime<Utc>,
+    /// The number of samples rolled up.
+    pub samples: usize,
+    /// Overall CPU utilization as a percentage.
+    pub cpu_utilization: Option<RollupStats>,
+    /// The hottest temperature sensor reading in degrees Celsius.
+    pub temperature_c: Option<RollupStats>,
+    /// Bogo operations per second reported by a stressor.
+    pub bogo_ops_per_sec: Option<RollupStats>,
+}
+
+impl MetricRollup {
+    /// Wraps a single sample as a point of the raw resolution.
+    pub fn from_sample(sample: &MetricSample) -> Self {
+        MetricRollup {
+            host: sample.host.clone(),
+            resolution: Resolution::Raw,
+            start: sample.timestamp,
+            samples: 1,
+            cpu_utilization: sample.cpu_utilization.map(RollupStats::from_value),
+            temperature_c: sample.temperature_c.map(RollupStats::from_value),
+            bogo_ops_per_sec: sample.bogo_ops_per_sec.map(RollupStats::from_value),
+        }
+    }
+
+    /// Rolls samples up into the buckets of a resolution, one rollup per
+    /// host and bucket, ordered by host and then by time.
+    pub fn roll_up(resolution: Resolution, samples: &[MetricSample]) -> Vec<MetricRollup> {
+        let mut buckets: BTreeMap<(&str, DateTime<Utc>), Vec<&MetricSample>> = BTreeMap::new();
+        for sample in samples {
+            buckets
+                .entry((&sample.host, resolution.bucket_start(sample.timestamp)))
+                .or_default()
+                .push(sample);
+        }
+
+        buckets
+            .into_iter()
+            .map(|((host, start), samples)| {
+                let stats = |value: fn(&MetricSample) -> Option<f64>| {
+                    RollupStats::from_values(samples.iter().filter_map(|s| value(s)).collect())
+                };
+                MetricRollup {
+                    host: host.to_string(),
+                    resolution,
+                    start,
+                    samples: samples.len(),
+                    cpu_utilization: stats(|s| s.cpu_utilization),
+                    temperature_c: stats(|s| s.temperature_c),
+                    bogo_ops_per_sec: stats(|s| s.bogo_ops_per_sec),
+                }
+            })
+            .collect()
+    }
+}
+
+impl Record for MetricRollup {
+    const TREE: &'static str = "rollups";
+
+    /// Keys are ordered by host, then by resolution and then by time.
+    fn key(&self) -> String {
+        format!(
+            "{}/{}/{:020}",
+            self.host,
+            self.resolution.name(),
+            self.start.timestamp_millis().max(0)
+        )
+    }
+
+    fn timestamp(&self) -> DateTime<Utc> {
+        self.start
+    }
+
+    fn host(&self) -> Option<&str> {
+        Some(&self.host)
+    }
+
+    /// Rollups are listed by the name of their resolution, e.g. "1m".
+    fn kind(&self) -> Option<&str> {
+        Some(self.resolution.name())
+    }
+
+    fn key_range(query: &RecordQuery) -> Option<(String, String)> {
+        let host = query.host.as_deref()?;
+        Some(match query.kind.as_deref() {
+            Some(kind) => time_key_range(&format!("{}/{}/", host, kind), query),
+            None => prefix_key_range(host),
+        })
+    }
+}
+
+/// The history of a host at one resolution.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HostHistory {
+    /// The name of the host.
+    pub host: String,
+    /// The resolution of the points.
+    pub resolution: Resolution,
+    /// The points of the history in chronological order; raw samples are
+    /// points of a single sample.
+    pub points: Vec<MetricRollup>,
+}
+
+/// The outcome of applying the retention policy once.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct RetentionReport {
+    /// The number of rollups computed by resolution name.
+    pub rolled_up: BTreeMap<String, usize>,
+    /// The number of expired raw samples removed.
+    pub expired_samples: usize,
+    /// The number of expired 1-minute rollups removed.
+    pub expired_rollups: usize,
+}
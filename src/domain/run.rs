@@ -0,0 +1,327 @@
+//! Run and Sample Domain Entities
+//!
+//! This module provides the domain entities recorded while commandant-rs is
+//! working: a `RunRecord` for every stress or benchmark run, and a
+//! `MetricSample` for every point-in-time reading of the host's utilization
+//! and temperature. Files a run produces, such as raw tool output and logs,
+//! are kept with it as `RunArtifact`s.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::domain::record::{prefix_key_range, time_key_range, Record, RecordQuery};
+
+/// Represents the outcome of a run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RunStatus {
+    /// The run has started and has not yet finished.
+    Running,
+    /// The run finished successfully.
+    Passed,
+    /// The run finished with an error.
+    Failed,
+}
+
+/// Whether higher or lower values of a metric are better.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MetricDirection {
+    /// Higher values are better, e.g. throughput or bogo ops/s.
+    HigherIsBetter,
+    /// Lower values are better, e.g. latency or lost packets.
+    LowerIsBetter,
+}
+
+/// A single named measurement produced by a run, e.g. the bogo ops/s of a
+/// stress-ng stressor.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunMetric {
+    /// The name of the metric, e.g. "cpu bogo ops/s".
+    pub name: String,
+    /// The measured value.
+    pub value: f64,
+    /// The unit of the measured value, e.g. "ops/s".
+    pub unit: String,
+    /// Whether higher or lower values are better.
+    pub direction: MetricDirection,
+    /// The time at which the metric was recorded.
+    pub recorded_at: DateTime<Utc>,
+}
+
+/// Represents a single stress or benchmark run on a host.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunRecord {
+    /// The unique identifier of the run.
+    pub id: String,
+    /// The host the run was executed on.
+    pub host: String,
+    /// The kind of run, e.g. "stress" or "benchmark".
+    pub kind: String,
+    /// What the run exercised, e.g. the benchmark suites or the stress-ng
+    /// arguments, so that only comparable runs are derived into a baseline.
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// The SKU of the host, used to find the baseline the run is compared with.
+    #[serde(default)]
+    pub sku: Option<String>,
+    /// The time at which the run started.
+    pub started_at: DateTime<Utc>,
+    /// The time at which the run finished, if it has finished.
+    pub finished_at: Option<DateTime<Utc>>,
+    /// The current status of the run.
+    pub status: RunStatus,
+    /// The metrics produced by the run.
+    pub metrics: Vec<RunMetric>,
+    /// The log file holding every message logged during the run, if any.
+    #[serde(default)]
+    pub log_file: Option<String>,
+}
+
+impl RunRecord {
+    /// Creates a new running `RunRecord` with a random identifier.
+    ///
+    /// # Arguments
+    ///
+    /// * `host` - The host the run is executed on.
+    /// * `kind` - The kind of run, e.g. "stress".
+    ///
+    /// # Returns
+    ///
+    /// * `RunRecord` - A new record started now.
+    pub fn start(host: &str, kind: &str) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            host: host.to_string(),
+            kind: kind.to_string(),
+            profile: None,
+            sku: None,
+            started_at: Utc::now(),
+            finished_at: None,
+            status: RunStatus::Running,
+            metrics: Vec::new(),
+            log_file: None,
+        }
+    }
+
+    /// Marks the run as finished with the given status.
+    pub fn finish(&mut self, status: RunStatus) {
+        self.finished_at = Some(Utc::now());
+        self.status = status;
+    }
+}
+
+impl Record for RunRecord {
+    const TREE: &'static str = "runs";
+    const INDEXED_BY_TIME: bool = true;
+
+    /// Runs are keyed by their identifier.
+    fn key(&self) -> String {
+        self.id.clone()
+    }
+
+    fn timestamp(&self) -> DateTime<Utc> {
+        self.started_at
+    }
+
+    fn host(&self) -> Option<&str> {
+        Some(&self.host)
+    }
+
+    fn sku(&self) -> Option<&str> {
+        self.sku.as_deref()
+    }
+
+    fn kind(&self) -> Option<&str> {
+        Some(&self.kind)
+    }
+
+    fn run_id(&self) -> Option<&str> {
+        Some(&self.id)
+    }
+}
+
+/// A point-in-time reading of a host's utilization and temperature.
+///
+/// Fields that could not be read on the host are left as `None`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetricSample {
+    /// The host the sample was taken on.
+    pub host: String,
+    /// The run the sample was taken during, if any.
+    pub run_id: Option<String>,
+    /// The time at which the sample was taken.
+    pub timestamp: DateTime<Utc>,
+    /// A number counting up with every sample the process takes, so that
+    /// samples of a host taken within the same millisecond keep distinct
+    /// keys.
+    pub sequence: u32,
+    /// Overall CPU utilization as a percentage.
+    pub cpu_utilization: Option<f64>,
+    /// The hottest temperature sensor reading in degrees Celsius.
+    pub temperature_c: Option<f64>,
+    /// Bogo operations per second reported by a stressor at this time.
+    pub bogo_ops_per_sec: Option<f64>,
+}
+
+impl Record for MetricSample {
+    const TREE: &'static str = "samples";
+
+    /// Keys are ordered by host and then by time, so a range scan returns a
+    /// host's history in chronological order. The sequence number follows
+    /// the time, e.g. "node1/00000001700000000000-0000000042".
+    fn key(&self) -> String {
+        format!(
+            "{}/{:020}-{:010}",
+            self.host,
+            self.timestamp.timestamp_millis().max(0),
+            self.sequence
+        )
+    }
+
+    fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+
+    fn host(&self) -> Option<&str> {
+        Some(&self.host)
+    }
+
+    fn run_id(&self) -> Option<&str> {
+        self.run_id.as_deref()
+    }
+
+    fn key_range(query: &RecordQuery) -> Option<(String, String)> {
+        let host = query.host.as_deref()?;
+        Some(time_key_range(&format!("{}/", host), query))
+    }
+}
+
+/// A file produced by a run, e.g. the raw output of stress-ng or the log of
+/// the run, kept with the run so that it can be archived.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunArtifact {
+    /// The run the artifact was produced by.
+    pub run_id: String,
+    /// The name of the artifact, a relative path such as "stress-ng.txt".
+    pub name: String,
+    /// The time at which the artifact was stored.
+    pub created_at: DateTime<Utc>,
+    /// The contents of the artifact, exactly as the run produced them.
+    #[serde(with = "artifact_content")]
+    pub content: Vec<u8>,
+}
+
+impl RunArtifact {
+    /// Creates an artifact of a run stored now.
+    pub fn new(run_id: &str, name: &str, content: Vec<u8>) -> Self {
+        Self {
+            run_id: run_id.to_string(),
+            name: name.to_string(),
+            created_at: Utc::now(),
+            content,
+        }
+    }
+}
+
+/// Stores the contents of an artifact as base64, so that binary output and
+/// logs cut off within a character keep their bytes.
+mod artifact_content {
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(content: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&STANDARD.encode(content))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        STANDARD.decode(encoded).map_err(D::Error::custom)
+    }
+}
+
+impl Record for RunArtifact {
+    const TREE: &'static str = "artifacts";
+
+    /// Keys are ordered by run and then by name.
+    fn key(&self) -> String {
+        format!("{}/{}", self.run_id, self.name)
+    }
+
+    fn timestamp(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+
+    fn run_id(&self) -> Option<&str> {
+        Some(&self.run_id)
+    }
+
+    fn key_range(query: &RecordQuery) -> Option<(String, String)> {
+        query.run_id.as_deref().map(prefix_key_range)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates a sample of "node1" at a millisecond.
+    fn sample(millis: i64, sequence: u32) -> MetricSample {
+        MetricSample {
+            host: "node1".to_string(),
+            run_id: None,
+            timestamp: DateTime::from_timestamp_millis(millis).unwrap(),
+            sequence,
+            cpu_utilization: Some(12.5),
+            temperature_c: None,
+            bogo_ops_per_sec: None,
+        }
+    }
+
+    #[test]
+    fn samples_of_the_same_millisecond_get_distinct_keys() {
+        let first = sample(1_700_000_000_000, 41);
+        let second = sample(1_700_000_000_000, 42);
+        assert_eq!(first.key(), "node1/00000001700000000000-0000000041");
+        assert!(first.key() < second.key());
+        assert!(second.key() < sample(1_700_000_000_001, 1).key());
+    }
+
+    #[test]
+    fn time_ranges_include_every_sample_of_their_last_millisecond() {
+        let at = DateTime::from_timestamp_millis(1_700_000_000_000).unwrap();
+        let query = RecordQuery::new().with_host("node1").since(at).until(at);
+        let (start, end) = MetricSample::key_range(&query).unwrap();
+
+        for key in [
+            sample(1_700_000_000_000, 1).key(),
+            sample(1_700_000_000_000, u32::MAX).key(),
+        ] {
+            assert!(
+                start <= key && key < end,
+                "{} not in {}..{}",
+                key,
+                start,
+                end
+            );
+        }
+        let next = sample(1_700_000_000_001, 1).key();
+        assert!(next >= end);
+    }
+
+    #[test]
+    fn artifacts_keep_their_bytes() {
+        let binary = RunArtifact::new("run1", "out.bin", vec![0x66, 0x6f, 0xff, 0x00, 0xc3]);
+        let text = RunArtifact::new("run1", "stress-ng.txt", b"cpu 1234.5\n".to_vec());
+
+        let json = serde_json::to_string(&binary).unwrap();
+        assert!(json.contains(r#""content":"Zm//AMM=""#), "{}", json);
+
+        for artifact in [binary, text] {
+            let json = serde_json::to_string(&artifact).unwrap();
+            let stored: RunArtifact = serde_json::from_str(&json).unwrap();
+            assert_eq!(stored.content, artifact.content);
+        }
+    }
+}
@@ -1,27 +1,75 @@
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::io;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 use std::sync::Arc;
 
 use serde::Deserialize;
 
-use clap::{Parser, Subcommand};
+use clap::{ArgAction, Parser, Subcommand};
 use futures::SinkExt;
 use tokio::time::{sleep, Duration};
 use tokio::{signal, spawn};
 
+use common::adapters::log_adapter::{
+    self, parse_level, LogFormat, LogRotation, LogSettings, LogSink,
+};
+use common::adapters::scoped_log_adapter::ScopedLogger;
 use common::adapters::web_server_adapter::WebServerAdapter;
 use common::ports::log_port::LoggerPort;
 use common::ports::web_server_port::WebServerPort;
 
 // use crate::adapters::burn_ai_model_adapter::BurnAiModelAdapter;
-use crate::adapters::database_adapter::DatabaseAdapter;
+use crate::adapters::bzip2_archive_adapter::Bzip2ArchiveAdapter;
+use crate::adapters::cpu_benchmark_adapter::CpuBenchmarkAdapter;
+use crate::adapters::cpu_frequency_adapter::CpuFrequencyAdapter;
+use crate::adapters::discovery_adapter::SysfsDiscoveryAdapter;
+use crate::adapters::fio_adapter::FioAdapter;
+use crate::adapters::host_info_adapter;
+use crate::adapters::iperf_adapter::IperfAdapter;
+use crate::adapters::memory_benchmark_adapter::MemoryBenchmarkAdapter;
+use crate::adapters::native_storage_benchmark_adapter::NativeStorageBenchmarkAdapter;
+use crate::adapters::netbench_adapter::{NetbenchAdapter, NetbenchServer};
+use crate::adapters::plotters_chart_adapter::PlottersChartAdapter;
 use crate::adapters::ps_command_adapter::PsAdapter;
-use crate::adapters::stress_ng_adapter::StressNgAdapter;
+use crate::adapters::report_adapter::ReportAdapter;
+use crate::adapters::memory_database_adapter::MemoryDatabaseAdapter;
+use crate::adapters::repository_adapter::TreeRepository;
+use crate::adapters::sled_database_adapter::SledDatabaseAdapter;
+use crate::adapters::stress_ng_adapter::{StressNgAdapter, STRESS_NG_OUTPUT_FILE};
+use crate::adapters::system_metrics_adapter::SystemMetricsAdapter;
+use crate::application::archives::{self, Archives};
+use crate::application::baselines::{self, Baselines};
+use crate::application::benchmark;
+use crate::application::database_ops::{self, DatabaseOps};
+use crate::application::discovery::{self, DiscoverySections};
+use crate::application::inventory_checks::InventoryExpectations;
+use crate::application::inventory_diff;
+use crate::application::inventory_snapshots::InventorySnapshots;
+use crate::application::migrations::{self, Migrator};
+use crate::application::retention::Retention;
+use crate::application::spec_check;
+use crate::application::run_recorder::RunRecorder;
 // use crate::domain::ai_model::AiModel;
+use crate::domain::baseline::{BaselineComparison, BaselineTolerances};
+use crate::domain::benchmark::{
+    BenchmarkReport, BenchmarkResult, BenchmarkSettings, StorageEngine,
+};
+use crate::domain::database::{DatabaseBackend, ExportFormat, MigrationReport, SCHEMA_VERSION};
+use crate::domain::fio::FioProfile;
+use crate::domain::iperf::IperfProfile;
+use crate::domain::record::Record;
+use crate::domain::rollup::RetentionPolicy;
+use crate::domain::run::{RunArtifact, RunRecord, RunStatus};
+use crate::ports::benchmark_port::BenchmarkPort;
+use crate::ports::cpu_frequency_port::CpuFrequencyPort;
 use crate::ports::database_port::DatabasePort;
 use crate::ports::ps_command_port::PsCommandPort;
 
 mod adapters;
+mod application;
+mod domain;
 mod ports;
 
 /// Main configuration struct that holds all sub-configurations
@@ -39,6 +87,15 @@ pub struct Config {
     pub overwatch: OverwatchConfig,
     /// Database operations settings
     pub database_ops: DatabaseOpsConfig,
+    /// Hardware discovery settings
+    #[serde(default)]
+    pub discovery: DiscoveryConfig,
+    /// Benchmark settings
+    #[serde(default)]
+    pub benchmark: BenchmarkConfig,
+    /// Baseline and regression detection settings
+    #[serde(default)]
+    pub baselines: BaselineConfig,
 }
 
 /// General configuration for the application
@@ -48,8 +105,52 @@ pub struct GeneralConfig {
     pub log_directory: String,
     /// Log level (e.g., "Info", "Debug", "Error")
     pub log_level: String,
+    /// Log levels of modules overriding log_level, e.g. "actix_server: Warn"
+    #[serde(default)]
+    pub log_modules: BTreeMap<String, String>,
+    /// Where log messages are written: "stdout", "level_files" and/or "combined_file"
+    #[serde(default = "default_log_sinks")]
+    pub log_sinks: Vec<LogSink>,
+    /// Format of log messages: "text", or "json" for one JSON object per line
+    #[serde(default)]
+    pub log_format: LogFormat,
+    /// When log files are rotated by size and age, and how many rotated files are kept
+    #[serde(default)]
+    pub log_rotation: LogRotation,
     /// Path to the database file
     pub database_path: String,
+    /// Storage backend of the database: "sled" on disk, or "memory" for ephemeral runs
+    #[serde(default)]
+    pub database_backend: DatabaseBackend,
+    /// Path to a legacy OneForAll database imported into the database once, if any
+    #[serde(default)]
+    pub legacy_database_path: Option<String>,
+}
+
+impl GeneralConfig {
+    /// Returns the settings the logger is initialized with
+    ///
+    /// # Arguments
+    /// * `verbose` - The number of levels the log level is raised by.
+    /// * `quiet` - The number of levels the log level is lowered by.
+    fn log_settings(&self, verbose: u8, quiet: u8) -> Result<LogSettings, String> {
+        let mut settings = LogSettings::new(&self.log_directory, parse_level(&self.log_level)?)
+            .with_sinks(self.log_sinks.clone())
+            .with_format(self.log_format)
+            .with_rotation(self.log_rotation)
+            .with_host(&host_info_adapter::hostname())
+            .with_crate_name(env!("CARGO_CRATE_NAME"))
+            .with_verbosity(verbose, quiet);
+        for (module, level) in &self.log_modules {
+            settings = settings.with_module(module, parse_level(level)?);
+        }
+        Ok(settings)
+    }
+}
+
+/// Default log sinks: standard output and a file per level
+fn default_log_sinks() -> Vec<LogSink> {
+    vec![LogSink::Stdout, LogSink::LevelFiles]
 }
 
 /// Web server configuration
@@ -99,6 +200,58 @@ pub struct OverwatchConfig {
     pub output_file: String,
     /// Interval (in seconds) for collecting monitoring data
     pub interval: u32,
+    /// Retention and downsampling of the collected samples
+    #[serde(default)]
+    pub retention: RetentionConfig,
+}
+
+/// Configuration for the retention of overwatch samples
+#[derive(Debug, Deserialize)]
+pub struct RetentionConfig {
+    /// Number of hours raw samples are kept
+    #[serde(default = "default_raw_hours")]
+    pub raw_hours: u64,
+    /// Number of days 1-minute rollups are kept; hourly rollups are kept forever
+    #[serde(default = "default_minute_days")]
+    pub minute_days: u64,
+    /// Interval (in seconds) at which samples are rolled up and expired
+    #[serde(default = "default_rollup_interval")]
+    pub rollup_interval: u64,
+}
+
+impl RetentionConfig {
+    /// Returns the policy samples and rollups are retained with
+    fn policy(&self) -> RetentionPolicy {
+        RetentionPolicy {
+            raw_hours: self.raw_hours,
+            minute_days: self.minute_days,
+        }
+    }
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        RetentionConfig {
+            raw_hours: default_raw_hours(),
+            minute_days: default_minute_days(),
+            rollup_interval: default_rollup_interval(),
+        }
+    }
+}
+
+/// Default number of hours raw samples are kept
+fn default_raw_hours() -> u64 {
+    24
+}
+
+/// Default number of days 1-minute rollups are kept
+fn default_minute_days() -> u64 {
+    30
+}
+
+/// Default interval in seconds at which samples are rolled up
+fn default_rollup_interval() -> u64 {
+    60
 }
 
 /// Configuration for database operations
@@ -108,6 +261,356 @@ pub struct DatabaseOpsConfig {
     pub enabled: bool,
 }
 
+/// Configuration for hardware discovery
+#[derive(Debug, Default, Deserialize)]
+pub struct DiscoveryConfig {
+    /// Expected negotiated link speed in Mb/s per network interface or RDMA
+    /// port (e.g. "mlx5_0/1"); names ending in "*" match by prefix
+    #[serde(default)]
+    pub expected_link_speeds: BTreeMap<String, u64>,
+}
+
+/// Configuration for baselines and regression detection
+#[derive(Debug, Deserialize)]
+pub struct BaselineConfig {
+    /// SKU of the host; unset uses the system product name read from SMBIOS
+    #[serde(default)]
+    pub sku: Option<String>,
+    /// Number of latest passed runs a baseline is derived from
+    #[serde(default = "default_baseline_runs")]
+    pub runs: usize,
+    /// Tolerance of metrics in percent before they count as regressed
+    #[serde(default = "default_tolerance_percent")]
+    pub tolerance_percent: f64,
+    /// Tolerances in percent by metric name; names ending in "*" match by prefix
+    #[serde(default)]
+    pub tolerances: BTreeMap<String, f64>,
+}
+
+impl BaselineConfig {
+    /// Returns the tolerances runs are compared against their baseline with
+    fn tolerances(&self) -> BaselineTolerances {
+        BaselineTolerances {
+            default_percent: self.tolerance_percent,
+            metrics: self.tolerances.clone(),
+        }
+    }
+}
+
+impl Default for BaselineConfig {
+    fn default() -> Self {
+        BaselineConfig {
+            sku: None,
+            runs: default_baseline_runs(),
+            tolerance_percent: default_tolerance_percent(),
+            tolerances: BTreeMap::new(),
+        }
+    }
+}
+
+/// Default number of runs a baseline is derived from
+fn default_baseline_runs() -> usize {
+    5
+}
+
+/// Default tolerance of metrics in percent
+fn default_tolerance_percent() -> f64 {
+    5.0
+}
+
+/// Configuration for benchmarks
+#[derive(Debug, Deserialize)]
+pub struct BenchmarkConfig {
+    /// Number of measured iterations of every benchmark
+    #[serde(default = "default_benchmark_iterations")]
+    pub iterations: usize,
+    /// Duration of a single iteration in milliseconds
+    #[serde(default = "default_benchmark_iteration_ms")]
+    pub iteration_ms: u64,
+    /// Number of unmeasured warmup iterations run before the measured ones
+    #[serde(default = "default_warmup_iterations")]
+    pub warmup_iterations: usize,
+    /// Whether iterations far from the others are left out of the statistics
+    #[serde(default = "default_reject_outliers")]
+    pub reject_outliers: bool,
+    /// Whether results measured while the CPUs throttled are marked untrustworthy
+    #[serde(default = "default_detect_throttling")]
+    pub detect_throttling: bool,
+    /// Memory bandwidth and latency benchmark settings
+    #[serde(default)]
+    pub memory: MemoryBenchmarkConfig,
+    /// Storage benchmark settings
+    #[serde(default)]
+    pub storage: StorageBenchmarkConfig,
+    /// Network benchmark settings
+    #[serde(default)]
+    pub network: NetworkBenchmarkConfig,
+    /// Native network benchmark settings
+    #[serde(default)]
+    pub netbench: NetbenchConfig,
+}
+
+impl BenchmarkConfig {
+    /// Returns the settings benchmarks are run with, overriding the configured
+    /// number of iterations
+    fn settings(&self, iterations: Option<usize>, warmup: Option<usize>) -> BenchmarkSettings {
+        BenchmarkSettings {
+            iterations: iterations.unwrap_or(self.iterations).max(1),
+            iteration_ms: self.iteration_ms,
+            warmup_iterations: warmup.unwrap_or(self.warmup_iterations),
+            reject_outliers: self.reject_outliers,
+        }
+    }
+
+    /// Returns the reader of the CPU frequency if throttling is detected
+    fn frequency(&self) -> Option<CpuFrequencyAdapter> {
+        self.detect_throttling.then(CpuFrequencyAdapter::new)
+    }
+}
+
+/// Configuration for the memory benchmarks
+#[derive(Debug, Default, Deserialize)]
+pub struct MemoryBenchmarkConfig {
+    /// Size of each STREAM array in MiB; empty uses 256 MiB
+    #[serde(default)]
+    pub array_sizes_mib: Vec<usize>,
+    /// Thread counts STREAM runs with; empty runs single-threaded and on every CPU
+    #[serde(default)]
+    pub threads: Vec<usize>,
+    /// Pointer-chasing working set sizes in KiB; empty covers L1 through DRAM
+    #[serde(default)]
+    pub working_sets_kib: Vec<usize>,
+}
+
+impl Default for BenchmarkConfig {
+    fn default() -> Self {
+        BenchmarkConfig {
+            iterations: default_benchmark_iterations(),
+            iteration_ms: default_benchmark_iteration_ms(),
+            warmup_iterations: default_warmup_iterations(),
+            reject_outliers: default_reject_outliers(),
+            detect_throttling: default_detect_throttling(),
+            memory: MemoryBenchmarkConfig::default(),
+            storage: StorageBenchmarkConfig::default(),
+            network: NetworkBenchmarkConfig::default(),
+            netbench: NetbenchConfig::default(),
+        }
+    }
+}
+
+/// Configuration for the storage benchmarks
+#[derive(Debug, Deserialize)]
+pub struct StorageBenchmarkConfig {
+    /// Benchmark engine: "fio", "native", or "auto" to use fio when it is installed
+    #[serde(default)]
+    pub engine: StorageEngine,
+    /// Path to the fio binary
+    #[serde(default = "default_fio_path")]
+    pub fio_path: String,
+    /// File fio reads and writes; a file created by the benchmark is removed afterwards
+    #[serde(default = "default_fio_target")]
+    pub target: String,
+    /// Size of the target file in MiB, for fio and the native benchmark
+    #[serde(default = "default_fio_size_mib")]
+    pub size_mib: u64,
+    /// fio I/O engine; unset uses libaio on Linux and posixaio elsewhere
+    #[serde(default)]
+    pub ioengine: Option<String>,
+    /// Duration of a single storage iteration in milliseconds; unset uses benchmark.iteration_ms
+    #[serde(default)]
+    pub iteration_ms: Option<u64>,
+    /// Job profiles; empty runs sequential, random and mixed read/write profiles
+    #[serde(default)]
+    pub profiles: Vec<FioProfile>,
+    /// Settings of the native benchmark used when fio is not installed
+    #[serde(default)]
+    pub native: NativeStorageBenchmarkConfig,
+}
+
+/// Configuration for the native storage benchmark
+#[derive(Debug, Deserialize)]
+pub struct NativeStorageBenchmarkConfig {
+    /// Directory on the mount to benchmark; the benchmark file is removed afterwards
+    #[serde(default = "default_native_storage_directory")]
+    pub directory: String,
+    /// Block size of sequential I/Os in KiB
+    #[serde(default = "default_seq_block_size_kib")]
+    pub seq_block_size_kib: usize,
+    /// Block size of random I/Os in KiB
+    #[serde(default = "default_random_block_size_kib")]
+    pub random_block_size_kib: usize,
+    /// Number of I/Os in flight, each issued by its own thread
+    #[serde(default = "default_native_queue_depth")]
+    pub queue_depth: usize,
+    /// Flag to bypass the page cache with O_DIRECT
+    #[serde(default = "default_native_direct")]
+    pub direct: bool,
+}
+
+impl Default for NativeStorageBenchmarkConfig {
+    fn default() -> Self {
+        NativeStorageBenchmarkConfig {
+            directory: default_native_storage_directory(),
+            seq_block_size_kib: default_seq_block_size_kib(),
+            random_block_size_kib: default_random_block_size_kib(),
+            queue_depth: default_native_queue_depth(),
+            direct: default_native_direct(),
+        }
+    }
+}
+
+/// Default directory of the native storage benchmark file
+fn default_native_storage_directory() -> String {
+    ".".to_string()
+}
+
+/// Default block size of sequential I/Os in KiB
+fn default_seq_block_size_kib() -> usize {
+    1024
+}
+
+/// Default block size of random I/Os in KiB
+fn default_random_block_size_kib() -> usize {
+    4
+}
+
+/// Default number of I/Os in flight in the native storage benchmark
+fn default_native_queue_depth() -> usize {
+    16
+}
+
+/// Default for bypassing the page cache in the native storage benchmark
+fn default_native_direct() -> bool {
+    true
+}
+
+impl Default for StorageBenchmarkConfig {
+    fn default() -> Self {
+        StorageBenchmarkConfig {
+            engine: StorageEngine::default(),
+            fio_path: default_fio_path(),
+            target: default_fio_target(),
+            size_mib: default_fio_size_mib(),
+            ioengine: None,
+            iteration_ms: None,
+            profiles: Vec::new(),
+            native: NativeStorageBenchmarkConfig::default(),
+        }
+    }
+}
+
+/// Default path to the fio binary
+fn default_fio_path() -> String {
+    "fio".to_string()
+}
+
+/// Default file fio runs against
+fn default_fio_target() -> String {
+    "commandant-rs-fio.dat".to_string()
+}
+
+/// Default size of the fio target file in MiB
+fn default_fio_size_mib() -> u64 {
+    1024
+}
+
+/// Configuration for the network benchmarks
+#[derive(Debug, Deserialize)]
+pub struct NetworkBenchmarkConfig {
+    /// Path to the iperf3 binary
+    #[serde(default = "default_iperf_path")]
+    pub iperf_path: String,
+    /// iperf3 server to benchmark against; unset starts a server on loopback
+    #[serde(default)]
+    pub server: Option<String>,
+    /// Port of the iperf3 server
+    #[serde(default = "default_iperf_port")]
+    pub port: u16,
+    /// Test profiles; empty runs TCP with one and four streams and UDP at 1 Gbit/s
+    #[serde(default)]
+    pub profiles: Vec<IperfProfile>,
+}
+
+impl Default for NetworkBenchmarkConfig {
+    fn default() -> Self {
+        NetworkBenchmarkConfig {
+            iperf_path: default_iperf_path(),
+            server: None,
+            port: default_iperf_port(),
+            profiles: Vec::new(),
+        }
+    }
+}
+
+/// Default path to the iperf3 binary
+fn default_iperf_path() -> String {
+    "iperf3".to_string()
+}
+
+/// Default port of the iperf3 server
+fn default_iperf_port() -> u16 {
+    5201
+}
+
+/// Configuration for the native network benchmark
+#[derive(Debug, Deserialize)]
+pub struct NetbenchConfig {
+    /// TCP and UDP port the server listens on
+    #[serde(default = "default_netbench_port")]
+    pub port: u16,
+    /// Number of parallel throughput streams
+    #[serde(default = "default_netbench_streams")]
+    pub streams: usize,
+    /// Sizes of latency messages in bytes; empty uses 64 and 4096 bytes
+    #[serde(default)]
+    pub message_sizes: Vec<usize>,
+}
+
+impl Default for NetbenchConfig {
+    fn default() -> Self {
+        NetbenchConfig {
+            port: default_netbench_port(),
+            streams: default_netbench_streams(),
+            message_sizes: Vec::new(),
+        }
+    }
+}
+
+/// Default port of the netbench server
+fn default_netbench_port() -> u16 {
+    5300
+}
+
+/// Default number of parallel netbench throughput streams
+fn default_netbench_streams() -> usize {
+    4
+}
+
+/// Default number of measured iterations of every benchmark
+fn default_benchmark_iterations() -> usize {
+    5
+}
+
+/// Default duration of a single benchmark iteration in milliseconds
+fn default_benchmark_iteration_ms() -> u64 {
+    1000
+}
+
+/// Default number of warmup iterations of every benchmark
+fn default_warmup_iterations() -> usize {
+    1
+}
+
+/// Default for rejecting outlier iterations
+fn default_reject_outliers() -> bool {
+    true
+}
+
+/// Default for detecting throttling during benchmarks
+fn default_detect_throttling() -> bool {
+    true
+}
+
 // Enum representing the supported architectures for the `stress-ng`
 // binary.
 // This enum is used to select the correct binary for the running operating
@@ -126,6 +629,14 @@ enum StressNgArch {
  An advanced tool for hardware performance testing and diagnostics.",
 long_about = long_description())]
 struct Cli {
+    // Logs more, repeat for more detail: -v for debug and -vv for trace messages
+    #[clap(short, long, action = ArgAction::Count, global = true)]
+    verbose: u8,
+
+    // Logs less, repeat for less: -q for warnings, -qq for errors and -qqq for nothing
+    #[clap(short, long, action = ArgAction::Count, global = true, conflicts_with = "verbose")]
+    quiet: u8,
+
     #[clap(subcommand)]
     command: Commands,
 }
@@ -135,19 +646,144 @@ struct Cli {
 #[derive(Subcommand, Debug)]
 enum Commands {
     // Runs benchmark tests
-    Benchmark,
+    Benchmark {
+        // Run the CPU microbenchmarks single-threaded and on every CPU
+        #[clap(long)]
+        cpu: bool,
+
+        // Run the STREAM bandwidth, pointer-chasing latency and NUMA benchmarks
+        #[clap(long)]
+        memory: bool,
+
+        // Run the storage benchmarks with fio, or natively without it; only run when requested
+        // as they write a file
+        #[clap(long)]
+        storage: bool,
+
+        // Run the iperf3 network benchmarks against the configured server, or over loopback
+        #[clap(long)]
+        network: bool,
+
+        // Serve iperf3 clients on the configured port until stopped instead of benchmarking
+        #[clap(long)]
+        network_server: bool,
+
+        // Number of measured iterations of every benchmark, overriding the config
+        #[clap(long)]
+        iterations: Option<usize>,
+
+        // Number of warmup iterations of every benchmark, overriding the config
+        #[clap(long)]
+        warmup: Option<usize>,
+
+        // Print the results as JSON
+        #[clap(long)]
+        json: bool,
+    },
 
     // Executes stress tests
     Stress,
 
     // Scans and analyzes hardware
-    Discover,
+    Discover {
+        // Discover processors, caches, NUMA nodes and frequencies
+        #[clap(long)]
+        cpu: bool,
+
+        // Discover the BIOS, system and baseboard identification
+        #[clap(long)]
+        platform: bool,
+
+        // Discover the memory arrays and DIMM slots
+        #[clap(long)]
+        memory: bool,
+
+        // Discover PCI devices and flag links running below their capability
+        #[clap(long)]
+        pci: bool,
+
+        // Discover block devices and mounted filesystems
+        #[clap(long)]
+        storage: bool,
+
+        // Discover network interfaces and RDMA devices and validate link speeds
+        #[clap(long)]
+        network: bool,
+
+        // Reports the changes since an earlier snapshot, by default the previous one that
+        // discovered the same sections
+        #[clap(long, value_name = "OLDER_ID")]
+        diff: Option<Option<String>>,
+
+        // Checks the host against a golden spec YAML file, discovering the sections
+        // the spec covers; exits with 0 on pass, 1 on fail and 2 on errors
+        #[clap(
+            long,
+            value_name = "SPEC",
+            conflicts_with_all = ["cpu", "platform", "memory", "pci", "storage", "network", "diff"]
+        )]
+        check: Option<PathBuf>,
+
+        // Print the inventory as JSON instead of a report
+        #[clap(long)]
+        json: bool,
+    },
 
     // Monitors hardware performance in real-time
     Overwatch,
 
     // Embedded Database Operations
-    DatabaseOps,
+    DatabaseOps {
+        #[clap(subcommand)]
+        action: DatabaseOpsAction,
+    },
+
+    // Bundles a run with its samples, raw tool output, logs and host inventory into a
+    // compressed archive (tar + bzip2) with a manifest
+    Archive {
+        // The run to archive
+        run_id: String,
+
+        // The archive file, <run-id>.tar.bz2 by default
+        #[clap(long)]
+        out: Option<PathBuf>,
+    },
+
+    // Imports a run archive, e.g. one created on another node, into the database
+    ImportArchive {
+        // The .tar.bz2 archive
+        file: PathBuf,
+    },
+
+    // Renders run and host history charts
+    Report {
+        // The run to chart; the host history is charted when omitted
+        run_id: Option<String>,
+
+        // The host whose history is charted, defaults to this host
+        #[clap(long)]
+        host: Option<String>,
+
+        // Limits the host history to the last N hours
+        #[clap(long)]
+        hours: Option<u64>,
+
+        // The PNG file the chart is written to
+        #[clap(long)]
+        chart: PathBuf,
+    },
+
+    // Runs the native network benchmark between a server and a client
+    Netbench {
+        #[clap(subcommand)]
+        action: NetbenchAction,
+    },
+
+    // Manages the per-SKU baselines runs are compared with to detect regressions
+    Baseline {
+        #[clap(subcommand)]
+        action: BaselineAction,
+    },
 
     // AIModel
     AIModel {
@@ -156,6 +792,154 @@ enum Commands {
     },
 }
 
+#[derive(Subcommand, Debug)]
+enum NetbenchAction {
+    // Serves netbench clients until stopped
+    Server {
+        // Address to listen on
+        #[clap(long, default_value = "0.0.0.0")]
+        bind: String,
+
+        // Port to listen on, overriding the config
+        #[clap(long)]
+        port: Option<u16>,
+    },
+    // Benchmarks throughput, latency and connection rate against a server
+    Client {
+        // Server to benchmark against; a server on loopback is started when omitted
+        server: Option<String>,
+
+        // Port of the server, overriding the config
+        #[clap(long)]
+        port: Option<u16>,
+
+        // Number of parallel throughput streams, overriding the config
+        #[clap(long)]
+        streams: Option<usize>,
+
+        // Number of measured iterations of every benchmark, overriding the config
+        #[clap(long)]
+        iterations: Option<usize>,
+
+        // Number of warmup iterations of every benchmark, overriding the config
+        #[clap(long)]
+        warmup: Option<usize>,
+
+        // Print the results as JSON
+        #[clap(long)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum BaselineAction {
+    // Derives the baseline of a SKU from the median of accepted runs
+    Derive {
+        // The accepted runs; the latest passed runs of the SKU are used when omitted
+        run_ids: Vec<String>,
+
+        // SKU of the baseline, defaults to the SKU of this host
+        #[clap(long)]
+        sku: Option<String>,
+
+        // Number of latest passed runs to derive from, overriding the config
+        #[clap(long)]
+        runs: Option<usize>,
+
+        // Kind of the runs to derive from, e.g. benchmark or stress
+        #[clap(long, default_value = "benchmark")]
+        kind: String,
+
+        // Profile of the runs to derive from, defaults to that of the latest passed run of the kind
+        #[clap(long)]
+        profile: Option<String>,
+    },
+    // Imports a baseline from a YAML file
+    Import {
+        // The baseline YAML file
+        file: PathBuf,
+    },
+    // Shows the baselines of a SKU, one for every kind and profile of runs
+    Show {
+        // SKU of the baseline, defaults to the SKU of this host
+        #[clap(long)]
+        sku: Option<String>,
+
+        // Print the baseline as JSON
+        #[clap(long)]
+        json: bool,
+    },
+    // Compares a stored run with the baseline of its SKU
+    Compare {
+        // The run to compare
+        run_id: String,
+
+        // Print the comparison as JSON
+        #[clap(long)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum DatabaseOpsAction {
+    // Upgrades the database layout and imports the legacy OneForAll database
+    Migrate {
+        // Only report what the migrations would do
+        #[clap(long)]
+        dry_run: bool,
+    },
+    // Lists the keys of every tree, or of one tree
+    List {
+        // The tree to list; every tree is listed when omitted
+        #[clap(long)]
+        tree: Option<String>,
+
+        // Only list keys starting with this prefix
+        #[clap(long, default_value = "")]
+        prefix: String,
+    },
+    // Prints the value of a key
+    Get {
+        // The key to read
+        key: String,
+
+        // The tree to read; every tree with the key is read when omitted
+        #[clap(long)]
+        tree: Option<String>,
+    },
+    // Exports every tree to a file
+    Export {
+        // Format of the export: json or ndjson
+        #[clap(long, default_value = "json")]
+        format: ExportFormat,
+
+        // The file the export is written to
+        #[clap(long)]
+        out: PathBuf,
+    },
+    // Imports an export, replacing entries with the same keys
+    Import {
+        // The json or ndjson export
+        file: PathBuf,
+    },
+    // Removes runs, samples and inventory snapshots older than an age
+    Prune {
+        // The age, e.g. 30d, 12h or 2w
+        #[clap(long, value_parser = database_ops::parse_age)]
+        older_than: chrono::Duration,
+    },
+    // Rolls up the sample history and expires it according to the retention config
+    Rollup,
+    // Compacts the database by copying every tree into a new database that replaces it
+    Compact,
+    // Shows the size of the database and the number of entries in every tree
+    Stats {
+        // Print the statistics as JSON
+        #[clap(long)]
+        json: bool,
+    },
+}
+
 #[derive(Subcommand, Debug)]
 enum AIModelAction {
     Predict {
@@ -228,7 +1012,13 @@ fn long_description() -> &'static str {
 
 // The entry point of the application using Actix's asynchronous runtime.
 #[tokio::main]
-async fn main() -> std::io::Result<()> {
+async fn main() -> std::io::Result<ExitCode> {
+    // Parse command-line arguments using the Cli struct, which is defined using the
+    // `clap` crate. This struct represents the command-line interface of the application,
+    // defining the available subcommands and their functionalities. They are parsed
+    // first so that their verbosity flags apply to the logger.
+    let cli = Cli::parse();
+
     // Attempt to open the configuration file
     // The '?' operator will return early if an error occurs
     let config_file = File::open("config.yaml")?;
@@ -240,57 +1030,185 @@ async fn main() -> std::io::Result<()> {
         io::Error::new(io::ErrorKind::InvalidData, e)
     })?;
 
-    // Initialize the logging system with a specified directory and log level.
-    // This setup is critical for ensuring that all parts of the application
-    // can perform logging activities coherently. The logger is part of the
-    // "adapters" layer in the Ports and Adapters architecture, interfacing
+    // Initialize the logging system with the configured directory, levels, sinks, format
+    // and rotation, adjusted by the -v/-q flags. This setup is critical for ensuring that
+    // all parts of the application can perform logging activities coherently. The logger
+    // is part of the "adapters" layer in the Ports and Adapters architecture, interfacing
     // with the external logging framework.
-    let log_directory = "logs"; // Directory where log files will be stored.
-    let log_level = log::LevelFilter::Info; // Log level indicating verbosity of the logs.
-    let logger = Arc::new(common::adapters::log_adapter::init(
-        log_directory,
-        log_level,
-    ));
+    let log_settings = config
+        .general
+        .log_settings(cli.verbose, cli.quiet)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let logger = match log_adapter::init(&log_settings) {
+        Ok(logger) => Arc::new(logger),
+        Err(e) => {
+            eprintln!("Error initializing logging: {}", e);
+            return Err(io::Error::other(e));
+        }
+    };
 
     // Clone the logger into an Arc<dyn LoggerPort> type. This abstraction (LoggerPort)
     // allows different logging implementations to be plugged into the application without
     // changing the core logic, adhering to the principles of the Ports and Adapters architecture.
     let logger_as_port: Arc<dyn LoggerPort> = logger.clone();
 
-    // Initialize the web server adapter with the logger. This adapter is responsible for
-    // handling HTTP requests and serving web content. It represents the web server
-    // "adapter" in the architecture.
-    let web_server = WebServerAdapter::new(logger.clone());
-
     let db_logger = logger.clone(); // Clone the logger for database handling.
 
-    // Attempt to create a new DatabaseAdapter
-    let path_to_db = "commandant-rs_database_file.db"; // database path
-    let db_adapter_result = DatabaseAdapter::new(path_to_db, db_logger.clone());
+    // Attempt to open the database with the configured backend at the configured path
+    let path_to_db = config.general.database_path.as_str(); // database path
+    let database_backend = config.general.database_backend;
+
+    // Compact the database before it is opened for the commands, as compaction replaces it.
+    if let Commands::DatabaseOps {
+        action: DatabaseOpsAction::Compact,
+    } = &cli.command
+    {
+        if !config.database_ops.enabled {
+            logger.log_error("Database operations are disabled in the config");
+        } else if database_backend == DatabaseBackend::Memory {
+            println!("Nothing to compact, the memory backend keeps no files");
+        } else {
+            match compact_database(path_to_db, logger.clone()) {
+                Ok((before, after)) => println!(
+                    "Compacted {} from {:.1} MiB to {:.1} MiB",
+                    path_to_db,
+                    before as f64 / (1024.0 * 1024.0),
+                    after as f64 / (1024.0 * 1024.0)
+                ),
+                Err(e) => logger.log_error(&format!("Failed to compact the database: {}", e)),
+            }
+        }
+    }
+    let db_adapter_result = open_database(database_backend, path_to_db, db_logger.clone());
 
-    // Handle the Result and create an Arc<dyn DatabasePort> if successful
+    // Handle the Result and keep the Arc<dyn DatabasePort> if successful
     let db_adapter: Arc<dyn DatabasePort> = match db_adapter_result {
         Ok(adapter) => {
-            db_logger.log_info("DatabaseAdapter created successfully.");
-            Arc::new(adapter) // Cast the DatabaseAdapter to a trait object
+            db_logger.log_info(&format!(
+                "Database adapter created successfully ({:?} backend).",
+                database_backend
+            ));
+            adapter
         }
         Err(e) => {
-            db_logger.log_error(&format!("Error creating DatabaseAdapter: {}", e));
+            db_logger.log_error(&format!("Error creating database adapter: {}", e));
             return Err(std::io::Error::new(
                 std::io::ErrorKind::Other,
-                "Failed to create DatabaseAdapter",
+                "Failed to create database adapter",
             ));
         }
     };
 
+    // Open the typed repositories, each storing one entity in its own tree of the database.
+    let runs = open_repository(logger.clone(), db_adapter.as_ref())?;
+    let samples = open_repository(logger.clone(), db_adapter.as_ref())?;
+    let inventories = open_repository(logger.clone(), db_adapter.as_ref())?;
+    let baseline_repository = open_repository(logger.clone(), db_adapter.as_ref())?;
+    let rollups = open_repository(logger.clone(), db_adapter.as_ref())?;
+    let artifacts = open_repository(logger.clone(), db_adapter.as_ref())?;
+
+    // Describe where the database is kept for the database statistics.
+    let database_location = match database_backend {
+        DatabaseBackend::Sled => config.general.database_path.clone(),
+        DatabaseBackend::Memory => "in memory".to_string(),
+    };
+
+    // Initialize the database operations, which list, export, import and prune the
+    // contents of the database.
+    let database_ops = DatabaseOps::new(
+        logger.clone(),
+        db_adapter.clone(),
+        runs.clone(),
+        samples.clone(),
+        inventories.clone(),
+        artifacts.clone(),
+    );
+
+    // Initialize the archives, which bundle runs into compressed archives and import them.
+    let run_archives = Archives::new(
+        logger.clone(),
+        Arc::new(Bzip2ArchiveAdapter::new()),
+        runs.clone(),
+        samples.clone(),
+        inventories.clone(),
+        artifacts.clone(),
+    );
+
+    // Initialize the RunRecorder, which stores runs and utilization/temperature samples
+    // taken by the SystemMetricsAdapter so they can be charted later, along with the raw
+    // output and logs of every run.
+    let run_recorder = Arc::new(RunRecorder::new(
+        logger.clone(),
+        runs.clone(),
+        samples.clone(),
+        artifacts.clone(),
+        Arc::new(SystemMetricsAdapter::new(logger.clone())),
+    ));
+    let sample_interval = Duration::from_secs(config.overwatch.interval as u64);
+
+    // Initialize the retention of the sample history, which rolls samples up into
+    // 1-minute and hourly rollups and expires them according to the config.
+    let retention_policy = config.overwatch.retention.policy();
+    let retention = Arc::new(Retention::new(
+        logger.clone(),
+        samples.clone(),
+        rollups.clone(),
+        retention_policy,
+    ));
+    let rollup_interval = Duration::from_secs(config.overwatch.retention.rollup_interval);
+
+    // Initialize the inventory snapshot store, which keeps every discovered
+    // inventory so that later runs can be diffed against it.
+    let inventory_snapshots = InventorySnapshots::new(logger.clone(), inventories);
+
+    // Initialize the baseline store, which runs are compared with to detect regressions.
+    let baselines = Baselines::new(logger.clone(), baseline_repository.clone(), runs.clone());
+    let tolerances = config.baselines.tolerances();
+
+    // Initialize the ReportAdapter, which renders the stored history with plotters.
+    let report_adapter = Arc::new(
+        ReportAdapter::new(
+            logger.clone(),
+            runs,
+            samples,
+            rollups,
+            baseline_repository,
+            Arc::new(PlottersChartAdapter::new()),
+        )
+        .with_tolerances(tolerances.clone())
+        .with_retention(
+            retention_policy,
+            chrono::Duration::seconds(config.overwatch.interval as i64),
+        ),
+    );
+
+    // Initialize the web server adapter with the logger. This adapter is responsible for
+    // handling HTTP requests and serving web content. It represents the web server
+    // "adapter" in the architecture.
+    let web_server = WebServerAdapter::new(logger.clone()).with_report_port(report_adapter.clone());
+
     // Initialize the PsAdapter with the logger and the DbAdapter for process monitoring and CPU usage analysis.
     let ps_adapter =
         Arc::new(PsAdapter::new(logger.clone(), db_adapter.clone())) as Arc<dyn PsCommandPort>;
 
-    // Parse command-line arguments using the Cli struct, which is defined using the
-    // `clap` crate. This struct represents the command-line interface of the application,
-    // defining the available subcommands and their functionalities.
-    let cli = Cli::parse();
+    // Upgrade the database layout and import the legacy database before any command uses
+    // the database, unless the `database-ops migrate` command runs or previews the migrations.
+    let migrator = Migrator::new(logger.clone(), db_adapter.clone());
+    if !matches!(
+        cli.command,
+        Commands::DatabaseOps {
+            action: DatabaseOpsAction::Migrate { .. }
+        }
+    ) {
+        if let Err(e) = migrate_database(
+            &migrator,
+            config.general.legacy_database_path.as_deref(),
+            false,
+            logger.clone(),
+        ) {
+            logger.log_error(&format!("Failed to migrate the database: {}", e));
+        }
+    }
 
     // Initialize the StressNgAdapter with the logger. This adapter is responsible for
     // conducting stress tests on the system, utilizing tools like `stress-ng`.
@@ -307,6 +1225,10 @@ async fn main() -> std::io::Result<()> {
 
     let (shutdown_sender, shutdown_receiver) = tokio::sync::mpsc::channel::<()>(1);
 
+    // Commands that report their outcome through the exit code, such as
+    // `discover --check`, send it here so that main can return it.
+    let (exit_sender, mut exit_receiver) = tokio::sync::mpsc::channel::<u8>(1);
+
     // Set up handling for the Ctrl+C (interrupt) signal in a separate async task.
     // This approach enables the application to gracefully shut down in response to
     // interrupt signals.
@@ -318,12 +1240,6 @@ async fn main() -> std::io::Result<()> {
         let _ = shutdown_sender.send(()).await;
     });
 
-    let db_logger = logger.clone(); // Clone the logger for database handling.
-
-    // Attempt to create a new DatabaseAdapter
-    let path_to_db = "commandant-rs_database_file.db"; // database path
-    let db_adapter_result = DatabaseAdapter::new(path_to_db, db_logger.clone());
-
     // CLI match command logic starts here //
 
     let _command_handle = spawn(async move {
@@ -331,9 +1247,201 @@ async fn main() -> std::io::Result<()> {
             // Handle each CLI command by invoking the appropriate functionality
             // and logging as needed. This part of the code can be seen as part of
             // the application's "core" or "domain logic."
-            Commands::Benchmark => {
-                // Logic for handling the 'Benchmark' command.
-                command_logger.log_info("Benchmarking functionality not yet implemented.");
+            Commands::Benchmark {
+                cpu,
+                memory,
+                storage,
+                network,
+                network_server,
+                iterations,
+                warmup,
+                json,
+            } => {
+                let network_config = &config.benchmark.network;
+                if network_server {
+                    let server = IperfAdapter::new(command_logger.clone(), "0.0.0.0")
+                        .with_iperf_path(network_config.iperf_path.clone())
+                        .with_port(network_config.port);
+                    if let Err(e) = server.serve() {
+                        command_logger.log_error(&e);
+                    }
+                    return;
+                }
+
+                // Run the requested suites, or the native suites if none was requested
+                let all = !(cpu || memory || storage || network);
+                let mut suites: Vec<Box<dyn BenchmarkPort>> = Vec::new();
+                if cpu || all {
+                    suites.push(Box::new(CpuBenchmarkAdapter::new(command_logger.clone())));
+                }
+                if memory || all {
+                    let memory_config = &config.benchmark.memory;
+                    suites.push(Box::new(
+                        MemoryBenchmarkAdapter::new(command_logger.clone())
+                            .with_array_sizes_mib(memory_config.array_sizes_mib.clone())
+                            .with_thread_counts(memory_config.threads.clone())
+                            .with_working_sets_kib(memory_config.working_sets_kib.clone()),
+                    ));
+                }
+                if storage {
+                    let storage_config = &config.benchmark.storage;
+                    let mut fio = FioAdapter::new(command_logger.clone(), &storage_config.target)
+                        .with_fio_path(storage_config.fio_path.clone())
+                        .with_size_mib(storage_config.size_mib)
+                        .with_profiles(storage_config.profiles.clone());
+                    if let Some(ioengine) = &storage_config.ioengine {
+                        fio = fio.with_ioengine(ioengine.clone());
+                    }
+                    let iteration_duration = storage_config.iteration_ms.map(Duration::from_millis);
+                    if let Some(duration) = iteration_duration {
+                        fio = fio.with_iteration_duration(duration);
+                    }
+                    let use_fio = match storage_config.engine {
+                        StorageEngine::Fio => true,
+                        StorageEngine::Native => false,
+                        StorageEngine::Auto => {
+                            let available = fio.is_available();
+                            if !available {
+                                command_logger.log_warn(&format!(
+                                    "{} is not available, running the native storage benchmark",
+                                    storage_config.fio_path
+                                ));
+                            }
+                            available
+                        }
+                    };
+                    if use_fio {
+                        suites.push(Box::new(fio));
+                    } else {
+                        let native = &storage_config.native;
+                        let mut adapter = NativeStorageBenchmarkAdapter::new(
+                            command_logger.clone(),
+                            &native.directory,
+                        )
+                        .with_size_mib(storage_config.size_mib)
+                        .with_block_sizes_kib(
+                            native.seq_block_size_kib,
+                            native.random_block_size_kib,
+                        )
+                        .with_queue_depth(native.queue_depth)
+                        .with_direct(native.direct);
+                        if let Some(duration) = iteration_duration {
+                            adapter = adapter.with_iteration_duration(duration);
+                        }
+                        suites.push(Box::new(adapter));
+                    }
+                }
+                if network {
+                    let mut iperf = IperfAdapter::new(
+                        command_logger.clone(),
+                        network_config.server.as_deref().unwrap_or("127.0.0.1"),
+                    )
+                    .with_iperf_path(network_config.iperf_path.clone())
+                    .with_port(network_config.port)
+                    .with_profiles(network_config.profiles.clone());
+                    if network_config.server.is_none() {
+                        iperf = iperf.with_local_server();
+                    }
+                    suites.push(Box::new(iperf));
+                }
+                let settings = config.benchmark.settings(iterations, warmup);
+                let frequency = config.benchmark.frequency();
+
+                // Record the run and sample the host while the benchmarks are running
+                let (run, results) = benchmark::run_recorded(
+                    &suites,
+                    host_sku(&config.baselines, command_logger.clone()),
+                    &settings,
+                    frequency.as_ref().map(|f| f as &dyn CpuFrequencyPort),
+                    &run_recorder,
+                    sample_interval,
+                    command_logger.as_ref(),
+                );
+                let comparison =
+                    compare_with_baseline(&baselines, &run, &tolerances, command_logger.as_ref());
+                print_benchmark_results(
+                    json,
+                    &run,
+                    &settings,
+                    results,
+                    comparison,
+                    command_logger.as_ref(),
+                );
+            }
+            Commands::Netbench { action } => {
+                let netbench_config = &config.benchmark.netbench;
+                match action {
+                    NetbenchAction::Server { bind, port } => {
+                        let port = port.unwrap_or(netbench_config.port);
+                        match NetbenchServer::bind(command_logger.clone(), &bind, port).await {
+                            Ok(server) => server.run().await,
+                            Err(e) => command_logger.log_error(&e),
+                        }
+                    }
+                    NetbenchAction::Client {
+                        server,
+                        port,
+                        streams,
+                        iterations,
+                        warmup,
+                        json,
+                    } => {
+                        let port = port.unwrap_or(netbench_config.port);
+                        // Serve on loopback for the duration of the benchmark if no server was given
+                        let (port, local_server) = match &server {
+                            Some(_) => (port, None),
+                            None => {
+                                match NetbenchServer::bind(command_logger.clone(), "127.0.0.1", port)
+                                    .await
+                                {
+                                    Ok(server) => (server.port(), Some(spawn(server.run()))),
+                                    Err(e) => {
+                                        command_logger.log_error(&e);
+                                        return;
+                                    }
+                                }
+                            }
+                        };
+
+                        let suites: Vec<Box<dyn BenchmarkPort>> = vec![Box::new(
+                            NetbenchAdapter::new(
+                                command_logger.clone(),
+                                server.as_deref().unwrap_or("127.0.0.1"),
+                            )
+                            .with_port(port)
+                            .with_streams(streams.unwrap_or(netbench_config.streams))
+                            .with_message_sizes(netbench_config.message_sizes.clone()),
+                        )];
+                        let settings = config.benchmark.settings(iterations, warmup);
+                        let frequency = config.benchmark.frequency();
+                        let (run, results) = benchmark::run_recorded(
+                            &suites,
+                            host_sku(&config.baselines, command_logger.clone()),
+                            &settings,
+                            frequency.as_ref().map(|f| f as &dyn CpuFrequencyPort),
+                            &run_recorder,
+                            sample_interval,
+                            command_logger.as_ref(),
+                        );
+                        if let Some(local_server) = local_server {
+                            local_server.abort();
+                        }
+                        let comparison = compare_with_baseline(
+                            &baselines,
+                            &run,
+                            &tolerances,
+                            command_logger.as_ref(),
+                        );
+                        print_benchmark_results(
+                            json,
+                            &run,
+                            &settings,
+                            results,
+                            comparison,
+                            command_logger.as_ref(),
+                        );
+                    }
+                }
             }
             Commands::Stress => {
                 // Pull parameters from the application config file
@@ -365,6 +1473,23 @@ async fn main() -> std::io::Result<()> {
                 // Log the final command for debugging purposes
                 command_logger.log_info(&format!("Executing stress test with args: {:?}", args));
 
+                // Record the run and sample the host while stress-ng is running
+                let mut run = RunRecord::start(&run_recorder.hostname(), "stress");
+                run.sku = host_sku(&config.baselines, command_logger.clone());
+                run.profile = Some(args.join(" "));
+                run_recorder.start_run_log(&mut run);
+                if let Err(e) = run_recorder.save_run(&run) {
+                    command_logger.log_error(&e);
+                }
+                command_logger.log_info(&format!("Recording stress run {}", run.id));
+                // Attach the run and stressor to every message of the stress test
+                let run_logger: Arc<dyn LoggerPort> = Arc::new(ScopedLogger::new(
+                    command_logger.clone(),
+                    &[("run_id", &run.id), ("stressor", "cpu")],
+                ));
+                let sampling = run_recorder.start_sampling(Some(run.id.clone()), sample_interval);
+                let mut status = RunStatus::Failed;
+
                 // Initialize the retry mechanism
                 // The test will be attempted up to 3 times (initial try + 2 retries)
                 let mut retries = 2;
@@ -372,35 +1497,36 @@ async fn main() -> std::io::Result<()> {
                 // Start a loop for executing the stress test with retries
                 while retries >= 0 {
                     // Log the start of a stress test attempt
-                    command_logger.log_info(&format!(
+                    run_logger.log_info(&format!(
                         "Executing CPU stress test. Attempts remaining: {}",
                         retries,
                     ));
 
                     // Execute the stress test command asynchronously
                     match StressNgAdapter::execute_stress_ng_command(
-                        command_logger.clone(),
+                        run_logger.clone(),
                         &args.iter().map(String::as_str).collect::<Vec<&str>>(),
                     )
                     .await
                     {
                         // In case of a successful execution
                         Ok(()) => {
-                            command_logger.log_info("CPU stress test executed successfully.");
+                            run_logger.log_info("CPU stress test executed successfully.");
+                            status = RunStatus::Passed;
                             break; // Exit the retry loop on success
                         }
                         // In case of an error, handle the retry mechanism
                         Err(e) => {
                             if retries > 0 {
                                 // If there are retries left, log a warning and wait before retrying
-                                command_logger.log_warn(&format!(
+                                run_logger.log_warn(&format!(
                                     "Retrying CPU stress test. Attempts remaining: {}",
                                     retries
                                 ));
                                 sleep(Duration::from_secs(10)).await; // Wait 10 seconds before retrying
                             } else {
                                 // If there are no retries left, log the error
-                                command_logger
+                                run_logger
                                     .log_error(&format!("Error executing CPU stress test: {}", e));
                             }
                         }
@@ -408,7 +1534,115 @@ async fn main() -> std::io::Result<()> {
                     // Decrement the retry counter after each attempt
                     retries -= 1;
                 }
+
+                // Stop sampling and store the bogo ops/s reported by stress-ng
+                sampling.stop();
+                if let Ok(output) = std::fs::read_to_string(STRESS_NG_OUTPUT_FILE) {
+                    run.metrics = StressNgAdapter::parse_bogo_ops(&output);
+                    let artifact =
+                        RunArtifact::new(&run.id, STRESS_NG_OUTPUT_FILE, output.into_bytes());
+                    if let Err(e) = run_recorder.save_artifact(&artifact) {
+                        command_logger.log_error(&e);
+                    }
+                }
+                if !run.metrics.is_empty() {
+                    match run_recorder.take_sample(Some(&run.id)) {
+                        Ok(mut sample) => {
+                            sample.bogo_ops_per_sec =
+                                Some(run.metrics.iter().map(|metric| metric.value).sum());
+                            if let Err(e) = run_recorder.save_sample(&sample) {
+                                command_logger.log_error(&e);
+                            }
+                        }
+                        Err(e) => command_logger.log_error(&e),
+                    }
+                }
+                run.finish(status);
+                match run_recorder.save_run(&run) {
+                    Ok(()) => command_logger.log_info(&format!(
+                        "Stress run {} finished with status {:?}",
+                        run.id, run.status
+                    )),
+                    Err(e) => command_logger.log_error(&e),
+                }
+                if let Err(e) = run_recorder.finish_run_log(&run) {
+                    command_logger.log_error(&e);
+                }
+                if let Some(comparison) =
+                    compare_with_baseline(&baselines, &run, &tolerances, command_logger.as_ref())
+                {
+                    println!();
+                    print!("{}", baselines::render_comparison(&comparison));
+                }
             }
+            Commands::Baseline { action } => match action {
+                BaselineAction::Derive {
+                    run_ids,
+                    sku,
+                    runs,
+                    kind,
+                    profile,
+                } => {
+                    let Some(sku) = sku.or_else(|| host_sku(&config.baselines, command_logger.clone()))
+                    else {
+                        command_logger.log_error("The SKU of this host is unknown, pass --sku");
+                        return;
+                    };
+                    let runs = runs.unwrap_or(config.baselines.runs);
+                    match baselines.derive(&sku, &kind, profile.as_deref(), &run_ids, runs) {
+                        Ok(baseline) => print!("{}", baselines::render_baseline(&baseline)),
+                        Err(e) => command_logger.log_error(&e),
+                    }
+                }
+                BaselineAction::Import { file } => match baselines.import(&file) {
+                    Ok(baseline) => print!("{}", baselines::render_baseline(&baseline)),
+                    Err(e) => command_logger.log_error(&e),
+                },
+                BaselineAction::Show { sku, json } => {
+                    let Some(sku) = sku.or_else(|| host_sku(&config.baselines, command_logger.clone()))
+                    else {
+                        command_logger.log_error("The SKU of this host is unknown, pass --sku");
+                        return;
+                    };
+                    match baselines.list(&sku) {
+                        Ok(list) if list.is_empty() => eprintln!("No baseline of {} found.", sku),
+                        Ok(list) if json => match serde_json::to_string_pretty(&list) {
+                            Ok(json) => println!("{}", json),
+                            Err(e) => command_logger
+                                .log_error(&format!("Error serializing baselines: {}", e)),
+                        },
+                        Ok(list) => {
+                            for baseline in &list {
+                                print!("{}", baselines::render_baseline(baseline));
+                            }
+                        }
+                        Err(e) => command_logger.log_error(&e),
+                    }
+                }
+                BaselineAction::Compare { run_id, json } => {
+                    let comparison = baselines.load_run(&run_id).and_then(|run| match run {
+                        Some(run) => baselines.compare(&run, &tolerances),
+                        None => Err(format!("No run with id {} found", run_id)),
+                    });
+                    match comparison {
+                        Ok(Some(comparison)) if json => {
+                            match serde_json::to_string_pretty(&comparison) {
+                                Ok(json) => println!("{}", json),
+                                Err(e) => command_logger
+                                    .log_error(&format!("Error serializing comparison: {}", e)),
+                            }
+                        }
+                        Ok(Some(comparison)) => {
+                            print!("{}", baselines::render_comparison(&comparison))
+                        }
+                        Ok(None) => eprintln!(
+                            "Run {} has no baseline for its SKU, kind and profile.",
+                            run_id
+                        ),
+                        Err(e) => command_logger.log_error(&e),
+                    }
+                }
+            },
             Commands::AIModel { action } => {
                 match action {
                     // Handle the Predict action
@@ -448,9 +1682,119 @@ async fn main() -> std::io::Result<()> {
                 }
             }
 
-            Commands::Discover => {
-                // Logic for handling the 'Discover' command.
-                command_logger.log_info("Discovery functionality not yet implemented.");
+            Commands::Discover {
+                cpu,
+                platform,
+                memory,
+                pci,
+                storage,
+                network,
+                diff,
+                check,
+                json,
+            } => {
+                // Discover the requested sections of the hardware inventory
+                let discovery_adapter = SysfsDiscoveryAdapter::new(command_logger.clone());
+                let spec = match check.as_deref().map(spec_check::load_spec) {
+                    Some(Ok(spec)) => Some(spec),
+                    Some(Err(e)) => {
+                        command_logger.log_error(&e);
+                        let _ = exit_sender.send(2).await;
+                        return;
+                    }
+                    None => None,
+                };
+                let sections = match &spec {
+                    Some(spec) => spec_check::required_sections(spec),
+                    None => DiscoverySections::select(cpu, platform, memory, pci, storage, network),
+                };
+                let expectations = InventoryExpectations {
+                    link_speeds_mbps: config.discovery.expected_link_speeds.clone(),
+                };
+
+                let inventory = match discovery::discover(
+                    &discovery_adapter,
+                    &run_recorder.hostname(),
+                    sections,
+                    &expectations,
+                ) {
+                    Ok(inventory) => inventory,
+                    Err(e) => {
+                        command_logger.log_error(&format!("Error discovering hardware: {}", e));
+                        if spec.is_some() {
+                            let _ = exit_sender.send(2).await;
+                        }
+                        return;
+                    }
+                };
+
+                // Keep every run so that later runs can be compared against it
+                if let Err(e) = inventory_snapshots.save(&inventory) {
+                    command_logger.log_error(&e);
+                }
+
+                // Report the conformance to the golden spec through the exit code
+                if let Some(spec) = spec {
+                    let report = spec_check::check_spec(&spec, &inventory);
+                    if json {
+                        match serde_json::to_string_pretty(&report) {
+                            Ok(json) => println!("{}", json),
+                            Err(e) => command_logger
+                                .log_error(&format!("Error serializing report: {}", e)),
+                        }
+                    } else {
+                        print!("{}", spec_check::render_report(&report));
+                    }
+                    let _ = exit_sender.send(if report.passed { 0 } else { 1 }).await;
+                    return;
+                }
+
+                let Some(older_id) = diff else {
+                    if json {
+                        match serde_json::to_string_pretty(&inventory) {
+                            Ok(json) => println!("{}", json),
+                            Err(e) => command_logger
+                                .log_error(&format!("Error serializing inventory: {}", e)),
+                        }
+                    } else {
+                        print!("{}", discovery::render_inventory(&inventory));
+                        println!("\nStored as snapshot {}", inventory.snapshot_id());
+                    }
+                    return;
+                };
+
+                // Compare against the requested snapshot or the previous one
+                let older = match &older_id {
+                    Some(id) => inventory_snapshots.get(&inventory.host, id),
+                    None => inventory_snapshots.previous(&inventory),
+                };
+                match older {
+                    Ok(Some(older)) => {
+                        let changes = inventory_diff::diff_inventories(&older, &inventory);
+                        if json {
+                            match serde_json::to_string_pretty(&changes) {
+                                Ok(json) => println!("{}", json),
+                                Err(e) => command_logger
+                                    .log_error(&format!("Error serializing changes: {}", e)),
+                            }
+                        } else {
+                            print!(
+                                "{}",
+                                discovery::render_changes(&older, &inventory, &changes)
+                            );
+                        }
+                    }
+                    Ok(None) => command_logger.log_warn(&match older_id {
+                        Some(id) => {
+                            format!("Inventory snapshot {} of {} not found", id, inventory.host)
+                        }
+                        None => format!(
+                            "No earlier inventory snapshot of {} with the same sections to compare against",
+                            inventory.host
+                        ),
+                    }),
+                    Err(e) => command_logger.log_error(&e),
+                }
             }
             Commands::Overwatch => {
                 command_logger.log_info("System overwatch functionality started.");
@@ -465,16 +1809,69 @@ async fn main() -> std::io::Result<()> {
                     ps_adapter.collect_cpu_statistics(output_file_path);
                 });
 
+                // Store utilization and temperature samples for the history charts.
+                // Sampling continues for as long as the application is running.
+                let _sampling = run_recorder.start_sampling(None, sample_interval);
+
+                // Roll up and expire the stored samples so the history does not grow
+                // without bound.
+                retention.start(rollup_interval);
+
                 command_logger.log_info("Monitoring CPU usage and top processes.");
             }
-            Commands::DatabaseOps => {
-                // Assuming `db_logger` is a reference to an implementation of `LoggerPort`
-                command_logger.log_info("Database operations functionality not yet implemented.");
-
-                match get_all_keys(logger.clone()) {
-                    // Pass a reference, not a clone
-                    Ok(_) => println!("Successfully retrieved all keys"),
-                    Err(e) => eprintln!("Error retrieving keys: {:?}", e),
+            Commands::DatabaseOps { action } => {
+                if !config.database_ops.enabled {
+                    command_logger.log_error("Database operations are disabled in the config");
+                    return;
+                }
+                if let Err(e) = run_database_ops(
+                    action,
+                    &database_ops,
+                    &migrator,
+                    &retention,
+                    &database_location,
+                    config.general.legacy_database_path.as_deref(),
+                    command_logger.clone(),
+                ) {
+                    command_logger.log_error(&e);
+                }
+            }
+            Commands::Archive { run_id, out } => {
+                let out = out.unwrap_or_else(|| PathBuf::from(format!("{}.tar.bz2", run_id)));
+                match run_archives.archive(&run_id, &out) {
+                    Ok(manifest) => {
+                        print!("{}", archives::render_manifest(&manifest));
+                        println!("Archive written to {}", out.display());
+                    }
+                    Err(e) => command_logger.log_error(&format!("Error archiving run: {}", e)),
+                }
+            }
+            Commands::ImportArchive { file } => match run_archives.import(&file) {
+                Ok(manifest) => {
+                    print!("{}", archives::render_manifest(&manifest));
+                    println!("Imported run {} from {}", manifest.run_id, file.display());
+                }
+                Err(e) => command_logger.log_error(&format!("Error importing archive: {}", e)),
+            },
+            Commands::Report {
+                run_id,
+                host,
+                hours,
+                chart,
+            } => {
+                // Render either a single run or the host history to a PNG file
+                let result = match &run_id {
+                    Some(run_id) => report_adapter.render_run_png(run_id, &chart),
+                    None => {
+                        let host = host.unwrap_or_else(|| run_recorder.hostname());
+                        report_adapter.render_host_png(&host, hours, &chart)
+                    }
+                };
+
+                match result {
+                    Ok(true) => println!("Chart written to {}", chart.display()),
+                    Ok(false) => eprintln!("No matching run or samples found."),
+                    Err(e) => command_logger.log_error(&format!("Error rendering chart: {}", e)),
                 }
             }
         }
@@ -497,6 +1894,11 @@ async fn main() -> std::io::Result<()> {
     // multitasking in asynchronous applications.
     // Await the completion of either the web server task or the Ctrl+C signal handling
     tokio::select! {
+        Some(code) = exit_receiver.recv() => {
+            // Flush the log files before exiting, which dropping the runtime does not do.
+            log::logger().flush();
+            return Ok(ExitCode::from(code));
+        },
         _ = server_handle => {
             println!("Web server has stopped.");
         },
@@ -506,69 +1908,265 @@ async fn main() -> std::io::Result<()> {
     }
 
     println!("Application is shutting down.");
-    Ok(())
+    log::logger().flush();
+    Ok(ExitCode::SUCCESS)
 }
 
-/// Retrieves all keys from the Sled database.
-///
-/// This function attempts to open the Sled database and create an iterator over all key-value pairs.
-/// It logs the process and counts the total number of keys found.
-///
-/// # Arguments
-///
-/// * `logger` - An Arc-wrapped LoggerPort trait object for logging.
+/// Prints benchmark results with the run they were recorded as and its
+/// comparison against the baseline of its SKU, as JSON or as tables.
+fn print_benchmark_results(
+    json: bool,
+    run: &RunRecord,
+    settings: &BenchmarkSettings,
+    results: Vec<BenchmarkResult>,
+    comparison: Option<BaselineComparison>,
+    logger: &dyn LoggerPort,
+) {
+    if json {
+        let report = BenchmarkReport {
+            run_id: run.id.clone(),
+            status: run.status,
+            results,
+            comparison,
+        };
+        match serde_json::to_string_pretty(&report) {
+            Ok(json) => println!("{}", json),
+            Err(e) => logger.log_error(&format!("Error serializing results: {}", e)),
+        }
+    } else {
+        print!("{}", benchmark::render_results(&run.host, settings, &results));
+        println!("\nRecorded as run {} ({:?})", run.id, run.status);
+        if let Some(comparison) = comparison {
+            println!();
+            print!("{}", baselines::render_comparison(&comparison));
+        }
+    }
+}
+
+/// Returns the SKU of this host, from the config or else SMBIOS.
+fn host_sku(config: &BaselineConfig, logger: Arc<dyn LoggerPort>) -> Option<String> {
+    let discovery_adapter = SysfsDiscoveryAdapter::new(logger);
+    baselines::resolve_sku(config.sku.as_deref(), &discovery_adapter)
+}
+
+/// Compares a run with the baseline of its SKU, warning about regressions.
 ///
 /// # Returns
-///
-/// * `Result<(), sled::Error>` - Returns Ok(()) if successful, or an Err containing the sled::Error if an error occurred.
-fn get_all_keys(logger: Arc<dyn LoggerPort>) -> Result<(), sled::Error> {
-    // Log the attempt to open the Sled database.
-    logger.log_info("Attempting to open the Sled database.");
-
-    // Attempt to open the Sled database, gracefully handling errors.
-    let db = match sled::open("commandant-rs_database_file.db") {
-        Ok(db) => {
-            // Log the successful opening of the database.
-            logger.log_info("Database opened successfully.");
-            db
+/// The comparison, or `None` if the run has no baseline or the comparison failed.
+fn compare_with_baseline(
+    baselines: &Baselines,
+    run: &RunRecord,
+    tolerances: &BaselineTolerances,
+    logger: &dyn LoggerPort,
+) -> Option<BaselineComparison> {
+    match baselines.compare(run, tolerances) {
+        Ok(Some(comparison)) => {
+            if comparison.regressions > 0 {
+                logger.log_warn(&format!(
+                    "{} of {} metrics of run {} regressed against the baseline of {}",
+                    comparison.regressions,
+                    comparison.comparisons.len(),
+                    run.id,
+                    comparison.sku
+                ));
+            }
+            Some(comparison)
+        }
+        Ok(None) => {
+            logger.log_debug(&format!("No baseline to compare run {} with", run.id));
+            None
         }
         Err(e) => {
-            // Log the error and return it if the database fails to open.
-            logger.log_error(&format!("Error opening database: {}", e));
-            return Err(e);
+            logger.log_error(&e);
+            None
         }
-    };
+    }
+}
 
-    // Log the creation of an iterator over all key-value pairs in the database.
-    logger.log_debug("Creating an iterator over all key-value pairs in the database.");
+/// Opens the database with a backend.
+///
+/// # Arguments
+/// * `backend` - The storage backend of the database.
+/// * `path` - The path of the database; unused by the memory backend.
+/// * `logger` - Logger implementation for logging messages.
+fn open_database(
+    backend: DatabaseBackend,
+    path: &str,
+    logger: Arc<dyn LoggerPort>,
+) -> Result<Arc<dyn DatabasePort>, Box<dyn std::error::Error>> {
+    match backend {
+        DatabaseBackend::Sled => Ok(Arc::new(SledDatabaseAdapter::new(path, logger)?)),
+        DatabaseBackend::Memory => Ok(Arc::new(MemoryDatabaseAdapter::new())),
+    }
+}
 
-    // Create an iterator over all key-value pairs in the database.
-    let mut iter = db.iter();
-    let mut key_count = 0;
+/// Compacts the sled database at a path: every tree is copied into a new
+/// database next to it, which then replaces the database. The copy leaves
+/// behind the space sled keeps for removed and overwritten entries.
+///
+/// # Returns
+/// A `Result` containing the size of the database before and after, or an
+/// error message.
+fn compact_database(path: &str, logger: Arc<dyn LoggerPort>) -> Result<(u64, u64), String> {
+    let compacted = format!("{}.compacted", path);
+    let replaced = format!("{}.replaced", path);
+    let _ = std::fs::remove_dir_all(&compacted);
+    // Both databases are closed at the end of the block, before they are moved.
+    let sizes = {
+        let source = SledDatabaseAdapter::new(path, logger.clone()).map_err(|e| e.to_string())?;
+        let target =
+            SledDatabaseAdapter::new(&compacted, logger.clone()).map_err(|e| e.to_string())?;
+        let copied = database_ops::copy_database(&source, &target)?;
+        logger.log_info(&format!(
+            "Copied {} entries into {}",
+            copied.values().sum::<usize>(),
+            compacted
+        ));
+        let size = |db: &SledDatabaseAdapter| db.size_on_disk().map_err(|e| e.to_string());
+        (size(&source)?, size(&target)?)
+    };
+    let moved = |from: &str, to: &str| {
+        std::fs::rename(from, to).map_err(|e| format!("Unable to move {} to {}: {}", from, to, e))
+    };
+    moved(path, &replaced)?;
+    if let Err(e) = moved(&compacted, path) {
+        let _ = moved(&replaced, path);
+        return Err(e);
+    }
+    std::fs::remove_dir_all(&replaced)
+        .map_err(|e| format!("Unable to remove {}: {}", replaced, e))?;
+    Ok(sizes)
+}
 
-    // Iterate over all keys.
-    while let Some(result) = iter.next() {
-        match result {
-            Ok((key, _)) => {
-                // Increment key count.
-                key_count += 1;
+/// Opens the repository of an entity in its tree of the database.
+fn open_repository<T: Record + 'static>(
+    logger: Arc<dyn LoggerPort>,
+    db: &dyn DatabasePort,
+) -> std::io::Result<Arc<TreeRepository<T>>> {
+    match TreeRepository::open(logger.clone(), db) {
+        Ok(repository) => Ok(Arc::new(repository)),
+        Err(e) => {
+            logger.log_error(&e);
+            Err(std::io::Error::other(e))
+        }
+    }
+}
 
-                // Log each key.
-                logger.log_debug(&format!("Key: {:?}", key));
+/// Upgrades the database layout and imports the legacy OneForAll database, if
+/// one is configured, exists and has not been imported yet. Once imported, the
+/// legacy database is not opened again.
+///
+/// # Arguments
+/// * `migrator` - The migrator of the database.
+/// * `legacy_path` - The path of the legacy database, if any.
+/// * `dry_run` - Only reports what the migrations would do.
+/// * `logger` - The logger the legacy database is opened with.
+fn migrate_database(
+    migrator: &Migrator,
+    legacy_path: Option<&str>,
+    dry_run: bool,
+    logger: Arc<dyn LoggerPort>,
+) -> Result<Vec<MigrationReport>, String> {
+    let mut reports = migrator.migrate(dry_run)?;
+    let Some(legacy_path) = legacy_path else {
+        return Ok(reports);
+    };
+    if Path::new(legacy_path).is_dir() && !migrator.is_imported(legacy_path)? {
+        let legacy = SledDatabaseAdapter::new(legacy_path, logger)
+            .map_err(|e| format!("Unable to open {}: {}", legacy_path, e))?;
+        reports.extend(migrator.import_legacy(&legacy, legacy_path, dry_run)?);
+    }
+    Ok(reports)
+}
+
+/// Runs a database operation, printing its outcome.
+///
+/// # Arguments
+/// * `action` - The operation to run.
+/// * `database_ops` - The operations on the contents of the database.
+/// * `migrator` - The migrator of the database.
+/// * `retention` - The retention of the sample history.
+/// * `database_location` - Where the database is kept, e.g. its path.
+/// * `legacy_path` - The path of the legacy database migrations import, if any.
+/// * `logger` - Logger implementation for logging messages.
+fn run_database_ops(
+    action: DatabaseOpsAction,
+    database_ops: &DatabaseOps,
+    migrator: &Migrator,
+    retention: &Retention,
+    database_location: &str,
+    legacy_path: Option<&str>,
+    logger: Arc<dyn LoggerPort>,
+) -> Result<(), String> {
+    match action {
+        DatabaseOpsAction::Migrate { dry_run } => {
+            let reports = migrate_database(migrator, legacy_path, dry_run, logger)
+                .map_err(|e| format!("Migration failed: {}", e))?;
+            if reports.is_empty() {
+                println!("Nothing to migrate");
+            }
+            print!("{}", migrations::render_reports(&reports));
+            println!(
+                "Schema version {} (current {})",
+                migrator.schema_version()?,
+                SCHEMA_VERSION
+            );
+        }
+        DatabaseOpsAction::List { tree, prefix } => {
+            let keys = database_ops.list(tree.as_deref(), &prefix)?;
+            for (tree, key) in &keys {
+                println!("{}\t{}", tree, key);
+            }
+            println!("{} keys", keys.len());
+        }
+        DatabaseOpsAction::Get { key, tree } => {
+            let entries = database_ops.get(tree.as_deref(), &key)?;
+            if entries.is_empty() {
+                return Err(format!("No entry with key {} found", key));
+            }
+            for entry in entries {
+                println!("[{}] {}", entry.tree, entry.key);
+                println!("{}", entry.value);
             }
-            Err(e) => {
-                // Log the error and return it if an error occurs while iterating over the keys.
-                logger.log_error(&format!("Error iterating over keys: {}", e));
-                return Err(e);
+        }
+        DatabaseOpsAction::Export { format, out } => {
+            let count = database_ops.export(format, &out)?;
+            println!("Exported {} entries to {}", count, out.display());
+        }
+        DatabaseOpsAction::Import { file } => {
+            let imported = database_ops.import(&file)?;
+            for (tree, count) in &imported {
+                println!("Imported {} entries into {}", count, tree);
+            }
+        }
+        DatabaseOpsAction::Prune { older_than } => {
+            let pruned = database_ops.prune(older_than)?;
+            for (tree, count) in &pruned {
+                println!("Pruned {} records from {}", count, tree);
+            }
+        }
+        DatabaseOpsAction::Rollup => {
+            let report = retention.apply(chrono::Utc::now())?;
+            for (resolution, count) in &report.rolled_up {
+                println!("Rolled up {} {} buckets", count, resolution);
+            }
+            println!(
+                "Expired {} raw samples and {} 1-minute rollups",
+                report.expired_samples, report.expired_rollups
+            );
+        }
+        // The database is compacted before it is opened.
+        DatabaseOpsAction::Compact => {}
+        DatabaseOpsAction::Stats { json } => {
+            let stats = database_ops.stats(database_location)?;
+            if json {
+                let json = serde_json::to_string_pretty(&stats)
+                    .map_err(|e| format!("Error serializing statistics: {}", e))?;
+                println!("{}", json);
+            } else {
+                print!("{}", database_ops::render_stats(&stats));
             }
         }
     }
-
-    // Log the total number of keys found.
-    logger.log_info(&format!(
-        "Successfully iterated over all keys. Total keys found: {}",
-        key_count
-    ));
-
     Ok(())
 }
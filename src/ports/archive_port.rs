@@ -0,0 +1,17 @@
+use std::path::Path;
+
+use crate::domain::archive::ArchiveEntry;
+
+/// `ArchivePort` Trait
+///
+/// Defines an interface for bundling files into a single compressed archive
+/// and reading them back. Implementations decide the container and the
+/// compression of the archive.
+pub trait ArchivePort: Send + Sync {
+    /// Writes entries, in order, to an archive file, replacing the file if
+    /// it exists.
+    fn write_archive(&self, path: &Path, entries: &[ArchiveEntry]) -> Result<(), String>;
+
+    /// Reads every entry of an archive file, in order.
+    fn read_archive(&self, path: &Path) -> Result<Vec<ArchiveEntry>, String>;
+}
@@ -0,0 +1,53 @@
+use std::time::Duration;
+
+use crate::domain::benchmark::{BenchmarkCase, BenchmarkMeasurement};
+
+/// `BenchmarkPort` Trait
+///
+/// Defines an interface for a suite of benchmarks, e.g. CPU microbenchmarks
+/// or storage throughput. A suite offers a list of cases and measures a single
+/// iteration of a case at a time, so that repetitions and their statistics
+/// are handled the same way for every suite.
+pub trait BenchmarkPort: Send + Sync {
+    /// Returns the name of the suite, e.g. "cpu".
+    fn suite(&self) -> &str;
+
+    /// Lists the cases of the suite in the order they are run.
+    fn cases(&self) -> Vec<BenchmarkCase>;
+
+    /// Returns how long an iteration of the suite runs for.
+    ///
+    /// Suites that need longer iterations than the configured default, such
+    /// as storage benchmarks that should get past the device cache, override
+    /// this.
+    ///
+    /// # Arguments
+    /// * `default` - The configured duration of an iteration.
+    fn iteration_duration(&self, default: Duration) -> Duration {
+        default
+    }
+
+    /// Returns whether the cases of the suite are bound by the CPUs or
+    /// memory, so that their results are skewed when the CPUs throttle.
+    /// Suites bound by storage or the network keep the default, and are not
+    /// checked for throttling.
+    fn cpu_bound(&self) -> bool {
+        false
+    }
+
+    /// Runs a single iteration of a case.
+    ///
+    /// # Arguments
+    /// * `case` - One of the cases returned by `cases`.
+    /// * `duration` - How long the iteration should run for.
+    ///
+    /// # Returns
+    /// A `Result` containing the measured value in the unit of the case, e.g.
+    /// a rate or a latency, with any details measured alongside, or an error
+    /// message.
+    fn run_iteration(
+        &self,
+        case: &BenchmarkCase,
+        duration: Duration,
+    ) -> Result<BenchmarkMeasurement, String>;
+}
@@ -0,0 +1,41 @@
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+
+/// A named time series to be drawn as one panel of a chart.
+#[derive(Debug, Clone)]
+pub struct ChartSeries {
+    /// The title of the panel, e.g. "CPU utilization".
+    pub title: String,
+    /// The unit of the values, used as the y-axis label.
+    pub unit: String,
+    /// The data points in chronological order.
+    pub points: Vec<(DateTime<Utc>, f64)>,
+}
+
+/// `ChartPort` Trait
+///
+/// Defines an interface for rendering time series charts. The same chart can
+/// be rendered as SVG for the web console or as PNG for attaching to tickets.
+pub trait ChartPort: Send + Sync {
+    /// Renders the series as an SVG document.
+    ///
+    /// # Arguments
+    /// * `title` - The title drawn above all panels.
+    /// * `series` - The series to draw, one panel each.
+    ///
+    /// # Returns
+    /// A `Result` containing the SVG document or an error message.
+    fn render_svg(&self, title: &str, series: &[ChartSeries]) -> Result<String, String>;
+
+    /// Renders the series as a PNG image written to `path`.
+    ///
+    /// # Arguments
+    /// * `title` - The title drawn above all panels.
+    /// * `series` - The series to draw, one panel each.
+    /// * `path` - The file the PNG image is written to.
+    ///
+    /// # Returns
+    /// A `Result` indicating the success or failure of the operation.
+    fn render_png(&self, title: &str, series: &[ChartSeries], path: &Path) -> Result<(), String>;
+}
@@ -0,0 +1,15 @@
+use crate::domain::benchmark::CpuFrequencyReading;
+
+/// `CpuFrequencyPort` Trait
+///
+/// Defines an interface for reading the current CPU frequency and thermal
+/// throttling counters, so that benchmarks can tell whether the CPUs
+/// throttled while they were measured.
+pub trait CpuFrequencyPort: Send + Sync {
+    /// Takes a reading of the CPU frequency and throttling counters.
+    ///
+    /// # Returns
+    /// A `Result` containing the `CpuFrequencyReading`, with the values that
+    /// are unavailable on the host left unset, or an error message.
+    fn read(&self) -> Result<CpuFrequencyReading, String>;
+}
@@ -1,48 +1,211 @@
-use sled::{Db, IVec};
 use std::error::Error;
+use std::sync::Arc;
+
+/// A key-value pair read from a tree.
+pub type KeyValue = (Vec<u8>, Vec<u8>);
+
+/// A write of a batch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BatchOp {
+    /// Inserts a key-value pair, replacing any value of the key.
+    Insert(Vec<u8>, Vec<u8>),
+    /// Removes a key.
+    Remove(Vec<u8>),
+}
+
+/// `WriteBatch` Struct
+///
+/// Writes applied to a tree at once: either all of them are applied or none,
+/// and the tree is flushed once instead of after every write.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WriteBatch {
+    ops: Vec<BatchOp>,
+}
+
+impl WriteBatch {
+    /// Creates an empty batch.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the insert of a key-value pair.
+    pub fn insert(&mut self, key: &[u8], value: &[u8]) {
+        self.ops.push(BatchOp::Insert(key.to_vec(), value.to_vec()));
+    }
+
+    /// Adds the removal of a key.
+    pub fn remove(&mut self, key: &[u8]) {
+        self.ops.push(BatchOp::Remove(key.to_vec()));
+    }
+
+    /// Returns the writes in the order they were added.
+    pub fn ops(&self) -> &[BatchOp] {
+        &self.ops
+    }
+
+    /// Returns the number of writes.
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    /// Returns whether the batch has no writes.
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+}
+
+/// `Transaction` Trait
+///
+/// The reads and writes of a transaction on a tree. Writes become visible to
+/// other readers only once the transaction commits.
+pub trait Transaction {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, Box<dyn Error>>;
+    fn insert(&self, key: &[u8], value: &[u8]) -> Result<Option<Vec<u8>>, Box<dyn Error>>;
+}
+
+/// The body of a transaction. It may run more than once when a backend
+/// retries conflicting transactions, so it must not have other side effects.
+pub type TransactionFn<'a> = dyn Fn(&dyn Transaction) -> Result<(), Box<dyn Error>> + 'a;
 
 /// `DatabasePort` Trait
 ///
-/// Defines an interface for interacting with a database. This trait abstracts the functionality
-/// for performing basic CRUD operations, allowing different implementations to tailor database
-/// interactions.
+/// Defines an interface for interacting with a key-value database made of
+/// named trees, each a separate keyspace ordered by key. This trait abstracts
+/// the storage backend, allowing different implementations, such as sled on
+/// disk or an in-memory map, to be plugged in without changing the callers.
 pub trait DatabasePort: Send + Sync {
-    fn insert(&self, key: &[u8], value: &[u8]) -> Result<Option<IVec>, Box<dyn Error>>;
-    fn get(&self, key: &[u8]) -> Result<Option<IVec>, Box<dyn Error>>;
-    fn remove(&self, key: &[u8]) -> Result<Option<IVec>, Box<dyn Error>>;
+    fn insert(&self, key: &[u8], value: &[u8]) -> Result<Option<Vec<u8>>, Box<dyn Error>>;
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, Box<dyn Error>>;
+    /// Returns all key-value pairs whose key starts with `prefix`, in key order.
+    fn scan_prefix(&self, prefix: &[u8]) -> Result<Vec<KeyValue>, Box<dyn Error>>;
+    /// Returns all key-value pairs with a key from `start` up to but excluding
+    /// `end`, in key order.
+    fn scan_range(&self, start: &[u8], end: &[u8]) -> Result<Vec<KeyValue>, Box<dyn Error>>;
+    /// Returns the pair with the first key from `start` up to but excluding
+    /// `end`, without reading the rest of the range.
+    fn first_in_range(&self, start: &[u8], end: &[u8]) -> Result<Option<KeyValue>, Box<dyn Error>>;
+    /// Returns the pair with the last key from `start` up to but excluding
+    /// `end`, without reading the rest of the range.
+    fn last_in_range(&self, start: &[u8], end: &[u8]) -> Result<Option<KeyValue>, Box<dyn Error>>;
+    /// Applies the writes of a batch atomically.
+    fn apply_batch(&self, batch: &WriteBatch) -> Result<(), Box<dyn Error>>;
+    /// Runs a transaction on the tree: its writes are committed if it
+    /// returns `Ok`, and discarded if it returns an error, which is returned.
+    fn transaction(&self, body: &TransactionFn) -> Result<(), Box<dyn Error>>;
+    /// Opens a named tree of the same database, a separate keyspace, creating
+    /// it if it doesn't exist.
+    fn open_tree(&self, name: &str) -> Result<Arc<dyn DatabasePort>, Box<dyn Error>>;
+    /// Returns the names of every tree of the database, including the default
+    /// tree as `DEFAULT_TREE`.
+    fn tree_names(&self) -> Result<Vec<String>, Box<dyn Error>>;
+    /// Returns the size of the whole database in bytes, on disk or in memory.
+    fn size_on_disk(&self) -> Result<u64, Box<dyn Error>>;
 }
 
-/// A struct that implements the `DatabasePort` trait using the Sled embedded database.
-pub struct SledDatabasePort {
-    db: Db,
-}
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use common::adapters::capture_log_adapter::CapturingLogger;
+
+    use super::*;
+    use crate::adapters::memory_database_adapter::MemoryDatabaseAdapter;
+    use crate::adapters::sled_database_adapter::SledDatabaseAdapter;
+    use crate::domain::database::DEFAULT_TREE;
 
-impl SledDatabasePort {
-    /// Opens a new database or creates it if it doesn't exist.
-    pub fn new(path: &str) -> Result<Self, Box<dyn Error>> {
-        let db = sled::open(path)?;
-        Ok(SledDatabasePort { db })
+    /// Returns the keys of pairs as text.
+    fn keys(pairs: &[KeyValue]) -> Vec<String> {
+        pairs
+            .iter()
+            .map(|(key, _)| String::from_utf8_lossy(key).into_owned())
+            .collect()
     }
-}
 
-impl DatabasePort for SledDatabasePort {
-    /// Inserts a key-value pair into the database.
-    fn insert(&self, key: &[u8], value: &[u8]) -> Result<Option<IVec>, Box<dyn Error>> {
-        let previous_value = self.db.insert(key, value)?;
-        self.db.flush()?; // Ensure that changes are written to disk.
-        Ok(previous_value)
+    /// Returns the key of a pair as text.
+    fn key(pair: Option<KeyValue>) -> Option<String> {
+        pair.map(|(key, _)| String::from_utf8_lossy(&key).into_owned())
+    }
+
+    /// Checks the behavior every backend of the port shares.
+    fn keeps_the_contract(db: &dyn DatabasePort) {
+        let tree = db.open_tree("contract").unwrap();
+        for key in ["b", "a/2", "ab", "a/1", "a0"] {
+            assert_eq!(tree.insert(key.as_bytes(), b"1").unwrap(), None);
+        }
+        assert_eq!(tree.insert(b"a/1", b"2").unwrap(), Some(b"1".to_vec()));
+        assert_eq!(tree.get(b"a/1").unwrap(), Some(b"2".to_vec()));
+        assert_eq!(tree.get(b"missing").unwrap(), None);
+
+        // Scans are in key order, with the end of ranges excluded.
+        assert_eq!(keys(&tree.scan_prefix(b"a/").unwrap()), ["a/1", "a/2"]);
+        assert_eq!(tree.scan_prefix(b"").unwrap().len(), 5);
+        assert_eq!(
+            keys(&tree.scan_range(b"a/", b"ab").unwrap()),
+            ["a/1", "a/2", "a0"]
+        );
+        assert!(tree.scan_range(b"b", b"a").unwrap().is_empty());
+
+        assert_eq!(
+            key(tree.first_in_range(b"a/", b"b").unwrap()).unwrap(),
+            "a/1"
+        );
+        assert_eq!(key(tree.last_in_range(b"a/", b"b").unwrap()).unwrap(), "ab");
+        assert_eq!(
+            key(tree.first_in_range(b"a0", &[0xff]).unwrap()).unwrap(),
+            "a0"
+        );
+        assert_eq!(key(tree.last_in_range(b"", &[0xff]).unwrap()).unwrap(), "b");
+        assert_eq!(tree.first_in_range(b"c", b"d").unwrap(), None);
+        assert_eq!(tree.last_in_range(b"b", b"b").unwrap(), None);
+        assert_eq!(tree.last_in_range(b"b", b"a").unwrap(), None);
+
+        let mut batch = WriteBatch::new();
+        batch.insert(b"c", b"3");
+        batch.remove(b"a0");
+        tree.apply_batch(&batch).unwrap();
+        assert_eq!(tree.get(b"c").unwrap(), Some(b"3".to_vec()));
+        assert_eq!(tree.get(b"a0").unwrap(), None);
+
+        // Transactions see their own writes, and keep none of them if they fail.
+        tree.transaction(&|tx| {
+            tx.insert(b"d", b"4")?;
+            assert_eq!(tx.get(b"d")?, Some(b"4".to_vec()));
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(tree.get(b"d").unwrap(), Some(b"4".to_vec()));
+        let error = tree
+            .transaction(&|tx| {
+                tx.insert(b"e", b"5")?;
+                Err("rejected".into())
+            })
+            .unwrap_err();
+        assert!(error.to_string().contains("rejected"));
+        assert_eq!(tree.get(b"e").unwrap(), None);
+
+        // Trees are separate keyspaces, listed with the default tree.
+        assert_eq!(db.get(b"c").unwrap(), None);
+        assert_eq!(
+            db.open_tree("contract").unwrap().get(b"c").unwrap(),
+            Some(b"3".to_vec())
+        );
+        let names = db.tree_names().unwrap();
+        assert!(names.iter().any(|name| name == DEFAULT_TREE));
+        assert!(names.iter().any(|name| name == "contract"));
+        db.size_on_disk().unwrap();
     }
 
-    /// Retrieves a value from the database by key.
-    fn get(&self, key: &[u8]) -> Result<Option<IVec>, Box<dyn Error>> {
-        let value = self.db.get(key)?;
-        Ok(value)
+    #[test]
+    fn memory_backend_keeps_the_contract() {
+        keeps_the_contract(&MemoryDatabaseAdapter::new());
     }
 
-    /// Removes a key-value pair from the database.
-    fn remove(&self, key: &[u8]) -> Result<Option<IVec>, Box<dyn Error>> {
-        let previous_value = self.db.remove(key)?;
-        self.db.flush()?; // Ensure that changes are written to disk.
-        Ok(previous_value)
+    #[test]
+    fn sled_backend_keeps_the_contract() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("db");
+        let db = SledDatabaseAdapter::new(path.to_str().unwrap(), Arc::new(CapturingLogger::new()))
+            .unwrap();
+        keeps_the_contract(&db);
     }
 }
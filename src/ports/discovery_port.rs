@@ -0,0 +1,51 @@
+use crate::domain::inventory::{
+    CpuInventory, MemoryInventory, NetworkInventory, PciDevice, PlatformInfo, StorageInventory,
+};
+
+/// `DiscoveryPort` Trait
+///
+/// Defines an interface for discovering the hardware of the host. Each method
+/// discovers one area of the inventory so that callers can run only the parts
+/// they need.
+///
+/// Implementors can read the hardware from different sources, e.g. the live
+/// procfs and sysfs trees or a copy captured from another node.
+pub trait DiscoveryPort: Send + Sync {
+    /// Discovers the processors: model, topology, caches, NUMA nodes,
+    /// frequencies and feature flags.
+    ///
+    /// # Returns
+    /// A `Result` containing the `CpuInventory` or an error message.
+    fn discover_cpu(&self) -> Result<CpuInventory, String>;
+
+    /// Discovers the BIOS, system and baseboard identification.
+    ///
+    /// # Returns
+    /// A `Result` containing the `PlatformInfo` or an error message.
+    fn discover_platform(&self) -> Result<PlatformInfo, String>;
+
+    /// Discovers the memory arrays and every DIMM slot, populated or not.
+    ///
+    /// # Returns
+    /// A `Result` containing the `MemoryInventory` or an error message.
+    fn discover_memory(&self) -> Result<MemoryInventory, String>;
+
+    /// Discovers the PCI devices with their names, drivers, NUMA affinity and
+    /// PCI Express link state.
+    ///
+    /// # Returns
+    /// A `Result` containing the devices or an error message.
+    fn discover_pci(&self) -> Result<Vec<PciDevice>, String>;
+
+    /// Discovers the block devices and the filesystems mounted from them.
+    ///
+    /// # Returns
+    /// A `Result` containing the `StorageInventory` or an error message.
+    fn discover_storage(&self) -> Result<StorageInventory, String>;
+
+    /// Discovers the network interfaces and RDMA devices with their link state.
+    ///
+    /// # Returns
+    /// A `Result` containing the `NetworkInventory` or an error message.
+    fn discover_network(&self) -> Result<NetworkInventory, String>;
+}
@@ -1,4 +1,11 @@
+pub mod archive_port;
+pub mod benchmark_port;
+pub mod chart_port;
+pub mod cpu_frequency_port;
 pub mod database_port;
+pub mod discovery_port;
 pub(crate) mod ps_command_port;
+pub mod repository_port;
 pub mod stress_test_port;
 pub mod ai_model_port;
+pub mod system_metrics_port;
@@ -0,0 +1,68 @@
+use crate::domain::baseline::Baseline;
+use crate::domain::inventory::HostInventory;
+use crate::domain::record::{Page, Record, RecordQuery};
+use crate::domain::rollup::MetricRollup;
+use crate::domain::run::{MetricSample, RunArtifact, RunRecord};
+
+/// `RepositoryPort` Trait
+///
+/// Defines an interface for storing and querying records of one entity, e.g.
+/// runs, as typed domain structs rather than raw bytes. Implementations
+/// decide how records are encoded and where they are stored.
+pub trait RepositoryPort<T: Record>: Send + Sync {
+    /// Stores a record under its key, replacing any record with the same key.
+    fn save(&self, record: &T) -> Result<(), String>;
+
+    /// Loads a record by key.
+    ///
+    /// # Returns
+    /// A `Result` containing the record, `None` if no record has the key, or
+    /// an error message if it could not be decoded.
+    fn get(&self, key: &str) -> Result<Option<T>, String>;
+
+    /// Stores records at once: either all of them are stored or none.
+    fn save_all(&self, records: &[T]) -> Result<(), String>;
+
+    /// Removes records by key at once: either all of them are removed or none.
+    fn remove_all(&self, keys: &[String]) -> Result<(), String>;
+
+    /// Lists the records matching a query, ordered by time.
+    ///
+    /// # Arguments
+    /// * `query` - The filters, order and page of the records.
+    ///
+    /// # Returns
+    /// A `Result` containing the page of matching records, or an error message.
+    fn list(&self, query: &RecordQuery) -> Result<Page<T>, String>;
+
+    /// Lists the owners of the records in key order: the distinct parts of
+    /// their keys before the first slash, e.g. the hosts of samples.
+    fn key_owners(&self) -> Result<Vec<String>, String>;
+
+    /// Loads the record with the last key in the key range of a query without
+    /// listing the range, e.g. the newest rollup of a host at a resolution,
+    /// whose keys end with its time. Other filters of the query are ignored.
+    ///
+    /// # Returns
+    /// A `Result` containing the record, `None` if the range is empty or the
+    /// query does not narrow the keys down, or an error message.
+    fn last_in_key_range(&self, query: &RecordQuery) -> Result<Option<T>, String>;
+}
+
+/// The repository of run records.
+pub type RunRepository = dyn RepositoryPort<RunRecord>;
+
+/// The repository of the files produced by runs.
+pub type ArtifactRepository = dyn RepositoryPort<RunArtifact>;
+
+/// The repository of metric samples.
+pub type SampleRepository = dyn RepositoryPort<MetricSample>;
+
+/// The repository of 1-minute and hourly sample rollups.
+pub type RollupRepository = dyn RepositoryPort<MetricRollup>;
+
+/// The repository of inventory snapshots.
+pub type InventoryRepository = dyn RepositoryPort<HostInventory>;
+
+/// The repository of SKU baselines.
+pub type BaselineRepository = dyn RepositoryPort<Baseline>;
@@ -0,0 +1,20 @@
+use crate::domain::run::MetricSample;
+
+/// `SystemMetricsPort` Trait
+///
+/// Defines an interface for taking point-in-time readings of the host's
+/// utilization and temperature. Implementations decide where the readings come
+/// from, e.g. procfs and sysfs on Linux.
+pub trait SystemMetricsPort: Send + Sync {
+    /// Returns the name of the host the readings are taken on.
+    fn hostname(&self) -> String;
+
+    /// Takes a sample of the host's current utilization and temperature.
+    ///
+    /// # Arguments
+    /// * `run_id` - The run the sample belongs to, if any.
+    ///
+    /// # Returns
+    /// A `Result` containing the `MetricSample` or an error message.
+    fn sample(&self, run_id: Option<&str>) -> Result<MetricSample, String>;
+}